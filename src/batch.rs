@@ -0,0 +1,308 @@
+//! Batches of frames of the same size stored in one contiguous buffer.
+
+use std::cmp;
+use std::marker::PhantomData;
+use std::mem;
+use std::ops::Deref;
+
+use num_traits::Zero;
+
+use buffer::{BufferError, ImageBuffer, Origin};
+use traits::Color as Pixel;
+
+/// A batch of frames of the same size stored one after another in a single `Vec`.
+///
+/// The subpixels are laid out frame by frame, row by row and pixel by pixel without padding,
+/// the `N×H×W×C` layout expected by machine learning frameworks. Every frame can be accessed
+/// as an `ImageBuffer` borrowing the batch, and the whole batch as one image of all frames
+/// stacked vertically.
+///
+/// # Examples
+///
+/// ```
+/// # use image_buffer::{BatchBuffer, GrayImage, color::Gray};
+/// let mut batch = BatchBuffer::<Gray<u8>>::new(2, 2, 3);
+/// for (i, mut frame) in batch.frames_mut().enumerate() {
+///     frame.fill(Gray::new([i as u8]));
+/// }
+/// assert_eq!(batch.frame(2)[(1, 1)], Gray::new([2]));
+/// assert_eq!(batch.as_stacked().dimensions(), (2, 6));
+/// assert_eq!(batch.into_raw(), vec![0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2]);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct BatchBuffer<P: Pixel> {
+    width: u32,
+    height: u32,
+    len: usize,
+    data: Vec<P::Subpixel>,
+    _pixel_type: PhantomData<P>,
+}
+
+impl<P: Pixel> BatchBuffer<P> {
+    /// Creates a batch of `len` frames filled with zeros.
+    pub fn new(width: u32, height: u32, len: usize) -> BatchBuffer<P> {
+        let size = width as usize * height as usize * P::channel_count() * len;
+        BatchBuffer::from_raw(width, height, len, vec![Zero::zero(); size]).unwrap()
+    }
+
+    /// Constructs a batch of `len` frames from a `Vec` holding exactly their subpixels.
+    pub fn from_raw(width: u32,
+                    height: u32,
+                    len: usize,
+                    data: Vec<P::Subpixel>)
+                    -> Result<BatchBuffer<P>, BufferError> {
+        let expected = (width as usize)
+            .checked_mul(height as usize)
+            .and_then(|n| n.checked_mul(P::channel_count()))
+            .and_then(|n| n.checked_mul(len))
+            .ok_or(BufferError::DimensionOverflow)?;
+        let got = data.len();
+        if got < expected {
+            return Err(BufferError::TooSmall { expected, got });
+        }
+        if got > expected {
+            return Err(BufferError::TooLarge { expected, got });
+        }
+        Ok(BatchBuffer {
+            width,
+            height,
+            len,
+            data,
+            _pixel_type: PhantomData,
+        })
+    }
+
+    /// Collects frames of the same size into a batch.
+    ///
+    /// The subpixels of the first frame are not copied if it has no padding, so a batch of a
+    /// single frame is built without copying. Returns None if there are no frames or their
+    /// dimensions differ.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{BatchBuffer, GrayImage};
+    /// let frames = vec![GrayImage::new(4, 3), GrayImage::new(4, 3)];
+    /// let batch = BatchBuffer::from_frames(frames).unwrap();
+    /// assert_eq!((batch.dimensions(), batch.len()), ((4, 3), 2));
+    /// assert_eq!(batch.into_frames().len(), 2);
+    /// ```
+    pub fn from_frames(frames: Vec<ImageBuffer<P, Vec<P::Subpixel>>>) -> Option<BatchBuffer<P>> {
+        let (width, height) = frames.first()?.dimensions();
+        if frames.iter().any(|frame| frame.dimensions() != (width, height)) {
+            return None;
+        }
+        let len = frames.len();
+        let mut frames = frames.into_iter();
+        let first = frames.next()?;
+        let mut data = if first.is_packed() {
+            let logical_len = first.logical_len();
+            let mut data = first.into_raw();
+            data.truncate(logical_len);
+            data
+        } else {
+            first.to_raw_with_origin(Origin::TopLeft)
+        };
+        data.reserve(data.len() * (len - 1));
+        for frame in frames {
+            extend_rows(&mut data, &frame);
+        }
+        BatchBuffer::from_raw(width, height, len, data).ok()
+    }
+
+    /// Splits the batch into separate frames.
+    ///
+    /// The first frame reuses the allocation of the batch, the others are copied.
+    pub fn into_frames(self) -> Vec<ImageBuffer<P, Vec<P::Subpixel>>> {
+        let (width, height, frame_len) = (self.width, self.height, self.frame_len());
+        let mut data = self.data;
+        let mut frames = Vec::with_capacity(self.len);
+        for i in (1..self.len).rev() {
+            let tail = data.split_off(i * frame_len);
+            frames.push(ImageBuffer::from_raw(width, height, tail).unwrap());
+        }
+        if self.len > 0 {
+            frames.push(ImageBuffer::from_raw(width, height, data).unwrap());
+        }
+        frames.reverse();
+        frames
+    }
+
+    /// Returns the underlying raw buffer.
+    pub fn into_raw(self) -> Vec<P::Subpixel> {
+        self.data
+    }
+
+    /// The width and height of the frames.
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// The width of the frames.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The height of the frames.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The number of frames.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the batch holds no frames.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The number of subpixels of a frame.
+    fn frame_len(&self) -> usize {
+        self.width as usize * self.height as usize * P::channel_count()
+    }
+
+    /// Returns frame `index`.
+    ///
+    /// # Panics
+    ///
+    /// If `index` is out of bounds.
+    pub fn frame(&self, index: usize) -> ImageBuffer<P, &[P::Subpixel]> {
+        assert!(index < self.len, "frame {} out of bounds {}", index, self.len);
+        let len = self.frame_len();
+        ImageBuffer::from_raw(self.width, self.height, &self.data[index * len..(index + 1) * len])
+            .unwrap()
+    }
+
+    /// Returns the mutable frame `index`.
+    ///
+    /// # Panics
+    ///
+    /// If `index` is out of bounds.
+    pub fn frame_mut(&mut self, index: usize) -> ImageBuffer<P, &mut [P::Subpixel]> {
+        assert!(index < self.len, "frame {} out of bounds {}", index, self.len);
+        let len = self.frame_len();
+        let data = &mut self.data[index * len..(index + 1) * len];
+        ImageBuffer::from_raw(self.width, self.height, data).unwrap()
+    }
+
+    /// Returns an iterator over the frames.
+    pub fn frames<'a>(&'a self)
+                      -> impl ExactSizeIterator<Item = ImageBuffer<P, &'a [P::Subpixel]>> + 'a {
+        (0..self.len).map(move |i| self.frame(i))
+    }
+
+    /// Returns an iterator over the mutable frames.
+    pub fn frames_mut<'a>
+        (&'a mut self)
+         -> impl ExactSizeIterator<Item = ImageBuffer<P, &'a mut [P::Subpixel]>> + 'a {
+        let (width, height, len) = (self.width, self.height, self.frame_len());
+        let mut rest = &mut self.data[..];
+        (0..self.len).map(move |_| {
+            let (frame, tail) = mem::take(&mut rest).split_at_mut(len);
+            rest = tail;
+            ImageBuffer::from_raw(width, height, frame).unwrap()
+        })
+    }
+
+    /// Appends a copy of `frame` to the batch.
+    ///
+    /// # Panics
+    ///
+    /// If the dimensions of `frame` differ from the dimensions of the batch.
+    pub fn push<C>(&mut self, frame: &ImageBuffer<P, C>)
+        where C: Deref<Target = [P::Subpixel]>
+    {
+        assert_eq!(frame.dimensions(),
+                   self.dimensions(),
+                   "frame dimensions differ from the batch");
+        extend_rows(&mut self.data, frame);
+        self.len += 1;
+    }
+
+    /// Returns the batch as one image of all frames stacked vertically, such that an operation
+    /// can process the whole batch in one pass.
+    ///
+    /// # Panics
+    ///
+    /// If the height of all frames together does not fit into `u32`.
+    pub fn as_stacked(&self) -> ImageBuffer<P, &[P::Subpixel]> {
+        ImageBuffer::from_raw(self.width, self.stacked_height(), &self.data[..]).unwrap()
+    }
+
+    /// Returns the batch as one mutable image of all frames stacked vertically.
+    ///
+    /// # Panics
+    ///
+    /// If the height of all frames together does not fit into `u32`.
+    pub fn as_stacked_mut(&mut self) -> ImageBuffer<P, &mut [P::Subpixel]> {
+        let height = self.stacked_height();
+        ImageBuffer::from_raw(self.width, height, &mut self.data[..]).unwrap()
+    }
+
+    fn stacked_height(&self) -> u32 {
+        let height = self.height as usize * self.len;
+        assert!(height <= u32::MAX as usize, "stacked height {} overflows u32", height);
+        height as u32
+    }
+}
+
+/// Appends the rows of `frame` without padding to `data`.
+fn extend_rows<P, C>(data: &mut Vec<P::Subpixel>, frame: &ImageBuffer<P, C>)
+    where P: Pixel,
+          C: Deref<Target = [P::Subpixel]>
+{
+    let row_len = frame.width() as usize * P::channel_count();
+    let subpixels: &[P::Subpixel] = frame;
+    for row in subpixels[..frame.logical_len()].chunks(cmp::max(1, frame.stride())) {
+        data.extend_from_slice(&row[..row_len]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buffer::{BufferError, GrayImage, RgbImage};
+    use color_model::{Gray, Rgb};
+    use super::BatchBuffer;
+
+    #[test]
+    fn test_frames() {
+        let frames: Vec<RgbImage> =
+            (0..3).map(|i| RgbImage::from_pixel(2, 1, Rgb::new([i, i, i]))).collect();
+        let mut batch = BatchBuffer::from_frames(frames).unwrap();
+        assert_eq!(batch.frame(1).into_raw(), &[1, 1, 1, 1, 1, 1]);
+        batch.frame_mut(0)[(1, 0)] = Rgb::new([7, 8, 9]);
+        batch.push(&RgbImage::from_pixel(2, 1, Rgb::new([5, 5, 5])));
+        assert_eq!(batch.len(), 4);
+        assert_eq!(batch.frames().map(|f| f[(0, 0)][0]).collect::<Vec<_>>(), vec![0, 1, 2, 5]);
+        assert_eq!(batch.as_stacked()[(1, 0)], Rgb::new([7, 8, 9]));
+        batch.as_stacked_mut()[(0, 3)] = Rgb::new([6, 6, 6]);
+
+        let frames = batch.into_frames();
+        assert_eq!(frames.len(), 4);
+        assert_eq!(frames[0].clone().into_raw(), vec![0, 0, 0, 7, 8, 9]);
+        assert_eq!(frames[3][(0, 0)], Rgb::new([6, 6, 6]));
+    }
+
+    #[test]
+    fn test_from_frames() {
+        assert!(BatchBuffer::<Gray<u8>>::from_frames(vec![]).is_none());
+        assert!(BatchBuffer::from_frames(vec![GrayImage::new(2, 2), GrayImage::new(2, 3)])
+            .is_none());
+
+        let strided = GrayImage::from_raw_with_stride(2, 2, 3, vec![1, 2, 0, 3, 4, 0, 0]).unwrap();
+        let mut batch = BatchBuffer::from_frames(vec![strided.clone(), GrayImage::new(2, 2)])
+            .unwrap();
+        batch.push(&strided);
+        assert_eq!(batch.into_raw(), vec![1, 2, 3, 4, 0, 0, 0, 0, 1, 2, 3, 4]);
+        let batch = BatchBuffer::from_frames(vec![GrayImage::new(2, 2), strided]).unwrap();
+        assert_eq!(batch.into_raw(), vec![0, 0, 0, 0, 1, 2, 3, 4]);
+
+        assert_eq!(BatchBuffer::<Gray<u8>>::from_raw(2, 2, 2, vec![0; 7]).err(),
+                   Some(BufferError::TooSmall { expected: 8, got: 7 }));
+        let mut empty = BatchBuffer::<Gray<u8>>::new(0, 0, 2);
+        assert_eq!(empty.frames_mut().count(), 2);
+        assert!(BatchBuffer::<Gray<u8>>::new(1, 1, 0).into_frames().is_empty());
+    }
+}
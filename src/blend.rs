@@ -0,0 +1,569 @@
+//! Alpha compositing and blending.
+//!
+//! Implements the compositing operators of Porter and Duff for colors with a straight (not
+//! premultiplied) alpha channel and the feathered blending of overlapping images. The `Blend`
+//! trait draws one color over another for every color type.
+
+use std::ops::{Deref, DerefMut};
+
+use buffer::ImageBuffer;
+use color_model::{Alpha, Cmyk, ColorSpace, Gray, Hsl, Hsv, Indexed, Lab, PremultipliedAlpha, Rgb,
+                  Xyz, YCbCr};
+use math::{from_f32, to_f32};
+use test_images::{splitmix64, XorShift};
+use traits::{ChannelMax, Color, Primitive};
+
+/// A Porter-Duff compositing operator.
+///
+/// The descriptions refer to the source `A` which is composited onto the destination `B`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Operator {
+    /// `A` is placed over `B`.
+    Over,
+    /// The part of `A` inside of `B` replaces `B`.
+    In,
+    /// The part of `A` outside of `B` replaces `B`.
+    Out,
+    /// The part of `A` inside of `B` is placed over `B`.
+    Atop,
+    /// The parts of `A` and `B` which do not overlap.
+    Xor,
+}
+
+impl Operator {
+    /// Returns the fractions of the source and destination given their alpha values.
+    fn fractions(self, alpha_src: f32, alpha_dst: f32) -> (f32, f32) {
+        match self {
+            Operator::Over => (1.0, 1.0 - alpha_src),
+            Operator::In => (alpha_dst, 0.0),
+            Operator::Out => (1.0 - alpha_dst, 0.0),
+            Operator::Atop => (alpha_dst, 1.0 - alpha_src),
+            Operator::Xor => (1.0 - alpha_dst, 1.0 - alpha_src),
+        }
+    }
+}
+
+impl<C: Color, const N: usize> Alpha<C, N>
+    where C::Subpixel: ChannelMax
+{
+    /// Composites this color onto `dst` with the operator `op`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::color::Rgba;
+    /// use image_buffer::blend::Operator;
+    /// let red = Rgba::new([255u8, 0, 0, 255]);
+    /// let blue = Rgba::new([0, 0, 255, 255]);
+    /// assert_eq!(red.composite(&blue, Operator::Over), red);
+    /// assert_eq!(red.composite(&blue, Operator::Xor), Rgba::new([0, 0, 0, 0]));
+    /// ```
+    pub fn composite(&self, dst: &Self, op: Operator) -> Self {
+        self.composite_in(dst, op, ColorSpace::Linear)
+    }
+
+    /// Like `composite` but interprets the color channels according to `space`, with
+    /// `ColorSpace::Srgb` the colors are mixed in linear light.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::color::{ColorSpace, Rgba};
+    /// use image_buffer::blend::Operator;
+    /// let white = Rgba::new([255u8, 255, 255, 128]);
+    /// let black = Rgba::new([0, 0, 0, 255]);
+    /// assert_eq!(white.composite_in(&black, Operator::Over, ColorSpace::Srgb),
+    ///            Rgba::new([188, 188, 188, 255]));
+    /// ```
+    pub fn composite_in(&self, dst: &Self, op: Operator, space: ColorSpace) -> Self {
+        let max = to_f32(C::Subpixel::channel_max());
+        let decode = |v: C::Subpixel| space.decode(to_f32(v) / max);
+        let (src, dst) = (self.channels(), dst.channels());
+        let (alpha_src, alpha_dst) = (to_f32(src[N - 1]) / max, to_f32(dst[N - 1]) / max);
+        let (fa, fb) = op.fractions(alpha_src, alpha_dst);
+        let (wa, wb) = (alpha_src * fa, alpha_dst * fb);
+        let alpha = wa + wb;
+        let mut result = *self;
+        {
+            let out = result.channels_mut();
+            for i in 0..N - 1 {
+                out[i] = if alpha > 0.0 {
+                    let linear = (decode(src[i]) * wa + decode(dst[i]) * wb) / alpha;
+                    from_f32(space.encode(linear) * max)
+                } else {
+                    from_f32(0.0)
+                };
+            }
+            out[N - 1] = from_f32(alpha * max);
+        }
+        result
+    }
+}
+
+/// Colors which can be drawn over each other, which allows to write drawing code generically
+/// over the color type.
+pub trait Blend {
+    /// Draws `other` over this color.
+    ///
+    /// Colors with alpha are composited with `Operator::Over`, opaque colors are replaced.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::color::{Gray, GrayA};
+    /// use image_buffer::blend::Blend;
+    /// let mut gray = Gray::new([10u8]);
+    /// gray.blend(&Gray::new([200]));
+    /// assert_eq!(gray, Gray::new([200]));
+    /// let mut gray = GrayA::new([0u8, 255]);
+    /// gray.blend(&GrayA::new([200, 128]));
+    /// assert_eq!(gray, GrayA::new([100, 255]));
+    /// ```
+    fn blend(&mut self, other: &Self);
+
+    /// Draws `other` over this color, of which the fraction `coverage` is covered, like the
+    /// edge pixels of an antialiased shape.
+    ///
+    /// Colors with alpha are composited with their alpha scaled by `coverage`, opaque colors
+    /// are mixed with `other` in proportion to it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::color::{Gray, GrayA};
+    /// use image_buffer::blend::Blend;
+    /// let mut gray = Gray::new([0u8]);
+    /// gray.blend_coverage(&Gray::new([200]), 0.25);
+    /// assert_eq!(gray, Gray::new([50]));
+    /// let mut gray = GrayA::new([0u8, 255]);
+    /// gray.blend_coverage(&GrayA::new([200, 255]), 0.5);
+    /// assert_eq!(gray, GrayA::new([100, 255]));
+    /// ```
+    fn blend_coverage(&mut self, other: &Self, coverage: f32)
+        where Self: Color,
+              Self::Subpixel: ChannelMax
+    {
+        if coverage <= 0.0 {
+            return;
+        }
+        if coverage >= 1.0 {
+            return self.blend(other);
+        }
+        let other = other.channels().as_ref();
+        for (v, &o) in self.channels_mut().as_mut().iter_mut().zip(other) {
+            let d = to_f32(*v);
+            *v = from_f32(d + (to_f32(o) - d) * coverage);
+        }
+    }
+}
+
+macro_rules! impl_opaque_blend {
+    ($($ident:ident),*) => {$(
+        impl<T: Primitive> Blend for $ident<T> {
+            fn blend(&mut self, other: &Self) {
+                *self = *other;
+            }
+        }
+    )*}
+}
+
+impl_opaque_blend!(Rgb, Xyz, Lab, Cmyk, YCbCr, Gray);
+
+macro_rules! impl_hue_blend {
+    ($($ident:ident),*) => {$(
+        impl<T: Primitive> Blend for $ident<T> {
+            fn blend(&mut self, other: &Self) {
+                *self = *other;
+            }
+
+            /// Mixes the colors like opaque colors, but interpolates the hue along the shorter
+            /// arc of the color wheel.
+            fn blend_coverage(&mut self, other: &Self, coverage: f32)
+                where Self: Color,
+                      <Self as Color>::Subpixel: ChannelMax
+            {
+                if coverage <= 0.0 {
+                    return;
+                }
+                if coverage >= 1.0 {
+                    return self.blend(other);
+                }
+                let max = to_f32(<Self as Color>::Subpixel::channel_max());
+                let other = other.channels().as_ref();
+                let channels = self.channels_mut().as_mut();
+                let (h, o) = (to_f32(channels[0]) / max, to_f32(other[0]) / max);
+                let hue = h + (o - h - (o - h).round()) * coverage;
+                channels[0] = from_f32((hue - hue.floor()) * max);
+                for (v, &o) in channels[1..].iter_mut().zip(&other[1..]) {
+                    let d = to_f32(*v);
+                    *v = from_f32(d + (to_f32(o) - d) * coverage);
+                }
+            }
+        }
+    )*}
+}
+
+impl_hue_blend!(Hsv, Hsl);
+
+impl<T: Primitive> Blend for Indexed<T> {
+    fn blend(&mut self, other: &Self) {
+        *self = *other;
+    }
+
+    /// Palette indices cannot be mixed, the pixel is replaced if at least half of it is covered.
+    fn blend_coverage(&mut self, other: &Self, coverage: f32)
+        where Self: Color,
+              <Self as Color>::Subpixel: ChannelMax
+    {
+        if coverage >= 0.5 {
+            self.blend(other);
+        }
+    }
+}
+
+impl<C: Color, const N: usize> Blend for Alpha<C, N>
+    where C::Subpixel: ChannelMax
+{
+    fn blend(&mut self, other: &Self) {
+        *self = other.composite(self, Operator::Over);
+    }
+
+    fn blend_coverage(&mut self, other: &Self, coverage: f32) {
+        if coverage > 0.0 {
+            let mut other = *other;
+            let alpha = &mut other.channels_mut()[N - 1];
+            *alpha = from_f32(to_f32(*alpha) * coverage.min(1.0));
+            self.blend(&other);
+        }
+    }
+}
+
+impl<C: Color, const N: usize> Blend for PremultipliedAlpha<C, N>
+    where C::Subpixel: ChannelMax
+{
+    fn blend(&mut self, other: &Self) {
+        let max = to_f32(C::Subpixel::channel_max());
+        let keep = 1.0 - to_f32(other.channels()[N - 1]) / max;
+        for (v, &o) in self.channels_mut().iter_mut().zip(other.channels()) {
+            *v = from_f32(to_f32(o) + to_f32(*v) * keep);
+        }
+    }
+
+    fn blend_coverage(&mut self, other: &Self, coverage: f32) {
+        if coverage > 0.0 {
+            // Premultiplied channels are all scaled with the alpha
+            self.blend(&other.map(|v| from_f32(to_f32(v) * coverage.min(1.0))));
+        }
+    }
+}
+
+impl<P, Container> ImageBuffer<P, Container>
+    where P: Blend + Color,
+          Container: Deref<Target = [P::Subpixel]> + DerefMut
+{
+    /// Draws `pixel` over the pixel at `(x, y)`.
+    ///
+    /// # Panics
+    ///
+    /// If `(x, y)` is out of bounds.
+    pub fn blend_pixel(&mut self, x: u32, y: u32, pixel: &P) {
+        self.get_pixel_mut(x, y).blend(pixel)
+    }
+}
+
+impl<C, Container, const N: usize> ImageBuffer<Alpha<C, N>, Container>
+    where C: Color,
+          C::Subpixel: ChannelMax,
+          Container: Deref<Target = [C::Subpixel]> + DerefMut
+{
+    /// Composites `other` onto this image with the top left corner at `(x, y)` using the
+    /// operator `op`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `other` does not fit into the image at `(x, y)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{RgbaImage, color};
+    /// use image_buffer::blend::Operator;
+    /// let mut buffer = RgbaImage::from_pixel(4, 4, color::Rgba::new([0, 0, 255, 255]));
+    /// let sprite = RgbaImage::from_pixel(2, 2, color::Rgba::new([255, 0, 0, 0]));
+    /// buffer.blend_from(&sprite, 1, 1, Operator::Over);
+    /// assert_eq!(buffer[(1, 1)], color::Rgba::new([0, 0, 255, 255]));
+    /// ```
+    pub fn blend_from<C2>(&mut self,
+                          other: &ImageBuffer<Alpha<C, N>, C2>,
+                          x: u32,
+                          y: u32,
+                          op: Operator)
+        where C2: Deref<Target = [C::Subpixel]>
+    {
+        let (width, height) = other.dimensions();
+        assert!(x as u64 + width as u64 <= self.width() as u64 &&
+                y as u64 + height as u64 <= self.height() as u64,
+                "image with dimensions ({}, {}) does not fit at ({}, {})",
+                width,
+                height,
+                x,
+                y);
+        for (src, dst) in other.rows().zip(self.rows_mut().skip(y as usize)) {
+            for (s, d) in src.iter().zip(dst[x as usize..].iter_mut()) {
+                *d = s.composite(d, op)
+            }
+        }
+    }
+}
+
+/// Combines two overlapping images into a composite with feathered seams.
+///
+/// `a` is placed at the origin and `b` at `offset` relative to it, for example the translation
+/// estimated by `registration::register_translation` rounded to whole pixels. The result
+/// covers both images, pixels covered by neither are zero.
+///
+/// Where the images overlap they are averaged with weights ramping linearly from the image
+/// borders to `1` at a distance of `feather` pixels, which hides the seams and exposure
+/// differences. With a `feather` of `0` the overlap is a uniform average.
+///
+/// # Examples
+///
+/// ```
+/// # use image_buffer::{GrayImage, color::Gray};
+/// use image_buffer::blend::blend_overlap;
+/// let a = GrayImage::from_pixel(4, 2, Gray::new([100]));
+/// let b = GrayImage::from_pixel(4, 2, Gray::new([200]));
+/// let panorama = blend_overlap(&a, &b, (2, 0), 0);
+/// assert_eq!(panorama.dimensions(), (6, 2));
+/// assert_eq!(panorama.into_raw()[..6], [100, 100, 150, 150, 200, 200]);
+/// ```
+pub fn blend_overlap<P, C1, C2>(a: &ImageBuffer<P, C1>,
+                                b: &ImageBuffer<P, C2>,
+                                offset: (i32, i32),
+                                feather: u32)
+                                -> ImageBuffer<P, Vec<P::Subpixel>>
+    where P: Color,
+          P::Subpixel: ChannelMax,
+          C1: Deref<Target = [P::Subpixel]>,
+          C2: Deref<Target = [P::Subpixel]>
+{
+    let (dx, dy) = (offset.0 as i64, offset.1 as i64);
+    let (left, top) = (dx.min(0), dy.min(0));
+    let right = (a.width() as i64).max(dx + b.width() as i64);
+    let bottom = (a.height() as i64).max(dy + b.height() as i64);
+    let weight = |x: i64, y: i64, (w, h): (u32, u32)| {
+        if x < 0 || y < 0 || x >= w as i64 || y >= h as i64 {
+            return 0.0;
+        }
+        let edge = x.min(y).min(w as i64 - 1 - x).min(h as i64 - 1 - y);
+        ((edge + 1) as f32 / (feather + 1) as f32).min(1.0)
+    };
+    let mut result: ImageBuffer<P, Vec<P::Subpixel>> =
+        ImageBuffer::new((right - left) as u32, (bottom - top) as u32);
+    let mut sum = vec![0.0f32; P::channel_count()];
+    for (x, y, pixel) in result.enumerate_pixels_mut() {
+        let (ax, ay) = (x as i64 + left, y as i64 + top);
+        let (bx, by) = (ax - dx, ay - dy);
+        let (wa, wb) = (weight(ax, ay, a.dimensions()), weight(bx, by, b.dimensions()));
+        if wa + wb == 0.0 {
+            continue;
+        }
+        for v in sum.iter_mut() {
+            *v = 0.0;
+        }
+        if wa > 0.0 {
+            let p = a.get_pixel(ax as u32, ay as u32);
+            for (v, &c) in sum.iter_mut().zip(p.channels().as_ref()) {
+                *v += wa * to_f32(c);
+            }
+        }
+        if wb > 0.0 {
+            let p = b.get_pixel(bx as u32, by as u32);
+            for (v, &c) in sum.iter_mut().zip(p.channels().as_ref()) {
+                *v += wb * to_f32(c);
+            }
+        }
+        for (c, v) in pixel.channels_mut().as_mut().iter_mut().zip(sum.iter()) {
+            *c = from_f32(v / (wa + wb));
+        }
+    }
+    result
+}
+
+/// Derives the random seed of the pixel `(x, y)` from `seed`.
+fn pixel_seed(seed: u64, x: u32, y: u32) -> u64 {
+    let position = (y as u64) << 32 | x as u64;
+    splitmix64(seed.wrapping_add(position.wrapping_mul(0x9E37_79B9_7F4A_7C15)))
+}
+
+impl<C, Container, const N: usize> ImageBuffer<Alpha<C, N>, Container>
+    where C: Color,
+          C::Subpixel: ChannelMax,
+          Container: Deref<Target = [C::Subpixel]>
+{
+    /// Converts the alpha channel to stochastic coverage masks of `samples` samples each.
+    ///
+    /// Bit `i` of a mask is set if sample `i` of the pixel is covered. The number of covered
+    /// samples is `alpha * samples`, rounded up or down at random such that the expected
+    /// coverage equals the alpha value, and the covered samples are chosen at random. An opaque
+    /// pixel covers all samples, a transparent one none. With a single sample this yields a
+    /// dithered 1-bit transparency mask.
+    ///
+    /// The random numbers of a pixel depend only on `seed` and its coordinates, the masks are
+    /// thus reproducible and independent of the order the pixels are processed in and of the
+    /// stride of the image.
+    ///
+    /// # Panics
+    ///
+    /// If `samples` is not in `1..=32`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{RgbaImage, color};
+    /// let image = RgbaImage::from_pixel(2, 2, color::Rgba::new([0, 0, 0, 255]));
+    /// let masks = image.alpha_to_coverage(4, 7);
+    /// assert!(masks.pixels().all(|m| m[0] == 0b1111));
+    /// ```
+    pub fn alpha_to_coverage(&self, samples: u32, seed: u64) -> ImageBuffer<Gray<u32>, Vec<u32>> {
+        assert!((1..=32).contains(&samples),
+                "invalid number of samples: {}",
+                samples);
+        let max = to_f32(C::Subpixel::channel_max());
+        let mut identity = [0u32; 32];
+        for (i, v) in identity.iter_mut().enumerate() {
+            *v = i as u32;
+        }
+        self.map_buffer_with_coords(|x, y, p| {
+            let mut rng = XorShift::new(pixel_seed(seed, x, y));
+            let coverage = (to_f32(p[N - 1]) / max).clamp(0.0, 1.0) * samples as f32;
+            let mut count = coverage as u32;
+            let uniform = (rng.next() >> 40) as f32 / (1u32 << 24) as f32;
+            if uniform < coverage - count as f32 {
+                count += 1;
+            }
+            // Partial Fisher-Yates shuffle of the sample indices
+            let mut order = identity;
+            let mut mask = 0;
+            for i in 0..count as usize {
+                let j = i + (rng.next() % (samples as usize - i) as u64) as usize;
+                order.swap(i, j);
+                mask |= 1 << order[i];
+            }
+            Gray::new([mask])
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{blend_overlap, Blend, Operator};
+    use buffer::{GrayImage, ImageBuffer, RgbImage, RgbaImage};
+    use color_model::{Gray, GrayA, Hsl, Hsv, Indexed, PremultipliedRgba, Rgb, Rgba};
+    use traits::Color;
+
+    #[test]
+    fn test_operators() {
+        let src = GrayA::new([200u8, 255]);
+        let dst = GrayA::new([100u8, 128]);
+        assert_eq!(src.composite(&dst, Operator::Over), src);
+        assert_eq!(src.composite(&dst, Operator::In), GrayA::new([200, 128]));
+        assert_eq!(src.composite(&dst, Operator::Out), GrayA::new([200, 127]));
+        assert_eq!(src.composite(&dst, Operator::Atop), GrayA::new([200, 128]));
+        assert_eq!(dst.composite(&src, Operator::Atop), GrayA::new([150, 255]));
+        assert_eq!(dst.composite(&src, Operator::Xor), GrayA::new([200, 127]));
+    }
+
+    #[test]
+    fn test_half_transparent_over() {
+        let src = Rgba::new([1.0f32, 0.0, 0.0, 0.5]);
+        let dst = Rgba::new([0.0f32, 0.0, 1.0, 0.5]);
+        let result = src.composite(&dst, Operator::Over);
+        assert_eq!(result, Rgba::new([2.0 / 3.0, 0.0, 1.0 / 3.0, 0.75]));
+    }
+
+    #[test]
+    fn test_blend_coverage_hue_and_index() {
+        // The hue moves across 0° instead of around the color wheel
+        let mut hsv = Hsv::new([240u8, 100, 200]);
+        hsv.blend_coverage(&Hsv::new([10, 200, 200]), 0.4);
+        assert_eq!(hsv, Hsv::new([250, 140, 200]));
+        hsv.blend_coverage(&Hsv::new([15, 140, 200]), 0.5);
+        assert_eq!(hsv, Hsv::new([5, 140, 200]));
+        let mut hsl = Hsl::new([10.0f32 / 360.0, 1.0, 0.5]);
+        hsl.blend_coverage(&Hsl::new([350.0 / 360.0, 1.0, 0.5]), 0.75);
+        assert!((hsl.channels()[0] - 355.0 / 360.0).abs() < 1e-6, "{:?}", hsl);
+
+        let mut index = Indexed::new([3u8]);
+        index.blend_coverage(&Indexed::new([7]), 0.25);
+        assert_eq!(index, Indexed::new([3]));
+        index.blend_coverage(&Indexed::new([7]), 0.5);
+        assert_eq!(index, Indexed::new([7]));
+    }
+
+    #[test]
+    fn test_alpha_to_coverage() {
+        let alphas = [0, 64, 191, 255];
+        let image = RgbaImage::from_fn(64, 64, |x, _| {
+            Rgba::new([0, 0, 0, alphas[x as usize / 16]])
+        });
+        let masks = image.alpha_to_coverage(8, 3);
+        assert_eq!(masks.into_raw(), image.alpha_to_coverage(8, 3).into_raw());
+        let masks = image.alpha_to_coverage(8, 3);
+        for (band, expected) in [0.0, 64.0 / 255.0, 191.0 / 255.0, 1.0].iter().enumerate() {
+            let covered: u32 = masks.enumerate_pixels()
+                .filter(|&(x, _, _)| x as usize / 16 == band)
+                .map(|(_, _, m)| m[0].count_ones())
+                .sum();
+            let mean = covered as f32 / (16.0 * 64.0 * 8.0);
+            assert!((mean - expected).abs() < 0.02, "{} != {}", mean, expected);
+        }
+        assert!(masks.pixels().all(|m| m[0] < 256));
+
+        let dithered = image.alpha_to_coverage(1, 3);
+        assert!(dithered.pixels().all(|m| m[0] <= 1));
+        assert!(image.alpha_to_coverage(8, 4).into_raw() != masks.into_raw());
+    }
+
+    #[test]
+    fn test_blend_overlap() {
+        let a = GrayImage::from_pixel(10, 10, Gray::new([100]));
+        let b = GrayImage::from_pixel(10, 10, Gray::new([200]));
+        let panorama = blend_overlap(&a, &b, (6, -1), 2);
+        assert_eq!(panorama.dimensions(), (16, 11));
+        // Pixels covered by a single image keep their value
+        assert_eq!(panorama[(0, 10)], Gray::new([100]));
+        assert_eq!(panorama[(15, 0)], Gray::new([200]));
+        assert_eq!(panorama[(0, 0)], Gray::new([0]));
+        assert_eq!(panorama[(15, 10)], Gray::new([0]));
+        // The seams are feathered
+        let row: Vec<u8> = (0..16).map(|x| panorama[(x, 5)][0]).collect();
+        assert_eq!(row, [100, 100, 100, 100, 100, 100, 125, 140, 160, 175, 200, 200, 200, 200,
+                         200, 200]);
+    }
+
+    /// Draws `pixel` along the diagonal, generically over the color type.
+    fn draw_diagonal<P, C>(image: &mut ImageBuffer<P, C>, pixel: P)
+        where P: Blend + Color,
+              C: ::std::ops::Deref<Target = [P::Subpixel]> + ::std::ops::DerefMut
+    {
+        for i in 0..image.width().min(image.height()) {
+            image.blend_pixel(i, i, &pixel);
+        }
+    }
+
+    #[test]
+    fn test_blend() {
+        let mut rgb = RgbImage::from_pixel(2, 2, Rgb::new([0, 0, 255]));
+        draw_diagonal(&mut rgb, Rgb::new([255, 0, 0]));
+        assert_eq!(rgb.into_raw(), vec![255, 0, 0, 0, 0, 255, 0, 0, 255, 255, 0, 0]);
+
+        let mut rgba = RgbaImage::from_pixel(2, 1, Rgba::new([0, 0, 255, 255]));
+        draw_diagonal(&mut rgba, Rgba::new([255, 0, 0, 0]));
+        assert_eq!(rgba[(0, 0)], Rgba::new([0, 0, 255, 255]));
+
+        let mut premultiplied = PremultipliedRgba::new([0u8, 0, 200, 200]);
+        premultiplied.blend(&PremultipliedRgba::new([100, 0, 0, 102]));
+        assert_eq!(premultiplied, PremultipliedRgba::new([100, 0, 120, 222]));
+    }
+}
@@ -1,16 +1,47 @@
-use std::slice::{Chunks, ChunksMut};
+use std::iter::{FusedIterator, StepBy};
+use std::slice::{self, Chunks, ChunksMut};
 use std::ops::{Deref, DerefMut, Index, IndexMut};
 use std::marker::PhantomData;
+use std::mem;
+use std::cmp;
+use std::error::Error;
+use std::fmt;
 use num_traits::Zero;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
 
-use color_model::{Rgb, Rgba, Gray, GrayA};
-use traits::{Color as Pixel, ImageView};
+use color_model::{convert_linear, Alpha, Rgb, Rgba, Gray, GrayA, Indexed, LinearRgba, LumaWeights,
+                  PremultipliedAlpha};
+use color_model::convert::convert_pixels;
+use math::rescale_channel;
+use color_model::rgb::{srgb_expansion_table, srgb_over_linear};
+use traits::{AlphaColor, ChannelMax, Color as Pixel, ImageView, ImageViewMut, PodColor,
+             Primitive};
+use view::check_region;
 
 /// Iterator over references to pixels.
 pub struct Pixels<'a, P: Pixel + 'a>
     where P::Subpixel: 'a
 {
-    chunks: Chunks<'a, P::Subpixel>,
+    rows: Chunks<'a, P::Subpixel>,
+    row_len: usize,
+    front: Chunks<'a, P::Subpixel>,
+    back: Chunks<'a, P::Subpixel>,
+}
+
+impl<'a, P: Pixel + 'a> Pixels<'a, P>
+    where P::Subpixel: 'a
+{
+    /// Iterates over the pixels of the first `row_len` subpixels of each row in `data`.
+    fn new(data: &'a [P::Subpixel], stride: usize, row_len: usize) -> Pixels<'a, P> {
+        let empty: &'a [P::Subpixel] = &[];
+        Pixels {
+            rows: data.chunks(cmp::max(1, stride)),
+            row_len,
+            front: empty.chunks(1),
+            back: empty.chunks(1),
+        }
+    }
 }
 
 impl<'a, P: Pixel + 'a> Iterator for Pixels<'a, P>
@@ -20,16 +51,60 @@ impl<'a, P: Pixel + 'a> Iterator for Pixels<'a, P>
 
     #[inline(always)]
     fn next(&mut self) -> Option<&'a P> {
-        self.chunks.next().map(|v| <P as Pixel>::from_slice(v))
+        loop {
+            if let Some(v) = self.front.next() {
+                return Some(<P as Pixel>::from_slice(v));
+            }
+            match self.rows.next() {
+                Some(row) => self.front = row[..self.row_len].chunks(P::channel_count()),
+                None => return self.back.next().map(|v| <P as Pixel>::from_slice(v)),
+            }
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.front.len() + self.back.len() +
+                  self.rows.len() * (self.row_len / P::channel_count());
+        (len, Some(len))
+    }
+
+    fn nth(&mut self, n: usize) -> Option<&'a P> {
+        let front = self.front.len();
+        if n < front {
+            return self.front.nth(n).map(|v| <P as Pixel>::from_slice(v));
+        }
+        let empty: &'a [P::Subpixel] = &[];
+        self.front = empty.chunks(1);
+        let (n, row) = skip_rows(&mut self.rows, n - front, self.row_len / P::channel_count());
+        match row {
+            Some(row) => {
+                self.front = row[..self.row_len].chunks(P::channel_count());
+                self.front.nth(n).map(|v| <P as Pixel>::from_slice(v))
+            }
+            None => self.back.nth(n).map(|v| <P as Pixel>::from_slice(v)),
+        }
     }
 }
 
+impl<'a, P: Pixel + 'a> ExactSizeIterator for Pixels<'a, P> where P::Subpixel: 'a {}
+
+impl<'a, P: Pixel + 'a> FusedIterator for Pixels<'a, P> where P::Subpixel: 'a {}
+
 impl<'a, P: Pixel + 'a> DoubleEndedIterator for Pixels<'a, P>
     where P::Subpixel: 'a
 {
     #[inline(always)]
     fn next_back(&mut self) -> Option<&'a P> {
-        self.chunks.next_back().map(|v| <P as Pixel>::from_slice(v))
+        loop {
+            if let Some(v) = self.back.next_back() {
+                return Some(<P as Pixel>::from_slice(v));
+            }
+            match self.rows.next_back() {
+                Some(row) => self.back = row[..self.row_len].chunks(P::channel_count()),
+                None => return self.front.next_back().map(|v| <P as Pixel>::from_slice(v)),
+            }
+        }
     }
 }
 
@@ -37,7 +112,26 @@ impl<'a, P: Pixel + 'a> DoubleEndedIterator for Pixels<'a, P>
 pub struct PixelsMut<'a, P: Pixel + 'a>
     where P::Subpixel: 'a
 {
-    chunks: ChunksMut<'a, P::Subpixel>,
+    rows: ChunksMut<'a, P::Subpixel>,
+    row_len: usize,
+    front: ChunksMut<'a, P::Subpixel>,
+    back: ChunksMut<'a, P::Subpixel>,
+}
+
+impl<'a, P: Pixel + 'a> PixelsMut<'a, P>
+    where P::Subpixel: 'a
+{
+    /// Iterates over the pixels of the first `row_len` subpixels of each row in `data`.
+    fn new(data: &'a mut [P::Subpixel], stride: usize, row_len: usize) -> PixelsMut<'a, P> {
+        let front: &'a mut [P::Subpixel] = &mut [];
+        let back: &'a mut [P::Subpixel] = &mut [];
+        PixelsMut {
+            rows: data.chunks_mut(cmp::max(1, stride)),
+            row_len,
+            front: front.chunks_mut(1),
+            back: back.chunks_mut(1),
+        }
+    }
 }
 
 impl<'a, P: Pixel + 'a> Iterator for PixelsMut<'a, P>
@@ -47,8 +141,61 @@ impl<'a, P: Pixel + 'a> Iterator for PixelsMut<'a, P>
 
     #[inline(always)]
     fn next(&mut self) -> Option<&'a mut P> {
-        self.chunks.next().map(|v| <P as Pixel>::from_slice_mut(v))
+        loop {
+            if let Some(v) = self.front.next() {
+                return Some(<P as Pixel>::from_slice_mut(v));
+            }
+            match self.rows.next() {
+                Some(row) => self.front = row[..self.row_len].chunks_mut(P::channel_count()),
+                None => return self.back.next().map(|v| <P as Pixel>::from_slice_mut(v)),
+            }
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.front.len() + self.back.len() +
+                  self.rows.len() * (self.row_len / P::channel_count());
+        (len, Some(len))
+    }
+
+    fn nth(&mut self, n: usize) -> Option<&'a mut P> {
+        let front = self.front.len();
+        if n < front {
+            return self.front.nth(n).map(|v| <P as Pixel>::from_slice_mut(v));
+        }
+        let empty: &'a mut [P::Subpixel] = &mut [];
+        self.front = empty.chunks_mut(1);
+        let (n, row) = skip_rows(&mut self.rows, n - front, self.row_len / P::channel_count());
+        match row {
+            Some(row) => {
+                self.front = row[..self.row_len].chunks_mut(P::channel_count());
+                self.front.nth(n).map(|v| <P as Pixel>::from_slice_mut(v))
+            }
+            None => self.back.nth(n).map(|v| <P as Pixel>::from_slice_mut(v)),
+        }
+    }
+}
+
+impl<'a, P: Pixel + 'a> ExactSizeIterator for PixelsMut<'a, P> where P::Subpixel: 'a {}
+
+impl<'a, P: Pixel + 'a> FusedIterator for PixelsMut<'a, P> where P::Subpixel: 'a {}
+
+/// Skips the rows containing the first `n` pixels, `per_row` pixels each.
+///
+/// Returns the row containing pixel `n` together with the index of the pixel within that row,
+/// or the number of pixels left to skip if the rows are exhausted.
+fn skip_rows<I>(rows: &mut I, n: usize, per_row: usize) -> (usize, Option<I::Item>)
+    where I: ExactSizeIterator
+{
+    let skip = match per_row {
+        0 => rows.len(),
+        _ => cmp::min(n / per_row, rows.len()),
+    };
+    if skip > 0 {
+        rows.nth(skip - 1);
     }
+    (n - skip * per_row, rows.next())
 }
 
 impl<'a, P: Pixel + 'a> DoubleEndedIterator for PixelsMut<'a, P>
@@ -56,10 +203,215 @@ impl<'a, P: Pixel + 'a> DoubleEndedIterator for PixelsMut<'a, P>
 {
     #[inline(always)]
     fn next_back(&mut self) -> Option<&'a mut P> {
-        self.chunks.next_back().map(|v| <P as Pixel>::from_slice_mut(v))
+        loop {
+            if let Some(v) = self.back.next_back() {
+                return Some(<P as Pixel>::from_slice_mut(v));
+            }
+            match self.rows.next_back() {
+                Some(row) => self.back = row[..self.row_len].chunks_mut(P::channel_count()),
+                None => return self.front.next_back().map(|v| <P as Pixel>::from_slice_mut(v)),
+            }
+        }
+    }
+}
+
+/// Iterator over the rows of an image.
+pub struct Rows<'a, P: PodColor + 'a>
+    where P::Subpixel: 'a
+{
+    chunks: Chunks<'a, P::Subpixel>,
+    row_len: usize,
+}
+
+impl<'a, P: PodColor + 'a> Iterator for Rows<'a, P>
+    where P::Subpixel: 'a
+{
+    type Item = &'a [P];
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<&'a [P]> {
+        let row_len = self.row_len;
+        self.chunks.next().map(|row| pixel_slice(&row[..row_len]))
+    }
+}
+
+impl<'a, P: PodColor + 'a> DoubleEndedIterator for Rows<'a, P>
+    where P::Subpixel: 'a
+{
+    #[inline(always)]
+    fn next_back(&mut self) -> Option<&'a [P]> {
+        let row_len = self.row_len;
+        self.chunks.next_back().map(|row| pixel_slice(&row[..row_len]))
+    }
+}
+
+/// Iterator over the mutable rows of an image.
+pub struct RowsMut<'a, P: PodColor + 'a>
+    where P::Subpixel: 'a
+{
+    chunks: ChunksMut<'a, P::Subpixel>,
+    row_len: usize,
+}
+
+impl<'a, P: PodColor + 'a> Iterator for RowsMut<'a, P>
+    where P::Subpixel: 'a
+{
+    type Item = &'a mut [P];
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<&'a mut [P]> {
+        let row_len = self.row_len;
+        self.chunks.next().map(|row| pixel_slice_mut(&mut row[..row_len]))
+    }
+}
+
+impl<'a, P: PodColor + 'a> DoubleEndedIterator for RowsMut<'a, P>
+    where P::Subpixel: 'a
+{
+    #[inline(always)]
+    fn next_back(&mut self) -> Option<&'a mut [P]> {
+        let row_len = self.row_len;
+        self.chunks.next_back().map(|row| pixel_slice_mut(&mut row[..row_len]))
+    }
+}
+
+/// Enumerate the rows of an image.
+pub struct EnumerateRows<'a, P: PodColor + 'a>
+    where P::Subpixel: 'a
+{
+    rows: Rows<'a, P>,
+    y: u32,
+}
+
+impl<'a, P: PodColor + 'a> Iterator for EnumerateRows<'a, P>
+    where P::Subpixel: 'a
+{
+    type Item = (u32, &'a [P]);
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<(u32, &'a [P])> {
+        let row = self.rows.next()?;
+        self.y += 1;
+        Some((self.y - 1, row))
+    }
+}
+
+/// Enumerate the mutable rows of an image.
+pub struct EnumerateRowsMut<'a, P: PodColor + 'a>
+    where P::Subpixel: 'a
+{
+    rows: RowsMut<'a, P>,
+    y: u32,
+}
+
+impl<'a, P: PodColor + 'a> Iterator for EnumerateRowsMut<'a, P>
+    where P::Subpixel: 'a
+{
+    type Item = (u32, &'a mut [P]);
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<(u32, &'a mut [P])> {
+        let row = self.rows.next()?;
+        self.y += 1;
+        Some((self.y - 1, row))
+    }
+}
+
+impl<P, Container> ImageBuffer<P, Container>
+    where P: PodColor,
+          Container: Deref<Target = [P::Subpixel]>
+{
+    /// Returns an iterator over the rows of this image.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{GrayImage, color};
+    /// let buffer = GrayImage::new(100, 100);
+    /// for row in buffer.rows() {
+    ///     assert_eq!(row.len(), 100);
+    /// }
+    /// ```
+    pub fn rows<'a>(&'a self) -> Rows<'a, P> {
+        Rows {
+            chunks: self.data[..self.logical_len()].chunks(cmp::max(1, self.stride)),
+            row_len: self.row_len(),
+        }
+    }
+
+    /// Enumerates over the rows of the image.
+    ///
+    /// The iterator yields the y coordinate of each row along with its pixels.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{GrayImage, color};
+    /// let buffer = GrayImage::from_fn(4, 3, |x, y| color::Gray::new([(x * y) as u8]));
+    /// for (y, row) in buffer.enumerate_rows() {
+    ///     assert_eq!(row[1][0], y as u8);
+    /// }
+    /// ```
+    pub fn enumerate_rows<'a>(&'a self) -> EnumerateRows<'a, P> {
+        EnumerateRows {
+            rows: self.rows(),
+            y: 0,
+        }
+    }
+}
+
+impl<P, Container> ImageBuffer<P, Container>
+    where P: PodColor,
+          Container: Deref<Target = [P::Subpixel]> + DerefMut
+{
+    /// Returns an iterator over the mutable rows of this image.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{ImageBuffer, color};
+    /// let mut buffer = ImageBuffer::new(100, 100);
+    /// for (y, row) in buffer.rows_mut().enumerate() {
+    ///     row[0] = color::Gray::new([y as u8]);
+    /// }
+    /// ```
+    pub fn rows_mut<'a>(&'a mut self) -> RowsMut<'a, P> {
+        let (len, stride, row_len) = (self.logical_len(), self.stride, self.row_len());
+        RowsMut {
+            chunks: self.data[..len].chunks_mut(cmp::max(1, stride)),
+            row_len,
+        }
+    }
+
+    /// Enumerates over the mutable rows of the image.
+    ///
+    /// The iterator yields the y coordinate of each row along with its pixels.
+    pub fn enumerate_rows_mut<'a>(&'a mut self) -> EnumerateRowsMut<'a, P> {
+        EnumerateRowsMut {
+            rows: self.rows_mut(),
+            y: 0,
+        }
     }
 }
 
+/// Reinterprets a slice of subpixels as a slice of pixels.
+///
+/// Trailing subpixels which do not form a complete pixel are ignored.
+pub(crate) fn pixel_slice<P: PodColor>(slice: &[P::Subpixel]) -> &[P] {
+    let len = slice.len() / P::channel_count();
+    // `PodColor` guarantees that pixels are laid out like arrays of subpixels
+    unsafe { slice::from_raw_parts(slice.as_ptr() as *const P, len) }
+}
+
+/// Reinterprets a mutable slice of subpixels as a mutable slice of pixels.
+///
+/// Trailing subpixels which do not form a complete pixel are ignored.
+pub(crate) fn pixel_slice_mut<P: PodColor>(slice: &mut [P::Subpixel]) -> &mut [P] {
+    let len = slice.len() / P::channel_count();
+    // `PodColor` guarantees that pixels are laid out like arrays of subpixels
+    unsafe { slice::from_raw_parts_mut(slice.as_mut_ptr() as *mut P, len) }
+}
+
 /// Enumerate the pixels of an image.
 pub struct EnumeratePixels<'a, P: Pixel + 'a>
     where <P as Pixel>::Subpixel: 'a
@@ -88,8 +440,27 @@ impl<'a, P: Pixel + 'a> Iterator for EnumeratePixels<'a, P>
             Some(p) => Some((x, y, p)),
         }
     }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.pixels.size_hint()
+    }
+
+    fn nth(&mut self, n: usize) -> Option<(u32, u32, &'a P)> {
+        let p = self.pixels.nth(n)?;
+        let width = self.width as u64;
+        let index = self.y as u64 * width + self.x as u64 + n as u64;
+        let (x, y) = ((index % width) as u32, (index / width) as u32);
+        self.x = x + 1;
+        self.y = y;
+        Some((x, y, p))
+    }
 }
 
+impl<'a, P: Pixel + 'a> ExactSizeIterator for EnumeratePixels<'a, P> where P::Subpixel: 'a {}
+
+impl<'a, P: Pixel + 'a> FusedIterator for EnumeratePixels<'a, P> where P::Subpixel: 'a {}
+
 /// Enumerate the mutable pixels of an image.
 pub struct EnumeratePixelsMut<'a, P: Pixel + 'a>
     where <P as Pixel>::Subpixel: 'a
@@ -118,12 +489,221 @@ impl<'a, P: Pixel + 'a> Iterator for EnumeratePixelsMut<'a, P>
             Some(p) => Some((x, y, p)),
         }
     }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.pixels.size_hint()
+    }
+
+    fn nth(&mut self, n: usize) -> Option<(u32, u32, &'a mut P)> {
+        let p = self.pixels.nth(n)?;
+        let width = self.width as u64;
+        let index = self.y as u64 * width + self.x as u64 + n as u64;
+        let (x, y) = ((index % width) as u32, (index / width) as u32);
+        self.x = x + 1;
+        self.y = y;
+        Some((x, y, p))
+    }
+}
+
+impl<'a, P: Pixel + 'a> ExactSizeIterator for EnumeratePixelsMut<'a, P> where P::Subpixel: 'a {}
+
+impl<'a, P: Pixel + 'a> FusedIterator for EnumeratePixelsMut<'a, P> where P::Subpixel: 'a {}
+
+/// Iterator over references to a single channel of every pixel.
+pub struct Channel<'a, T: 'a> {
+    rows: Chunks<'a, T>,
+    row_len: usize,
+    offset: usize,
+    step: usize,
+    current: StepBy<slice::Iter<'a, T>>,
+}
+
+impl<'a, T: 'a> Channel<'a, T> {
+    /// Iterates over every `step`-th subpixel starting at `offset` of the first `row_len`
+    /// subpixels of each row in `data`.
+    fn new(data: &'a [T], stride: usize, row_len: usize, offset: usize, step: usize) -> Self {
+        let empty: &'a [T] = &[];
+        Channel {
+            rows: data.chunks(cmp::max(1, stride)),
+            row_len,
+            offset,
+            step,
+            current: empty.iter().step_by(1),
+        }
+    }
+}
+
+impl<'a, T: 'a> Iterator for Channel<'a, T> {
+    type Item = &'a T;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<&'a T> {
+        loop {
+            if let Some(v) = self.current.next() {
+                return Some(v);
+            }
+            let row = self.rows.next()?;
+            let start = cmp::min(self.offset, self.row_len);
+            self.current = row[start..self.row_len].iter().step_by(self.step);
+        }
+    }
+}
+
+/// Iterator over mutable references to a single channel of every pixel.
+pub struct ChannelMut<'a, T: 'a> {
+    rows: ChunksMut<'a, T>,
+    row_len: usize,
+    offset: usize,
+    step: usize,
+    current: StepBy<slice::IterMut<'a, T>>,
+}
+
+impl<'a, T: 'a> ChannelMut<'a, T> {
+    /// Iterates over every `step`-th subpixel starting at `offset` of the first `row_len`
+    /// subpixels of each row in `data`.
+    fn new(data: &'a mut [T], stride: usize, row_len: usize, offset: usize, step: usize) -> Self {
+        let empty: &'a mut [T] = &mut [];
+        ChannelMut {
+            rows: data.chunks_mut(cmp::max(1, stride)),
+            row_len,
+            offset,
+            step,
+            current: empty.iter_mut().step_by(1),
+        }
+    }
+}
+
+impl<'a, T: 'a> Iterator for ChannelMut<'a, T> {
+    type Item = &'a mut T;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<&'a mut T> {
+        loop {
+            if let Some(v) = self.current.next() {
+                return Some(v);
+            }
+            let row = self.rows.next()?;
+            let start = cmp::min(self.offset, self.row_len);
+            self.current = row[start..self.row_len].iter_mut().step_by(self.step);
+        }
+    }
+}
+
+/// Panics if `idx` is not a valid channel index of `P`.
+fn check_channel<P: Pixel>(idx: usize) {
+    assert!(idx < P::channel_count(),
+            "channel {} out of bounds for pixels with {} channels",
+            idx,
+            P::channel_count());
+}
+
+/// Description of the memory layout of an image buffer for interoperation with other libraries.
+///
+/// Pixel `(x, y)` starts at the subpixel `y * row_stride + x * channels` of `samples`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FlatSamples<'a, T: 'a> {
+    /// The width of the image in pixels.
+    pub width: u32,
+    /// The height of the image in pixels.
+    pub height: u32,
+    /// The number of channels of each pixel.
+    pub channels: usize,
+    /// The distance between the start of two rows in subpixels.
+    pub row_stride: usize,
+    /// The subpixels of the image, including any padding between the rows.
+    pub samples: &'a [T],
+}
+
+/// The reason a container could not be turned into an image buffer.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum BufferError {
+    /// The container holds fewer subpixels than the image needs.
+    TooSmall {
+        /// The number of subpixels the image needs.
+        expected: usize,
+        /// The number of subpixels in the container.
+        got: usize,
+    },
+    /// The container holds more subpixels than the image needs, only reported by
+    /// `SizeCheck::Exact`.
+    TooLarge {
+        /// The number of subpixels the image needs.
+        expected: usize,
+        /// The number of subpixels in the container.
+        got: usize,
+    },
+    /// The stride is smaller than the number of subpixels in a row.
+    StrideTooSmall {
+        /// The number of subpixels in a row.
+        row_len: usize,
+        /// The requested stride.
+        stride: usize,
+    },
+    /// The number of subpixels of the image does not fit into `usize`.
+    DimensionOverflow,
+}
+
+impl fmt::Display for BufferError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BufferError::TooSmall { expected, got } => {
+                write!(f, "container of {} subpixels is smaller than the image ({})", got, expected)
+            }
+            BufferError::TooLarge { expected, got } => {
+                write!(f, "container of {} subpixels is larger than the image ({})", got, expected)
+            }
+            BufferError::StrideTooSmall { row_len, stride } => {
+                write!(f, "stride {} is smaller than a row of {} subpixels", stride, row_len)
+            }
+            BufferError::DimensionOverflow => write!(f, "image dimensions overflow usize"),
+        }
+    }
+}
+
+impl Error for BufferError {}
+
+/// The position of the first row of raw image data, see `ImageBuffer::from_raw_with_origin`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Origin {
+    /// The first row is the top row, as in most image formats.
+    TopLeft,
+    /// The first row is the bottom row, as in OpenGL and BMP.
+    BottomLeft,
+}
+
+/// How `ImageBuffer::try_from_raw` treats containers which are larger than the image.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SizeCheck {
+    /// Accepts larger containers, the trailing subpixels are ignored.
+    AtLeast,
+    /// Rejects larger containers with `BufferError::TooLarge`.
+    Exact,
+}
+
+/// Returns the number of subpixels covered by an image with the given layout.
+fn required_len<P: Pixel>(width: u32, height: u32, stride: usize) -> Result<usize, BufferError> {
+    let row_len = (width as usize)
+        .checked_mul(P::channel_count())
+        .ok_or(BufferError::DimensionOverflow)?;
+    if stride < row_len {
+        return Err(BufferError::StrideTooSmall { row_len, stride });
+    }
+    match height {
+        0 => Ok(0),
+        h => {
+            stride.checked_mul(h as usize - 1)
+                .and_then(|len| len.checked_add(row_len))
+                .ok_or(BufferError::DimensionOverflow)
+        }
+    }
 }
 
 /// Generic image buffer.
 pub struct ImageBuffer<P: Pixel, Container: Deref<Target = [P::Subpixel]>> {
     width: u32,
     height: u32,
+    stride: usize,
     data: Container,
     _pixel_type: PhantomData<P>,
 }
@@ -138,16 +718,81 @@ impl<P, Container> ImageBuffer<P, Container>
     ///
     /// Returns None if the container is not big enough
     pub fn from_raw(width: u32, height: u32, buf: Container) -> Option<ImageBuffer<P, Container>> {
-        if width as usize * height as usize * <P as Pixel>::channel_count() as usize <= buf.len() {
-            Some(ImageBuffer {
-                data: buf,
-                width: width,
-                height: height,
-                _pixel_type: PhantomData,
-            })
-        } else {
-            None
+        ImageBuffer::try_from_raw(width, height, buf, SizeCheck::AtLeast).ok()
+    }
+
+    /// Contructs a buffer from a generic container, reporting why it does not fit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{BufferError, GrayImage, ImageBuffer, SizeCheck};
+    /// let result = GrayImage::try_from_raw(3, 2, vec![0; 5], SizeCheck::AtLeast);
+    /// assert_eq!(result.err(), Some(BufferError::TooSmall { expected: 6, got: 5 }));
+    /// let result = GrayImage::try_from_raw(3, 2, vec![0; 7], SizeCheck::Exact);
+    /// assert_eq!(result.err(), Some(BufferError::TooLarge { expected: 6, got: 7 }));
+    /// ```
+    pub fn try_from_raw(width: u32,
+                        height: u32,
+                        buf: Container,
+                        check: SizeCheck)
+                        -> Result<ImageBuffer<P, Container>, BufferError> {
+        let stride = (width as usize)
+            .checked_mul(<P as Pixel>::channel_count())
+            .ok_or(BufferError::DimensionOverflow)?;
+        ImageBuffer::try_from_raw_with_stride(width, height, stride, buf, check)
+    }
+
+    /// Contructs a buffer from a generic container whose rows are `stride` subpixels apart.
+    ///
+    /// The subpixels between the end of a row and the start of the next row are padding and
+    /// not part of the image.
+    ///
+    /// Returns None if the stride is smaller than a row or the container is not big enough
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{GrayImage, ImageBuffer, color};
+    /// // Rows of 3 pixels padded to 4 subpixels
+    /// let data = vec![1, 2, 3, 0, 4, 5, 6, 0];
+    /// let buffer: GrayImage = ImageBuffer::from_raw_with_stride(3, 2, 4, data).unwrap();
+    /// assert_eq!(buffer[(0, 1)], color::Gray::new([4]));
+    /// ```
+    pub fn from_raw_with_stride(width: u32,
+                                height: u32,
+                                stride: usize,
+                                buf: Container)
+                                -> Option<ImageBuffer<P, Container>> {
+        ImageBuffer::try_from_raw_with_stride(width, height, stride, buf, SizeCheck::AtLeast).ok()
+    }
+
+    /// Contructs a buffer from a generic container whose rows are `stride` subpixels apart,
+    /// reporting why it does not fit.
+    ///
+    /// With `SizeCheck::Exact` the container must end with the last pixel of the last row,
+    /// the last row has no padding.
+    pub fn try_from_raw_with_stride(width: u32,
+                                    height: u32,
+                                    stride: usize,
+                                    buf: Container,
+                                    check: SizeCheck)
+                                    -> Result<ImageBuffer<P, Container>, BufferError> {
+        let expected = required_len::<P>(width, height, stride)?;
+        let got = buf.len();
+        if got < expected {
+            return Err(BufferError::TooSmall { expected, got });
         }
+        if check == SizeCheck::Exact && got > expected {
+            return Err(BufferError::TooLarge { expected, got });
+        }
+        Ok(ImageBuffer {
+            data: buf,
+            width,
+            height,
+            stride,
+            _pixel_type: PhantomData,
+        })
     }
 
     /// Returns the underlying raw buffer
@@ -155,6 +800,38 @@ impl<P, Container> ImageBuffer<P, Container>
         self.data
     }
 
+    /// The number of subpixels covered by the image, including the padding between rows.
+    ///
+    /// This can be less than the length of the underlying container if the buffer was
+    /// constructed with `from_raw` from an oversized container.
+    pub fn logical_len(&self) -> usize {
+        match self.height {
+            0 => 0,
+            h => self.stride * (h as usize - 1) + self.row_len(),
+        }
+    }
+
+    /// The number of subpixels from the start of one row to the start of the next row.
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    /// Returns `true` if the rows are stored without padding.
+    pub fn is_packed(&self) -> bool {
+        self.stride == self.row_len()
+    }
+
+    /// The number of subpixels in a row without padding.
+    fn row_len(&self) -> usize {
+        self.width as usize * <P as Pixel>::channel_count()
+    }
+
+    /// Returns `true` if the underlying container holds trailing subpixels
+    /// which are not part of the image.
+    pub fn has_slack(&self) -> bool {
+        self.data.len() > self.logical_len()
+    }
+
     /// The width and height of this image.
     pub fn dimensions(&self) -> (u32, u32) {
         (self.width, self.height)
@@ -183,7 +860,15 @@ impl<P, Container> ImageBuffer<P, Container>
     /// }
     /// ```
     pub fn pixels<'a>(&'a self) -> Pixels<'a, P> {
-        Pixels { chunks: self.data.chunks(<P as Pixel>::channel_count() as usize) }
+        Pixels::new(&self.data[..self.logical_len()], self.stride, self.row_len())
+    }
+
+    /// Returns an iterator over the subpixels of each row without the padding.
+    pub(crate) fn subpixel_rows(&self) -> impl DoubleEndedIterator<Item = &[P::Subpixel]> {
+        let row_len = self.row_len();
+        self.data[..self.logical_len()].chunks(cmp::max(1, self.stride)).map(move |row| {
+            &row[..row_len]
+        })
     }
 
     /// Enumerates over the pixels of the image.
@@ -215,32 +900,202 @@ impl<P, Container> ImageBuffer<P, Container>
     /// # Panics
     ///
     /// Panics if `(x, y)` is out of the bounds `(width, height)`.
-    fn get_pixel(&self, x: u32, y: u32) -> &P {
+    pub fn get_pixel(&self, x: u32, y: u32) -> &P {
+        match self.get_pixel_checked(x, y) {
+            Some(pixel) => pixel,
+            None => panic!("pixel ({}, {}) out of bounds {:?}", x, y, self.dimensions()),
+        }
+    }
+
+    /// Gets a reference to the pixel at location `(x, y)` or None if `(x, y)` is out of the
+    /// bounds `(width, height)`.
+    pub fn get_pixel_checked(&self, x: u32, y: u32) -> Option<&P> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
         let no_channels = <P as Pixel>::channel_count() as usize;
-        let index = no_channels * (y * self.width + x) as usize;
-        <P as Pixel>::from_slice(&self.data[index..index + no_channels])
+        let index = y as usize * self.stride + no_channels * x as usize;
+        Some(<P as Pixel>::from_slice(&self.data[index..index + no_channels]))
     }
-}
 
-impl<P, Container> ImageBuffer<P, Container>
-    where P: Pixel,
-          Container: Deref<Target = [P::Subpixel]> + DerefMut
-{
-    /// Returns an iterator over the mutable pixels of this image.
-    /// The iterator yields the coordinates of each pixel
-    /// along with a mutable reference to them.
+    /// Copies the pixels into a new packed, `Vec`-backed image buffer.
     ///
     /// # Examples
     ///
     /// ```
-    /// # use image_buffer::{ImageBuffer, color};
-    /// let mut buffer = ImageBuffer::new(100, 100);
+    /// # use image_buffer::{GrayImage, ImageBuffer};
+    /// use std::sync::Arc;
+    /// let data: Arc<[u8]> = vec![1, 2, 3, 4].into();
+    /// let shared: ImageBuffer<_, Arc<[u8]>> = ImageBuffer::from_raw(2, 2, data).unwrap();
+    /// let mut owned: GrayImage = shared.to_vec();
+    /// owned[(0, 0)][0] = 0;
+    /// ```
+    pub fn to_vec(&self) -> ImageBuffer<P, Vec<P::Subpixel>> {
+        let mut buffer = ImageBuffer::new(self.width, self.height);
+        for (to, from) in buffer.subpixel_rows_mut().zip(self.subpixel_rows()) {
+            to.copy_from_slice(from)
+        }
+        buffer
+    }
+
+    /// Copies the subpixels into a packed `Vec` whose first row is at `origin`, as expected by
+    /// OpenGL uploads for `Origin::BottomLeft`.
+    pub fn to_raw_with_origin(&self, origin: Origin) -> Vec<P::Subpixel> {
+        let row_len = self.row_len();
+        let rows = self.data[..self.logical_len()].chunks(cmp::max(1, self.stride));
+        let mut data = Vec::with_capacity(row_len * self.height as usize);
+        {
+            let push = |row: &[P::Subpixel]| data.extend_from_slice(&row[..row_len]);
+            match origin {
+                Origin::TopLeft => rows.for_each(push),
+                Origin::BottomLeft => rows.rev().for_each(push),
+            }
+        }
+        data
+    }
+
+    /// Copies the pixels into a new packed image buffer in a container returned by `alloc`,
+    /// see `new_in`.
+    pub fn to_container_in<D, F>(&self, alloc: F) -> Result<ImageBuffer<P, D>, BufferError>
+        where D: Deref<Target = [P::Subpixel]> + DerefMut,
+              F: FnOnce(usize) -> D
+    {
+        let mut buffer = ImageBuffer::new_in(self.width, self.height, alloc)?;
+        for (to, from) in buffer.subpixel_rows_mut().zip(self.subpixel_rows()) {
+            to.copy_from_slice(from)
+        }
+        Ok(buffer)
+    }
+
+    /// Returns a description of the memory layout of this image.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::RgbImage;
+    /// let buffer = RgbImage::new(4, 3);
+    /// let flat = buffer.as_flat_samples();
+    /// assert_eq!((flat.channels, flat.row_stride, flat.samples.len()), (3, 12, 36));
+    /// ```
+    pub fn as_flat_samples(&self) -> FlatSamples<'_, P::Subpixel> {
+        FlatSamples {
+            width: self.width,
+            height: self.height,
+            channels: P::channel_count(),
+            row_stride: self.stride,
+            samples: &self.data[..self.logical_len()],
+        }
+    }
+
+    /// Returns an iterator over the `idx`-th channel of every pixel.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `idx` is not less than the number of channels.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{RgbImage, color};
+    /// let buffer = RgbImage::from_pixel(10, 10, color::Rgb::new([255, 128, 0]));
+    /// assert!(buffer.channel_iter(1).all(|&g| g == 128));
+    /// ```
+    pub fn channel_iter(&self, idx: usize) -> Channel<'_, P::Subpixel> {
+        check_channel::<P>(idx);
+        Channel::new(&self.data[..self.logical_len()],
+                     self.stride,
+                     self.row_len(),
+                     idx,
+                     P::channel_count())
+    }
+}
+
+impl<P, Container> ImageBuffer<P, Container>
+    where P: Pixel,
+          Container: Deref<Target = [P::Subpixel]> + DerefMut
+{
+    /// Constructs a buffer from a container whose first row is at `origin`, rows stored
+    /// bottom-up like OpenGL readbacks are reversed in place.
+    ///
+    /// Returns None if the container is not big enough.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{GrayImage, ImageBuffer, Origin, color::Gray};
+    /// let readback = vec![1, 2, 3, 4, 5, 6];
+    /// let image: GrayImage = ImageBuffer::from_raw_with_origin(2, 3, readback, Origin::BottomLeft)
+    ///     .unwrap();
+    /// assert_eq!(image[(0, 0)], Gray::new([5]));
+    /// assert_eq!(image.to_raw_with_origin(Origin::BottomLeft), vec![1, 2, 3, 4, 5, 6]);
+    /// ```
+    pub fn from_raw_with_origin(width: u32,
+                                height: u32,
+                                buf: Container,
+                                origin: Origin)
+                                -> Option<ImageBuffer<P, Container>> {
+        let mut buffer = ImageBuffer::from_raw(width, height, buf)?;
+        if origin == Origin::BottomLeft {
+            buffer.flip_vertical_in_place();
+        }
+        Some(buffer)
+    }
+
+    /// Creates a black image in a container returned by `alloc`, which allows to allocate the
+    /// image with an arena or a tracking allocator.
+    ///
+    /// `alloc` is called with the number of subpixels of the image and may return a larger
+    /// container, the image is packed at its start.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{ImageBuffer, color::Gray};
+    /// let mut arena = vec![7u8; 1024];
+    /// let (image_memory, _) = arena.split_at_mut(64);
+    /// let image: ImageBuffer<Gray<u8>, &mut [u8]> =
+    ///     ImageBuffer::new_in(8, 4, |len| &mut image_memory[..len]).unwrap();
+    /// assert_eq!(image.pixels().len(), 32);
+    /// assert!(image.pixels().all(|p| p[0] == 0));
+    /// ```
+    pub fn new_in<F>(width: u32,
+                     height: u32,
+                     alloc: F)
+                     -> Result<ImageBuffer<P, Container>, BufferError>
+        where F: FnOnce(usize) -> Container
+    {
+        let stride = (width as usize)
+            .checked_mul(P::channel_count())
+            .ok_or(BufferError::DimensionOverflow)?;
+        let len = required_len::<P>(width, height, stride)?;
+        let mut buffer = ImageBuffer::try_from_raw(width, height, alloc(len), SizeCheck::AtLeast)?;
+        buffer.clear();
+        Ok(buffer)
+    }
+
+    /// Returns an iterator over the mutable pixels of this image.
+    /// The iterator yields the coordinates of each pixel
+    /// along with a mutable reference to them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{ImageBuffer, color};
+    /// let mut buffer = ImageBuffer::new(100, 100);
     /// for (i, pixel) in buffer.pixels_mut().enumerate() {
     ///     *pixel = color::Gray::new([i as u16]);
     /// }
     /// ```
     pub fn pixels_mut(&mut self) -> PixelsMut<P> {
-        PixelsMut { chunks: self.data.chunks_mut(<P as Pixel>::channel_count() as usize) }
+        let (len, stride, row_len) = (self.logical_len(), self.stride, self.row_len());
+        PixelsMut::new(&mut self.data[..len], stride, row_len)
+    }
+
+    /// Returns an iterator over the mutable subpixels of each row without the padding.
+    pub(crate) fn subpixel_rows_mut(&mut self)
+                                    -> impl DoubleEndedIterator<Item = &mut [P::Subpixel]> {
+        let (len, stride, row_len) = (self.logical_len(), self.stride, self.row_len());
+        self.data[..len].chunks_mut(cmp::max(1, stride)).map(move |row| &mut row[..row_len])
     }
 
     /// Enumerates over the mutable pixels of the image.
@@ -269,10 +1124,154 @@ impl<P, Container> ImageBuffer<P, Container>
     /// # Panics
     ///
     /// Panics if `(x, y)` is out of the bounds `(width, height)`.
-    fn get_pixel_mut(&mut self, x: u32, y: u32) -> &mut P {
+    pub fn get_pixel_mut(&mut self, x: u32, y: u32) -> &mut P {
+        let dimensions = self.dimensions();
+        match self.get_pixel_mut_checked(x, y) {
+            Some(pixel) => pixel,
+            None => panic!("pixel ({}, {}) out of bounds {:?}", x, y, dimensions),
+        }
+    }
+
+    /// Gets a reference to the mutable pixel at location `(x, y)` or None if `(x, y)` is out
+    /// of the bounds `(width, height)`.
+    pub fn get_pixel_mut_checked(&mut self, x: u32, y: u32) -> Option<&mut P> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
         let no_channels = <P as Pixel>::channel_count() as usize;
-        let index = no_channels * (y * self.width + x) as usize;
-        <P as Pixel>::from_slice_mut(&mut self.data[index..index + no_channels])
+        let index = y as usize * self.stride + no_channels * x as usize;
+        Some(<P as Pixel>::from_slice_mut(&mut self.data[index..index + no_channels]))
+    }
+
+    /// Sets every pixel of the image to `pixel`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{RgbImage, color};
+    /// let mut buffer = RgbImage::new(10, 10);
+    /// buffer.fill(color::Rgb::new([255, 0, 0]));
+    /// assert_eq!(buffer[(9, 9)], color::Rgb::new([255, 0, 0]));
+    /// ```
+    pub fn fill(&mut self, pixel: P) {
+        let (width, height) = self.dimensions();
+        self.fill_rect(0, 0, width, height, pixel)
+    }
+
+    /// Sets every subpixel of the image to zero.
+    pub fn clear(&mut self) {
+        let (len, stride, row_len) = (self.logical_len(), self.stride, self.row_len());
+        for row in self.data[..len].chunks_mut(cmp::max(1, stride)) {
+            row[..row_len].fill(Zero::zero());
+        }
+    }
+
+    /// Sets the pixels of the rectangle with the top left corner `(x, y)` and the dimensions
+    /// `(width, height)` to `pixel`.
+    ///
+    /// The rectangle is clipped to the image.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{GrayImage, color};
+    /// let mut buffer = GrayImage::new(4, 4);
+    /// buffer.fill_rect(2, 3, 10, 10, color::Gray::new([255]));
+    /// assert_eq!(buffer.pixels().filter(|p| p[0] == 255).count(), 2);
+    /// ```
+    pub fn fill_rect(&mut self, x: u32, y: u32, width: u32, height: u32, pixel: P) {
+        let x1 = cmp::min(x as u64 + width as u64, self.width as u64) as usize;
+        let y1 = cmp::min(y as u64 + height as u64, self.height as u64) as usize;
+        let (x0, y0) = (x as usize, y as usize);
+        if x0 >= x1 || y0 >= y1 {
+            return;
+        }
+        let channels = P::channel_count();
+        let subpixels = pixel.channels().as_ref();
+        let uniform = subpixels.iter().all(|&v| v == subpixels[0]);
+        for row in y0..y1 {
+            let start = row * self.stride;
+            let span = &mut self.data[start + x0 * channels..start + x1 * channels];
+            if uniform {
+                // A single value can be filled with a memset
+                span.fill(subpixels[0]);
+            } else {
+                for p in span.chunks_mut(channels) {
+                    p.copy_from_slice(subpixels);
+                }
+            }
+        }
+    }
+
+    /// Returns an iterator over the mutable `idx`-th channel of every pixel.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `idx` is not less than the number of channels.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{RgbImage, color};
+    /// let mut buffer = RgbImage::new(10, 10);
+    /// for red in buffer.channel_iter_mut(0) {
+    ///     *red = 255;
+    /// }
+    /// assert_eq!(buffer[(3, 7)], color::Rgb::new([255, 0, 0]));
+    /// ```
+    pub fn channel_iter_mut(&mut self, idx: usize) -> ChannelMut<'_, P::Subpixel> {
+        check_channel::<P>(idx);
+        let (len, stride, row_len) = (self.logical_len(), self.stride, self.row_len());
+        ChannelMut::new(&mut self.data[..len], stride, row_len, idx, P::channel_count())
+    }
+}
+
+impl<T, Container> ImageBuffer<GrayA<T>, Container>
+    where T: Primitive,
+          Container: Deref<Target = [T]>
+{
+    /// Returns an iterator over the gray values of the image without copying them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{GrayAlphaImage, color};
+    /// let buffer = GrayAlphaImage::from_pixel(2, 2, color::GrayA::new([10, 255]));
+    /// assert_eq!(buffer.values().map(|&v| v as u32).sum::<u32>(), 40);
+    /// ```
+    pub fn values(&self) -> Channel<'_, T> {
+        self.channel_iter(0)
+    }
+
+    /// Returns an iterator over the alpha values of the image without copying them.
+    pub fn alphas(&self) -> Channel<'_, T> {
+        self.channel_iter(1)
+    }
+}
+
+impl<T, Container> ImageBuffer<GrayA<T>, Container>
+    where T: Primitive,
+          Container: Deref<Target = [T]> + DerefMut
+{
+    /// Returns an iterator over the mutable gray values of the image.
+    pub fn values_mut(&mut self) -> ChannelMut<'_, T> {
+        self.channel_iter_mut(0)
+    }
+
+    /// Returns an iterator over the mutable alpha values of the image.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{GrayAlphaImage, color};
+    /// let mut buffer = GrayAlphaImage::new(2, 2);
+    /// for alpha in buffer.alphas_mut() {
+    ///     *alpha = 255;
+    /// }
+    /// assert_eq!(buffer[(1, 1)], color::GrayA::new([0, 255]));
+    /// ```
+    pub fn alphas_mut(&mut self) -> ChannelMut<'_, T> {
+        self.channel_iter_mut(1)
     }
 }
 
@@ -317,6 +1316,15 @@ impl<P, Container> IndexMut<(u32, u32)> for ImageBuffer<P, Container>
 }
 
 impl<P, Container> ImageView<P> for ImageBuffer<P, Container>
+    where P: Pixel,
+          Container: Deref<Target = [P::Subpixel]>
+{
+    fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+}
+
+impl<P, Container> ImageViewMut<P> for ImageBuffer<P, Container>
     where P: Pixel,
           Container: Deref<Target = [P::Subpixel]> + DerefMut
 {
@@ -331,6 +1339,7 @@ impl<P, Container> Clone for ImageBuffer<P, Container>
             data: self.data.clone(),
             width: self.width,
             height: self.height,
+            stride: self.stride,
             _pixel_type: PhantomData,
         }
     }
@@ -348,10 +1357,92 @@ impl<P: Pixel> ImageBuffer<P, Vec<P::Subpixel>> {
                       ) as usize],
             width: width,
             height: height,
+            stride: width as usize * <P as Pixel>::channel_count(),
+            _pixel_type: PhantomData,
+        }
+    }
+
+    /// Constructs a buffer from a `Vec` holding exactly `width * height` pixels.
+    ///
+    /// Unlike `from_raw`, a `Vec` with trailing subpixels is rejected.
+    pub fn from_vec(width: u32,
+                    height: u32,
+                    buf: Vec<P::Subpixel>)
+                    -> Result<ImageBuffer<P, Vec<P::Subpixel>>, BufferError> {
+        ImageBuffer::try_from_raw(width, height, buf, SizeCheck::Exact)
+    }
+
+    /// Removes trailing subpixels which are not part of the image and releases
+    /// the excess capacity of the underlying `Vec`.
+    pub fn shrink_to_fit(&mut self) {
+        let len = self.logical_len();
+        self.data.truncate(len);
+        self.data.shrink_to_fit();
+    }
+
+    /// Converts the buffer into a buffer backed by a boxed slice.
+    ///
+    /// Releases the excess capacity of the `Vec`. A boxed slice can in turn be converted into
+    /// an `Rc<[T]>` or `Arc<[T]>` to share the image.
+    pub fn into_boxed_slice(mut self) -> ImageBuffer<P, Box<[P::Subpixel]>> {
+        self.shrink_to_fit();
+        ImageBuffer {
+            width: self.width,
+            height: self.height,
+            stride: self.stride,
+            data: self.data.into_boxed_slice(),
             _pixel_type: PhantomData,
         }
     }
 
+    /// Shrinks the image to the rectangular region with the top left corner `(x, y)` and the
+    /// dimensions `(width, height)` without allocating.
+    ///
+    /// The region is moved to the start of the `Vec`, which is truncated and releases its
+    /// excess capacity.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the region is not contained in the image.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{GrayImage, color};
+    /// let mut buffer = GrayImage::from_fn(4, 4, |x, y| color::Gray::new([(x + 4 * y) as u8]));
+    /// buffer.crop_in_place(1, 2, 2, 2);
+    /// assert_eq!(buffer.dimensions(), (2, 2));
+    /// assert_eq!(buffer.into_raw(), vec![9, 10, 13, 14]);
+    /// ```
+    pub fn crop_in_place(&mut self, x: u32, y: u32, width: u32, height: u32) {
+        check_region(self.dimensions(), x, y, width, height);
+        let channels = <P as Pixel>::channel_count();
+        let row_len = width as usize * channels;
+        for j in 0..height as usize {
+            let start = (y as usize + j) * self.stride + x as usize * channels;
+            self.data.copy_within(start..start + row_len, j * row_len);
+        }
+        self.width = width;
+        self.height = height;
+        self.stride = row_len;
+        self.data.truncate(row_len * height as usize);
+        self.data.shrink_to_fit();
+    }
+
+    /// Removes the padding between rows and the trailing subpixels in place.
+    fn pack(&mut self) {
+        let row_len = self.row_len();
+        if self.stride != row_len {
+            for y in 1..self.height as usize {
+                let start = y * self.stride;
+                self.data.copy_within(start..start + row_len, y * row_len);
+            }
+            self.stride = row_len;
+        }
+        let len = self.logical_len();
+        self.data.truncate(len);
+    }
+
     /// Constructs a new ImageBuffer by copying a pixel
     pub fn from_pixel(width: u32, height: u32, pixel: P) -> ImageBuffer<P, Vec<P::Subpixel>> {
         let mut buf = ImageBuffer::new(width, height);
@@ -374,6 +1465,46 @@ impl<P: Pixel> ImageBuffer<P, Vec<P::Subpixel>> {
     }
 }
 
+impl<P: PodColor> ImageBuffer<P, Vec<P::Subpixel>> {
+    /// Constructs a buffer from a `Vec` of pixels without copying the data.
+    ///
+    /// Returns None if the `Vec` does not contain at least `width * height` pixels.
+    pub fn from_pixel_vec(width: u32,
+                          height: u32,
+                          pixels: Vec<P>)
+                          -> Option<ImageBuffer<P, Vec<P::Subpixel>>> {
+        ImageBuffer::from_raw(width, height, cast_vec(pixels))
+    }
+
+    /// Returns the pixels of this image as a `Vec` of pixels without copying the data.
+    ///
+    /// Padding and trailing subpixels which are not part of the image are dropped.
+    pub fn into_pixel_vec(mut self) -> Vec<P> {
+        self.pack();
+        cast_vec(self.data)
+    }
+
+    /// Constructs a buffer from a `Vec` of channel arrays (for example `Vec<[f32; 4]>`)
+    /// without copying the data.
+    ///
+    /// Returns None if the `Vec` does not contain at least `width * height` pixels.
+    pub fn from_array_vec(width: u32,
+                          height: u32,
+                          arrays: Vec<P::Storage>)
+                          -> Option<ImageBuffer<P, Vec<P::Subpixel>>> {
+        ImageBuffer::from_raw(width, height, cast_vec(arrays))
+    }
+
+    /// Returns the pixels of this image as a `Vec` of channel arrays (for example
+    /// `Vec<[f32; 4]>`) without copying the data.
+    ///
+    /// Padding and trailing subpixels which are not part of the image are dropped.
+    pub fn into_array_vec(mut self) -> Vec<P::Storage> {
+        self.pack();
+        cast_vec(self.data)
+    }
+}
+
 impl<'a, 'b, Container, FromColor: Pixel> ImageBuffer<FromColor, Container>
     where Container: Deref<Target = [FromColor::Subpixel]>
 {
@@ -381,6 +1512,9 @@ impl<'a, 'b, Container, FromColor: Pixel> ImageBuffer<FromColor, Container>
     ///
     /// Converts the color `FromColor` to the color `ToColor`. Allocates a new image buffer.
     ///
+    /// Identity conversions, channel reorders and common sRGB conversions are dispatched to
+    /// specialized routines at compile time. All other pairs fall back to a per-pixel `From`.
+    ///
     /// # Examples
     ///
     /// ```
@@ -392,14 +1526,509 @@ impl<'a, 'b, Container, FromColor: Pixel> ImageBuffer<FromColor, Container>
     pub fn convert_buffer<ToColor>(&self) -> ImageBuffer<ToColor, Vec<ToColor::Subpixel>>
         where ToColor: Pixel + From<FromColor>
     {
+        profile_op!("convert_buffer",
+                    ::profiling::image_bytes(self) +
+                    ::profiling::pixel_bytes::<ToColor>(self.width, self.height));
+        let mut buffer = ImageBuffer::new(self.width, self.height);
+        let to_row_len = buffer.row_len();
+        convert_rows::<FromColor, ToColor>(&self.data[..self.logical_len()],
+                                           self.stride,
+                                           self.row_len(),
+                                           &mut buffer.data,
+                                           to_row_len);
+        buffer
+    }
+
+    /// Converts between gray and RGB images with and without alpha at the depths `u8`, `u16`
+    /// and `f32`, see `color::convert_linear`. Allocates a new image buffer.
+    ///
+    /// Unlike `convert_depth`, float channels are linear and the models may differ.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{GrayF32Image, RgbImage, color};
+    /// let rgb = RgbImage::from_pixel(2, 2, color::Rgb::new([188, 188, 188]));
+    /// let gray: GrayF32Image = rgb.convert_linear();
+    /// assert!((gray[(1, 1)][0] - 0.5).abs() < 0.01);
+    /// ```
+    pub fn convert_linear<ToColor>(&self) -> ImageBuffer<ToColor, Vec<ToColor::Subpixel>>
+        where FromColor: LinearRgba,
+              FromColor::Subpixel: ChannelMax,
+              ToColor: LinearRgba,
+              ToColor::Subpixel: ChannelMax
+    {
+        self.map_buffer(|&p| convert_linear(p))
+    }
+
+    /// Creates a new image buffer by applying `f` to every pixel.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{RgbImage, color};
+    /// let rgb = RgbImage::from_pixel(2, 2, color::Rgb::new([10, 20, 30]));
+    /// let blue = rgb.map_buffer(|p| color::Gray::new([p[2]]));
+    /// assert_eq!(blue[(1, 1)], color::Gray::new([30]));
+    /// ```
+    pub fn map_buffer<ToColor, F>(&self, f: F) -> ImageBuffer<ToColor, Vec<ToColor::Subpixel>>
+        where ToColor: Pixel,
+              F: Fn(&FromColor) -> ToColor
+    {
+        let mut buffer = ImageBuffer::new(self.width, self.height);
+        for (to, from) in buffer.pixels_mut().zip(self.pixels()) {
+            *to = f(from)
+        }
+        buffer
+    }
+
+    /// Creates a new image buffer by applying `f` to the coordinates and value of every pixel.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{GrayImage, color};
+    /// let gray = GrayImage::new(4, 4);
+    /// let ramp = gray.map_buffer_with_coords(|x, _, p| color::Gray::new([p[0] + x as u8]));
+    /// assert_eq!(ramp[(3, 0)], color::Gray::new([3]));
+    /// ```
+    pub fn map_buffer_with_coords<ToColor, F>(&self,
+                                              f: F)
+                                              -> ImageBuffer<ToColor, Vec<ToColor::Subpixel>>
+        where ToColor: Pixel,
+              F: Fn(u32, u32, &FromColor) -> ToColor
+    {
+        let mut buffer = ImageBuffer::new(self.width, self.height);
+        for (to, (x, y, from)) in buffer.pixels_mut().zip(self.enumerate_pixels()) {
+            *to = f(x, y, from)
+        }
+        buffer
+    }
+
+    /// Converts the image to another subpixel type of the same color model, for example from
+    /// `Rgb<u8>` to `Rgb<u16>`.
+    ///
+    /// The values are rescaled to the range of the new type, `u8` values are multiplied by 257
+    /// to get `u16` values and divided by 255 to get floats.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ToColor` differs in color model, number of channels or channel layout.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{RgbImage, ImageBuffer, color};
+    /// let rgb = RgbImage::from_pixel(2, 2, color::Rgb::new([0, 128, 255]));
+    /// let deep: ImageBuffer<color::Rgb<u16>, _> = rgb.convert_depth();
+    /// assert_eq!(deep[(0, 0)], color::Rgb::new([0, 32896, 65535]));
+    /// ```
+    pub fn convert_depth<ToColor>(&self) -> ImageBuffer<ToColor, Vec<ToColor::Subpixel>>
+        where ToColor: Pixel,
+              FromColor::Subpixel: ChannelMax,
+              ToColor::Subpixel: ChannelMax
+    {
+        assert!(FromColor::color_model() == ToColor::color_model() &&
+                FromColor::layout() == ToColor::layout(),
+                "depth conversion from {} to {} changes the color model or layout",
+                FromColor::color_model(),
+                ToColor::color_model());
+        let mut buffer: ImageBuffer<ToColor, _> = ImageBuffer::new(self.width, self.height);
+        for (to, from) in buffer.subpixel_rows_mut().zip(self.subpixel_rows()) {
+            for (t, &f) in to.iter_mut().zip(from) {
+                *t = rescale_channel(f);
+            }
+        }
+        buffer
+    }
+
+    /// Performs a color conversion of the image buffer into an existing `target` buffer.
+    ///
+    /// Unlike `convert_buffer` this does not allocate, so the allocation of `target` can be
+    /// reused for many conversions. Panics if the dimensions of `target` differ.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{RgbImage, GrayImage};
+    /// let rgb = RgbImage::new(100, 100);
+    /// let mut gray = GrayImage::new(100, 100);
+    /// rgb.convert_into(&mut gray);
+    /// ```
+    pub fn convert_into<ToColor, C>(&self, target: &mut ImageBuffer<ToColor, C>)
+        where ToColor: Pixel + From<FromColor>,
+              C: Deref<Target = [ToColor::Subpixel]> + DerefMut
+    {
+        assert_eq!(self.dimensions(),
+                   target.dimensions(),
+                   "dimensions of the target buffer differ");
+        let (len, to_row_len) = (target.logical_len(), target.row_len());
+        if target.is_packed() {
+            convert_rows::<FromColor, ToColor>(&self.data[..self.logical_len()],
+                                               self.stride,
+                                               self.row_len(),
+                                               &mut target.data[..len],
+                                               to_row_len);
+        } else {
+            let (stride, row_len) = (self.stride, self.row_len());
+            for (y, to) in target.data[..len].chunks_mut(target.stride).enumerate() {
+                convert_pixels::<FromColor, ToColor>(&self.data[y * stride..y * stride + row_len],
+                                                     &mut to[..to_row_len]);
+            }
+        }
+    }
+}
+
+impl<T, Container> ImageBuffer<Rgb<T>, Container>
+    where T: Primitive + ChannelMax,
+          Container: Deref<Target = [T]>
+{
+    /// Converts the image to grayscale using the luma `weights`.
+    ///
+    /// See `Gray::from_rgb_with_weights`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{RgbImage, color};
+    /// let rgb = RgbImage::new(100, 100);
+    /// let luma = rgb.convert_buffer_with(color::LumaWeights::Bt601);
+    /// ```
+    pub fn convert_buffer_with(&self, weights: LumaWeights) -> ImageBuffer<Gray<T>, Vec<T>> {
+        let mut buffer = ImageBuffer::new(self.width, self.height);
+        for (to, &from) in buffer.pixels_mut().zip(self.pixels()) {
+            *to = Gray::from_rgb_with_weights(from, weights)
+        }
+        buffer
+    }
+}
+
+impl<FromColor: Pixel> ImageBuffer<FromColor, Vec<FromColor::Subpixel>> {
+    /// Performs a color conversion reusing the allocation of the image buffer.
+    ///
+    /// `ToColor` has to have the same subpixel type and number of channels as `FromColor`, for
+    /// example `Rgb<u8>` and `Hsv<u8>`. Panics if the number of channels differs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{RgbImage, ImageBuffer, color};
+    /// let rgb = RgbImage::from_pixel(2, 2, color::Rgb::new([255, 0, 0]));
+    /// let hsv: ImageBuffer<color::Hsv<u8>, _> = rgb.convert_buffer_in_place();
+    /// assert_eq!(hsv[(1, 1)], color::Hsv::new([0, 255, 255]));
+    /// ```
+    pub fn convert_buffer_in_place<ToColor>(mut self)
+                                            -> ImageBuffer<ToColor, Vec<FromColor::Subpixel>>
+        where ToColor: Pixel<Subpixel = FromColor::Subpixel> + From<FromColor>
+    {
+        assert_eq!(FromColor::channel_count(),
+                   ToColor::channel_count(),
+                   "number of channels differs");
+        for p in self.pixels_mut() {
+            let converted = ToColor::from(*p);
+            *ToColor::from_slice_mut(p.channels_mut().as_mut()) = converted;
+        }
+        ImageBuffer {
+            width: self.width,
+            height: self.height,
+            stride: self.stride,
+            data: self.data,
+            _pixel_type: PhantomData,
+        }
+    }
+}
+
+/// Converts the rows starting at row `y0` of `src` to the packed rows of `dst`.
+fn convert_row_block<F, T>(src: &[F::Subpixel],
+                           stride: usize,
+                           row_len: usize,
+                           y0: usize,
+                           dst: &mut [T::Subpixel],
+                           to_row_len: usize)
+    where F: Pixel,
+          T: Pixel + From<F>
+{
+    if to_row_len == 0 {
+        return;
+    }
+    if stride == row_len {
+        let start = y0 * row_len;
+        let rows = dst.len() / to_row_len;
+        convert_pixels::<F, T>(&src[start..start + rows * row_len], dst);
+    } else {
+        for (i, to) in dst.chunks_mut(to_row_len).enumerate() {
+            let start = (y0 + i) * stride;
+            convert_pixels::<F, T>(&src[start..start + row_len], to);
+        }
+    }
+}
+
+/// Converts the rows of `src` to the packed rows of `dst`.
+fn convert_rows<F, T>(src: &[F::Subpixel],
+                      stride: usize,
+                      row_len: usize,
+                      dst: &mut [T::Subpixel],
+                      to_row_len: usize)
+    where F: Pixel,
+          T: Pixel + From<F>
+{
+    convert_row_block::<F, T>(src, stride, row_len, 0, dst, to_row_len)
+}
+
+#[cfg(feature = "rayon")]
+impl<FromColor, Container> ImageBuffer<FromColor, Container>
+    where FromColor: Pixel + Sync,
+          FromColor::Subpixel: Sync,
+          Container: Deref<Target = [FromColor::Subpixel]>
+{
+    /// Converts the color `FromColor` to the color `ToColor` like `convert_buffer`, with bands
+    /// of rows converted in parallel. Allocates a new image buffer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{RgbImage, GrayImage, color};
+    /// let rgb = RgbImage::from_pixel(100, 100, color::Rgb::new([255, 255, 255]));
+    /// let gray: GrayImage = rgb.par_convert_buffer();
+    /// assert_eq!(gray.into_raw(), rgb.convert_buffer::<color::Gray<u8>>().into_raw());
+    /// ```
+    pub fn par_convert_buffer<ToColor>(&self) -> ImageBuffer<ToColor, Vec<ToColor::Subpixel>>
+        where ToColor: Pixel + From<FromColor> + Send,
+              ToColor::Subpixel: Send
+    {
+        // Number of subpixels converted by a single task
+        const TASK_SIZE: usize = 1 << 16;
+        profile_op!("par_convert_buffer",
+                    ::profiling::image_bytes(self) +
+                    ::profiling::pixel_bytes::<ToColor>(self.width, self.height));
+        let mut buffer = ImageBuffer::new(self.width, self.height);
+        let to_row_len = buffer.row_len();
+        let rows_per_task = cmp::max(1, TASK_SIZE / cmp::max(1, to_row_len));
+        let task_len = cmp::max(1, rows_per_task * to_row_len);
+        let src = &self.data[..self.logical_len()];
+        let (stride, row_len) = (self.stride, self.row_len());
+        buffer.data.par_chunks_mut(task_len).enumerate().for_each(|(i, to)| {
+            convert_row_block::<FromColor, ToColor>(src,
+                                                    stride,
+                                                    row_len,
+                                                    i * rows_per_task,
+                                                    to,
+                                                    to_row_len)
+        });
+        buffer
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<P, Container> ImageBuffer<P, Container>
+    where P: Pixel + Send,
+          P::Subpixel: Send,
+          Container: Deref<Target = [P::Subpixel]> + DerefMut
+{
+    /// Returns a parallel iterator over the mutable pixels of this image.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate rayon;
+    /// # extern crate image_buffer;
+    /// # use image_buffer::{GrayImage, color};
+    /// use rayon::prelude::*;
+    /// # fn main() {
+    /// let mut buffer = GrayImage::new(100, 100);
+    /// buffer.par_pixels_mut().for_each(|pixel| *pixel = color::Gray::new([255]));
+    /// # }
+    /// ```
+    pub fn par_pixels_mut<'a>(&'a mut self) -> impl ParallelIterator<Item = &'a mut P> + 'a {
+        let (len, stride, row_len) = (self.logical_len(), self.stride, self.row_len());
+        self.data[..len]
+            .par_chunks_mut(cmp::max(1, stride))
+            .flat_map(move |row| {
+                row[..row_len].par_chunks_mut(P::channel_count()).map(P::from_slice_mut)
+            })
+    }
+
+    /// Returns a parallel iterator over disjoint bands of `rows` rows, the last band may have
+    /// fewer rows.
+    ///
+    /// Each band is yielded with the y coordinate of its first row as an image borrowing the
+    /// pixels of this image.
+    ///
+    /// # Panics
+    ///
+    /// If `rows` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate rayon;
+    /// # extern crate image_buffer;
+    /// # use image_buffer::{GrayImage, color};
+    /// use rayon::prelude::*;
+    /// # fn main() {
+    /// let mut buffer = GrayImage::new(8, 10);
+    /// buffer.par_chunks_rows(4).for_each(|(y, mut band)| {
+    ///     band.fill(color::Gray::new([y as u8]));
+    /// });
+    /// assert_eq!(buffer[(3, 9)], color::Gray::new([8]));
+    /// # }
+    /// ```
+    pub fn par_chunks_rows<'a>
+        (&'a mut self,
+         rows: u32)
+         -> impl IndexedParallelIterator<Item = (u32, ImageBuffer<P, &'a mut [P::Subpixel]>)> + 'a {
+        assert!(rows > 0, "empty bands");
+        let (len, stride) = (self.logical_len(), cmp::max(1, self.stride));
+        let (width, total_height) = self.dimensions();
+        self.data[..len]
+            .par_chunks_mut(stride * rows as usize)
+            .enumerate()
+            .map(move |(i, band)| {
+                let height = cmp::min(rows, total_height - i as u32 * rows);
+                let band = ImageBuffer::from_raw_with_stride(width, height, stride, band).unwrap();
+                (i as u32 * rows, band)
+            })
+    }
+
+    /// Returns a parallel iterator over the mutable pixels of this image which yields the
+    /// coordinates of each pixel along with a mutable reference to it.
+    pub fn par_enumerate_pixels_mut<'a>
+        (&'a mut self)
+         -> impl ParallelIterator<Item = (u32, u32, &'a mut P)> + 'a {
+        let (len, stride, row_len) = (self.logical_len(), self.stride, self.row_len());
+        self.data[..len]
+            .par_chunks_mut(cmp::max(1, stride))
+            .enumerate()
+            .flat_map(move |(y, row)| {
+                row[..row_len]
+                    .par_chunks_mut(P::channel_count())
+                    .enumerate()
+                    .map(move |(x, p)| (x as u32, y as u32, P::from_slice_mut(p)))
+            })
+    }
+}
+
+impl<P, Container> ImageBuffer<P, Container>
+    where P: AlphaColor,
+          Container: Deref<Target = [P::Subpixel]>
+{
+    /// Composites the image over an opaque `background` color.
+    ///
+    /// Returns a new image buffer without alpha channel.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{RgbaImage, GrayImage, color};
+    /// let rgba = RgbaImage::new(100, 100);
+    /// let gray: GrayImage = rgba.flatten(color::Rgb::new([255, 255, 255])).convert_buffer();
+    /// ```
+    pub fn flatten(&self, background: P::Opaque) -> ImageBuffer<P::Opaque, Vec<P::Subpixel>> {
         let mut buffer = ImageBuffer::new(self.width, self.height);
-        for (mut to, from) in buffer.pixels_mut().zip(self.pixels()) {
-            *to = From::from(*from)
+        for (to, from) in buffer.pixels_mut().zip(self.pixels()) {
+            *to = from.flatten(background)
         }
         buffer
     }
 }
 
+impl<C, Container, const N: usize> ImageBuffer<Alpha<C, N>, Container>
+    where C: Pixel,
+          C::Subpixel: ChannelMax,
+          Container: Deref<Target = [C::Subpixel]>
+{
+    /// Returns a copy of the image with the color channels multiplied by the alpha channel.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{RgbaImage, color};
+    /// let rgba = RgbaImage::from_pixel(2, 2, color::Rgba::new([255, 255, 255, 51]));
+    /// let premultiplied = rgba.premultiply_alpha();
+    /// assert_eq!(premultiplied[(0, 0)], color::PremultipliedRgba::new([51, 51, 51, 51]));
+    /// ```
+    pub fn premultiply_alpha(&self)
+                             -> ImageBuffer<PremultipliedAlpha<C, N>, Vec<C::Subpixel>> {
+        self.convert_buffer()
+    }
+}
+
+impl<C, Container, const N: usize> ImageBuffer<PremultipliedAlpha<C, N>, Container>
+    where C: Pixel,
+          C::Subpixel: ChannelMax,
+          Container: Deref<Target = [C::Subpixel]>
+{
+    /// Returns a copy of the image with the color channels divided by the alpha channel.
+    pub fn unpremultiply_alpha(&self) -> ImageBuffer<Alpha<C, N>, Vec<C::Subpixel>> {
+        self.convert_buffer()
+    }
+}
+
+macro_rules! implement_over_linear {
+    {$(
+        $ident: ty,
+    )*} => {
+$( // START Implementations
+
+impl<Container> ImageBuffer<$ident, Container>
+    where Container: Deref<Target = [u8]> + DerefMut
+{
+    /// Composites the image `top` over this image in linear light.
+    ///
+    /// Both images are assumed to be sRGB encoded with straight alpha.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the dimensions of the images differ.
+    pub fn over_linear<C>(&mut self, top: &ImageBuffer<$ident, C>)
+        where C: Deref<Target = [u8]>
+    {
+        assert_eq!(self.dimensions(), top.dimensions());
+        let table = srgb_expansion_table();
+        for (to, from) in self.pixels_mut().zip(top.pixels()) {
+            srgb_over_linear(from.as_ref(), to.as_mut(), |v| table[v as usize]);
+        }
+    }
+}
+
+)* // END Implementations
+    }
+}
+
+implement_over_linear!(
+    Rgba<u8>,
+    GrayA<u8>,
+);
+
+/// Reinterprets a `Vec<A>` as a `Vec<B>` without copying the elements.
+///
+/// Either `B` has to be layout compatible with `[A; N]` or `A` with `[B; N]`, which callers
+/// guarantee through `PodColor`.
+///
+/// # Panics
+///
+/// Panics if the sizes or alignments of `A` and `B` are incompatible or if the length
+/// of the `Vec` is not a multiple of `N`.
+// `usize::is_multiple_of` is only available since Rust 1.87
+#[allow(unknown_lints, clippy::manual_is_multiple_of)]
+fn cast_vec<A, B>(mut vec: Vec<A>) -> Vec<B> {
+    let (size_a, size_b) = (mem::size_of::<A>(), mem::size_of::<B>());
+    assert!(size_a != 0 && size_b != 0);
+    assert_eq!(mem::align_of::<A>(), mem::align_of::<B>());
+    assert!(size_a % size_b == 0 || size_b % size_a == 0);
+    assert!((vec.len() * size_a) % size_b == 0);
+    if (vec.capacity() * size_a) % size_b != 0 {
+        vec.shrink_to_fit();
+        assert!((vec.capacity() * size_a) % size_b == 0);
+    }
+    let len = vec.len() * size_a / size_b;
+    let capacity = vec.capacity() * size_a / size_b;
+    let ptr = vec.as_mut_ptr() as *mut B;
+    mem::forget(vec);
+    // The allocation keeps its size in bytes and its alignment.
+    unsafe { Vec::from_raw_parts(ptr, len, capacity) }
+}
+
 /// Sendable Rgb image buffer
 pub type RgbImage = ImageBuffer<Rgb<u8>, Vec<u8>>;
 /// Sendable Rgb + alpha channel image buffer
@@ -408,11 +2037,30 @@ pub type RgbaImage = ImageBuffer<Rgba<u8>, Vec<u8>>;
 pub type GrayImage = ImageBuffer<Gray<u8>, Vec<u8>>;
 /// Sendable grayscale + alpha channel image buffer
 pub type GrayAlphaImage = ImageBuffer<GrayA<u8>, Vec<u8>>;
+/// Sendable image buffer of palette indices
+pub type IndexedImage = ImageBuffer<Indexed<u8>, Vec<u8>>;
+/// Sendable Rgb image buffer with 16 bits per channel
+pub type Rgb16Image = ImageBuffer<Rgb<u16>, Vec<u16>>;
+/// Sendable Rgb + alpha channel image buffer with 16 bits per channel
+pub type Rgba16Image = ImageBuffer<Rgba<u16>, Vec<u16>>;
+/// Sendable grayscale image buffer with 16 bits per channel
+pub type Gray16Image = ImageBuffer<Gray<u16>, Vec<u16>>;
+/// Sendable grayscale + alpha channel image buffer with 16 bits per channel
+pub type GrayAlpha16Image = ImageBuffer<GrayA<u16>, Vec<u16>>;
+/// Sendable linear Rgb image buffer with floating point channels
+pub type RgbF32Image = ImageBuffer<Rgb<f32>, Vec<f32>>;
+/// Sendable linear Rgb + alpha channel image buffer with floating point channels
+pub type RgbaF32Image = ImageBuffer<Rgba<f32>, Vec<f32>>;
+/// Sendable linear grayscale image buffer with floating point channels
+pub type GrayF32Image = ImageBuffer<Gray<f32>, Vec<f32>>;
+/// Sendable linear grayscale + alpha channel image buffer with floating point channels
+pub type GrayAlphaF32Image = ImageBuffer<GrayA<f32>, Vec<f32>>;
 
 #[cfg(test)]
 mod test {
 
-    use super::{ImageBuffer, RgbImage, GrayImage};
+    use super::{BufferError, ImageBuffer, RgbImage, RgbaImage, GrayImage, GrayAlphaImage,
+                Origin, SizeCheck};
     use Color;
     use color_model;
 
@@ -425,6 +2073,131 @@ mod test {
         assert_eq!(&*buf, &data[..])
     }
 
+    #[test]
+    fn test_shrink_to_fit() {
+        let mut buf: GrayImage = ImageBuffer::from_raw(3, 3, vec![0; 12]).unwrap();
+        assert_eq!(buf.logical_len(), 9);
+        assert!(buf.has_slack());
+        buf.shrink_to_fit();
+        assert!(!buf.has_slack());
+        assert_eq!(buf.into_raw().len(), 9)
+    }
+
+    #[test]
+    fn test_pixel_vec() {
+        let pixels = vec![[1u8, 2, 3], [4, 5, 6]];
+        let buf: RgbImage = ImageBuffer::from_array_vec(2, 1, pixels).unwrap();
+        assert_eq!(&*buf, &[1, 2, 3, 4, 5, 6]);
+        let pixels = buf.into_pixel_vec();
+        assert_eq!(pixels[1], color_model::Rgb::new([4, 5, 6]));
+        let buf: RgbImage = ImageBuffer::from_pixel_vec(1, 2, pixels).unwrap();
+        assert_eq!(buf.into_array_vec(), vec![[1, 2, 3], [4, 5, 6]])
+    }
+
+    #[test]
+    fn test_flatten() {
+        let mut a: RgbaImage = ImageBuffer::new(2, 1);
+        a[(0, 0)] = color_model::Rgba::new([255, 0, 0, 255]);
+        a[(1, 0)] = color_model::Rgba::new([255, 0, 0, 51]);
+        let b = a.flatten(color_model::Rgb::new([0, 0, 255]));
+        assert_eq!(&*b, &[255, 0, 0, 51, 0, 204])
+    }
+
+    #[test]
+    fn test_over_linear() {
+        let mut a = RgbaImage::from_pixel(2, 2, color_model::Rgba::new([0, 0, 0, 255]));
+        let b = RgbaImage::from_pixel(2, 2, color_model::Rgba::new([255, 255, 255, 128]));
+        a.over_linear(&b);
+        assert_eq!(a[(1, 1)], color_model::Rgba::new([188, 188, 188, 255]))
+    }
+
+    #[test]
+    fn test_rows() {
+        let mut a: RgbImage = ImageBuffer::new(3, 2);
+        for (y, row) in a.rows_mut().enumerate() {
+            row[2] = color_model::Rgb::new([y as u8, 0, 0]);
+        }
+        let rows: Vec<_> = a.rows().collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1].len(), 3);
+        assert_eq!(rows[1][2], color_model::Rgb::new([1, 0, 0]));
+        assert_eq!(a.rows().next_back().unwrap()[2], rows[1][2])
+    }
+
+    #[test]
+    fn test_stride() {
+        let data = [1, 2, 3, 0, 0, 4, 5, 6, 0, 0];
+        let buf: ImageBuffer<color_model::Gray<u8>, _> =
+            ImageBuffer::from_raw_with_stride(3, 2, 5, &data[..]).unwrap();
+        assert!(!buf.is_packed());
+        assert_eq!(buf.logical_len(), 8);
+        let values: Vec<u8> = buf.pixels().map(|p| p[0]).collect();
+        assert_eq!(values, [1, 2, 3, 4, 5, 6]);
+        let values: Vec<u8> = buf.pixels().rev().map(|p| p[0]).collect();
+        assert_eq!(values, [6, 5, 4, 3, 2, 1]);
+        assert_eq!(buf.enumerate_pixels().last().unwrap(), (2, 1, &color_model::Gray::new([6])));
+        assert_eq!(buf.rows().nth(1).unwrap()[0], color_model::Gray::new([4]));
+        let rgb: RgbImage = buf.convert_buffer();
+        assert_eq!(rgb[(2, 1)], color_model::Rgb::new([6, 6, 6]));
+
+        let mut owned: GrayImage = ImageBuffer::from_raw_with_stride(3, 2, 5, data.to_vec())
+            .unwrap();
+        for p in owned.pixels_mut() {
+            p[0] += 1;
+        }
+        assert_eq!(owned.into_pixel_vec().iter().map(|p| p[0]).collect::<Vec<_>>(),
+                   [2, 3, 4, 5, 6, 7]);
+        assert!(GrayImage::from_raw_with_stride(3, 2, 2, vec![0; 10]).is_none());
+        assert!(GrayImage::from_raw_with_stride(3, 2, 5, vec![0; 7]).is_none());
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_par_enumerate_pixels_mut() {
+        use rayon::prelude::*;
+
+        let data = vec![0; 10];
+        let mut a: GrayImage = ImageBuffer::from_raw_with_stride(3, 2, 5, data).unwrap();
+        a.par_enumerate_pixels_mut()
+            .for_each(|(x, y, p)| *p = color_model::Gray::new([(x + 3 * y) as u8]));
+        assert_eq!(&*a, &[0, 1, 2, 0, 0, 3, 4, 5, 0, 0]);
+        a.par_pixels_mut().for_each(|p| p[0] += 1);
+        assert_eq!(a.par_convert_buffer::<color_model::Gray<u8>>().into_raw(), [1, 2, 3, 4, 5, 6]);
+
+        // Many tasks, the last one with fewer rows
+        let rgb = RgbImage::from_fn(300, 301, |x, y| color_model::Rgb::new([x as u8, y as u8, 7]));
+        let gray: GrayImage = rgb.par_convert_buffer();
+        assert_eq!(gray.into_raw(), rgb.convert_buffer::<color_model::Gray<u8>>().into_raw());
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_par_chunks_rows() {
+        use rayon::prelude::*;
+
+        let mut a = GrayImage::from_raw_with_stride(2, 5, 3, vec![0; 14]).unwrap();
+        a.par_chunks_rows(2).for_each(|(y, mut band)| {
+            assert_eq!(band.width(), 2);
+            assert_eq!(band.height(), if y == 4 { 1 } else { 2 });
+            for (dy, row) in band.enumerate_rows_mut() {
+                row[1] = color_model::Gray::new([(y + dy) as u8]);
+            }
+        });
+        assert_eq!(a.into_raw(), vec![0, 0, 0, 0, 1, 0, 0, 2, 0, 0, 3, 0, 0, 4]);
+    }
+
+    #[test]
+    fn test_enumerate_rows() {
+        let mut a = GrayImage::from_raw_with_stride(2, 3, 4, vec![0; 10]).unwrap();
+        for (y, row) in a.enumerate_rows_mut() {
+            row[0] = color_model::Gray::new([y as u8 + 1]);
+        }
+        let rows: Vec<(u32, Vec<u8>)> = a.enumerate_rows()
+            .map(|(y, row)| (y, row.iter().map(|p| p[0]).collect()))
+            .collect();
+        assert_eq!(rows, vec![(0, vec![1, 0]), (1, vec![2, 0]), (2, vec![3, 0])]);
+    }
+
     #[test]
     fn test_get_pixel() {
         let mut a: RgbImage = ImageBuffer::new(10, 10);
@@ -436,6 +2209,22 @@ mod test {
 
     }
 
+    #[test]
+    fn test_get_pixel_checked() {
+        let mut a: GrayImage = ImageBuffer::new(3, 2);
+        *a.get_pixel_mut_checked(2, 1).unwrap() = color_model::Gray::new([7]);
+        assert_eq!(a.get_pixel_checked(2, 1), Some(&color_model::Gray::new([7])));
+        assert!(a.get_pixel_checked(3, 0).is_none());
+        assert!(a.get_pixel_mut_checked(0, 2).is_none());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_get_pixel_out_of_bounds() {
+        let a: GrayImage = ImageBuffer::new(3, 2);
+        a.get_pixel(3, 0);
+    }
+
     #[test]
     fn test_mut_iter() {
         let mut a: RgbImage = ImageBuffer::new(10, 10);
@@ -458,4 +2247,157 @@ mod test {
         let b: GrayImage = a.convert_buffer();
         assert_eq!(b.data[0], 129)
     }
+
+    #[test]
+    fn test_gray_alpha_channels() {
+        let data = vec![1, 10, 2, 20, 0, 3, 30, 4, 40];
+        let mut buf: GrayAlphaImage = ImageBuffer::from_raw_with_stride(2, 2, 5, data).unwrap();
+        assert_eq!(buf.values().cloned().collect::<Vec<_>>(), [1, 2, 3, 4]);
+        assert_eq!(buf.alphas().cloned().collect::<Vec<_>>(), [10, 20, 30, 40]);
+        for v in buf.values_mut() {
+            *v *= 2
+        }
+        assert_eq!(buf.into_raw(), [2, 10, 4, 20, 0, 6, 30, 8, 40]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_channel_iter_out_of_bounds() {
+        RgbImage::new(1, 1).channel_iter(3);
+    }
+
+    #[test]
+    fn test_containers() {
+        let padded: GrayImage = ImageBuffer::from_raw_with_stride(2, 2, 3, vec![1, 2, 0, 3, 4, 9])
+            .unwrap();
+        let boxed = padded.into_boxed_slice();
+        assert_eq!(boxed.as_flat_samples().samples, &[1, 2, 0, 3, 4]);
+        assert_eq!(boxed.to_vec().into_raw(), [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_convert_depth() {
+        let deep: ImageBuffer<color_model::Rgba<u16>, _> =
+            ImageBuffer::from_pixel(1, 1, color_model::Rgba::new([257, 32896, 32897, 65535]));
+        let rgba: RgbaImage = deep.convert_depth();
+        assert_eq!(rgba.into_raw(), [1, 128, 128, 255]);
+        let float: ImageBuffer<color_model::Rgba<f32>, _> = deep.convert_depth();
+        assert_eq!(float[(0, 0)][3], 1.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_convert_depth_model_mismatch() {
+        let _: ImageBuffer<color_model::Hsv<u16>, _> = RgbImage::new(1, 1).convert_depth();
+    }
+
+    #[test]
+    fn test_fill_with_stride() {
+        let data = vec![9; 8];
+        let mut buf: RgbImage = ImageBuffer::from_raw_with_stride(1, 2, 4, data).unwrap();
+        buf.fill(color_model::Rgb::new([1, 2, 3]));
+        assert_eq!(buf.clone().into_raw(), [1, 2, 3, 9, 1, 2, 3, 9]);
+        buf.fill_rect(0, 1, 1, 1, color_model::Rgb::new([4, 4, 4]));
+        assert_eq!(buf.clone().into_raw(), [1, 2, 3, 9, 4, 4, 4, 9]);
+        buf.fill_rect(1, 0, 5, 5, color_model::Rgb::new([0, 0, 0]));
+        buf.clear();
+        assert_eq!(buf.into_raw(), [0, 0, 0, 9, 0, 0, 0, 9]);
+    }
+
+    #[test]
+    fn test_convert_into() {
+        let a = RgbImage::from_pixel(3, 2, color_model::Rgb::new([255, 23, 42]));
+        let mut b = GrayImage::from_raw_with_stride(3, 2, 4, vec![0; 7]).unwrap();
+        a.convert_into(&mut b);
+        assert_eq!(b.into_raw(), [129, 129, 129, 0, 129, 129, 129]);
+    }
+
+    #[test]
+    fn test_convert_buffer_in_place() {
+        let a = RgbImage::from_pixel(3, 2, color_model::Rgb::new([0, 0, 255]));
+        let ptr = a.as_ptr();
+        let b: ImageBuffer<color_model::Hsv<u8>, _> = a.convert_buffer_in_place();
+        assert_eq!(b.as_ptr(), ptr);
+        assert!(b.pixels().all(|p| p.channels() == &[170, 255, 255]));
+    }
+
+    #[test]
+    fn test_exact_size_pixels() {
+        let data: Vec<u8> = (1..16).collect();
+        let mut buf: GrayImage = ImageBuffer::from_raw_with_stride(3, 3, 5, data).unwrap();
+        let mut pixels = buf.pixels();
+        assert_eq!(pixels.len(), 9);
+        assert_eq!(pixels.next().unwrap()[0], 1);
+        assert_eq!(pixels.next_back().unwrap()[0], 13);
+        assert_eq!(pixels.len(), 7);
+        assert_eq!(pixels.nth(3).unwrap()[0], 7);
+        assert_eq!(pixels.len(), 3);
+        assert_eq!(pixels.nth(2).unwrap()[0], 12);
+        assert_eq!(pixels.nth(1), None);
+        assert_eq!(pixels.next(), None);
+
+        let mut enumerate = buf.enumerate_pixels();
+        assert_eq!(enumerate.nth(4), Some((1, 1, &color_model::Gray::new([7]))));
+        assert_eq!(enumerate.next(), Some((2, 1, &color_model::Gray::new([8]))));
+        assert_eq!(enumerate.len(), 3);
+        assert_eq!(enumerate.nth(1), Some((1, 2, &color_model::Gray::new([12]))));
+
+        let mut pixels_mut = buf.pixels_mut();
+        assert_eq!(pixels_mut.nth(7).unwrap()[0], 12);
+        assert_eq!(pixels_mut.len(), 1);
+        let mut enumerate_mut = buf.enumerate_pixels_mut();
+        assert_eq!(enumerate_mut.nth(8).map(|(x, y, _)| (x, y)), Some((2, 2)));
+        assert!(enumerate_mut.nth(1).is_none());
+        assert_eq!(RgbImage::new(0, 4).pixels().len(), 0);
+    }
+
+    #[test]
+    fn test_try_from_raw() {
+        let ok = RgbImage::try_from_raw(2, 2, vec![0; 13], SizeCheck::AtLeast).unwrap();
+        assert_eq!(ok.dimensions(), (2, 2));
+        assert_eq!(RgbImage::from_vec(2, 2, vec![0; 13]).err(),
+                   Some(BufferError::TooLarge { expected: 12, got: 13 }));
+        assert!(RgbImage::from_vec(2, 2, vec![0; 12]).is_ok());
+        assert_eq!(RgbImage::try_from_raw(u32::MAX, u32::MAX, vec![], SizeCheck::AtLeast).err(),
+                   Some(BufferError::DimensionOverflow));
+        assert_eq!(GrayImage::try_from_raw_with_stride(3, 2, 2, vec![0; 6], SizeCheck::AtLeast)
+                       .err(),
+                   Some(BufferError::StrideTooSmall { row_len: 3, stride: 2 }));
+        let exact = GrayImage::try_from_raw_with_stride(2, 2, 3, vec![0; 5], SizeCheck::Exact);
+        assert!(exact.is_ok());
+        assert!(GrayImage::from_raw(2, 2, vec![0; 3]).is_none());
+    }
+
+    #[test]
+    fn test_origin() {
+        let data = vec![1, 2, 3, 4, 5, 6];
+        let image = GrayImage::from_raw_with_origin(2, 3, data, Origin::BottomLeft).unwrap();
+        assert_eq!(image.clone().into_raw(), vec![5, 6, 3, 4, 1, 2]);
+        assert_eq!(image.to_raw_with_origin(Origin::BottomLeft), vec![1, 2, 3, 4, 5, 6]);
+        let strided = GrayImage::from_raw_with_stride(2, 3, 3, vec![1, 2, 0, 3, 4, 0, 5, 6])
+            .unwrap();
+        assert_eq!(strided.to_raw_with_origin(Origin::TopLeft), vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(strided.to_raw_with_origin(Origin::BottomLeft), vec![5, 6, 3, 4, 1, 2]);
+        assert!(GrayImage::from_raw_with_origin(2, 3, vec![0; 5], Origin::TopLeft).is_none());
+    }
+
+    #[test]
+    fn test_new_in() {
+        let mut requested = 0;
+        let image: RgbImage = ImageBuffer::new_in(3, 2, |len| {
+                requested = len;
+                vec![1; len + 2]
+            })
+            .unwrap();
+        assert_eq!(requested, 18);
+        assert_eq!(&image.clone().into_raw()[16..], &[0, 0, 1, 1]);
+        let small = GrayImage::new_in(3, 2, |len| vec![0; len - 1]);
+        assert_eq!(small.err(), Some(BufferError::TooSmall { expected: 6, got: 5 }));
+
+        let source = GrayImage::from_raw_with_stride(2, 2, 3, vec![1, 2, 0, 3, 4]).unwrap();
+        let mut memory = [9; 4];
+        let copy = source.to_container_in(|len| &mut memory[..len]).unwrap();
+        assert!(copy.is_packed());
+        assert_eq!(memory, [1, 2, 3, 4]);
+    }
 }
@@ -0,0 +1,198 @@
+//! Conversion of image buffers to and from bytes in an explicit byte order.
+
+use std::borrow::Cow;
+use std::mem;
+use std::ops::Deref;
+use std::slice;
+
+use buffer::{BufferError, ImageBuffer};
+use traits::{Color as Pixel, Primitive};
+
+/// The order of the bytes of a subpixel.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Endianness {
+    /// The least significant byte first, as on x86 and most ARM platforms.
+    Little,
+    /// The most significant byte first, the network byte order.
+    Big,
+}
+
+impl Endianness {
+    /// The byte order of the target platform.
+    pub fn native() -> Endianness {
+        if cfg!(target_endian = "little") {
+            Endianness::Little
+        } else {
+            Endianness::Big
+        }
+    }
+}
+
+mod sealed {
+    /// Restricts `ByteSample` to the primitive types of this crate.
+    pub trait Sealed {}
+}
+
+/// Subpixel types which can be converted to and from bytes.
+///
+/// This trait is sealed and implemented for the primitive integer and float types.
+pub trait ByteSample: Primitive + sealed::Sealed {
+    /// The number of bytes of a subpixel.
+    const SIZE: usize;
+
+    /// Writes the subpixel to the `SIZE` bytes of `bytes` in the order `endianness`.
+    fn write_bytes(self, bytes: &mut [u8], endianness: Endianness);
+
+    /// Reads a subpixel from the `SIZE` bytes of `bytes` in the order `endianness`.
+    fn read_bytes(bytes: &[u8], endianness: Endianness) -> Self;
+}
+
+macro_rules! byte_sample_impls {
+    ($($ident:ident),*) => {
+        $(
+        impl sealed::Sealed for $ident {}
+
+        impl ByteSample for $ident {
+            const SIZE: usize = mem::size_of::<$ident>();
+
+            fn write_bytes(self, bytes: &mut [u8], endianness: Endianness) {
+                bytes.copy_from_slice(&match endianness {
+                    Endianness::Little => self.to_le_bytes(),
+                    Endianness::Big => self.to_be_bytes(),
+                });
+            }
+
+            fn read_bytes(bytes: &[u8], endianness: Endianness) -> $ident {
+                let mut array = [0; mem::size_of::<$ident>()];
+                array.copy_from_slice(bytes);
+                match endianness {
+                    Endianness::Little => $ident::from_le_bytes(array),
+                    Endianness::Big => $ident::from_be_bytes(array),
+                }
+            }
+        }
+        )*
+    }
+}
+
+byte_sample_impls!(u8, u16, u32, u64, i8, i16, i32, i64, f32, f64);
+
+impl<P, Container> ImageBuffer<P, Container>
+    where P: Pixel,
+          P::Subpixel: ByteSample,
+          Container: Deref<Target = [P::Subpixel]>
+{
+    /// Returns the subpixels of the image row by row without padding as bytes in the order
+    /// `endianness`.
+    ///
+    /// The bytes are borrowed if the image is packed and `endianness` is the native byte
+    /// order, and copied otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{Endianness, ImageBuffer, color::Gray};
+    /// let image = ImageBuffer::from_raw(2, 1, vec![1u16, 0x0203]).unwrap();
+    /// let image: ImageBuffer<Gray<u16>, _> = image;
+    /// assert_eq!(&*image.as_bytes(Endianness::Big), &[0, 1, 2, 3]);
+    /// let bytes = [1, 0, 3, 2];
+    /// let decoded = ImageBuffer::<Gray<u16>, _>::from_bytes(2, 1, &bytes, Endianness::Little);
+    /// assert_eq!(decoded.unwrap().into_raw(), vec![1, 0x0203]);
+    /// ```
+    pub fn as_bytes(&self, endianness: Endianness) -> Cow<'_, [u8]> {
+        let size = P::Subpixel::SIZE;
+        if self.is_packed() && endianness == Endianness::native() {
+            let data: &[P::Subpixel] = self;
+            let data = &data[..self.logical_len()];
+            // Primitive subpixels have no padding and any alignment is valid for bytes
+            let len = mem::size_of_val(data);
+            return Cow::Borrowed(unsafe { slice::from_raw_parts(data.as_ptr() as *const u8, len) });
+        }
+        let len = self.width() as usize * self.height() as usize * P::channel_count();
+        let mut bytes = vec![0; len * size];
+        let subpixels = self.pixels().flat_map(|p| p.channels().as_ref());
+        for (chunk, &v) in bytes.chunks_exact_mut(size).zip(subpixels) {
+            v.write_bytes(chunk, endianness);
+        }
+        Cow::Owned(bytes)
+    }
+}
+
+impl<P> ImageBuffer<P, Vec<P::Subpixel>>
+    where P: Pixel,
+          P::Subpixel: ByteSample
+{
+    /// Constructs a buffer from the bytes of exactly `width * height` pixels in the order
+    /// `endianness`, row by row without padding.
+    ///
+    /// The lengths in the error are counted in subpixels, an incomplete trailing subpixel
+    /// counts as one.
+    pub fn from_bytes(width: u32,
+                      height: u32,
+                      bytes: &[u8],
+                      endianness: Endianness)
+                      -> Result<ImageBuffer<P, Vec<P::Subpixel>>, BufferError> {
+        let size = P::Subpixel::SIZE;
+        let expected = (width as usize)
+            .checked_mul(height as usize)
+            .and_then(|n| n.checked_mul(P::channel_count()))
+            .ok_or(BufferError::DimensionOverflow)?;
+        let byte_len = expected.checked_mul(size).ok_or(BufferError::DimensionOverflow)?;
+        if bytes.len() < byte_len {
+            return Err(BufferError::TooSmall {
+                expected,
+                got: bytes.len() / size,
+            });
+        }
+        if bytes.len() > byte_len {
+            return Err(BufferError::TooLarge {
+                expected,
+                got: bytes.len().div_ceil(size),
+            });
+        }
+        let data = bytes.chunks_exact(size)
+            .map(|b| ByteSample::read_bytes(b, endianness))
+            .collect();
+        ImageBuffer::from_vec(width, height, data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use buffer::{BufferError, ImageBuffer};
+    use color_model::{Gray, Rgb};
+    use super::Endianness;
+
+    #[test]
+    fn test_roundtrip() {
+        let data = vec![0.5f32, -1.0, 3.25, 0.0, 1.0, 2.0, 9.0, 9.0];
+        let image: ImageBuffer<Rgb<f32>, _> = ImageBuffer::from_raw_with_stride(2, 1, 8, data)
+            .unwrap();
+        for &endianness in &[Endianness::Little, Endianness::Big] {
+            let bytes = image.as_bytes(endianness);
+            assert_eq!(bytes.len(), 24);
+            let decoded = ImageBuffer::<Rgb<f32>, _>::from_bytes(2, 1, &bytes, endianness)
+                .unwrap();
+            assert_eq!(decoded.into_raw(), vec![0.5, -1.0, 3.25, 0.0, 1.0, 2.0]);
+        }
+        assert_eq!(&image.as_bytes(Endianness::Big)[..4], &[0x3f, 0, 0, 0]);
+
+        let packed: ImageBuffer<Gray<u32>, _> = ImageBuffer::from_raw(1, 1, vec![7u32]).unwrap();
+        assert!(match packed.as_bytes(Endianness::native()) {
+            Cow::Borrowed(bytes) => bytes.len() == 4,
+            Cow::Owned(_) => false,
+        });
+    }
+
+    #[test]
+    fn test_from_bytes_errors() {
+        let decode = |len: usize| {
+            ImageBuffer::<Gray<u16>, _>::from_bytes(2, 2, &vec![0; len], Endianness::Big).err()
+        };
+        assert_eq!(decode(7), Some(BufferError::TooSmall { expected: 4, got: 3 }));
+        assert_eq!(decode(9), Some(BufferError::TooLarge { expected: 4, got: 5 }));
+        assert_eq!(decode(8), None);
+    }
+}
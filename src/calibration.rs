@@ -0,0 +1,287 @@
+//! Calibration of raw sensor frames.
+//!
+//! Removes the fixed patterns of a sensor with calibration frames: a dark frame, taken without
+//! light, records the offset of every pixel, and a flat frame, taken of a uniformly lit target,
+//! records its gain including vignetting and dust shadows. Defective pixels, which are stuck
+//! bright or dark, are found as outliers in these frames and replaced by their neighborhood.
+
+use std::ops::{Deref, DerefMut};
+
+use buffer::ImageBuffer;
+use math::{from_f32, to_f32};
+use plane::Plane;
+use traits::{ChannelMax, Color as Pixel};
+
+/// Configuration of `ImageBuffer::flat_field_correct`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct FlatFieldConfig {
+    /// Scales the result by the mean of `flat - dark` per channel, such that the corrected
+    /// image keeps the brightness of the input. Otherwise the result is the fraction of the flat
+    /// response, where 1 maps to the maximal channel value.
+    pub normalize: bool,
+    /// Clamps float results to `[0, 1]`. Integer results are always clamped to their range.
+    pub clamp: bool,
+}
+
+impl Default for FlatFieldConfig {
+    fn default() -> FlatFieldConfig {
+        FlatFieldConfig {
+            normalize: true,
+            clamp: true,
+        }
+    }
+}
+
+impl<P, C> ImageBuffer<P, C>
+    where P: Pixel,
+          P::Subpixel: ChannelMax,
+          C: Deref<Target = [P::Subpixel]>
+{
+    /// Corrects the image with a flat and a dark frame by computing
+    /// `(image - dark) / (flat - dark)` in `f32`.
+    ///
+    /// Pixels without response, where the flat frame does not exceed the dark frame, are set to
+    /// zero. All channels including alpha are corrected.
+    ///
+    /// # Panics
+    ///
+    /// If the frames do not have the dimensions of the image.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{GrayImage, color::Gray};
+    /// use image_buffer::calibration::FlatFieldConfig;
+    /// // The right pixel receives half the light of the left one
+    /// let flat = GrayImage::from_raw(2, 1, vec![210, 110]).unwrap();
+    /// let dark = GrayImage::from_pixel(2, 1, Gray::new([10]));
+    /// let image = GrayImage::from_raw(2, 1, vec![90, 50]).unwrap();
+    /// let corrected = image.flat_field_correct(&flat, &dark, &FlatFieldConfig::default());
+    /// assert_eq!(corrected.into_raw(), vec![60, 60]);
+    /// ```
+    pub fn flat_field_correct<C1, C2>(&self,
+                                      flat: &ImageBuffer<P, C1>,
+                                      dark: &ImageBuffer<P, C2>,
+                                      config: &FlatFieldConfig)
+                                      -> ImageBuffer<P, Vec<P::Subpixel>>
+        where C1: Deref<Target = [P::Subpixel]>,
+              C2: Deref<Target = [P::Subpixel]>
+    {
+        assert!(flat.dimensions() == self.dimensions() && dark.dimensions() == self.dimensions(),
+                "the calibration frames must have the dimensions of the image");
+        let channels = P::channel_count();
+        let max = to_f32(P::Subpixel::channel_max());
+        let dark_values = Plane::from_image(dark).data;
+        let response: Vec<f32> = Plane::from_image(flat).data
+            .into_iter()
+            .zip(&dark_values)
+            .map(|(f, d)| f - d)
+            .collect();
+        let scale: Vec<f32> = (0..channels)
+            .map(|c| {
+                if !config.normalize || response.is_empty() {
+                    return max;
+                }
+                let sum: f64 = response.iter().skip(c).step_by(channels).map(|&r| r as f64).sum();
+                (sum / (response.len() / channels) as f64) as f32
+            })
+            .collect();
+        let data = Plane::from_image(self).data
+            .into_iter()
+            .zip(dark_values.iter().zip(&response))
+            .enumerate()
+            .map(|(i, (v, (d, &r)))| {
+                let corrected = if r > 0.0 { (v - d) / r * scale[i % channels] } else { 0.0 };
+                if config.clamp {
+                    from_f32(corrected.clamp(0.0, max))
+                } else {
+                    from_f32(corrected)
+                }
+            })
+            .collect();
+        ImageBuffer::from_raw(self.width(), self.height(), data).unwrap()
+    }
+}
+
+/// Returns the in-bounds 8-neighbors of `(x, y)`.
+fn neighbors(x: u32, y: u32, width: u32, height: u32) -> impl Iterator<Item = (u32, u32)> {
+    (-1i64..2)
+        .flat_map(move |dy| (-1i64..2).map(move |dx| (x as i64 + dx, y as i64 + dy)))
+        .filter(move |&(nx, ny)| {
+            (nx, ny) != (x as i64, y as i64) && nx >= 0 && ny >= 0 && nx < width as i64 &&
+            ny < height as i64
+        })
+        .map(|(nx, ny)| (nx as u32, ny as u32))
+}
+
+/// Returns the median of `values`, the mean of the middle values for an even count. The values
+/// are ordered by `f32::total_cmp`, such that NaN does not panic.
+fn median(values: &mut [f32]) -> f32 {
+    values.sort_by(f32::total_cmp);
+    let n = values.len();
+    if n % 2 == 1 {
+        values[n / 2]
+    } else {
+        (values[n / 2 - 1] + values[n / 2]) / 2.0
+    }
+}
+
+impl<P, C> ImageBuffer<P, C>
+    where P: Pixel,
+          C: Deref<Target = [P::Subpixel]>
+{
+    /// Returns the coordinates of the pixels which differ from the median of their 8-neighbors
+    /// by more than `threshold` in any channel, row by row.
+    ///
+    /// Applied to a dark frame this finds hot pixels, applied to a flat frame it also finds dead
+    /// pixels.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{GrayImage, color::Gray};
+    /// let mut dark = GrayImage::from_pixel(4, 4, Gray::new([3]));
+    /// dark[(2, 1)] = Gray::new([250]);
+    /// assert_eq!(dark.detect_defective_pixels(20), vec![(2, 1)]);
+    /// ```
+    pub fn detect_defective_pixels(&self, threshold: P::Subpixel) -> Vec<(u32, u32)> {
+        let (width, height) = self.dimensions();
+        let threshold = to_f32(threshold);
+        let mut values = Vec::with_capacity(8);
+        self.enumerate_pixels()
+            .filter(|&(x, y, pixel)| {
+                (0..P::channel_count()).any(|c| {
+                    values.clear();
+                    values.extend(neighbors(x, y, width, height)
+                        .map(|(nx, ny)| to_f32(self[(nx, ny)].as_ref().as_ref()[c])));
+                    !values.is_empty() &&
+                    (to_f32(pixel.as_ref().as_ref()[c]) - median(&mut values)).abs() > threshold
+                })
+            })
+            .map(|(x, y, _)| (x, y))
+            .collect()
+    }
+}
+
+impl<P, C> ImageBuffer<P, C>
+    where P: Pixel,
+          P::Subpixel: ChannelMax,
+          C: Deref<Target = [P::Subpixel]> + DerefMut
+{
+    /// Replaces the pixels at `defects` by the median of their 8-neighbors which are not
+    /// defective themselves, channel by channel.
+    ///
+    /// Pixels whose neighbors are all defective are left unchanged.
+    ///
+    /// # Panics
+    ///
+    /// If a defect is out of bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{GrayImage, color::Gray};
+    /// let mut image = GrayImage::from_fn(3, 3, |x, _| Gray::new([x as u8 * 10]));
+    /// image[(1, 1)] = Gray::new([255]);
+    /// image.correct_pixels(&[(1, 1)]);
+    /// assert_eq!(image[(1, 1)], Gray::new([10]));
+    /// ```
+    pub fn correct_pixels(&mut self, defects: &[(u32, u32)]) {
+        let (width, height) = self.dimensions();
+        let mut defective = defects.to_vec();
+        defective.sort_by_key(|&(x, y)| (y, x));
+        let is_defective = |p: &(u32, u32)| {
+            defective.binary_search_by_key(&(p.1, p.0), |&(x, y)| (y, x)).is_ok()
+        };
+        let mut replacements = Vec::with_capacity(defects.len());
+        let mut values = Vec::with_capacity(8);
+        for &(x, y) in defects {
+            assert!(x < width && y < height, "defect ({}, {}) out of bounds", x, y);
+            let good: Vec<(u32, u32)> = neighbors(x, y, width, height)
+                .filter(|p| !is_defective(p))
+                .collect();
+            if good.is_empty() {
+                continue;
+            }
+            let mut pixel = self[(x, y)];
+            for (c, out) in pixel.as_mut().as_mut().iter_mut().enumerate() {
+                values.clear();
+                values.extend(good.iter().map(|&p| to_f32(self[p].as_ref().as_ref()[c])));
+                *out = from_f32(median(&mut values));
+            }
+            replacements.push((x, y, pixel));
+        }
+        for (x, y, pixel) in replacements {
+            self[(x, y)] = pixel;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buffer::{ImageBuffer, RgbImage};
+    use color_model::{Gray, Rgb};
+    use super::FlatFieldConfig;
+
+    type GrayF32 = ImageBuffer<Gray<f32>, Vec<f32>>;
+
+    #[test]
+    fn test_vignetting() {
+        let gain = |x: u32, y: u32| {
+            let (dx, dy) = (x as f32 - 8.0, y as f32 - 6.0);
+            1.0 - 0.003 * (dx * dx + dy * dy)
+        };
+        let frame = |level: f32| {
+            GrayF32::from_fn(16, 12, |x, y| Gray::new([0.05 + level * gain(x, y)]))
+        };
+        let (flat, dark, image) = (frame(0.8), frame(0.0), frame(0.3));
+        let config = FlatFieldConfig {
+            normalize: false,
+            clamp: true,
+        };
+        let ratio = image.flat_field_correct(&flat, &dark, &config);
+        assert!(ratio.pixels().all(|p| (p[0] - 0.375).abs() < 1e-5));
+
+        let normalized = image.flat_field_correct(&flat, &dark, &FlatFieldConfig::default());
+        let mean_gain = flat.pixels().map(|p| p[0] - 0.05).sum::<f32>() / (16 * 12) as f32;
+        assert!(normalized.pixels().all(|p| (p[0] - 0.375 * mean_gain).abs() < 1e-5));
+    }
+
+    #[test]
+    fn test_clamping() {
+        let flat = RgbImage::from_raw(2, 1, vec![100, 100, 100, 50, 10, 50]).unwrap();
+        let dark = RgbImage::from_pixel(2, 1, Rgb::new([10, 10, 10]));
+        let image = RgbImage::from_raw(2, 1, vec![5, 100, 55, 30, 30, 255]).unwrap();
+        let corrected = image.flat_field_correct(&flat, &dark, &FlatFieldConfig::default());
+        // The channel means of the response are 65, 45 and 65
+        assert_eq!(corrected.into_raw(), vec![0, 45, 33, 33, 0, 255]);
+
+        let flat = GrayF32::from_pixel(1, 1, Gray::new([0.5]));
+        let image = GrayF32::from_pixel(1, 1, Gray::new([0.75]));
+        let dark = GrayF32::new(1, 1);
+        let config = FlatFieldConfig {
+            normalize: false,
+            clamp: false,
+        };
+        assert_eq!(image.flat_field_correct(&flat, &dark, &config)[(0, 0)], Gray::new([1.5]));
+        let config = FlatFieldConfig { clamp: true, ..config };
+        assert_eq!(image.flat_field_correct(&flat, &dark, &config)[(0, 0)], Gray::new([1.0]));
+    }
+
+    #[test]
+    fn test_defective_pixels() {
+        let mut frame = RgbImage::from_fn(6, 5, |x, y| Rgb::new([(x + y) as u8, 10, 20]));
+        frame[(0, 0)] = Rgb::new([0, 200, 20]);
+        frame[(3, 2)] = Rgb::new([5, 10, 0]);
+        frame[(4, 2)] = Rgb::new([255, 255, 255]);
+        let defects = frame.detect_defective_pixels(12);
+        assert_eq!(defects, vec![(0, 0), (3, 2), (4, 2)]);
+
+        frame.correct_pixels(&defects);
+        assert_eq!(frame[(0, 0)], Rgb::new([1, 10, 20]));
+        // Neighbors (2..=5, 1..=3) without the other defect
+        assert_eq!(frame[(3, 2)], Rgb::new([5, 10, 20]));
+        assert_eq!(frame[(4, 2)], Rgb::new([6, 10, 20]));
+        assert!(frame.detect_defective_pixels(12).is_empty());
+    }
+}
@@ -1,84 +1,86 @@
-use std::mem;
+use std::any::TypeId;
+use std::convert::TryFrom;
 use std::ops::{Index, IndexMut};
 use num_traits::Zero;
 
-use traits::{Color, ColorMathOps, ChannelMax};
-
-macro_rules! implement_alpha {
-    {$(
-        $ident: ident,
-        $CHANNELS: expr;
-    )*} => {
-$( // START Structure definitions
+use traits::{AlphaColor, Color, ColorMathOps, ChannelMax, PodColor};
+use math::{from_f32, to_f32};
+use super::{AlphaMode, PixelLayout};
 
 /// Color with an associated alpha value.
-#[derive(Copy, Clone)]
-pub struct $ident<C: Color>([C::Subpixel; $CHANNELS]);
-
-impl<C: Color> $ident<C> {
-    pub fn new(array: [C::Subpixel; $CHANNELS]) -> Self {
-        $ident(array)
+///
+/// `N` is the number of channels including the alpha channel, it has to be one more than the
+/// number of channels of `C`.
+#[derive(PartialEq, Eq, Clone, Debug, Copy, Hash)]
+#[repr(transparent)]
+pub struct Alpha<C: Color, const N: usize>([C::Subpixel; N]);
+
+impl<C: Color, const N: usize> Alpha<C, N> {
+    /// Fails to compile if `N` does not match the number of channels of `C`.
+    const VALID: () = assert!(N == C::NUM_CHANNELS + 1, "invalid number of channels");
+
+    pub fn new(array: [C::Subpixel; N]) -> Self {
+        let () = Self::VALID;
+        Alpha(array)
     }
 }
 
-impl<C: Color> AsRef<<$ident<C> as Color>::Storage> for $ident<C> {
+impl<C: Color, const N: usize> AsRef<<Alpha<C, N> as Color>::Storage> for Alpha<C, N> {
     fn as_ref(&self) -> &<Self as Color>::Storage {
         &self.0
     }
 }
 
-impl<C: Color> AsMut<<$ident<C> as Color>::Storage> for $ident<C> {
+impl<C: Color, const N: usize> AsMut<<Alpha<C, N> as Color>::Storage> for Alpha<C, N> {
     fn as_mut(&mut self) -> &mut <Self as Color>::Storage {
         &mut self.0
     }
 }
 
-impl<C: Color> Color for $ident<C> {
+// A `repr(transparent)` wrapper around the array of channels
+unsafe impl<C: Color, const N: usize> PodColor for Alpha<C, N> {}
+
+impl<C: Color, const N: usize> Color for Alpha<C, N> {
     type Subpixel = C::Subpixel;
-    type Storage = [C::Subpixel; $CHANNELS];
+    type Storage = [C::Subpixel; N];
 
-    /// Returns the number of channels of this pixel type.
-    fn channel_count() -> usize {
-        $CHANNELS
-    }
+    const NUM_CHANNELS: usize = N;
 
     #[inline(always)]
-    fn channels(&self) -> &[Self::Subpixel; $CHANNELS] {
+    fn channels(&self) -> &[Self::Subpixel; N] {
         &self.0
     }
 
     #[inline(always)]
-    fn channels_mut(&mut self) -> &mut [Self::Subpixel; $CHANNELS] {
+    fn channels_mut(&mut self) -> &mut [Self::Subpixel; N] {
         &mut self.0
     }
 
-    fn from_channels(other: [Self::Subpixel; $CHANNELS]) -> Self {
-        *<Self as Color>::from_slice(&other[..$CHANNELS])
-    }
-
     fn from_slice<'a>(slice: &'a [Self::Subpixel]) -> &'a Self {
-        unsafe {
-            assert_eq!(slice.len(), $CHANNELS);
-            mem::transmute(slice.as_ptr())
-        }
+        let () = Self::VALID;
+        let array: &[Self::Subpixel; N] = TryFrom::try_from(slice)
+            .unwrap_or_else(|_| panic!("expected {} channels, got {}", N, slice.len()));
+        // The color is a transparent wrapper of the array
+        unsafe { &*(array as *const [Self::Subpixel; N] as *const Self) }
     }
 
     fn from_slice_mut<'a>(slice: &'a mut [Self::Subpixel]) -> &'a mut Self {
-        unsafe {
-            assert_eq!(slice.len(), $CHANNELS);
-            mem::transmute(slice.as_ptr())
-        }
+        let () = Self::VALID;
+        let len = slice.len();
+        let array: &mut [Self::Subpixel; N] = TryFrom::try_from(slice)
+            .unwrap_or_else(|_| panic!("expected {} channels, got {}", N, len));
+        unsafe { &mut *(array as *mut [Self::Subpixel; N] as *mut Self) }
     }
 
     fn apply_with_alpha<F, G>(&mut self, f: F, g: G)
         where F: Fn(Self::Subpixel) -> Self::Subpixel,
               G: Fn(Self::Subpixel) -> Self::Subpixel
     {
-        for v in self.0[..$CHANNELS as usize - 1 as usize].iter_mut() {
+        for v in self.0[..N - 1].iter_mut() {
             *v = f(*v)
         }
-        if $CHANNELS as usize != 0 {
-            let v = &mut self.0[$CHANNELS - 1];
+        if N != 0 {
+            let v = &mut self.0[N - 1];
             *v = g(*v)
         }
     }
@@ -86,11 +88,42 @@ impl<C: Color> Color for $ident<C> {
     fn color_model() -> &'static str {
         C::color_model()
     }
+
+    fn color_model_id() -> TypeId {
+        C::color_model_id()
+    }
+
+    fn layout() -> PixelLayout {
+        PixelLayout::canonical(N, AlphaMode::Straight)
+    }
 }
 
+impl<C: Color, const N: usize> AlphaColor for Alpha<C, N>
+    where C::Subpixel: ChannelMax
+{
+    type Opaque = C;
+
+    fn flatten(&self, mut background: C) -> C {
+        let alpha = to_f32(self.0[N - 1]) / to_f32(C::Subpixel::channel_max());
+        for (b, &c) in background.as_mut().as_mut().iter_mut().zip(self.0.iter()) {
+            let (bf, cf) = (to_f32(*b), to_f32(c));
+            *b = from_f32(bf + (cf - bf) * alpha);
+        }
+        background
+    }
 
+    fn from_opaque(color: C, alpha: C::Subpixel) -> Self {
+        let mut storage = [alpha; N];
+        storage[..N - 1].copy_from_slice(color.as_ref().as_ref());
+        Alpha::new(storage)
+    }
 
-impl<C: Color> Index<usize> for $ident<C> {
+    fn without_alpha(&self) -> C {
+        *C::from_slice(&self.0[..N - 1])
+    }
+}
+
+impl<C: Color, const N: usize> Index<usize> for Alpha<C, N> {
     type Output = C::Subpixel;
     #[inline(always)]
     fn index<'a>(&'a self, _index: usize) -> &'a C::Subpixel {
@@ -98,45 +131,48 @@ impl<C: Color> Index<usize> for $ident<C> {
     }
 }
 
-impl<C: Color> IndexMut<usize> for $ident<C> {
+impl<C: Color, const N: usize> IndexMut<usize> for Alpha<C, N> {
     #[inline(always)]
     fn index_mut<'a>(&'a mut self, _index: usize) -> &'a mut C::Subpixel {
         &mut self.0[_index]
     }
 }
 
-impl<C: Color> ColorMathOps<$ident<C>> for $ident<C> {
+impl<C: Color, const N: usize> ColorMathOps<Alpha<C, N>> for Alpha<C, N> {
     #[inline(always)]
     fn add(mut self, rhs: Self) -> Self {
-        for i in 0..$CHANNELS {
+        for i in 0..N {
             self.0[i] = self.0[i] + rhs.0[i]
         }
         self
     }
     #[inline(always)]
     fn sub(mut self, rhs: Self) -> Self {
-        for i in 0..$CHANNELS {
+        for i in 0..N {
             self.0[i] = self.0[i] - rhs.0[i]
         }
         self
     }
     #[inline(always)]
     fn div(mut self, rhs: Self) -> Self {
-        for i in 0..$CHANNELS {
+        for i in 0..N {
             self.0[i] = self.0[i] / rhs.0[i]
         }
         self
     }
     #[inline(always)]
     fn mul(mut self, rhs: Self) -> Self {
-        for i in 0..$CHANNELS {
+        for i in 0..N {
             self.0[i] = self.0[i] * rhs.0[i]
         }
         self
     }
 }
 
-impl<C: Color, T: ColorMathOps<$ident<C>>> ::std::ops::Add<T> for $ident<C> {
+impl<C, T, const N: usize> ::std::ops::Add<T> for Alpha<C, N>
+    where C: Color,
+          T: ColorMathOps<Alpha<C, N>>
+{
     type Output = Self;
     #[inline]
     fn add(self, rhs: T) -> Self::Output {
@@ -144,85 +180,300 @@ impl<C: Color, T: ColorMathOps<$ident<C>>> ::std::ops::Add<T> for $ident<C> {
     }
 }
 
-impl<C: Color> ::std::ops::AddAssign for $ident<C> {
+impl<C: Color, const N: usize> ::std::ops::AddAssign for Alpha<C, N> {
     #[inline]
     fn add_assign(&mut self, rhs: Self) {
         *self = *self + rhs;
     }
 }
 
-impl<C: Color> ::std::ops::Sub for $ident<C> {
+impl<C: Color, const N: usize> ::std::ops::Sub for Alpha<C, N> {
     type Output = Self;
     #[inline]
     fn sub(mut self, rhs: Self) -> Self::Output {
-        for i in 0..$CHANNELS {
+        for i in 0..N {
             self.0[i] = self.0[i] - rhs.0[i]
         }
         self
     }
 }
 
-impl<C: Color> ::std::ops::SubAssign for $ident<C> {
+impl<C: Color, const N: usize> ::std::ops::SubAssign for Alpha<C, N> {
     #[inline]
     fn sub_assign(&mut self, rhs: Self) {
         *self = *self - rhs;
     }
 }
 
-impl<C: Color> ::std::ops::Div for $ident<C> {
+impl<C: Color, const N: usize> ::std::ops::Div for Alpha<C, N> {
     type Output = Self;
     #[inline]
     fn div(mut self, rhs: Self) -> Self::Output {
-        for i in 0..$CHANNELS {
+        for i in 0..N {
             self.0[i] = self.0[i] / rhs.0[i]
         }
         self
     }
 }
 
-impl<C: Color> ::std::ops::DivAssign for $ident<C> {
+impl<C: Color, const N: usize> ::std::ops::DivAssign for Alpha<C, N> {
     #[inline]
     fn div_assign(&mut self, rhs: Self) {
         *self = *self / rhs;
     }
 }
 
-impl<C: Color> ::std::ops::Mul for $ident<C> {
+impl<C: Color, const N: usize> ::std::ops::Mul for Alpha<C, N> {
     type Output = Self;
     #[inline]
     fn mul(mut self, rhs: Self) -> Self::Output {
-        for i in 0..$CHANNELS {
+        for i in 0..N {
             self.0[i] = self.0[i] * rhs.0[i]
         }
         self
     }
 }
 
-impl<C: Color> ::std::ops::MulAssign for $ident<C> {
+impl<C: Color, const N: usize> ::std::ops::MulAssign for Alpha<C, N> {
     #[inline]
     fn mul_assign(&mut self, rhs: Self) {
         *self = *self * rhs;
     }
 }
 
-impl<C: Color> From<C> for $ident<C>
+impl<C: Color, const N: usize> From<C> for Alpha<C, N>
     where C::Subpixel: ChannelMax
 {
     fn from(other: C) -> Self {
-        let mut storage = [Zero::zero(); $CHANNELS];
+        let mut storage = [Zero::zero(); N];
         storage.as_mut()[..C::channel_count()].copy_from_slice(other.as_ref().as_ref());
-        storage[$CHANNELS - 1] = ChannelMax::channel_max();
-        $ident(storage)
+        storage[N - 1] = ChannelMax::channel_max();
+        Alpha::new(storage)
+    }
+}
+
+/// Color with an associated premultiplied alpha value.
+///
+/// The color channels are already multiplied by the alpha value, which is the representation
+/// expected by most GPU blending pipelines. Convert from and to `Alpha` with `From` or
+/// `Alpha::premultiply` and `PremultipliedAlpha::unpremultiply`.
+#[derive(PartialEq, Eq, Clone, Debug, Copy, Hash)]
+#[repr(transparent)]
+pub struct PremultipliedAlpha<C: Color, const N: usize>([C::Subpixel; N]);
+
+impl<C: Color, const N: usize> PremultipliedAlpha<C, N> {
+    pub fn new(array: [C::Subpixel; N]) -> Self {
+        let () = Alpha::<C, N>::VALID;
+        PremultipliedAlpha(array)
     }
 }
 
-)* // END Structure definitions
+impl<C: Color, const N: usize> AsRef<[C::Subpixel; N]> for PremultipliedAlpha<C, N> {
+    fn as_ref(&self) -> &[C::Subpixel; N] {
+        &self.0
+    }
+}
 
+impl<C: Color, const N: usize> AsMut<[C::Subpixel; N]> for PremultipliedAlpha<C, N> {
+    fn as_mut(&mut self) -> &mut [C::Subpixel; N] {
+        &mut self.0
     }
 }
 
-implement_alpha!(
-    Alpha2, 2;
-    Alpha3, 3;
-    Alpha4, 4;
-);
+// A `repr(transparent)` wrapper around the array of channels
+unsafe impl<C: Color, const N: usize> PodColor for PremultipliedAlpha<C, N> {}
+
+impl<C: Color, const N: usize> Color for PremultipliedAlpha<C, N> {
+    type Subpixel = C::Subpixel;
+    type Storage = [C::Subpixel; N];
+
+    const NUM_CHANNELS: usize = N;
+
+    #[inline(always)]
+    fn channels(&self) -> &[Self::Subpixel; N] {
+        &self.0
+    }
+
+    #[inline(always)]
+    fn channels_mut(&mut self) -> &mut [Self::Subpixel; N] {
+        &mut self.0
+    }
+
+    fn from_slice<'a>(slice: &'a [Self::Subpixel]) -> &'a Self {
+        let () = Alpha::<C, N>::VALID;
+        let array: &[Self::Subpixel; N] = TryFrom::try_from(slice)
+            .unwrap_or_else(|_| panic!("expected {} channels, got {}", N, slice.len()));
+        // The color is a transparent wrapper of the array
+        unsafe { &*(array as *const [Self::Subpixel; N] as *const Self) }
+    }
+
+    fn from_slice_mut<'a>(slice: &'a mut [Self::Subpixel]) -> &'a mut Self {
+        let () = Alpha::<C, N>::VALID;
+        let len = slice.len();
+        let array: &mut [Self::Subpixel; N] = TryFrom::try_from(slice)
+            .unwrap_or_else(|_| panic!("expected {} channels, got {}", N, len));
+        unsafe { &mut *(array as *mut [Self::Subpixel; N] as *mut Self) }
+    }
+
+    fn apply_with_alpha<F, G>(&mut self, f: F, g: G)
+        where F: Fn(Self::Subpixel) -> Self::Subpixel,
+              G: Fn(Self::Subpixel) -> Self::Subpixel
+    {
+        for v in self.0[..N - 1].iter_mut() {
+            *v = f(*v)
+        }
+        let v = &mut self.0[N - 1];
+        *v = g(*v)
+    }
+
+    fn color_model() -> &'static str {
+        C::color_model()
+    }
+
+    fn color_model_id() -> TypeId {
+        C::color_model_id()
+    }
+
+    fn layout() -> PixelLayout {
+        PixelLayout::canonical(N, AlphaMode::Premultiplied)
+    }
+}
+
+impl<C: Color, const N: usize> Index<usize> for PremultipliedAlpha<C, N> {
+    type Output = C::Subpixel;
+    #[inline(always)]
+    fn index(&self, index: usize) -> &C::Subpixel {
+        &self.0[index]
+    }
+}
+
+impl<C: Color, const N: usize> IndexMut<usize> for PremultipliedAlpha<C, N> {
+    #[inline(always)]
+    fn index_mut(&mut self, index: usize) -> &mut C::Subpixel {
+        &mut self.0[index]
+    }
+}
+
+impl<C: Color, const N: usize> Alpha<C, N>
+    where C::Subpixel: ChannelMax
+{
+    /// Multiplies the color channels by the alpha value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::color::{PremultipliedAlpha, Rgba};
+    /// let color = Rgba::new([255u8, 100, 0, 128]);
+    /// assert_eq!(color.premultiply(), PremultipliedAlpha::new([128, 50, 0, 128]));
+    /// ```
+    pub fn premultiply(&self) -> PremultipliedAlpha<C, N> {
+        let alpha = to_f32(self.0[N - 1]) / to_f32(C::Subpixel::channel_max());
+        let mut result = PremultipliedAlpha::new(self.0);
+        for v in result.0[..N - 1].iter_mut() {
+            *v = from_f32(to_f32(*v) * alpha);
+        }
+        result
+    }
+}
+
+impl<C: Color, const N: usize> PremultipliedAlpha<C, N>
+    where C::Subpixel: ChannelMax
+{
+    /// Divides the color channels by the alpha value.
+    ///
+    /// Fully transparent colors become transparent black. Integer colors lose precision for
+    /// small alpha values.
+    pub fn unpremultiply(&self) -> Alpha<C, N> {
+        let max = to_f32(C::Subpixel::channel_max());
+        let alpha = to_f32(self.0[N - 1]) / max;
+        let mut result = Alpha::new(self.0);
+        for v in result.0[..N - 1].iter_mut() {
+            *v = if alpha > 0.0 {
+                from_f32((to_f32(*v) / alpha).min(max))
+            } else {
+                Zero::zero()
+            };
+        }
+        result
+    }
+}
+
+impl<C: Color, const N: usize> From<Alpha<C, N>> for PremultipliedAlpha<C, N>
+    where C::Subpixel: ChannelMax
+{
+    fn from(other: Alpha<C, N>) -> Self {
+        other.premultiply()
+    }
+}
+
+impl<C: Color, const N: usize> From<PremultipliedAlpha<C, N>> for Alpha<C, N>
+    where C::Subpixel: ChannelMax
+{
+    fn from(other: PremultipliedAlpha<C, N>) -> Self {
+        other.unpremultiply()
+    }
+}
+
+/// Color with two channels including alpha.
+pub type Alpha2<C> = Alpha<C, 2>;
+/// Color with three channels including alpha.
+pub type Alpha3<C> = Alpha<C, 3>;
+/// Color with four channels including alpha.
+pub type Alpha4<C> = Alpha<C, 4>;
+/// Color with five channels including alpha.
+pub type Alpha5<C> = Alpha<C, 5>;
+
+#[cfg(test)]
+mod tests {
+    use traits::{AlphaColor, Color};
+    use super::super::{Gray, GrayA, PremultipliedAlpha, PremultipliedRgba, Rgb, Rgba};
+
+    #[test]
+    fn test_premultiply_round_trip() {
+        let color = Rgba::new([200u8, 100, 50, 255]);
+        assert_eq!(color.premultiply().unpremultiply(), color);
+        let color = Rgba::new([1.0f32, 0.5, 0.25, 0.5]);
+        assert_eq!(color.premultiply(), PremultipliedAlpha::new([0.5, 0.25, 0.125, 0.5]));
+        assert_eq!(color.premultiply().unpremultiply(), color);
+        let transparent = GrayA::new([200u8, 0]);
+        assert_eq!(transparent.premultiply().unpremultiply(), GrayA::new([0, 0]));
+    }
+
+    #[test]
+    fn test_try_from_slice() {
+        let mut data = [1u8, 2, 3, 4, 5];
+        assert_eq!(Rgba::try_from_slice(&data[..4]), Some(&Rgba::new([1, 2, 3, 4])));
+        assert_eq!(Rgba::try_from_slice(&data[1..]), Some(&Rgba::new([2, 3, 4, 5])));
+        assert_eq!(Rgba::try_from_slice(&data), None);
+        assert_eq!(Rgb::try_from_slice(&data[..2]), None);
+        Rgb::try_from_slice_mut(&mut data[2..]).unwrap()[0] = 9;
+        PremultipliedAlpha::<Rgb<u8>, 4>::from_slice_mut(&mut data[1..])[3] = 0;
+        assert_eq!(data, [1, 2, 9, 4, 0]);
+    }
+
+    #[test]
+    fn test_alpha_accessors() {
+        let mut color = Rgb::new([1u8, 2, 3]).with_alpha(4);
+        assert_eq!(color, Rgba::new([1, 2, 3, 4]));
+        assert_eq!(color.without_alpha(), Rgb::new([1, 2, 3]));
+        assert_eq!(Rgba::from_opaque(Rgb::new([1u8, 2, 3]), 9), Rgba::new([1, 2, 3, 9]));
+        color.set_alpha(200);
+        assert_eq!(color.alpha(), 200);
+        assert!(Rgba::<u8>::has_alpha() && !Rgb::<u8>::has_alpha());
+        assert_eq!(PremultipliedRgba::<f32>::alpha_position(), Some(3));
+
+        let mut gray = Gray::new([0.25f32]);
+        gray.set_alpha(0.5);
+        assert_eq!((gray, gray.alpha()), (Gray::new([0.25]), 1.0));
+        assert_eq!(gray.with_alpha(0.5), GrayA::new([0.25, 0.5]));
+    }
+
+    #[test]
+    fn test_scale_by() {
+        assert_eq!(Rgba::new([100u8, 3, 255, 128]).scale_by(0.5), Rgba::new([50, 2, 128, 128]));
+        assert_eq!(Gray::new([1000u16]).scale_by(-2.0), Gray::new([0]));
+        assert_eq!(Gray::new([7u8]).scale_by(f32::NAN), Gray::new([0]));
+        assert_eq!(Gray::new([u64::MAX]).scale_by(2.0), Gray::new([u64::MAX]));
+        assert_eq!(GrayA::new([0.5f32, 1.0]).scale_by(4.0), GrayA::new([2.0, 1.0]));
+    }
+}
@@ -1,18 +1,29 @@
-use super::{Rgb, Xyz};
+use super::{Lab, Rgb, Xyz};
+use super::rgb::srgb_compress_gamma_rounded;
+use super::space::{apply, chromaticity_to_xyz, srgb_to_xyz_matrix, ChromaticAdaptation, D65};
+
+/// Threshold of the linear segment of the Lab companding function.
+const LAB_EPSILON: f32 = 216.0 / 24389.0;
+
+/// Slope of the linear segment of the Lab companding function.
+const LAB_KAPPA: f32 = 24389.0 / 27.0;
 
 /// Converts sRGB to the X component of CIE 1931.
 pub fn rgb_to_x(r: f32, g: f32, b: f32) -> f32 {
-    0.4124 * r + 0.3576 * g + 0.1805 * b
+    let m = srgb_to_xyz_matrix();
+    m[0][0] * r + m[0][1] * g + m[0][2] * b
 }
 
 /// Converts sRGB to the Y component of CIE 1931.
 pub fn rgb_to_y(r: f32, g: f32, b: f32) -> f32 {
-    0.2126 * r + 0.7152 * g + 0.0722 * b
+    let m = srgb_to_xyz_matrix();
+    m[1][0] * r + m[1][1] * g + m[1][2] * b
 }
 
 /// Converts sRGB to the Z component of CIE 1931.
 pub fn rgb_to_z(r: f32, g: f32, b: f32) -> f32 {
-    0.0193 * r + 0.1192 * g + 0.9505 * b
+    let m = srgb_to_xyz_matrix();
+    m[2][0] * r + m[2][1] * g + m[2][2] * b
 }
 
 impl From<Rgb<f32>> for Xyz<f32> {
@@ -23,3 +34,247 @@ impl From<Rgb<f32>> for Xyz<f32> {
         Xyz([rgb_to_x(r, g, b), rgb_to_y(r, g, b), rgb_to_z(r, g, b)])
     }
 }
+
+impl Xyz<f32> {
+    /// Adapts this color seen under the white point `from` to the white point `to` using the
+    /// chromatic adaptation transform `cat`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::color::{ChromaticAdaptation, Xyz, D50, D65};
+    /// let white = Xyz::new([0.9505, 1.0, 1.0890]);
+    /// let adapted = white.adapt(D65, D50, ChromaticAdaptation::Bradford);
+    /// assert!((adapted[0] - 0.9642).abs() < 1e-3 && (adapted[2] - 0.8251).abs() < 1e-3);
+    /// ```
+    pub fn adapt(&self, from: [f32; 2], to: [f32; 2], cat: ChromaticAdaptation) -> Xyz<f32> {
+        let m = cat.adaptation(from, to);
+        let v = apply(&m, [self.0[0] as f64, self.0[1] as f64, self.0[2] as f64]);
+        Xyz([v[0] as f32, v[1] as f32, v[2] as f32])
+    }
+}
+
+impl Lab<f32> {
+    /// Converts `xyz` to CIE L*a*b* relative to the reference `white`.
+    ///
+    /// The reference white is expected to have `Y = 1`. Use `Xyz::adapt` first if the color
+    /// was measured under a different white point, for example to get the D50 Lab values used
+    /// by ICC profiles from sRGB colors.
+    pub fn from_xyz(xyz: Xyz<f32>, white: [f32; 2]) -> Lab<f32> {
+        let w = chromaticity_to_xyz(white);
+        let f = |t: f32| if t > LAB_EPSILON {
+            t.cbrt()
+        } else {
+            (LAB_KAPPA * t + 16.0) / 116.0
+        };
+        let fx = f(xyz.0[0] / w[0] as f32);
+        let fy = f(xyz.0[1] / w[1] as f32);
+        let fz = f(xyz.0[2] / w[2] as f32);
+        Lab([116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)])
+    }
+
+    /// Converts this color to CIE XYZ given the reference `white` it is relative to.
+    pub fn to_xyz(&self, white: [f32; 2]) -> Xyz<f32> {
+        let w = chromaticity_to_xyz(white);
+        let fy = (self.0[0] + 16.0) / 116.0;
+        let fx = fy + self.0[1] / 500.0;
+        let fz = fy - self.0[2] / 200.0;
+        let f_inv = |f: f32| if f * f * f > LAB_EPSILON {
+            f * f * f
+        } else {
+            (116.0 * f - 16.0) / LAB_KAPPA
+        };
+        let y = if self.0[0] > LAB_KAPPA * LAB_EPSILON {
+            fy * fy * fy
+        } else {
+            self.0[0] / LAB_KAPPA
+        };
+        Xyz([f_inv(fx) * w[0] as f32, y * w[1] as f32, f_inv(fz) * w[2] as f32])
+    }
+}
+
+// From for Lab, relative to the D65 white point of sRGB
+
+impl From<Xyz<f32>> for Lab<f32> {
+    fn from(other: Xyz<f32>) -> Self {
+        Lab::from_xyz(other, D65)
+    }
+}
+
+impl From<Lab<f32>> for Xyz<f32> {
+    fn from(other: Lab<f32>) -> Self {
+        other.to_xyz(D65)
+    }
+}
+
+impl From<Rgb<f32>> for Lab<f32> {
+    fn from(other: Rgb<f32>) -> Self {
+        Xyz::from(other).into()
+    }
+}
+
+impl From<Rgb<u8>> for Lab<f32> {
+    fn from(other: Rgb<u8>) -> Self {
+        Rgb::<f32>::from(other).into()
+    }
+}
+
+impl From<Rgb<u16>> for Lab<f32> {
+    fn from(other: Rgb<u16>) -> Self {
+        Rgb::<f32>::from(other).into()
+    }
+}
+
+impl From<Lab<f32>> for Rgb<f32> {
+    fn from(other: Lab<f32>) -> Self {
+        Xyz::from(other).into()
+    }
+}
+
+impl From<Lab<f32>> for Rgb<u8> {
+    fn from(other: Lab<f32>) -> Self {
+        // Rounds such that encoded colors survive a roundtrip through Lab
+        let rgb = Rgb::<f32>::from(other).0;
+        Rgb(rgb.map(srgb_compress_gamma_rounded))
+    }
+}
+
+impl From<Lab<f32>> for Rgb<u16> {
+    fn from(other: Lab<f32>) -> Self {
+        // Rounds such that encoded colors survive a roundtrip through Lab
+        let rgb = Rgb::<f32>::from(other).0;
+        Rgb(rgb.map(srgb_compress_gamma_rounded))
+    }
+}
+
+/// Returns the CIE 1976 color difference, the euclidean distance of `a` and `b`.
+///
+/// A difference of about 2.3 is just noticeable.
+pub fn delta_e_76(a: Lab<f32>, b: Lab<f32>) -> f32 {
+    let (dl, da, db) = (a.0[0] - b.0[0], a.0[1] - b.0[1], a.0[2] - b.0[2]);
+    (dl * dl + da * da + db * db).sqrt()
+}
+
+/// Returns the CIEDE2000 color difference of `a` and `b`.
+///
+/// Corrects the perceptual non-uniformity of `delta_e_76`, mostly in the blue and the near
+/// neutral colors. Uses the weighting factors `k_L = k_C = k_H = 1`.
+///
+/// # Examples
+///
+/// ```
+/// # use image_buffer::color::{delta_e_2000, Lab};
+/// let a = Lab::new([50.0, 2.6772, -79.7751]);
+/// let b = Lab::new([50.0, 0.0, -82.7485]);
+/// assert!((delta_e_2000(a, b) - 2.0425).abs() < 1e-4);
+/// ```
+pub fn delta_e_2000(a: Lab<f32>, b: Lab<f32>) -> f32 {
+    let (l1, a1, b1) = (a.0[0] as f64, a.0[1] as f64, a.0[2] as f64);
+    let (l2, a2, b2) = (b.0[0] as f64, b.0[1] as f64, b.0[2] as f64);
+    let pow7 = |v: f64| v.powi(7);
+
+    let c_mean = (a1.hypot(b1) + a2.hypot(b2)) / 2.0;
+    let g = 0.5 * (1.0 - (pow7(c_mean) / (pow7(c_mean) + pow7(25.0))).sqrt());
+    let (a1, a2) = (a1 * (1.0 + g), a2 * (1.0 + g));
+    let (c1, c2) = (a1.hypot(b1), a2.hypot(b2));
+    let hue = |b: f64, a: f64| if a == 0.0 && b == 0.0 {
+        0.0
+    } else {
+        b.atan2(a).to_degrees().rem_euclid(360.0)
+    };
+    let (h1, h2) = (hue(b1, a1), hue(b2, a2));
+
+    let dl = l2 - l1;
+    let dc = c2 - c1;
+    let dh = if c1 * c2 == 0.0 {
+        0.0
+    } else if (h2 - h1).abs() <= 180.0 {
+        h2 - h1
+    } else if h2 <= h1 {
+        h2 - h1 + 360.0
+    } else {
+        h2 - h1 - 360.0
+    };
+    let dh = 2.0 * (c1 * c2).sqrt() * (dh / 2.0).to_radians().sin();
+
+    let l_mean = (l1 + l2) / 2.0;
+    let c_mean = (c1 + c2) / 2.0;
+    let h_mean = if c1 * c2 == 0.0 {
+        h1 + h2
+    } else if (h1 - h2).abs() <= 180.0 {
+        (h1 + h2) / 2.0
+    } else if h1 + h2 < 360.0 {
+        (h1 + h2 + 360.0) / 2.0
+    } else {
+        (h1 + h2 - 360.0) / 2.0
+    };
+    let t = 1.0 - 0.17 * (h_mean - 30.0).to_radians().cos() +
+            0.24 * (2.0 * h_mean).to_radians().cos() +
+            0.32 * (3.0 * h_mean + 6.0).to_radians().cos() -
+            0.20 * (4.0 * h_mean - 63.0).to_radians().cos();
+    let d_theta = 30.0 * (-((h_mean - 275.0) / 25.0).powi(2)).exp();
+    let r_c = 2.0 * (pow7(c_mean) / (pow7(c_mean) + pow7(25.0))).sqrt();
+    let s_l = 1.0 + 0.015 * (l_mean - 50.0).powi(2) / (20.0 + (l_mean - 50.0).powi(2)).sqrt();
+    let s_c = 1.0 + 0.045 * c_mean;
+    let s_h = 1.0 + 0.015 * c_mean * t;
+    let r_t = -(2.0 * d_theta).to_radians().sin() * r_c;
+
+    let (l, c, h) = (dl / s_l, dc / s_c, dh / s_h);
+    (l * l + c * c + h * h + r_t * c * h).sqrt() as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{delta_e_2000, delta_e_76, ChromaticAdaptation, Lab, Rgb, Xyz, D50, D65};
+
+    #[test]
+    fn test_lab_white() {
+        let white = Xyz::new([0.95047, 1.0, 1.08883]);
+        let lab = Lab::from_xyz(white, D65);
+        assert!((lab[0] - 100.0).abs() < 1e-3 && lab[1].abs() < 0.05 && lab[2].abs() < 0.05);
+        // Assuming the wrong reference white gives a visible color cast
+        assert!(Lab::from_xyz(white, D50)[2] < -15.0);
+        let adapted = white.adapt(D65, D50, ChromaticAdaptation::Bradford);
+        assert!(Lab::from_xyz(adapted, D50)[2].abs() < 0.05);
+    }
+
+    #[test]
+    fn test_lab_round_trip() {
+        for &xyz in &[[0.2, 0.3, 0.4], [0.001, 0.002, 0.003], [0.9, 0.8, 0.1]] {
+            let back = Lab::from_xyz(Xyz::new(xyz), D50).to_xyz(D50);
+            for i in 0..3 {
+                assert!((back[i] - xyz[i]).abs() < 1e-5, "{:?} {:?}", back, xyz);
+            }
+        }
+    }
+
+    #[test]
+    fn test_rgb_lab() {
+        let lab = Lab::from(Rgb::new([255u8, 0, 0]));
+        assert!((lab[0] - 53.24).abs() < 0.1 && (lab[1] - 80.09).abs() < 0.2 &&
+                (lab[2] - 67.20).abs() < 0.2,
+                "{:?}",
+                lab);
+        for &rgb in &[[255u8, 0, 0], [12, 200, 99], [128, 128, 128], [0, 0, 0]] {
+            assert_eq!(Rgb::<u8>::from(Lab::from(Rgb::new(rgb))), Rgb::new(rgb));
+        }
+    }
+
+    #[test]
+    fn test_delta_e() {
+        let a = Lab::new([50.0, 0.0, 0.0]);
+        assert_eq!(delta_e_76(a, Lab::new([53.0, 4.0, 0.0])), 5.0);
+        // Reference data of Sharma, Wu and Dalal
+        let pairs = [([50.0, 2.6772, -79.7751], [50.0, 0.0, -82.7485], 2.0425),
+                     ([50.0, 0.0, 0.0], [50.0, -1.0, 2.0], 2.3669),
+                     ([50.0, 2.5, 0.0], [73.0, 25.0, -18.0], 27.1492),
+                     ([60.2574, -34.0099, 36.2677], [60.4626, -34.1751, 39.4387], 1.2644),
+                     ([2.0776, 0.0795, -1.1350], [0.9033, -0.0636, -0.5514], 0.9082)];
+        for &(x, y, expected) in &pairs {
+            let (x, y) = (Lab::new(x), Lab::new(y));
+            assert!((delta_e_2000(x, y) - expected).abs() < 1e-4);
+            assert!((delta_e_2000(y, x) - expected).abs() < 1e-4);
+        }
+        assert_eq!(delta_e_2000(a, a), 0.0);
+    }
+}
@@ -0,0 +1,71 @@
+//! CMYK colors
+//!
+//! The conversions use the naive device independent formulas without ink limits or color
+//! profiles. They operate on the channel values of `Rgb` as they are, no gamma expansion is
+//! performed.
+
+use traits::{Primitive, ChannelMax};
+use super::{Rgb, Cmyk};
+use math::{from_f32, to_f32};
+
+/// Converts normalized RGB to normalized CMYK.
+pub fn rgb_to_cmyk(r: f32, g: f32, b: f32) -> [f32; 4] {
+    let k = 1.0 - r.max(g).max(b);
+    if k >= 1.0 {
+        return [0.0, 0.0, 0.0, 1.0];
+    }
+    let w = 1.0 - k;
+    [(w - r) / w, (w - g) / w, (w - b) / w, k]
+}
+
+/// Converts normalized CMYK to normalized RGB.
+pub fn cmyk_to_rgb(c: f32, m: f32, y: f32, k: f32) -> [f32; 3] {
+    let w = 1.0 - k;
+    [(1.0 - c) * w, (1.0 - m) * w, (1.0 - y) * w]
+}
+
+// From for CMYK
+
+impl<T: Primitive + ChannelMax> From<Rgb<T>> for Cmyk<T> {
+    fn from(other: Rgb<T>) -> Self {
+        let max = to_f32(T::channel_max());
+        let rgb = other.0;
+        let cmyk = rgb_to_cmyk(to_f32(rgb[0]) / max, to_f32(rgb[1]) / max, to_f32(rgb[2]) / max);
+        Cmyk([from_f32(cmyk[0] * max),
+              from_f32(cmyk[1] * max),
+              from_f32(cmyk[2] * max),
+              from_f32(cmyk[3] * max)])
+    }
+}
+
+// From for RGB
+
+impl<T: Primitive + ChannelMax> From<Cmyk<T>> for Rgb<T> {
+    fn from(other: Cmyk<T>) -> Self {
+        let max = to_f32(T::channel_max());
+        let c = other.0;
+        let rgb = cmyk_to_rgb(to_f32(c[0]) / max,
+                              to_f32(c[1]) / max,
+                              to_f32(c[2]) / max,
+                              to_f32(c[3]) / max);
+        Rgb([from_f32(rgb[0] * max), from_f32(rgb[1] * max), from_f32(rgb[2] * max)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+
+    #[test]
+    fn test_cmyk_conversions() {
+        let cmyk: Cmyk<u8> = Rgb::new([255u8, 0, 0]).into();
+        assert_eq!(cmyk, Cmyk::new([0, 255, 255, 0]));
+        let cmyk: Cmyk<u8> = Rgb::new([0u8, 0, 0]).into();
+        assert_eq!(cmyk, Cmyk::new([0, 0, 0, 255]));
+        for &c in &[[12u8, 200, 99], [255, 255, 255], [0, 0, 0], [128, 128, 64]] {
+            let rgb = Rgb::new(c);
+            assert_eq!(Rgb::from(Cmyk::from(rgb)), rgb);
+        }
+        assert_eq!(ColorType::Cmyk(8).bits_per_pixel(), 32);
+    }
+}
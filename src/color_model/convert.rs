@@ -0,0 +1,195 @@
+//! Specialized pixel conversions.
+//!
+//! `convert_pixels` picks an optimized conversion routine for the pair of color types. The
+//! selection is based on `TypeId` comparisons which are resolved by the compiler, such that only
+//! the matching routine remains in the monomorphized code. Every routine produces exactly the
+//! same result as the generic per-pixel `From` conversion.
+
+use std::any::TypeId;
+use std::slice;
+
+use traits::Color;
+use super::{Rgb, Gray, rgb};
+use super::space::srgb_to_xyz_matrix;
+
+/// Reinterprets `slice` as a slice of `B` if `A` and `B` are the same type.
+#[inline(always)]
+fn cast_slice<A: 'static, B: 'static>(slice: &[A]) -> Option<&[B]> {
+    if TypeId::of::<A>() == TypeId::of::<B>() {
+        Some(unsafe { slice::from_raw_parts(slice.as_ptr() as *const B, slice.len()) })
+    } else {
+        None
+    }
+}
+
+/// Reinterprets `slice` as a mutable slice of `B` if `A` and `B` are the same type.
+#[inline(always)]
+fn cast_slice_mut<A: 'static, B: 'static>(slice: &mut [A]) -> Option<&mut [B]> {
+    if TypeId::of::<A>() == TypeId::of::<B>() {
+        Some(unsafe { slice::from_raw_parts_mut(slice.as_mut_ptr() as *mut B, slice.len()) })
+    } else {
+        None
+    }
+}
+
+#[inline(always)]
+fn is<A: 'static, B: 'static>() -> bool {
+    TypeId::of::<A>() == TypeId::of::<B>()
+}
+
+/// Returns `true` if `F` and `T` only differ in the order of their channels.
+fn is_swizzle<F: Color, T: Color>() -> bool {
+    let (from, to) = (F::layout(), T::layout());
+    is::<F::Subpixel, T::Subpixel>() && F::color_model_id() == T::color_model_id() &&
+    from.channel_count() == to.channel_count() && from.alpha_mode() == to.alpha_mode()
+}
+
+/// Converts the pixels stored in `src` to the pixels stored in `dst`.
+///
+/// Both slices have to contain the same number of pixels.
+pub fn convert_pixels<F, T>(src: &[F::Subpixel], dst: &mut [T::Subpixel])
+    where F: Color,
+          T: Color + From<F>
+{
+    debug_assert_eq!(src.len() / F::channel_count(), dst.len() / T::channel_count());
+    if dst.is_empty() {
+        return;
+    }
+    if is::<F, T>() {
+        // Identity
+        dst.copy_from_slice(cast_slice(src).unwrap());
+    } else if is_swizzle::<F, T>() {
+        let (from, to) = (F::layout(), T::layout());
+        let src: &[T::Subpixel] = cast_slice(src).unwrap();
+        let mut canonical = *T::from_slice(&src[..T::channel_count()]);
+        for (s, d) in src.chunks(F::channel_count()).zip(dst.chunks_mut(T::channel_count())) {
+            from.to_canonical(s, canonical.channels_mut().as_mut());
+            to.from_canonical(canonical.channels().as_ref(), d);
+        }
+    } else if is::<F, Rgb<u8>>() && is::<T, Rgb<f32>>() {
+        let table = rgb::srgb_expansion_table();
+        let src: &[u8] = cast_slice(src).unwrap();
+        let dst: &mut [f32] = cast_slice_mut(dst).unwrap();
+        for (d, &s) in dst.iter_mut().zip(src) {
+            *d = table[s as usize];
+        }
+    } else if is::<F, Rgb<u8>>() && is::<T, Gray<u8>>() {
+        rgb_to_gray_u8(cast_slice(src).unwrap(), cast_slice_mut(dst).unwrap());
+    } else {
+        for (s, d) in src.chunks(F::channel_count()).zip(dst.chunks_mut(T::channel_count())) {
+            *T::from_slice_mut(d) = From::from(*F::from_slice(s));
+        }
+    }
+}
+
+/// The number of pixels converted at once by `rgb_to_gray_u8`.
+const BLOCK: usize = 16;
+
+/// Computes the luminance of a block of linear RGB values with SSE2 instructions.
+///
+/// The weights and the order of the sums are the same as in `cie::rgb_to_y`, such that the
+/// results are identical.
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+fn luminance_block(r: &[f32; BLOCK], g: &[f32; BLOCK], b: &[f32; BLOCK], y: &mut [f32; BLOCK]) {
+    use std::arch::x86_64::{_mm_add_ps, _mm_loadu_ps, _mm_mul_ps, _mm_set1_ps, _mm_storeu_ps};
+
+    let w = srgb_to_xyz_matrix()[1];
+    // SSE2 is part of the x86_64 baseline and all accesses are within the blocks
+    unsafe {
+        let (wr, wg, wb) = (_mm_set1_ps(w[0]), _mm_set1_ps(w[1]), _mm_set1_ps(w[2]));
+        for i in (0..BLOCK).step_by(4) {
+            let r = _mm_mul_ps(wr, _mm_loadu_ps(r.as_ptr().add(i)));
+            let g = _mm_mul_ps(wg, _mm_loadu_ps(g.as_ptr().add(i)));
+            let b = _mm_mul_ps(wb, _mm_loadu_ps(b.as_ptr().add(i)));
+            _mm_storeu_ps(y.as_mut_ptr().add(i), _mm_add_ps(_mm_add_ps(r, g), b));
+        }
+    }
+}
+
+/// Computes the luminance of a block of linear RGB values in a loop without lookups, which the
+/// compiler vectorizes.
+#[cfg(not(all(feature = "simd", target_arch = "x86_64")))]
+fn luminance_block(r: &[f32; BLOCK], g: &[f32; BLOCK], b: &[f32; BLOCK], y: &mut [f32; BLOCK]) {
+    let w = srgb_to_xyz_matrix()[1];
+    for i in 0..BLOCK {
+        y[i] = w[0] * r[i] + w[1] * g[i] + w[2] * b[i];
+    }
+}
+
+/// Converts sRGB to gamma corrected gray, both with 8 bits per channel.
+///
+/// Gamma expansion and compression are replaced by table lookups, the luminance is computed by
+/// `luminance_block`, which uses explicit SIMD instructions with the `simd` feature.
+fn rgb_to_gray_u8(src: &[u8], dst: &mut [u8]) {
+    let table = rgb::srgb_expansion_table();
+    let thresholds = rgb::srgb_compression_thresholds();
+    let (mut r, mut g, mut b) = ([0f32; BLOCK], [0f32; BLOCK], [0f32; BLOCK]);
+    let mut y = [0f32; BLOCK];
+    for (dst, src) in dst.chunks_mut(BLOCK).zip(src.chunks(3 * BLOCK)) {
+        for (i, s) in src.chunks(3).enumerate() {
+            r[i] = table[s[0] as usize];
+            g[i] = table[s[1] as usize];
+            b[i] = table[s[2] as usize];
+        }
+        luminance_block(&r, &g, &b, &mut y);
+        for (d, &y) in dst.iter_mut().zip(y.iter()) {
+            *d = rgb::srgb_compress_with_thresholds(thresholds, y);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use traits::Color;
+    use super::{convert_pixels, is_swizzle};
+    use super::super::{Rgb, Gray, Lab, Rgba, Xyz};
+
+    #[test]
+    fn test_specializations_match_from() {
+        let src: Vec<u8> = (0..256 * 3).map(|v| (v * 7 % 256) as u8).collect();
+
+        let mut gray = vec![0u8; 256];
+        convert_pixels::<Rgb<u8>, Gray<u8>>(&src, &mut gray);
+        let mut rgbf = vec![0f32; 256 * 3];
+        convert_pixels::<Rgb<u8>, Rgb<f32>>(&src, &mut rgbf);
+        let mut same = vec![0u8; 256 * 3];
+        convert_pixels::<Rgb<u8>, Rgb<u8>>(&src, &mut same);
+        let mut rgba = vec![0u8; 256 * 4];
+        convert_pixels::<Rgb<u8>, Rgba<u8>>(&src, &mut rgba);
+
+        assert_eq!(src, same);
+        for (i, s) in src.chunks(3).enumerate() {
+            let pixel = Rgb::new([s[0], s[1], s[2]]);
+            assert_eq!(Gray::from(pixel).channels()[0], gray[i]);
+            assert_eq!(Rgb::<f32>::from(pixel).channels()[..], rgbf[i * 3..i * 3 + 3]);
+            assert_eq!(Rgba::from(pixel).channels()[..], rgba[i * 4..i * 4 + 4]);
+        }
+    }
+
+    #[test]
+    fn test_is_swizzle() {
+        assert!(is_swizzle::<Rgba<u8>, Rgba<u8>>());
+        assert!(!is_swizzle::<Rgb<u8>, Lab<u8>>());
+        assert!(!is_swizzle::<Rgb<f32>, Xyz<f32>>());
+        assert!(!is_swizzle::<Rgb<u8>, Rgb<u16>>());
+        assert_eq!(Rgba::<u8>::color_model_id(), Rgb::<u8>::color_model_id());
+    }
+
+    #[test]
+    fn test_rgb_to_gray_u8_matches_from() {
+        // All values of each channel combined with a coarse grid of the other channels
+        let mut src = Vec::new();
+        for v in 0..256u32 {
+            for w in (0..256u32).step_by(15) {
+                for &(r, g, b) in &[(v, w, 255 - w), (w, v, 255 - w), (w, 255 - w, v)] {
+                    src.extend_from_slice(&[r as u8, g as u8, b as u8]);
+                }
+            }
+        }
+        let mut gray = vec![0u8; src.len() / 3];
+        convert_pixels::<Rgb<u8>, Gray<u8>>(&src, &mut gray);
+        for (s, &g) in src.chunks(3).zip(gray.iter()) {
+            assert_eq!(Gray::from(Rgb::new([s[0], s[1], s[2]])).channels()[0], g, "{:?}", s);
+        }
+    }
+}
@@ -1,5 +1,77 @@
+use math::{from_f32, to_f32};
 use traits::{Primitive, ChannelMax};
-use super::{Rgb, Gray, cie, rgb};
+use super::{ColorSpace, Rgb, Gray, cie, rgb};
+
+/// Weights of the red, green and blue channels used to compute luma.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum LumaWeights {
+    /// ITU-R BT.601 coefficients used by standard definition video and JPEG.
+    Bt601,
+    /// ITU-R BT.709 coefficients used by high definition video.
+    Bt709,
+    /// Custom coefficients of the red, green and blue channels, which should sum up to one.
+    Custom([f32; 3]),
+}
+
+impl LumaWeights {
+    /// Returns the coefficients of the red, green and blue channels.
+    pub fn coefficients(&self) -> [f32; 3] {
+        match *self {
+            LumaWeights::Bt601 => [0.299, 0.587, 0.114],
+            LumaWeights::Bt709 => [0.2126, 0.7152, 0.0722],
+            LumaWeights::Custom(weights) => weights,
+        }
+    }
+}
+
+impl<T: Primitive + ChannelMax> Gray<T> {
+    /// Computes the luma of `rgb` as the weighted sum of its gamma encoded channels.
+    ///
+    /// In contrast to `From<Rgb<T>>`, which computes the luminance in linear light, this is the
+    /// definition used by video standards.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::color::{Gray, LumaWeights, Rgb};
+    /// let gray = Gray::from_rgb_with_weights(Rgb::new([0u8, 255, 0]), LumaWeights::Bt601);
+    /// assert_eq!(gray, Gray::new([150]));
+    /// ```
+    pub fn from_rgb_with_weights(rgb: Rgb<T>, weights: LumaWeights) -> Gray<T> {
+        let [r, g, b] = weights.coefficients();
+        let c = rgb.0;
+        Gray([from_f32(r * to_f32(c[0]) + g * to_f32(c[1]) + b * to_f32(c[2]))])
+    }
+}
+
+impl<T: Primitive + ChannelMax> Gray<T> {
+    /// Computes the luminance of `rgb`, both encoded according to `space`.
+    ///
+    /// With `ColorSpace::Srgb` this equals `From<Rgb<T>>`, with `ColorSpace::Linear` the
+    /// channels are weighted without gamma expansion.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::color::{ColorSpace, Gray, Rgb};
+    /// let red = Rgb::new([255u8, 0, 0]);
+    /// assert_eq!(Gray::from_rgb_in(red, ColorSpace::Linear), Gray::new([54]));
+    /// assert_eq!(Gray::from_rgb_in(red, ColorSpace::Srgb), Gray::from(red));
+    /// ```
+    pub fn from_rgb_in(rgb: Rgb<T>, space: ColorSpace) -> Gray<T>
+        where Rgb<f32>: From<Rgb<T>>
+    {
+        match space {
+            ColorSpace::Srgb => rgb.into(),
+            ColorSpace::Linear => {
+                let max = to_f32(T::channel_max());
+                let c = rgb.0;
+                let y = cie::rgb_to_y(to_f32(c[0]) / max, to_f32(c[1]) / max, to_f32(c[2]) / max);
+                Gray([from_f32(y * max)])
+            }
+        }
+    }
+}
 
 /// sRGB to Y conversion for integer values
 fn srgb_to_luminance<T: Primitive + ChannelMax, V: Primitive + ChannelMax>(rgb: Rgb<T>) -> V
@@ -18,3 +90,30 @@ impl<T: Primitive + ChannelMax> From<Rgb<T>> for Gray<T>
         Gray([srgb_to_luminance(other)])
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::super::{ColorSpace, Gray, LumaWeights, Rgb};
+
+    #[test]
+    fn test_luma_weights() {
+        let white = Rgb::new([255u8, 255, 255]);
+        assert_eq!(Gray::from_rgb_with_weights(white, LumaWeights::Bt709), Gray::new([255]));
+        let red = Rgb::new([200u8, 10, 10]);
+        assert_eq!(Gray::from_rgb_with_weights(red, LumaWeights::Custom([1.0, 0.0, 0.0])),
+                   Gray::new([200]));
+        let blue = Rgb::new([0.0f32, 0.0, 1.0]);
+        assert_eq!(Gray::from_rgb_with_weights(blue, LumaWeights::Bt709), Gray::new([0.0722]));
+    }
+
+    #[test]
+    fn test_from_rgb_in() {
+        for &rgb in &[Rgb::new([0u8, 0, 0]), Rgb::new([12, 200, 90]), Rgb::new([255; 3])] {
+            assert_eq!(Gray::from_rgb_in(rgb, ColorSpace::Srgb), Gray::from(rgb));
+        }
+        let linear = Rgb::new([0.5f32, 0.5, 0.5]);
+        assert_eq!(Gray::from_rgb_in(linear, ColorSpace::Linear), Gray::new([0.5]));
+        assert_eq!(Gray::from_rgb_in(Rgb::new([0u16, 65535, 0]), ColorSpace::Linear),
+                   Gray::new([46869]));
+    }
+}
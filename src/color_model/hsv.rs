@@ -0,0 +1,141 @@
+//! HSV and HSL colors
+//!
+//! The hue is scaled to the range of the channel such that the maximal channel value corresponds
+//! to a full turn of 360°. The conversions operate on the channel values of `Rgb` as they are,
+//! no gamma expansion is performed.
+
+use traits::{Primitive, ChannelMax};
+use super::{Rgb, Hsv, Hsl};
+use math::{from_f32, to_f32};
+
+/// Normalizes the channels of a color to the range `[0, 1]`.
+#[inline]
+fn normalize<T: Primitive + ChannelMax>(c: [T; 3]) -> [f32; 3] {
+    let max = to_f32(T::channel_max());
+    [to_f32(c[0]) / max, to_f32(c[1]) / max, to_f32(c[2]) / max]
+}
+
+/// Scales normalized channels to the range of `T`.
+#[inline]
+fn denormalize<T: Primitive + ChannelMax>(c: [f32; 3]) -> [T; 3] {
+    let max = to_f32(T::channel_max());
+    [from_f32(c[0] * max), from_f32(c[1] * max), from_f32(c[2] * max)]
+}
+
+/// Returns the hue in the range `[0, 1)`, the maximal and the minimal component.
+fn hue(r: f32, g: f32, b: f32) -> (f32, f32, f32) {
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+    let h = if delta == 0.0 {
+        0.0
+    } else if max == r {
+        ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        (b - r) / delta + 2.0
+    } else {
+        (r - g) / delta + 4.0
+    };
+    ((h / 6.0).rem_euclid(1.0), max, min)
+}
+
+/// Converts the hue `h` in the range `[0, 1)`, the chroma and the offset `m` to RGB.
+fn from_hue(h: f32, chroma: f32, m: f32) -> [f32; 3] {
+    let h = h.rem_euclid(1.0) * 6.0;
+    let x = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    [r + m, g + m, b + m]
+}
+
+/// Converts normalized RGB to normalized HSV.
+pub fn rgb_to_hsv(r: f32, g: f32, b: f32) -> [f32; 3] {
+    let (h, max, min) = hue(r, g, b);
+    let s = if max == 0.0 { 0.0 } else { (max - min) / max };
+    [h, s, max]
+}
+
+/// Converts normalized HSV to normalized RGB.
+pub fn hsv_to_rgb(h: f32, s: f32, v: f32) -> [f32; 3] {
+    let chroma = v * s;
+    from_hue(h, chroma, v - chroma)
+}
+
+/// Converts normalized RGB to normalized HSL.
+pub fn rgb_to_hsl(r: f32, g: f32, b: f32) -> [f32; 3] {
+    let (h, max, min) = hue(r, g, b);
+    let l = (max + min) / 2.0;
+    let s = if max == min {
+        0.0
+    } else {
+        (max - min) / (1.0 - (2.0 * l - 1.0).abs())
+    };
+    [h, s, l]
+}
+
+/// Converts normalized HSL to normalized RGB.
+pub fn hsl_to_rgb(h: f32, s: f32, l: f32) -> [f32; 3] {
+    let chroma = (1.0 - (2.0 * l - 1.0).abs()) * s;
+    from_hue(h, chroma, l - chroma / 2.0)
+}
+
+// From for HSV and HSL
+
+impl<T: Primitive + ChannelMax> From<Rgb<T>> for Hsv<T> {
+    fn from(other: Rgb<T>) -> Self {
+        let [r, g, b] = normalize(other.0);
+        Hsv(denormalize(rgb_to_hsv(r, g, b)))
+    }
+}
+
+impl<T: Primitive + ChannelMax> From<Rgb<T>> for Hsl<T> {
+    fn from(other: Rgb<T>) -> Self {
+        let [r, g, b] = normalize(other.0);
+        Hsl(denormalize(rgb_to_hsl(r, g, b)))
+    }
+}
+
+// From for RGB
+
+impl<T: Primitive + ChannelMax> From<Hsv<T>> for Rgb<T> {
+    fn from(other: Hsv<T>) -> Self {
+        let [h, s, v] = normalize(other.0);
+        Rgb(denormalize(hsv_to_rgb(h, s, v)))
+    }
+}
+
+impl<T: Primitive + ChannelMax> From<Hsl<T>> for Rgb<T> {
+    fn from(other: Hsl<T>) -> Self {
+        let [h, s, l] = normalize(other.0);
+        Rgb(denormalize(hsl_to_rgb(h, s, l)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+
+    #[test]
+    fn test_hsv_conversions() {
+        let red: Hsv<f32> = Rgb::new([1.0f32, 0.0, 0.0]).into();
+        assert_eq!(red, Hsv::new([0.0, 1.0, 1.0]));
+        let blue: Hsl<f32> = Rgb::new([0.0f32, 0.0, 1.0]).into();
+        assert_eq!(blue, Hsl::new([2.0 / 3.0, 1.0, 0.5]));
+        for &c in &[[255u8, 0, 0], [255, 255, 255], [0, 0, 0], [128, 128, 128]] {
+            let rgb = Rgb::new(c);
+            assert_eq!(Rgb::from(Hsv::from(rgb)), rgb);
+        }
+        let gray = Rgb::new([128u8, 128, 128]);
+        assert_eq!(Rgb::from(Hsl::from(gray)), gray);
+        let rgb = Rgb::new([0.05f32, 0.8, 0.4]);
+        for (a, b) in Rgb::from(Hsl::from(rgb)).0.iter().zip(rgb.0.iter()) {
+            assert!((a - b).abs() < 1e-6);
+        }
+    }
+}
@@ -0,0 +1,151 @@
+//! Channel order and alpha metadata of pixel types.
+
+/// Maximum number of channels a `PixelLayout` can describe.
+const MAX_CHANNELS: usize = 8;
+
+/// Interpretation of the alpha channel of a pixel.
+#[derive(Copy, PartialEq, Eq, Debug, Clone, Hash)]
+pub enum AlphaMode {
+    /// The pixel has no alpha channel.
+    None,
+    /// The color channels are independent of the alpha channel.
+    Straight,
+    /// The color channels are premultiplied with the alpha channel.
+    Premultiplied,
+}
+
+/// Describes how the channels of a pixel are stored in memory.
+///
+/// Each color model has a canonical channel order, e.g. R, G, B for `Rgb`. The alpha channel,
+/// if present, is always the last canonical channel. The layout maps every storage position to
+/// the canonical channel which is stored there, such that for example BGRA is described by the
+/// order `[2, 1, 0, 3]`.
+#[derive(Copy, PartialEq, Eq, Debug, Clone, Hash)]
+pub struct PixelLayout {
+    order: [u8; MAX_CHANNELS],
+    channels: u8,
+    alpha: AlphaMode,
+}
+
+impl PixelLayout {
+    /// Creates a layout with `channels` channels stored in canonical order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `channels` is zero or exceeds the supported number of channels or if an alpha
+    /// mode is given for a single channel.
+    pub fn canonical(channels: usize, alpha: AlphaMode) -> PixelLayout {
+        assert!(channels > 0 && channels <= MAX_CHANNELS);
+        assert!(channels > 1 || alpha == AlphaMode::None);
+        let mut order = [0; MAX_CHANNELS];
+        for (i, v) in order.iter_mut().enumerate() {
+            *v = i as u8;
+        }
+        PixelLayout {
+            order,
+            channels: channels as u8,
+            alpha,
+        }
+    }
+
+    /// Creates a layout from an explicit channel order.
+    ///
+    /// `order[i]` is the canonical channel stored at position `i`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `order` is not a permutation of `0..order.len()`.
+    pub fn with_order(order: &[u8], alpha: AlphaMode) -> PixelLayout {
+        let mut layout = PixelLayout::canonical(order.len(), alpha);
+        let mut seen = [false; MAX_CHANNELS];
+        for (i, &c) in order.iter().enumerate() {
+            assert!((c as usize) < order.len() && !seen[c as usize],
+                    "channel order is not a permutation");
+            seen[c as usize] = true;
+            layout.order[i] = c;
+        }
+        layout
+    }
+
+    /// Returns the same layout with a different alpha mode.
+    pub fn with_alpha_mode(mut self, alpha: AlphaMode) -> PixelLayout {
+        assert!(self.channels > 1 || alpha == AlphaMode::None);
+        self.alpha = alpha;
+        self
+    }
+
+    /// The number of channels.
+    pub fn channel_count(&self) -> usize {
+        self.channels as usize
+    }
+
+    /// The canonical channel stored at each position.
+    pub fn channel_order(&self) -> &[u8] {
+        &self.order[..self.channels as usize]
+    }
+
+    /// The interpretation of the alpha channel.
+    pub fn alpha_mode(&self) -> AlphaMode {
+        self.alpha
+    }
+
+    /// Returns `true` if the channels are stored in canonical order.
+    pub fn is_canonical(&self) -> bool {
+        self.channel_order().iter().enumerate().all(|(i, &c)| i == c as usize)
+    }
+
+    /// The storage position of the alpha channel, if any.
+    pub fn alpha_position(&self) -> Option<usize> {
+        match self.alpha {
+            AlphaMode::None => None,
+            _ => self.position_of(self.channel_count() - 1),
+        }
+    }
+
+    /// The storage position of the canonical channel `channel`.
+    pub fn position_of(&self, channel: usize) -> Option<usize> {
+        self.channel_order().iter().position(|&c| c as usize == channel)
+    }
+
+    /// Copies the channels of `src`, which are stored in canonical order, to `dst` using this
+    /// layout.
+    ///
+    /// # Panics
+    ///
+    /// Panics if one of the slices has less channels than the layout.
+    pub fn from_canonical<T: Copy>(&self, src: &[T], dst: &mut [T]) {
+        for (d, &c) in dst[..self.channel_count()].iter_mut().zip(self.channel_order()) {
+            *d = src[c as usize];
+        }
+    }
+
+    /// Copies the channels of `src`, which are stored in this layout, to `dst` using the
+    /// canonical order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if one of the slices has less channels than the layout.
+    pub fn to_canonical<T: Copy>(&self, src: &[T], dst: &mut [T]) {
+        for (&s, &c) in src[..self.channel_count()].iter().zip(self.channel_order()) {
+            dst[c as usize] = s;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AlphaMode, PixelLayout};
+
+    #[test]
+    fn test_swizzle() {
+        let bgra = PixelLayout::with_order(&[2, 1, 0, 3], AlphaMode::Straight);
+        assert!(!bgra.is_canonical());
+        assert_eq!(bgra.alpha_position(), Some(3));
+        let mut stored = [0u8; 4];
+        bgra.from_canonical(&[1, 2, 3, 4], &mut stored);
+        assert_eq!(stored, [3, 2, 1, 4]);
+        let mut rgba = [0u8; 4];
+        bgra.to_canonical(&stored, &mut rgba);
+        assert_eq!(rgba, [1, 2, 3, 4]);
+    }
+}
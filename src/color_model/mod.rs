@@ -1,15 +1,29 @@
 mod cie;
+mod cmyk;
 mod gray;
-mod rgb;
+mod hsv;
+pub(crate) mod rgb;
+mod space;
+mod ycbcr;
 mod alpha;
+mod layout;
+pub(crate) mod convert;
 
 use std::ops::{Index, IndexMut};
-use std::mem;
 
-use traits::Color;
+use traits::{Color, PodColor};
 use traits::{Primitive, ColorMathOps};
-
-pub use self::alpha::{Alpha2, Alpha3, Alpha4};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+pub use self::alpha::{Alpha, Alpha2, Alpha3, Alpha4, Alpha5, PremultipliedAlpha};
+pub use self::cie::{delta_e_2000, delta_e_76};
+pub use self::gray::LumaWeights;
+pub use self::rgb::{convert_linear, ColorSpace, LinearRgba};
+pub use self::ycbcr::YCbCrRange;
+pub use self::layout::{AlphaMode, PixelLayout};
+pub use self::space::{conversion_matrix, transform, ChromaticAdaptation, Matrix3, RgbSpace, D50,
+                      D65};
 
 macro_rules! define_color_model {
     {$(
@@ -24,14 +38,22 @@ $( // START Structure definitions
 
 #[$doc]
 #[derive(PartialEq, Eq, Clone, Debug, Copy, Hash)]
-#[repr(C)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[repr(transparent)]
 #[allow(missing_docs)]
 pub struct $ident<T: Primitive>([T; $channels]);
 
 impl<T: Primitive> $ident<T> {
-    pub fn new(array: [T; $channels]) -> Self {
+    pub const fn new(array: [T; $channels]) -> Self {
         $ident(array)
     }
+
+    /// Adds the straight alpha value `alpha` to the color.
+    pub fn with_alpha(&self, alpha: T) -> $alpha_ident<$ident<T>> {
+        let mut storage = [alpha; $channels + 1];
+        storage[..$channels].copy_from_slice(&self.0);
+        $alpha_ident::new(storage)
+    }
 }
 
 impl<T: Primitive> AsRef<[T; $channels]> for $ident<T> {
@@ -46,15 +68,15 @@ impl<T: Primitive> AsMut<[T; $channels]> for $ident<T> {
     }
 }
 
+// A `repr(transparent)` wrapper around the array of channels
+unsafe impl<T: Primitive> PodColor for $ident<T> {}
+
 impl<T: Primitive> Color for $ident<T> {
 
     type Subpixel = T;
     type Storage = [T; $channels];
 
-    /// Returns the number of channels of this pixel type.
-    fn channel_count() -> usize {
-        $channels
-    }
+    const NUM_CHANNELS: usize = $channels;
 
     #[inline(always)]
     fn channels(&self) -> &[T; $channels] {
@@ -66,22 +88,18 @@ impl<T: Primitive> Color for $ident<T> {
         &mut self.0
     }
 
-    fn from_channels(other: [T; $channels]) -> $ident<T> {
-        *<$ident<T> as Color>::from_slice(&other[..$channels])
-    }
-
     fn from_slice<'a>(slice: &'a [T]) -> &'a $ident<T> {
-        unsafe {
-            assert_eq!(slice.len(), $channels);
-            mem::transmute(slice.as_ptr())
-        }
+        let array: &[T; $channels] = ::std::convert::TryFrom::try_from(slice)
+            .unwrap_or_else(|_| panic!("expected {} channels, got {}", $channels, slice.len()));
+        // The color is a transparent wrapper of the array
+        unsafe { &*(array as *const [T; $channels] as *const $ident<T>) }
     }
 
     fn from_slice_mut<'a>(slice: &'a mut [T]) -> &'a mut $ident<T> {
-        unsafe {
-            assert_eq!(slice.len(), $channels);
-            mem::transmute(slice.as_ptr())
-        }
+        let len = slice.len();
+        let array: &mut [T; $channels] = ::std::convert::TryFrom::try_from(slice)
+            .unwrap_or_else(|_| panic!("expected {} channels, got {}", $channels, len));
+        unsafe { &mut *(array as *mut [T; $channels] as *mut $ident<T>) }
     }
 
     fn apply_with_alpha<F, G>(&mut self, f: F, g: G) where F: Fn(T) -> T, G: Fn(T) -> T {
@@ -218,8 +236,13 @@ impl<T: Primitive> From<$alpha_ident<$ident<T>>> for $ident<T> {
 
 /// An enumeration over supported color types and their bit depths.
 #[derive(Copy, PartialEq, Eq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum ColorType {
     $(#[$doc]$ident(u8),)*
+    /// Grayscale with straight alpha.
+    GrayA(u8),
+    /// sRGB with straight alpha.
+    Rgba(u8),
 }
 
 impl ColorType {
@@ -227,6 +250,8 @@ impl ColorType {
     pub fn bits_per_pixel(self) -> usize {
         match self {
             $(ColorType::$ident(n) => $channels * n as usize,)*
+            ColorType::GrayA(n) => 2 * n as usize,
+            ColorType::Rgba(n) => 4 * n as usize,
         }
     }
 
@@ -234,8 +259,19 @@ impl ColorType {
     pub fn num_components(self) -> usize {
         match self {
             $(ColorType::$ident(_) => $channels,)*
+            ColorType::GrayA(_) => 2,
+            ColorType::Rgba(_) => 4,
         }
     }
+
+    /// Returns the channel order and alpha interpretation of a pixel of ColorType `self`.
+    pub fn layout(self) -> PixelLayout {
+        let alpha = match self {
+            ColorType::GrayA(_) | ColorType::Rgba(_) => AlphaMode::Straight,
+            _ => AlphaMode::None,
+        };
+        PixelLayout::canonical(self.num_components(), alpha)
+    }
 }
 
     }
@@ -245,6 +281,10 @@ define_color_model! {
     Rgb, Alpha4, 3, 0, "RGB", #[doc = "sRGB."];
     Xyz, Alpha4, 3, 0, "XYZ", #[doc = "CIE XYZ."];
     Lab, Alpha4, 3, 0, "CIE Lab", #[doc = "CIE L*a*b*."];
+    Hsv, Alpha4, 3, 0, "HSV", #[doc = "Hue, saturation and value."];
+    Hsl, Alpha4, 3, 0, "HSL", #[doc = "Hue, saturation and lightness."];
+    Cmyk, Alpha5, 4, 0, "CMYK", #[doc = "Cyan, magenta, yellow and key (black)."];
+    YCbCr, Alpha4, 3, 0, "Y'CbCr", #[doc = "Luma and blue and red difference chroma."];
     Gray, Alpha2, 1, 0, "Y", #[doc = "Grayscale"];
     Indexed, Alpha2, 1, 0, "Idx", #[doc = "Indexed colors.\n\nNo specific color moddel is assumed."];
 }
@@ -252,7 +292,13 @@ define_color_model! {
 pub type Rgba<T> = Alpha4<Rgb<T>>;
 pub type Xyza<T> = Alpha4<Xyz<T>>;
 pub type LabA<T> = Alpha4<Lab<T>>;
+pub type Hsva<T> = Alpha4<Hsv<T>>;
+pub type Hsla<T> = Alpha4<Hsl<T>>;
 pub type GrayA<T> = Alpha2<Gray<T>>;
+pub type CmykA<T> = Alpha5<Cmyk<T>>;
+pub type YCbCrA<T> = Alpha4<YCbCr<T>>;
+pub type PremultipliedRgba<T> = PremultipliedAlpha<Rgb<T>, 4>;
+pub type PremultipliedGrayA<T> = PremultipliedAlpha<Gray<T>, 2>;
 
 #[test]
 fn test_add() {
@@ -261,3 +307,11 @@ fn test_add() {
     assert_eq!(&[1, 1, 1, 1], b.as_ref());
     assert_eq!(&[2, 2, 2, 2], (b + b).as_ref());
 }
+
+#[test]
+fn test_num_channels() {
+    assert_eq!(Rgb::<u8>::NUM_CHANNELS, 3);
+    assert_eq!(Rgba::<u8>::NUM_CHANNELS, 4);
+    assert_eq!(<Alpha<Cmyk<f32>, 5> as Color>::channel_count(), 5);
+    assert_eq!(GrayA::from_channels([1u8, 2]), Alpha2::new([1, 2]));
+}
@@ -1,24 +1,31 @@
 //! sRGB colors
 
+use std::cmp;
+use std::sync::OnceLock;
+
 use num_traits::NumCast;
 
-use traits::{Primitive, ChannelMax};
-use super::{Rgb, Gray, Xyz};
-use math::clamp;
+use traits::{Color, Primitive, ChannelMax};
+use super::{cie, Alpha, Rgb, Rgba, Gray, GrayA, Xyz};
+use super::space::xyz_to_srgb_matrix;
+use math::{clamp, from_f32, rescale_channel, to_f32};
 
 /// Converts CIE 1931 XYZ to the R component of sRGB.
 pub fn xyz_to_r(x: f32, y: f32, z: f32) -> f32 {
-    3.2406 * x - 1.5372 * y - 0.4986 * z
+    let m = xyz_to_srgb_matrix();
+    m[0][0] * x + m[0][1] * y + m[0][2] * z
 }
 
 /// Converts CIE 1931 XYZ to the G component of sRGB.
 pub fn xyz_to_g(x: f32, y: f32, z: f32) -> f32 {
-    -0.9689 * x + 1.8758 * y + 0.0415 * z
+    let m = xyz_to_srgb_matrix();
+    m[1][0] * x + m[1][1] * y + m[1][2] * z
 }
 
 /// Converts CIE 1931 XYZ to the B component of sRGB.
 pub fn xyz_to_b(x: f32, y: f32, z: f32) -> f32 {
-    0.0557 * x - 0.2040 * y + 1.0570 * z
+    let m = xyz_to_srgb_matrix();
+    m[2][0] * x + m[2][1] * y + m[2][2] * z
 }
 
 #[inline]
@@ -62,6 +69,142 @@ pub fn srgb_compress_gamma<T: Primitive + ChannelMax>(c: f32) -> T {
     })
 }
 
+/// Gamma compression as defined for sRGB, rounding to the nearest integer value.
+///
+/// Unlike `srgb_compress_gamma`, which truncates like the `From` conversions, an encoded value
+/// survives a roundtrip through `srgb_expand_gamma` unchanged.
+pub fn srgb_compress_gamma_rounded<T: Primitive + ChannelMax>(c: f32) -> T {
+    from_f32(srgb_compress_gamma::<f32>(c) * to_f32(T::channel_max()))
+}
+
+/// The encoding of the color channels, which determines whether arithmetic on them happens in
+/// linear light.
+///
+/// Unlike `RgbSpace`, which describes primaries and white point, this only tells linear values
+/// from values encoded with the sRGB transfer function. Alpha channels are always linear.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ColorSpace {
+    /// The values are proportional to light intensity.
+    Linear,
+    /// The values are encoded with the sRGB transfer function, as in most 8-bit images.
+    Srgb,
+}
+
+impl ColorSpace {
+    /// Converts a channel value in `[0, 1]` to linear light.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::color::ColorSpace;
+    /// assert_eq!(ColorSpace::Linear.decode(0.5), 0.5);
+    /// assert!((ColorSpace::Srgb.decode(0.5) - 0.214).abs() < 1e-3);
+    /// ```
+    pub fn decode(self, v: f32) -> f32 {
+        match self {
+            ColorSpace::Linear => v,
+            ColorSpace::Srgb => srgb_expand_gamma(v),
+        }
+    }
+
+    /// Converts a linear value in `[0, 1]` to this encoding.
+    pub fn encode(self, v: f32) -> f32 {
+        match self {
+            ColorSpace::Linear => v,
+            ColorSpace::Srgb => srgb_compress_gamma(v),
+        }
+    }
+}
+
+/// Table of the gamma expanded values of all `u8` sRGB values.
+///
+/// The table is computed once on first use.
+pub fn srgb_expansion_table() -> &'static [f32; 256] {
+    static TABLE: OnceLock<[f32; 256]> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let mut table = [0.0; 256];
+        for (v, entry) in table.iter_mut().enumerate() {
+            *entry = srgb_expand_gamma(v as u8);
+        }
+        table
+    })
+}
+
+/// Table of the smallest linear values which `srgb_compress_gamma` maps to `1..=255`.
+///
+/// The number of thresholds not greater than a linear value `c` equals the compressed `u8`
+/// value of `c`, see `srgb_compress_with_thresholds`. This replaces the costly `powf` with a
+/// binary search. The table is computed once on first use.
+pub fn srgb_compression_thresholds() -> &'static [f32; 255] {
+    static THRESHOLDS: OnceLock<[f32; 255]> = OnceLock::new();
+    THRESHOLDS.get_or_init(|| {
+        let mut thresholds = [0.0; 255];
+        for (k, threshold) in (1..256u32).zip(thresholds.iter_mut()) {
+            // Bisection over the bit patterns, which are ordered like non-negative floats
+            let (mut lo, mut hi) = (0f32.to_bits(), 2f32.to_bits());
+            while hi - lo > 1 {
+                let mid = lo + (hi - lo) / 2;
+                if srgb_compress_gamma::<u8>(f32::from_bits(mid)) as u32 >= k {
+                    hi = mid;
+                } else {
+                    lo = mid;
+                }
+            }
+            *threshold = f32::from_bits(hi);
+        }
+        thresholds
+    })
+}
+
+/// Gamma compression of the non-negative linear value `c` using the `thresholds` of
+/// `srgb_compression_thresholds`.
+#[inline]
+pub fn srgb_compress_with_thresholds(thresholds: &[f32], c: f32) -> u8 {
+    thresholds.partition_point(|&t| t <= c) as u8
+}
+
+/// Composites the sRGB channels `src` over `dst` in linear light.
+///
+/// The last channel of both slices is the alpha channel which is not gamma encoded. The
+/// function `expand` has to perform the sRGB gamma expansion of a channel.
+pub fn srgb_over_linear<F: Fn(u8) -> f32>(src: &[u8], dst: &mut [u8], expand: F) {
+    let n = src.len() - 1;
+    let src_alpha = src[n] as f32 / 255.0;
+    let dst_alpha = dst[n] as f32 / 255.0;
+    let alpha = src_alpha + dst_alpha * (1.0 - src_alpha);
+    if alpha == 0.0 {
+        for v in dst.iter_mut() {
+            *v = 0;
+        }
+        return;
+    }
+    for (d, &s) in dst[..n].iter_mut().zip(&src[..n]) {
+        let c = (expand(s) * src_alpha + expand(*d) * dst_alpha * (1.0 - src_alpha)) / alpha;
+        *d = srgb_compress_gamma_rounded(c);
+    }
+    dst[n] = from_f32(alpha * 255.0);
+}
+
+impl Rgba<u8> {
+    /// Composites this color over `background` in linear light.
+    ///
+    /// Both colors are assumed to be sRGB encoded with straight alpha.
+    pub fn over_linear(&self, mut background: Rgba<u8>) -> Rgba<u8> {
+        srgb_over_linear(self.as_ref(), background.as_mut(), srgb_expand_gamma);
+        background
+    }
+}
+
+impl GrayA<u8> {
+    /// Composites this color over `background` in linear light.
+    ///
+    /// Both colors are assumed to be sRGB encoded with straight alpha.
+    pub fn over_linear(&self, mut background: GrayA<u8>) -> GrayA<u8> {
+        srgb_over_linear(self.as_ref(), background.as_mut(), srgb_expand_gamma);
+        background
+    }
+}
+
 // From for RGB
 
 impl<T: Primitive> From<Gray<T>> for Rgb<T> {
@@ -126,6 +269,149 @@ impl From<Rgb<f32>> for Rgb<u16> {
     }
 }
 
+// Conversions between the depths of gray and RGB with and without alpha
+
+/// Subpixel types of gray and RGB colors, integers are sRGB encoded and floats linear.
+trait Transfer: Primitive + ChannelMax {
+    /// Converts a color channel to linear light.
+    fn decode(self) -> f32;
+
+    /// Converts a color channel from linear light.
+    fn encode(v: f32) -> Self;
+}
+
+impl Transfer for u8 {
+    fn decode(self) -> f32 {
+        srgb_expand_gamma(self)
+    }
+
+    fn encode(v: f32) -> u8 {
+        srgb_compress_gamma_rounded(v)
+    }
+}
+
+impl Transfer for u16 {
+    fn decode(self) -> f32 {
+        srgb_expand_gamma(self)
+    }
+
+    fn encode(v: f32) -> u16 {
+        srgb_compress_gamma_rounded(v)
+    }
+}
+
+impl Transfer for f32 {
+    fn decode(self) -> f32 {
+        self
+    }
+
+    fn encode(v: f32) -> f32 {
+        v
+    }
+}
+
+/// Gray and RGB colors with and without alpha at the depths `u8`, `u16` and `f32`, which can
+/// be converted to each other with `convert_linear`.
+///
+/// Integer channels are sRGB encoded and float channels are linear, like in the conversions
+/// between `Rgb<u8>` and `Rgb<f32>`.
+pub trait LinearRgba: Color {
+    /// Returns the linear RGB channels and the straight alpha value in `[0, 1]`.
+    fn to_linear_rgba(&self) -> [f32; 4];
+
+    /// Constructs the color from linear RGB channels and a straight alpha value in `[0, 1]`.
+    fn from_linear_rgba(rgba: [f32; 4]) -> Self;
+}
+
+impl<T: Transfer> LinearRgba for Gray<T> {
+    fn to_linear_rgba(&self) -> [f32; 4] {
+        let y = self.0[0].decode();
+        [y, y, y, 1.0]
+    }
+
+    fn from_linear_rgba([r, g, b, _]: [f32; 4]) -> Self {
+        Gray([T::encode(cie::rgb_to_y(r, g, b))])
+    }
+}
+
+impl<T: Transfer> LinearRgba for Rgb<T> {
+    fn to_linear_rgba(&self) -> [f32; 4] {
+        [self.0[0].decode(), self.0[1].decode(), self.0[2].decode(), 1.0]
+    }
+
+    fn from_linear_rgba([r, g, b, _]: [f32; 4]) -> Self {
+        Rgb([T::encode(r), T::encode(g), T::encode(b)])
+    }
+}
+
+impl<T: Transfer> LinearRgba for GrayA<T> {
+    fn to_linear_rgba(&self) -> [f32; 4] {
+        let [y, _, _, _] = Gray([self[0]]).to_linear_rgba();
+        [y, y, y, rescale_channel(self[1])]
+    }
+
+    fn from_linear_rgba(rgba: [f32; 4]) -> Self {
+        let Gray([y]) = Gray::from_linear_rgba(rgba);
+        Alpha::new([y, rescale_channel(rgba[3])])
+    }
+}
+
+impl<T: Transfer> LinearRgba for Rgba<T> {
+    fn to_linear_rgba(&self) -> [f32; 4] {
+        let [r, g, b, _] = Rgb([self[0], self[1], self[2]]).to_linear_rgba();
+        [r, g, b, rescale_channel(self[3])]
+    }
+
+    fn from_linear_rgba(rgba: [f32; 4]) -> Self {
+        let Rgb([r, g, b]) = Rgb::from_linear_rgba(rgba);
+        Alpha::new([r, g, b, rescale_channel(rgba[3])])
+    }
+}
+
+/// Converts between gray and RGB colors with and without alpha at the depths `u8`, `u16` and
+/// `f32`.
+///
+/// Integer channels are rescaled exactly between integer depths, channels are only converted
+/// through linear light between integer and float depths and to compute the luminance of RGB
+/// colors converted to gray. Colors without alpha are opaque.
+///
+/// The conversions are not implemented with `From`, such that `Gray::from(rgb)` keeps the depth
+/// of `rgb`.
+///
+/// # Examples
+///
+/// ```
+/// # use image_buffer::color::{convert_linear, Gray, GrayA, Rgb, Rgba};
+/// let deep: Gray<u16> = convert_linear(Gray::new([255u8]));
+/// assert_eq!(deep, Gray::new([65535]));
+/// let float: Rgba<f32> = convert_linear(GrayA::new([255u8, 51]));
+/// assert_eq!(float, Rgba::new([1.0, 1.0, 1.0, 0.2]));
+/// let gray: Gray<u8> = convert_linear(Rgb::new([1.0f32, 1.0, 1.0]));
+/// assert_eq!(gray, Gray::new([255]));
+/// ```
+pub fn convert_linear<F, T>(color: F) -> T
+    where F: LinearRgba,
+          F::Subpixel: ChannelMax,
+          T: LinearRgba,
+          T::Subpixel: ChannelMax
+{
+    let from_colors = F::channel_count() - F::has_alpha() as usize;
+    let to_colors = T::channel_count() - T::has_alpha() as usize;
+    let from_linear = to_f32(F::Subpixel::channel_max()) == 1.0;
+    let to_linear = to_f32(T::Subpixel::channel_max()) == 1.0;
+    if from_linear != to_linear || from_colors > to_colors {
+        return T::from_linear_rgba(color.to_linear_rgba());
+    }
+    // The same transfer function and no luminance, such that the channels are only rescaled
+    let channels = color.channels().as_ref();
+    let mut converted = T::from_linear_rgba([0.0; 4]);
+    for (i, c) in converted.channels_mut().as_mut()[..to_colors].iter_mut().enumerate() {
+        *c = rescale_channel(channels[cmp::min(i, from_colors - 1)]);
+    }
+    converted.set_alpha(rescale_channel(color.alpha()));
+    converted
+}
+
 #[cfg(test)]
 mod tests {
     use super::super::*;
@@ -139,6 +425,35 @@ mod tests {
         assert_eq!(0xFFFFu16, super::rescale(1.0f32));
     }
 
+    #[test]
+    fn test_over_linear() {
+        let white = Rgba::new([255, 255, 255, 255]);
+        let black = Rgba::new([0, 0, 0, 255]);
+        let half: Rgba<u8> = Rgba::new([255, 255, 255, 128]);
+        assert_eq!(white.over_linear(black), white);
+        // Linear light blending is brighter than blending the encoded values
+        assert_eq!(half.over_linear(black), Rgba::new([188, 188, 188, 255]));
+        assert_eq!(Rgba::new([0, 0, 0, 0]).over_linear(black), black);
+    }
+
+    #[test]
+    fn test_lerp_saturating() {
+        use traits::Color;
+        let a = Rgba::new([10u8, 200, 0, 255]);
+        let b = Rgba::new([20u8, 100, 255, 0]);
+        assert_eq!(a.lerp(&b, 0.0), a);
+        assert_eq!(a.lerp(&b, 1.0), b);
+        assert_eq!(a.lerp(&b, 0.25), Rgba::new([13, 175, 64, 191]));
+        assert_eq!(a.lerp(&b, 2.0), Rgba::new([30, 0, 255, 0]));
+        assert_eq!(a.lerp(&b, f32::NAN), a);
+        assert_eq!(Rgb::new([0.0f32, 1.0, 0.5]).lerp(&Rgb::new([1.0, 0.0, 0.5]), 0.25),
+                   Rgb::new([0.25, 0.75, 0.5]));
+        assert_eq!(a.saturating_add(&b), Rgba::new([30, 255, 255, 255]));
+        assert_eq!(a.saturating_sub(&b), Rgba::new([0, 100, 0, 255]));
+        assert_eq!(Rgb::new([-100i8, 0, 100]).saturating_sub(&Rgb::new([100, 0, -100])),
+                   Rgb::new([-128, 0, 127]));
+    }
+
     #[test]
     fn test_rgb_conversions() {
         let val: Rgb<f32> = VAL_RGB_U8.into();
@@ -151,4 +466,26 @@ mod tests {
         assert_eq!(val.as_ref()[1], 251);
     }
 
+    #[test]
+    fn test_convert_linear() {
+        for v in 0..=255u8 {
+            assert_eq!(convert_linear::<_, Gray<u16>>(Gray::new([v])), Gray::new([v as u16 * 257]));
+            assert_eq!(convert_linear::<_, Gray<u8>>(Gray::new([v as u16 * 257])), Gray::new([v]));
+            let float: Gray<f32> = convert_linear(Gray::new([v]));
+            assert_eq!(convert_linear::<_, Gray<u8>>(float), Gray::new([v]));
+            let rgb = Rgb::new([v, 255 - v, v / 2]);
+            let deep: Rgb<u16> = convert_linear(rgb);
+            assert_eq!(convert_linear::<_, Rgb<u8>>(deep), rgb);
+            assert_eq!(convert_linear::<_, Gray<u16>>(rgb), convert_linear(deep));
+        }
+        let gray = Gray::new([0.25f32]);
+        assert_eq!(convert_linear::<_, Gray<f32>>(convert_linear::<_, Rgb<f32>>(gray)), gray);
+        assert_eq!(convert_linear::<_, GrayA<f32>>(GrayA::new([255u8, 51])),
+                   GrayA::new([1.0, 0.2]));
+        assert_eq!(convert_linear::<_, Rgba<u16>>(GrayA::new([0.0f32, 0.5])),
+                   Rgba::new([0, 0, 0, 32768]));
+        assert_eq!(convert_linear::<_, Gray<u8>>(Rgba::new([1.0f32, 1.0, 1.0, 0.0])),
+                   Gray::new([255]));
+        assert_eq!(convert_linear::<_, Rgb<u16>>(Gray::new([255u8])), Rgb::new([65535; 3]));
+    }
 }
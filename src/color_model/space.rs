@@ -0,0 +1,273 @@
+//! RGB working spaces.
+
+use std::sync::OnceLock;
+
+/// A 3×3 matrix in row-major order which maps column vectors.
+pub type Matrix3 = [[f32; 3]; 3];
+
+/// Chromaticity of the CIE standard illuminant D50, the reference white of ICC profiles.
+pub const D50: [f32; 2] = [0.3457, 0.3585];
+
+/// Chromaticity of the CIE standard illuminant D65, the white point of sRGB.
+pub const D65: [f32; 2] = [0.3127, 0.3290];
+
+/// A chromatic adaptation transform.
+///
+/// Chromatic adaptation maps XYZ colors seen under one white point to the corresponding colors
+/// under another white point by scaling the responses of a cone-like space.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ChromaticAdaptation {
+    /// Scales the XYZ values directly.
+    XyzScaling,
+    /// Scales the LMS cone responses of the Hunt-Pointer-Estevez space.
+    VonKries,
+    /// The Bradford transform used by ICC color management.
+    Bradford,
+}
+
+impl ChromaticAdaptation {
+    /// Returns the matrix converting XYZ to the cone response space of this transform.
+    fn cone_matrix(self) -> [[f64; 3]; 3] {
+        match self {
+            ChromaticAdaptation::XyzScaling => [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+            ChromaticAdaptation::VonKries => {
+                [[0.40024, 0.70760, -0.08081], [-0.22630, 1.16532, 0.04570], [0.0, 0.0, 0.91822]]
+            }
+            ChromaticAdaptation::Bradford => {
+                [[0.8951, 0.2664, -0.1614], [-0.7502, 1.7135, 0.0367], [0.0389, -0.0685, 1.0296]]
+            }
+        }
+    }
+
+    /// Returns the matrix adapting XYZ colors from the white point `from` to the white point
+    /// `to`.
+    pub fn matrix(self, from: [f32; 2], to: [f32; 2]) -> Matrix3 {
+        to_f32_matrix(&self.adaptation(from, to))
+    }
+
+    /// Computes the adaptation matrix in double precision.
+    pub(crate) fn adaptation(self, from: [f32; 2], to: [f32; 2]) -> [[f64; 3]; 3] {
+        let cone = self.cone_matrix();
+        let (src, dst) = (apply(&cone, chromaticity_to_xyz(from)),
+                          apply(&cone, chromaticity_to_xyz(to)));
+        let mut scaled = cone;
+        for (row, (s, d)) in scaled.iter_mut().zip(src.iter().zip(dst.iter())) {
+            for v in row.iter_mut() {
+                *v *= d / s;
+            }
+        }
+        multiply(&invert(&cone), &scaled)
+    }
+}
+
+/// Description of a linear RGB space by the chromaticities of its primaries and white point.
+///
+/// Chromaticities are given as CIE 1931 `[x, y]` coordinates.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RgbSpace {
+    /// Chromaticity of the red primary.
+    pub red: [f32; 2],
+    /// Chromaticity of the green primary.
+    pub green: [f32; 2],
+    /// Chromaticity of the blue primary.
+    pub blue: [f32; 2],
+    /// Chromaticity of the white point.
+    pub white: [f32; 2],
+}
+
+impl RgbSpace {
+    /// sRGB and ITU-R BT.709.
+    pub const SRGB: RgbSpace = RgbSpace {
+        red: [0.64, 0.33],
+        green: [0.30, 0.60],
+        blue: [0.15, 0.06],
+        white: D65,
+    };
+
+    /// Adobe RGB (1998).
+    pub const ADOBE_RGB: RgbSpace = RgbSpace {
+        red: [0.64, 0.33],
+        green: [0.21, 0.71],
+        blue: [0.15, 0.06],
+        white: D65,
+    };
+
+    /// Display P3.
+    pub const DISPLAY_P3: RgbSpace = RgbSpace {
+        red: [0.680, 0.320],
+        green: [0.265, 0.690],
+        blue: [0.150, 0.060],
+        white: D65,
+    };
+
+    /// ITU-R BT.2020.
+    pub const REC2020: RgbSpace = RgbSpace {
+        red: [0.708, 0.292],
+        green: [0.170, 0.797],
+        blue: [0.131, 0.046],
+        white: D65,
+    };
+
+    /// Creates a space from the chromaticities of its primaries and white point.
+    pub fn new(red: [f32; 2], green: [f32; 2], blue: [f32; 2], white: [f32; 2]) -> RgbSpace {
+        RgbSpace { red, green, blue, white }
+    }
+
+    /// Returns the matrix converting linear RGB values of this space to CIE 1931 XYZ.
+    ///
+    /// The white point of the space is mapped to `Y = 1`.
+    pub fn rgb_to_xyz_matrix(&self) -> Matrix3 {
+        to_f32_matrix(&self.xyz_matrix())
+    }
+
+    /// Returns the matrix converting CIE 1931 XYZ to linear RGB values of this space.
+    pub fn xyz_to_rgb_matrix(&self) -> Matrix3 {
+        to_f32_matrix(&invert(&self.xyz_matrix()))
+    }
+
+    /// Computes the RGB to XYZ matrix in double precision.
+    fn xyz_matrix(&self) -> [[f64; 3]; 3] {
+        let (r, g, b) = (chromaticity_to_xyz(self.red),
+                         chromaticity_to_xyz(self.green),
+                         chromaticity_to_xyz(self.blue));
+        let primaries = [[r[0], g[0], b[0]], [r[1], g[1], b[1]], [r[2], g[2], b[2]]];
+        let scale = apply(&invert(&primaries), chromaticity_to_xyz(self.white));
+        let mut m = primaries;
+        for row in m.iter_mut() {
+            for (v, s) in row.iter_mut().zip(scale.iter()) {
+                *v *= s;
+            }
+        }
+        m
+    }
+}
+
+/// The matrix converting linear sRGB to CIE 1931 XYZ, computed once on first use.
+pub(crate) fn srgb_to_xyz_matrix() -> &'static Matrix3 {
+    static MATRIX: OnceLock<Matrix3> = OnceLock::new();
+    MATRIX.get_or_init(|| RgbSpace::SRGB.rgb_to_xyz_matrix())
+}
+
+/// The matrix converting CIE 1931 XYZ to linear sRGB, computed once on first use.
+pub(crate) fn xyz_to_srgb_matrix() -> &'static Matrix3 {
+    static MATRIX: OnceLock<Matrix3> = OnceLock::new();
+    MATRIX.get_or_init(|| RgbSpace::SRGB.xyz_to_rgb_matrix())
+}
+
+/// Returns the matrix converting linear RGB values of the space `from` to the space `to`.
+///
+/// The conversion is colorimetric, the XYZ values are preserved. No chromatic adaptation is
+/// performed if the white points of the spaces differ.
+///
+/// # Examples
+///
+/// ```
+/// # use image_buffer::color::{conversion_matrix, transform, RgbSpace};
+/// let m = conversion_matrix(&RgbSpace::SRGB, &RgbSpace::REC2020);
+/// let white = transform(&m, [1.0, 1.0, 1.0]);
+/// assert!(white.iter().all(|&v| (v - 1.0).abs() < 1e-4));
+/// ```
+pub fn conversion_matrix(from: &RgbSpace, to: &RgbSpace) -> Matrix3 {
+    to_f32_matrix(&multiply(&invert(&to.xyz_matrix()), &from.xyz_matrix()))
+}
+
+/// Applies the matrix `m` to the vector `v`.
+pub fn transform(m: &Matrix3, v: [f32; 3]) -> [f32; 3] {
+    let row = |r: &[f32; 3]| r[0] * v[0] + r[1] * v[1] + r[2] * v[2];
+    [row(&m[0]), row(&m[1]), row(&m[2])]
+}
+
+/// Returns the XYZ values with `Y = 1` of the chromaticity `[x, y]`.
+pub(crate) fn chromaticity_to_xyz([x, y]: [f32; 2]) -> [f64; 3] {
+    let (x, y) = (x as f64, y as f64);
+    [x / y, 1.0, (1.0 - x - y) / y]
+}
+
+/// Converts a double precision matrix to single precision.
+pub(crate) fn to_f32_matrix(m: &[[f64; 3]; 3]) -> Matrix3 {
+    let row = |r: &[f64; 3]| [r[0] as f32, r[1] as f32, r[2] as f32];
+    [row(&m[0]), row(&m[1]), row(&m[2])]
+}
+
+/// Applies the double precision matrix `m` to the vector `v`.
+pub(crate) fn apply(m: &[[f64; 3]; 3], v: [f64; 3]) -> [f64; 3] {
+    let row = |r: &[f64; 3]| r[0] * v[0] + r[1] * v[1] + r[2] * v[2];
+    [row(&m[0]), row(&m[1]), row(&m[2])]
+}
+
+/// Returns the matrix product `a * b`.
+pub(crate) fn multiply(a: &[[f64; 3]; 3], b: &[[f64; 3]; 3]) -> [[f64; 3]; 3] {
+    let mut m = [[0.0; 3]; 3];
+    for (i, row) in m.iter_mut().enumerate() {
+        for (j, v) in row.iter_mut().enumerate() {
+            *v = (0..3).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+    m
+}
+
+/// Inverts the matrix `m` with the adjugate. The matrix has to be regular.
+pub(crate) fn invert(m: &[[f64; 3]; 3]) -> [[f64; 3]; 3] {
+    let cofactor = |r0: usize, r1: usize, c0: usize, c1: usize| {
+        m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0]
+    };
+    let adjugate = [[cofactor(1, 2, 1, 2), -cofactor(0, 2, 1, 2), cofactor(0, 1, 1, 2)],
+                    [-cofactor(1, 2, 0, 2), cofactor(0, 2, 0, 2), -cofactor(0, 1, 0, 2)],
+                    [cofactor(1, 2, 0, 1), -cofactor(0, 2, 0, 1), cofactor(0, 1, 0, 1)]];
+    let det = m[0][0] * adjugate[0][0] + m[0][1] * adjugate[1][0] + m[0][2] * adjugate[2][0];
+    let mut inverse = adjugate;
+    for v in inverse.iter_mut().flat_map(|row| row.iter_mut()) {
+        *v /= det;
+    }
+    inverse
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply, chromaticity_to_xyz, conversion_matrix, ChromaticAdaptation, RgbSpace,
+                D50, D65};
+
+    #[test]
+    fn test_srgb_matrix() {
+        // The matrices published with sRGB, rounded to four digits
+        let m = RgbSpace::SRGB.rgb_to_xyz_matrix();
+        let expected = [[0.4124, 0.3576, 0.1805],
+                        [0.2126, 0.7152, 0.0722],
+                        [0.0193, 0.1192, 0.9505]];
+        let inverse = RgbSpace::SRGB.xyz_to_rgb_matrix();
+        let expected_inverse = [[3.2406, -1.5372, -0.4986],
+                                [-0.9689, 1.8758, 0.0415],
+                                [0.0557, -0.2040, 1.0570]];
+        for (m, e) in [(m, expected), (inverse, expected_inverse)] {
+            for (a, b) in m.iter().flatten().zip(e.iter().flatten()) {
+                assert!((a - b).abs() < 1e-3, "{:?}", m);
+            }
+        }
+    }
+
+    #[test]
+    fn test_adaptation() {
+        for &cat in &[ChromaticAdaptation::XyzScaling,
+                      ChromaticAdaptation::VonKries,
+                      ChromaticAdaptation::Bradford] {
+            let m = cat.adaptation(D65, D50);
+            let white = apply(&m, chromaticity_to_xyz(D65));
+            for (a, b) in white.iter().zip(chromaticity_to_xyz(D50).iter()) {
+                assert!((a - b).abs() < 1e-9);
+            }
+        }
+        // Reference value of the Bradford matrix from D65 to D50
+        let m = ChromaticAdaptation::Bradford.matrix(D65, D50);
+        assert!((m[0][0] - 1.0478).abs() < 1e-3, "{:?}", m);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let forward = conversion_matrix(&RgbSpace::SRGB, &RgbSpace::DISPLAY_P3);
+        let backward = conversion_matrix(&RgbSpace::DISPLAY_P3, &RgbSpace::SRGB);
+        let v = super::transform(&backward, super::transform(&forward, [0.2, 0.5, 0.9]));
+        for (a, b) in v.iter().zip([0.2, 0.5, 0.9].iter()) {
+            assert!((a - b).abs() < 1e-5);
+        }
+    }
+}
@@ -0,0 +1,113 @@
+//! Y'CbCr colors as used by JPEG and video.
+
+use math::clamp;
+use super::{LumaWeights, Rgb, YCbCr};
+
+/// The range of the quantized Y'CbCr values.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum YCbCrRange {
+    /// Luma and chroma use the full range `0..=255`, as in JPEG.
+    Full,
+    /// Luma uses `16..=235` and chroma `16..=240`, as in broadcast video.
+    Studio,
+}
+
+impl YCbCrRange {
+    /// Returns the offset and scale of luma and the scale of chroma.
+    fn scales(self) -> (f32, f32, f32) {
+        match self {
+            YCbCrRange::Full => (0.0, 255.0, 255.0),
+            YCbCrRange::Studio => (16.0, 219.0, 224.0),
+        }
+    }
+}
+
+/// Rounds and clamps `v` to `u8`.
+fn quantize(v: f32) -> u8 {
+    clamp(v.round(), 0.0, 255.0) as u8
+}
+
+impl YCbCr<u8> {
+    /// Converts gamma encoded `rgb` using the luma coefficients `weights` and the value range
+    /// `range`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::color::{LumaWeights, Rgb, YCbCr, YCbCrRange};
+    /// let white = Rgb::new([255, 255, 255]);
+    /// let ycbcr = YCbCr::from_rgb(white, LumaWeights::Bt709, YCbCrRange::Studio);
+    /// assert_eq!(ycbcr, YCbCr::new([235, 128, 128]));
+    /// ```
+    pub fn from_rgb(rgb: Rgb<u8>, weights: LumaWeights, range: YCbCrRange) -> YCbCr<u8> {
+        let [kr, kg, kb] = weights.coefficients();
+        let (offset, luma_scale, chroma_scale) = range.scales();
+        let c = rgb.as_ref();
+        let (r, g, b) = (c[0] as f32 / 255.0, c[1] as f32 / 255.0, c[2] as f32 / 255.0);
+        let y = kr * r + kg * g + kb * b;
+        let cb = (b - y) / (2.0 * (1.0 - kb));
+        let cr = (r - y) / (2.0 * (1.0 - kr));
+        YCbCr::new([quantize(offset + luma_scale * y),
+                    quantize(128.0 + chroma_scale * cb),
+                    quantize(128.0 + chroma_scale * cr)])
+    }
+
+    /// Converts this color to gamma encoded RGB given the luma coefficients `weights` and the
+    /// value range `range` it was encoded with.
+    pub fn to_rgb(&self, weights: LumaWeights, range: YCbCrRange) -> Rgb<u8> {
+        let [kr, kg, kb] = weights.coefficients();
+        let (offset, luma_scale, chroma_scale) = range.scales();
+        let c = self.as_ref();
+        let y = (c[0] as f32 - offset) / luma_scale;
+        let cb = (c[1] as f32 - 128.0) / chroma_scale;
+        let cr = (c[2] as f32 - 128.0) / chroma_scale;
+        let r = y + 2.0 * (1.0 - kr) * cr;
+        let b = y + 2.0 * (1.0 - kb) * cb;
+        let g = (y - kr * r - kb * b) / kg;
+        Rgb::new([quantize(r * 255.0), quantize(g * 255.0), quantize(b * 255.0)])
+    }
+}
+
+/// Converts with the BT.601 coefficients in the full range, as defined by JFIF.
+impl From<Rgb<u8>> for YCbCr<u8> {
+    fn from(other: Rgb<u8>) -> Self {
+        YCbCr::from_rgb(other, LumaWeights::Bt601, YCbCrRange::Full)
+    }
+}
+
+/// Converts with the BT.601 coefficients in the full range, as defined by JFIF.
+impl From<YCbCr<u8>> for Rgb<u8> {
+    fn from(other: YCbCr<u8>) -> Self {
+        other.to_rgb(LumaWeights::Bt601, YCbCrRange::Full)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::YCbCrRange;
+    use super::super::{LumaWeights, Rgb, YCbCr};
+
+    #[test]
+    fn test_jfif() {
+        assert_eq!(YCbCr::from(Rgb::new([255, 0, 0])), YCbCr::new([76, 85, 255]));
+        assert_eq!(Rgb::from(YCbCr::new([128, 128, 128])), Rgb::new([128, 128, 128]));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let colors = [[0, 0, 0], [255, 255, 255], [12, 200, 99], [250, 128, 3]];
+        for &weights in &[LumaWeights::Bt601, LumaWeights::Bt709] {
+            for &range in &[YCbCrRange::Full, YCbCrRange::Studio] {
+                for &c in &colors {
+                    let rgb = Rgb::new(c);
+                    let back = YCbCr::from_rgb(rgb, weights, range).to_rgb(weights, range);
+                    for i in 0..3 {
+                        assert!((back[i] as i32 - c[i] as i32).abs() <= 2, "{:?} {:?}", back, c);
+                    }
+                }
+            }
+        }
+        let black = YCbCr::from_rgb(Rgb::new([0, 0, 0]), LumaWeights::Bt601, YCbCrRange::Studio);
+        assert_eq!(black, YCbCr::new([16, 128, 128]));
+    }
+}
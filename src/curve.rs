@@ -0,0 +1,379 @@
+//! Pixel orders along the Morton (Z-order) and Hilbert space filling curves.
+//!
+//! Both curves visit a square whose side is a power of two such that pixels which are close in
+//! the order are also close in the image, which improves the cache locality of algorithms that
+//! touch neighboring pixels and matches the tiled texture layouts of GPUs. Images of other
+//! dimensions are traversed along the curve of the smallest enclosing square, skipping the
+//! blocks of the curve outside of the image.
+
+use std::ops::{Deref, Range};
+
+use buffer::{BufferError, ImageBuffer};
+use traits::Color as Pixel;
+
+/// Spreads the bits of `v` to the even bits of the result.
+fn spread_bits(v: u32) -> u64 {
+    let mut v = v as u64;
+    v = (v | (v << 16)) & 0x0000_ffff_0000_ffff;
+    v = (v | (v << 8)) & 0x00ff_00ff_00ff_00ff;
+    v = (v | (v << 4)) & 0x0f0f_0f0f_0f0f_0f0f;
+    v = (v | (v << 2)) & 0x3333_3333_3333_3333;
+    (v | (v << 1)) & 0x5555_5555_5555_5555
+}
+
+/// Collects the even bits of `v`, the inverse of `spread_bits`.
+fn compact_bits(v: u64) -> u32 {
+    let mut v = v & 0x5555_5555_5555_5555;
+    v = (v | (v >> 1)) & 0x3333_3333_3333_3333;
+    v = (v | (v >> 2)) & 0x0f0f_0f0f_0f0f_0f0f;
+    v = (v | (v >> 4)) & 0x00ff_00ff_00ff_00ff;
+    v = (v | (v >> 8)) & 0x0000_ffff_0000_ffff;
+    ((v | (v >> 16)) & 0x0000_0000_ffff_ffff) as u32
+}
+
+/// Returns the position of `(x, y)` along the Morton curve, which interleaves the bits of `x`
+/// and `y` with the bits of `x` in the even positions.
+///
+/// # Examples
+///
+/// ```
+/// use image_buffer::curve::{morton_index, morton_point};
+/// assert_eq!(morton_index(1, 0), 1);
+/// assert_eq!(morton_index(0, 1), 2);
+/// assert_eq!(morton_index(3, 3), 15);
+/// assert_eq!(morton_point(morton_index(1234, 5678)), (1234, 5678));
+/// ```
+pub fn morton_index(x: u32, y: u32) -> u64 {
+    spread_bits(x) | (spread_bits(y) << 1)
+}
+
+/// Returns the point at `index` along the Morton curve, the inverse of `morton_index`.
+pub fn morton_point(index: u64) -> (u32, u32) {
+    (compact_bits(index), compact_bits(index >> 1))
+}
+
+/// Rotates and flips the quadrant of `(x, y)` of the Hilbert curve in a square of side `side`.
+fn hilbert_rotate(side: u32, (x, y): (u32, u32), rx: u32, ry: u32) -> (u32, u32) {
+    if ry != 0 {
+        return (x, y);
+    }
+    if rx == 1 {
+        (side - 1 - y, side - 1 - x)
+    } else {
+        (y, x)
+    }
+}
+
+/// Panics if `side` is not a power of two.
+fn check_side(side: u32) {
+    assert!(side.is_power_of_two(),
+            "side {} of the Hilbert curve is not a power of two",
+            side);
+}
+
+/// Returns the position of `(x, y)` along the Hilbert curve filling the square of side `side`,
+/// which starts at `(0, 0)` and ends at `(side - 1, 0)`.
+///
+/// # Panics
+///
+/// If `side` is not a power of two or the point lies outside of the square.
+///
+/// # Examples
+///
+/// ```
+/// use image_buffer::curve::{hilbert_index, hilbert_point};
+/// let order: Vec<(u32, u32)> = (0..4).map(|i| hilbert_point(2, i)).collect();
+/// assert_eq!(order, [(0, 0), (0, 1), (1, 1), (1, 0)]);
+/// assert_eq!(hilbert_index(16, hilbert_point(16, 100)), 100);
+/// ```
+pub fn hilbert_index(side: u32, (x, y): (u32, u32)) -> u64 {
+    check_side(side);
+    assert!(x < side && y < side,
+            "point ({}, {}) out of bounds of the square of side {}",
+            x,
+            y,
+            side);
+    let (mut x, mut y) = (x, y);
+    let mut index = 0;
+    let mut s = side / 2;
+    while s > 0 {
+        let rx = (x & s != 0) as u32;
+        let ry = (y & s != 0) as u32;
+        index += s as u64 * s as u64 * ((3 * rx) ^ ry) as u64;
+        let (rotated_x, rotated_y) = hilbert_rotate(side, (x, y), rx, ry);
+        x = rotated_x & (s - 1);
+        y = rotated_y & (s - 1);
+        s /= 2;
+    }
+    index
+}
+
+/// Returns the point at `index` along the Hilbert curve filling the square of side `side`, the
+/// inverse of `hilbert_index`.
+///
+/// # Panics
+///
+/// If `side` is not a power of two or `index` is not smaller than `side * side`.
+pub fn hilbert_point(side: u32, index: u64) -> (u32, u32) {
+    check_side(side);
+    assert!(index < side as u64 * side as u64,
+            "index {} out of bounds of the square of side {}",
+            index,
+            side);
+    let (mut x, mut y) = (0, 0);
+    let mut t = index;
+    let mut s = 1;
+    while s < side {
+        let rx = (1 & (t / 2)) as u32;
+        let ry = (1 & (t ^ rx as u64)) as u32;
+        let (rotated_x, rotated_y) = hilbert_rotate(s, (x, y), rx, ry);
+        x = rotated_x + s * rx;
+        y = rotated_y + s * ry;
+        t /= 4;
+        s *= 2;
+    }
+    (x, y)
+}
+
+/// The side of the smallest square with a side that is a power of two enclosing an image.
+///
+/// The side can be `2^32`, which does not fit into `u32`.
+fn enclosing_side((width, height): (u32, u32)) -> u64 {
+    (width.max(height).max(1) as u64).next_power_of_two()
+}
+
+/// Iterates over the points of an image along a curve filling the enclosing square.
+///
+/// Every block of `side * side` indices starting at a multiple of `side * side` covers an
+/// aligned square of side `side`. Blocks outside of the image are skipped as a whole and blocks
+/// inside of the image are traversed without checks, such that elongated images are traversed
+/// in time proportional to the number of pixels times the depth of the curve.
+struct CurvePoints<F> {
+    point: F,
+    dimensions: (u32, u32),
+    blocks: Vec<(u64, u64)>,
+    run: Range<u64>,
+}
+
+impl<F: Fn(u64) -> (u32, u32)> CurvePoints<F> {
+    fn new(dimensions: (u32, u32), side: u64, point: F) -> CurvePoints<F> {
+        let blocks = if dimensions.0 == 0 || dimensions.1 == 0 {
+            Vec::new()
+        } else {
+            vec![(0, side)]
+        };
+        CurvePoints {
+            point,
+            dimensions,
+            blocks,
+            run: 0..0,
+        }
+    }
+}
+
+impl<F: Fn(u64) -> (u32, u32)> Iterator for CurvePoints<F> {
+    type Item = (u32, u32);
+
+    fn next(&mut self) -> Option<(u32, u32)> {
+        loop {
+            if let Some(i) = self.run.next() {
+                return Some((self.point)(i));
+            }
+            let (start, side) = self.blocks.pop()?;
+            let (x, y) = (self.point)(start);
+            let (x, y) = (x as u64 & !(side - 1), y as u64 & !(side - 1));
+            let (width, height) = (self.dimensions.0 as u64, self.dimensions.1 as u64);
+            if x >= width || y >= height {
+                continue;
+            }
+            if x + side <= width && y + side <= height {
+                // Images are smaller than the square of side 2^32, such that this cannot overflow
+                self.run = start..start + side * side;
+            } else {
+                let quarter = side / 2 * (side / 2);
+                for k in (0..4).rev() {
+                    self.blocks.push((start + k * quarter, side / 2));
+                }
+            }
+        }
+    }
+}
+
+/// Returns the points of an image of the given dimensions in Morton order.
+fn morton_points(dimensions: (u32, u32)) -> impl Iterator<Item = (u32, u32)> {
+    CurvePoints::new(dimensions, enclosing_side(dimensions), morton_point)
+}
+
+/// Returns the points of an image of the given dimensions in Hilbert order.
+///
+/// # Panics
+///
+/// If a dimension is larger than `2^31`.
+fn hilbert_points(dimensions: (u32, u32)) -> impl Iterator<Item = (u32, u32)> {
+    let side = enclosing_side(dimensions);
+    assert!(side <= 1 << 31,
+            "dimensions {:?} too large for the Hilbert curve",
+            dimensions);
+    let side = side as u32;
+    CurvePoints::new(dimensions, side as u64, move |i| hilbert_point(side, i))
+}
+
+impl<P, Container> ImageBuffer<P, Container>
+    where P: Pixel,
+          Container: Deref<Target = [P::Subpixel]>
+{
+    /// Returns an iterator over the coordinates and pixels of the image in Morton order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{GrayImage, color::Gray};
+    /// let image = GrayImage::from_fn(3, 2, |x, y| Gray::new([(x + 3 * y) as u8]));
+    /// let order: Vec<u8> = image.morton_pixels().map(|(_, _, p)| p[0]).collect();
+    /// assert_eq!(order, [0, 1, 3, 4, 2, 5]);
+    /// ```
+    pub fn morton_pixels<'a>(&'a self) -> impl Iterator<Item = (u32, u32, &'a P)> + 'a {
+        morton_points(self.dimensions()).map(move |(x, y)| (x, y, &self[(x, y)]))
+    }
+
+    /// Returns an iterator over the coordinates and pixels of the image in Hilbert order.
+    ///
+    /// # Panics
+    ///
+    /// If a dimension of the image is larger than `2^31`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{GrayImage, color::Gray};
+    /// let image = GrayImage::from_fn(2, 2, |x, y| Gray::new([(x + 2 * y) as u8]));
+    /// let order: Vec<u8> = image.hilbert_pixels().map(|(_, _, p)| p[0]).collect();
+    /// assert_eq!(order, [0, 2, 3, 1]);
+    /// ```
+    pub fn hilbert_pixels<'a>(&'a self) -> impl Iterator<Item = (u32, u32, &'a P)> + 'a {
+        hilbert_points(self.dimensions()).map(move |(x, y)| (x, y, &self[(x, y)]))
+    }
+
+    /// Copies the subpixels into a `Vec` with the pixels in Morton order.
+    ///
+    /// The pixels outside of the image are skipped, such that the result holds exactly
+    /// `width * height` pixels. Use `from_morton_layout` to restore the image.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{GrayImage, color::Gray};
+    /// let image = GrayImage::from_fn(4, 4, |x, y| Gray::new([(x + 4 * y) as u8]));
+    /// let morton = image.to_morton_layout();
+    /// assert_eq!(&morton[..8], &[0, 1, 4, 5, 2, 3, 6, 7]);
+    /// assert_eq!(GrayImage::from_morton_layout(4, 4, morton).unwrap().into_raw(),
+    ///            image.into_raw());
+    /// ```
+    pub fn to_morton_layout(&self) -> Vec<P::Subpixel> {
+        let len = self.width() as usize * self.height() as usize * P::channel_count();
+        let mut data = Vec::with_capacity(len);
+        for (_, _, p) in self.morton_pixels() {
+            data.extend_from_slice(p.channels().as_ref());
+        }
+        data
+    }
+}
+
+impl<P: Pixel> ImageBuffer<P, Vec<P::Subpixel>> {
+    /// Constructs a buffer from exactly `width * height` pixels in Morton order, as returned by
+    /// `to_morton_layout`.
+    pub fn from_morton_layout(width: u32,
+                              height: u32,
+                              data: Vec<P::Subpixel>)
+                              -> Result<ImageBuffer<P, Vec<P::Subpixel>>, BufferError> {
+        let source = ImageBuffer::<P, _>::from_vec(width, height, data)?.into_raw();
+        let mut image = ImageBuffer::new(width, height);
+        let pixels = source.chunks_exact(P::channel_count());
+        for ((x, y), p) in morton_points((width, height)).zip(pixels) {
+            image[(x, y)] = *P::from_slice(p);
+        }
+        Ok(image)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buffer::{BufferError, GrayImage, RgbImage};
+    use color_model::Rgb;
+    use super::{enclosing_side, hilbert_index, hilbert_point, hilbert_points, morton_index,
+                morton_point, morton_points};
+
+    #[test]
+    fn test_indices() {
+        for &(x, y) in &[(0, 0), (5, 9), (65535, 1), (u32::MAX, u32::MAX), (0x8000_0001, 7)] {
+            assert_eq!(morton_point(morton_index(x, y)), (x, y));
+        }
+        assert_eq!(morton_index(u32::MAX, 0), 0x5555_5555_5555_5555);
+
+        for &side in &[1, 2, 8, 64] {
+            let mut seen = vec![false; (side * side) as usize];
+            let mut last = hilbert_point(side, 0);
+            assert_eq!(last, (0, 0));
+            for i in 0..side as u64 * side as u64 {
+                let (x, y) = hilbert_point(side, i);
+                assert_eq!(hilbert_index(side, (x, y)), i);
+                seen[(x + side * y) as usize] = true;
+                // Consecutive points are neighbors
+                let step = (x as i64 - last.0 as i64).abs() + (y as i64 - last.1 as i64).abs();
+                assert!(i == 0 || step == 1, "jump at {} of side {}", i, side);
+                last = (x, y);
+            }
+            assert!(seen.iter().all(|&s| s));
+        }
+    }
+
+    #[test]
+    fn test_image_orders() {
+        let image = RgbImage::from_fn(5, 3, |x, y| Rgb::new([x as u8, y as u8, 0]));
+        let mut morton: Vec<(u32, u32)> = image.morton_pixels().map(|(x, y, _)| (x, y)).collect();
+        let mut hilbert: Vec<(u32, u32)> =
+            image.hilbert_pixels().map(|(_, _, p)| (p[0] as u32, p[1] as u32)).collect();
+        assert_eq!(morton.len(), 15);
+        assert_eq!(&morton[..5], &[(0, 0), (1, 0), (0, 1), (1, 1), (2, 0)]);
+        morton.sort();
+        hilbert.sort();
+        let expected: Vec<(u32, u32)> = (0..5).flat_map(|x| (0..3).map(move |y| (x, y))).collect();
+        assert_eq!(morton, expected);
+        assert_eq!(hilbert, expected);
+
+        let layout = image.to_morton_layout();
+        assert_eq!(&layout[..6], &[0, 0, 0, 1, 0, 0]);
+        let restored = RgbImage::from_morton_layout(5, 3, layout).unwrap();
+        assert_eq!(restored.into_raw(), image.into_raw());
+
+        assert_eq!(GrayImage::new(0, 4).morton_pixels().count(), 0);
+        assert_eq!(GrayImage::new(4, 0).to_morton_layout(), Vec::<u8>::new());
+        assert_eq!(GrayImage::from_morton_layout(2, 2, vec![0; 3]).err(),
+                   Some(BufferError::TooSmall { expected: 4, got: 3 }));
+        let strided = GrayImage::from_raw_with_stride(1, 2, 3, vec![7, 0, 0, 8]).unwrap();
+        assert_eq!(strided.hilbert_pixels().map(|(_, _, p)| p[0]).collect::<Vec<_>>(), [7, 8]);
+    }
+
+    #[test]
+    fn test_skipped_blocks() {
+        for &(width, height) in &[(1, 1), (3, 5), (7, 2), (1, 17), (16, 16), (33, 1)] {
+            let side = enclosing_side((width, height)) as u32;
+            let inside = |&(x, y): &(u32, u32)| x < width && y < height;
+            let morton: Vec<_> = (0..side as u64 * side as u64).map(morton_point)
+                .filter(&inside)
+                .collect();
+            let hilbert: Vec<_> = (0..side as u64 * side as u64)
+                .map(|i| hilbert_point(side, i))
+                .filter(&inside)
+                .collect();
+            assert_eq!(morton_points((width, height)).collect::<Vec<_>>(), morton);
+            assert_eq!(hilbert_points((width, height)).collect::<Vec<_>>(), hilbert);
+        }
+
+        // Filtering the enclosing square would take 2^40 steps
+        assert_eq!(morton_points((1, 1 << 20)).count(), 1 << 20);
+        assert_eq!(hilbert_points((1 << 20, 1)).count(), 1 << 20);
+        assert_eq!(enclosing_side((u32::MAX, 1)), 1 << 32);
+        assert_eq!(morton_points((u32::MAX, 1)).nth(2), Some((2, 0)));
+    }
+}
@@ -0,0 +1,237 @@
+//! Preprocessing of scanned documents.
+
+use std::ops::Deref;
+
+use buffer::{GrayImage, ImageBuffer};
+use color_model::Gray;
+
+/// Dynamic range of the standard deviation used by Sauvola's method.
+const SAUVOLA_RANGE: f64 = 128.0;
+
+/// Maximal skew angle in degrees considered by `estimate_skew`.
+const MAX_SKEW: f32 = 15.0;
+
+/// Integral images of the values and the squared values.
+struct Integral {
+    width: usize,
+    sum: Vec<f64>,
+    sum_sq: Vec<f64>,
+}
+
+impl Integral {
+    fn new<C: Deref<Target = [u8]>>(image: &ImageBuffer<Gray<u8>, C>) -> Integral {
+        let width = image.width() as usize + 1;
+        let len = width * (image.height() as usize + 1);
+        let (mut sum, mut sum_sq) = (vec![0.0; len], vec![0.0; len]);
+        for (y, row) in image.rows().enumerate() {
+            let (mut row_sum, mut row_sum_sq) = (0.0, 0.0);
+            for (x, p) in row.iter().enumerate() {
+                let v = p[0] as f64;
+                row_sum += v;
+                row_sum_sq += v * v;
+                let i = (y + 1) * width + x + 1;
+                sum[i] = sum[i - width] + row_sum;
+                sum_sq[i] = sum_sq[i - width] + row_sum_sq;
+            }
+        }
+        Integral { width, sum, sum_sq }
+    }
+
+    /// Mean and standard deviation of the rectangle `[x0, x1) × [y0, y1)`.
+    fn stats(&self, x0: usize, y0: usize, x1: usize, y1: usize) -> (f64, f64) {
+        let w = self.width;
+        let area = |t: &[f64]| t[y1 * w + x1] - t[y0 * w + x1] - t[y1 * w + x0] + t[y0 * w + x0];
+        let n = ((x1 - x0) * (y1 - y0)) as f64;
+        let mean = area(&self.sum) / n;
+        let variance = (area(&self.sum_sq) / n - mean * mean).max(0.0);
+        (mean, variance.sqrt())
+    }
+}
+
+impl<C: Deref<Target = [u8]>> ImageBuffer<Gray<u8>, C> {
+    /// Binarizes the image with Sauvola's adaptive threshold.
+    ///
+    /// The threshold of each pixel is computed from the mean and standard deviation of the
+    /// `window`×`window` neighborhood centered on it. `k` controls how much the threshold is
+    /// lowered in low contrast regions, typical values are between `0.2` and `0.5`. Pixels above
+    /// the threshold become white (255), all others black (0).
+    pub fn binarize_sauvola(&self, window: u32, k: f32) -> GrayImage {
+        let (width, height) = self.dimensions();
+        let integral = Integral::new(self);
+        let r = (window / 2) as usize;
+        let k = k as f64;
+        GrayImage::from_fn(width, height, |x, y| {
+            let (x, y) = (x as usize, y as usize);
+            let (x0, y0) = (x.saturating_sub(r), y.saturating_sub(r));
+            let x1 = (x + r + 1).min(width as usize);
+            let y1 = (y + r + 1).min(height as usize);
+            let (mean, std_dev) = integral.stats(x0, y0, x1, y1);
+            let threshold = mean * (1.0 + k * (std_dev / SAUVOLA_RANGE - 1.0));
+            if self[(x as u32, y as u32)][0] as f64 > threshold {
+                Gray::new([255])
+            } else {
+                Gray::new([0])
+            }
+        })
+    }
+
+    /// Removes uneven illumination by dividing the image by an estimate of its background.
+    ///
+    /// The background is estimated with a box blur of the given `radius`, which should be large
+    /// compared to the features of the image, like the height of a line of text. The result is
+    /// rescaled such that the mean brightness of the background is preserved.
+    pub fn flatten_background(&self, radius: u32) -> GrayImage {
+        let (width, height) = self.dimensions();
+        let integral = Integral::new(self);
+        let r = radius as usize;
+        let background: Vec<f64> = (0..height as usize)
+            .flat_map(|y| (0..width as usize).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let (x0, y0) = (x.saturating_sub(r), y.saturating_sub(r));
+                let x1 = (x + r + 1).min(width as usize);
+                let y1 = (y + r + 1).min(height as usize);
+                integral.stats(x0, y0, x1, y1).0.max(1.0)
+            })
+            .collect();
+        let level = background.iter().sum::<f64>() / background.len().max(1) as f64;
+        GrayImage::from_fn(width, height, |x, y| {
+            let v = self[(x, y)][0] as f64 / background[(y * width + x) as usize] * level;
+            Gray::new([v.round().min(255.0) as u8])
+        })
+    }
+
+    /// Estimates the skew of the text lines in degrees with a projection profile.
+    ///
+    /// Positive angles mean that the lines descend from left to right. Dark pixels (below 128)
+    /// are considered to be text. Angles up to ±15° are detected.
+    pub fn estimate_skew(&self) -> f32 {
+        let dark: Vec<(f32, f32)> = self.enumerate_pixels()
+            .filter(|&(_, _, p)| p[0] < 128)
+            .map(|(x, y, _)| (x as f32, y as f32))
+            .collect();
+        if dark.is_empty() {
+            return 0.0;
+        }
+        let search = |from: f32, to: f32, step: f32| {
+            let mut best = (from, -1.0);
+            let mut angle = from;
+            while angle <= to + step / 2.0 {
+                let score = profile_score(&dark, angle, self.width() + self.height());
+                if score > best.1 {
+                    best = (angle, score);
+                }
+                angle += step;
+            }
+            best.0
+        };
+        let coarse = search(-MAX_SKEW, MAX_SKEW, 1.0);
+        search(coarse - 1.0, coarse + 1.0, 0.05)
+    }
+
+    /// Rotates the image such that the skew estimated by `estimate_skew` is removed.
+    ///
+    /// Areas outside of the original image are filled with white.
+    pub fn deskew(&self) -> GrayImage {
+        self.rotate_about_center(self.estimate_skew())
+    }
+
+    /// Rotates the image by `angle` degrees clockwise about its center using bilinear
+    /// interpolation. Areas outside of the original image are filled with white.
+    fn rotate_about_center(&self, angle: f32) -> GrayImage {
+        let (width, height) = self.dimensions();
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let (cx, cy) = (width as f32 / 2.0, height as f32 / 2.0);
+        let sample = |x: i64, y: i64| if x < 0 || y < 0 || x >= width as i64 || y >= height as i64 {
+            255.0
+        } else {
+            self[(x as u32, y as u32)][0] as f32
+        };
+        GrayImage::from_fn(width, height, |x, y| {
+            let (dx, dy) = (x as f32 + 0.5 - cx, y as f32 + 0.5 - cy);
+            let sx = dx * cos - dy * sin + cx - 0.5;
+            let sy = dx * sin + dy * cos + cy - 0.5;
+            let (x0, y0) = (sx.floor(), sy.floor());
+            let (fx, fy) = (sx - x0, sy - y0);
+            let (x0, y0) = (x0 as i64, y0 as i64);
+            let top = sample(x0, y0) * (1.0 - fx) + sample(x0 + 1, y0) * fx;
+            let bottom = sample(x0, y0 + 1) * (1.0 - fx) + sample(x0 + 1, y0 + 1) * fx;
+            Gray::new([(top * (1.0 - fy) + bottom * fy).round() as u8])
+        })
+    }
+}
+
+/// Sum of the squared bins of the projection of `points` along lines with the slope `angle`.
+fn profile_score(points: &[(f32, f32)], angle: f32, size: u32) -> f64 {
+    let tan = angle.to_radians().tan();
+    let offset = size as f32;
+    let mut bins = vec![0u32; 3 * size as usize + 1];
+    for &(x, y) in points {
+        let r = (y - x * tan + offset).round();
+        if r >= 0.0 && (r as usize) < bins.len() {
+            bins[r as usize] += 1;
+        }
+    }
+    bins.iter().map(|&b| b as f64 * b as f64).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use buffer::GrayImage;
+    use color_model::Gray;
+
+    /// White page with black lines of text descending with `angle` degrees.
+    fn page(angle: f32) -> GrayImage {
+        let tan = angle.to_radians().tan();
+        GrayImage::from_fn(240, 160, |x, y| {
+            let r = y as f32 - (x as f32 - 120.0) * tan;
+            if x > 20 && x < 220 && r > 20.0 && r < 140.0 && (r as u32) % 12 < 2 {
+                Gray::new([0])
+            } else {
+                Gray::new([255])
+            }
+        })
+    }
+
+    #[test]
+    fn test_skew() {
+        let image = page(4.0);
+        assert!((image.estimate_skew() - 4.0).abs() < 0.2);
+        assert!(image.deskew().estimate_skew().abs() < 0.2);
+        assert!((page(-2.5).estimate_skew() + 2.5).abs() < 0.2);
+    }
+
+    #[test]
+    fn test_flatten_background() {
+        // Paper illuminated from the left with a dark dot in the center
+        let image = GrayImage::from_fn(64, 64, |x, y| {
+            let light = 100 + 2 * x as u8;
+            if x / 4 == 8 && y / 4 == 8 { Gray::new([light / 4]) } else { Gray::new([light]) }
+        });
+        let flat = image.flatten_background(12);
+        let paper: Vec<u8> = flat.enumerate_pixels()
+            .filter(|&(x, y, _)| x > 12 && x < 52 && (x / 4 != 8 || y / 4 != 8))
+            .map(|(_, _, p)| p[0])
+            .collect();
+        let (min, max) = (paper.iter().min().unwrap(), paper.iter().max().unwrap());
+        assert!(max - min < 10, "{} {}", min, max);
+        assert!(flat[(33, 33)][0] < min / 2);
+    }
+
+    #[test]
+    fn test_binarize_sauvola() {
+        // Text on a strongly varying background
+        let image = GrayImage::from_fn(64, 16, |x, y| {
+            let background = 90 + 2 * x as u8;
+            if x % 8 == 4 && y > 4 && y < 12 {
+                Gray::new([background - 80])
+            } else {
+                Gray::new([background])
+            }
+        });
+        let binary = image.binarize_sauvola(15, 0.3);
+        for (x, y, p) in binary.enumerate_pixels() {
+            let text = x % 8 == 4 && y > 4 && y < 12;
+            assert_eq!(p[0], if text { 0 } else { 255 }, "({}, {})", x, y);
+        }
+    }
+}
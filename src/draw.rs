@@ -0,0 +1,1302 @@
+//! Drawing of simple shapes and text for debug visualizations.
+//!
+//! Coordinates are signed, shapes may lie partially or entirely outside of the image and are
+//! clipped to it. The shapes are drawn without antialiasing by replacing the pixels they cover.
+//! A `Canvas` additionally clips to a stack of rectangles and masks, such that overlapping
+//! elements can be drawn without clamping the coordinates of every call.
+//!
+//! The functions ending in `_aa` draw antialiased shapes at fractional coordinates, where the
+//! pixel `(x, y)` covers the square from `(x, y)` to `(x + 1, y + 1)`. The color is composited
+//! over the image with the fraction of every pixel covered by the shape by
+//! `Blend::blend_coverage`, colors with alpha are composited with the operator over. They are
+//! built on `rasterize`, which emits the covered spans of a `Shape` for custom paint sources,
+//! and `paint_shape`. `fill_shape` paints a shape with a `PaintSource` such as a gradient or a
+//! tiled image.
+
+use std::cmp;
+use std::f32::consts::PI;
+use std::ops::{Deref, DerefMut};
+
+use buffer::{GrayImage, ImageBuffer};
+use blend::Blend;
+use color_model::Gray;
+use traits::{ChannelMax, Color as Pixel, ImageViewMut};
+
+/// The width and height of a glyph of the bitmap font in pixels.
+pub const GLYPH_SIZE: u32 = 8;
+
+/// The 8×8 glyphs of the printable ASCII characters from the public domain font8x8, each byte
+/// is a row with the leftmost pixel in the lowest bit.
+const FONT: [[u8; 8]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // space
+    [0x18, 0x3C, 0x3C, 0x18, 0x18, 0x00, 0x18, 0x00], // !
+    [0x36, 0x36, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // "
+    [0x36, 0x36, 0x7F, 0x36, 0x7F, 0x36, 0x36, 0x00], // #
+    [0x0C, 0x3E, 0x03, 0x1E, 0x30, 0x1F, 0x0C, 0x00], // $
+    [0x00, 0x63, 0x33, 0x18, 0x0C, 0x66, 0x63, 0x00], // %
+    [0x1C, 0x36, 0x1C, 0x6E, 0x3B, 0x33, 0x6E, 0x00], // &
+    [0x06, 0x06, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00], // '
+    [0x18, 0x0C, 0x06, 0x06, 0x06, 0x0C, 0x18, 0x00], // (
+    [0x06, 0x0C, 0x18, 0x18, 0x18, 0x0C, 0x06, 0x00], // )
+    [0x00, 0x66, 0x3C, 0xFF, 0x3C, 0x66, 0x00, 0x00], // *
+    [0x00, 0x0C, 0x0C, 0x3F, 0x0C, 0x0C, 0x00, 0x00], // +
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C, 0x06], // ,
+    [0x00, 0x00, 0x00, 0x3F, 0x00, 0x00, 0x00, 0x00], // -
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C, 0x00], // .
+    [0x60, 0x30, 0x18, 0x0C, 0x06, 0x03, 0x01, 0x00], // /
+    [0x3E, 0x63, 0x73, 0x7B, 0x6F, 0x67, 0x3E, 0x00], // 0
+    [0x0C, 0x0E, 0x0C, 0x0C, 0x0C, 0x0C, 0x3F, 0x00], // 1
+    [0x1E, 0x33, 0x30, 0x1C, 0x06, 0x33, 0x3F, 0x00], // 2
+    [0x1E, 0x33, 0x30, 0x1C, 0x30, 0x33, 0x1E, 0x00], // 3
+    [0x38, 0x3C, 0x36, 0x33, 0x7F, 0x30, 0x78, 0x00], // 4
+    [0x3F, 0x03, 0x1F, 0x30, 0x30, 0x33, 0x1E, 0x00], // 5
+    [0x1C, 0x06, 0x03, 0x1F, 0x33, 0x33, 0x1E, 0x00], // 6
+    [0x3F, 0x33, 0x30, 0x18, 0x0C, 0x0C, 0x0C, 0x00], // 7
+    [0x1E, 0x33, 0x33, 0x1E, 0x33, 0x33, 0x1E, 0x00], // 8
+    [0x1E, 0x33, 0x33, 0x3E, 0x30, 0x18, 0x0E, 0x00], // 9
+    [0x00, 0x0C, 0x0C, 0x00, 0x00, 0x0C, 0x0C, 0x00], // :
+    [0x00, 0x0C, 0x0C, 0x00, 0x00, 0x0C, 0x0C, 0x06], // ;
+    [0x18, 0x0C, 0x06, 0x03, 0x06, 0x0C, 0x18, 0x00], // <
+    [0x00, 0x00, 0x3F, 0x00, 0x00, 0x3F, 0x00, 0x00], // =
+    [0x06, 0x0C, 0x18, 0x30, 0x18, 0x0C, 0x06, 0x00], // >
+    [0x1E, 0x33, 0x30, 0x18, 0x0C, 0x00, 0x0C, 0x00], // ?
+    [0x3E, 0x63, 0x7B, 0x7B, 0x7B, 0x03, 0x1E, 0x00], // @
+    [0x0C, 0x1E, 0x33, 0x33, 0x3F, 0x33, 0x33, 0x00], // A
+    [0x3F, 0x66, 0x66, 0x3E, 0x66, 0x66, 0x3F, 0x00], // B
+    [0x3C, 0x66, 0x03, 0x03, 0x03, 0x66, 0x3C, 0x00], // C
+    [0x1F, 0x36, 0x66, 0x66, 0x66, 0x36, 0x1F, 0x00], // D
+    [0x7F, 0x46, 0x16, 0x1E, 0x16, 0x46, 0x7F, 0x00], // E
+    [0x7F, 0x46, 0x16, 0x1E, 0x16, 0x06, 0x0F, 0x00], // F
+    [0x3C, 0x66, 0x03, 0x03, 0x73, 0x66, 0x7C, 0x00], // G
+    [0x33, 0x33, 0x33, 0x3F, 0x33, 0x33, 0x33, 0x00], // H
+    [0x1E, 0x0C, 0x0C, 0x0C, 0x0C, 0x0C, 0x1E, 0x00], // I
+    [0x78, 0x30, 0x30, 0x30, 0x33, 0x33, 0x1E, 0x00], // J
+    [0x67, 0x66, 0x36, 0x1E, 0x36, 0x66, 0x67, 0x00], // K
+    [0x0F, 0x06, 0x06, 0x06, 0x46, 0x66, 0x7F, 0x00], // L
+    [0x63, 0x77, 0x7F, 0x7F, 0x6B, 0x63, 0x63, 0x00], // M
+    [0x63, 0x67, 0x6F, 0x7B, 0x73, 0x63, 0x63, 0x00], // N
+    [0x1C, 0x36, 0x63, 0x63, 0x63, 0x36, 0x1C, 0x00], // O
+    [0x3F, 0x66, 0x66, 0x3E, 0x06, 0x06, 0x0F, 0x00], // P
+    [0x1E, 0x33, 0x33, 0x33, 0x3B, 0x1E, 0x38, 0x00], // Q
+    [0x3F, 0x66, 0x66, 0x3E, 0x36, 0x66, 0x67, 0x00], // R
+    [0x1E, 0x33, 0x07, 0x0E, 0x38, 0x33, 0x1E, 0x00], // S
+    [0x3F, 0x2D, 0x0C, 0x0C, 0x0C, 0x0C, 0x1E, 0x00], // T
+    [0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x3F, 0x00], // U
+    [0x33, 0x33, 0x33, 0x33, 0x33, 0x1E, 0x0C, 0x00], // V
+    [0x63, 0x63, 0x63, 0x6B, 0x7F, 0x77, 0x63, 0x00], // W
+    [0x63, 0x63, 0x36, 0x1C, 0x1C, 0x36, 0x63, 0x00], // X
+    [0x33, 0x33, 0x33, 0x1E, 0x0C, 0x0C, 0x1E, 0x00], // Y
+    [0x7F, 0x63, 0x31, 0x18, 0x4C, 0x66, 0x7F, 0x00], // Z
+    [0x1E, 0x06, 0x06, 0x06, 0x06, 0x06, 0x1E, 0x00], // [
+    [0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x40, 0x00], // \
+    [0x1E, 0x18, 0x18, 0x18, 0x18, 0x18, 0x1E, 0x00], // ]
+    [0x08, 0x1C, 0x36, 0x63, 0x00, 0x00, 0x00, 0x00], // ^
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xFF], // _
+    [0x0C, 0x0C, 0x18, 0x00, 0x00, 0x00, 0x00, 0x00], // `
+    [0x00, 0x00, 0x1E, 0x30, 0x3E, 0x33, 0x6E, 0x00], // a
+    [0x07, 0x06, 0x06, 0x3E, 0x66, 0x66, 0x3B, 0x00], // b
+    [0x00, 0x00, 0x1E, 0x33, 0x03, 0x33, 0x1E, 0x00], // c
+    [0x38, 0x30, 0x30, 0x3E, 0x33, 0x33, 0x6E, 0x00], // d
+    [0x00, 0x00, 0x1E, 0x33, 0x3F, 0x03, 0x1E, 0x00], // e
+    [0x1C, 0x36, 0x06, 0x0F, 0x06, 0x06, 0x0F, 0x00], // f
+    [0x00, 0x00, 0x6E, 0x33, 0x33, 0x3E, 0x30, 0x1F], // g
+    [0x07, 0x06, 0x36, 0x6E, 0x66, 0x66, 0x67, 0x00], // h
+    [0x0C, 0x00, 0x0E, 0x0C, 0x0C, 0x0C, 0x1E, 0x00], // i
+    [0x30, 0x00, 0x30, 0x30, 0x30, 0x33, 0x33, 0x1E], // j
+    [0x07, 0x06, 0x66, 0x36, 0x1E, 0x36, 0x67, 0x00], // k
+    [0x0E, 0x0C, 0x0C, 0x0C, 0x0C, 0x0C, 0x1E, 0x00], // l
+    [0x00, 0x00, 0x33, 0x7F, 0x7F, 0x6B, 0x63, 0x00], // m
+    [0x00, 0x00, 0x1F, 0x33, 0x33, 0x33, 0x33, 0x00], // n
+    [0x00, 0x00, 0x1E, 0x33, 0x33, 0x33, 0x1E, 0x00], // o
+    [0x00, 0x00, 0x3B, 0x66, 0x66, 0x3E, 0x06, 0x0F], // p
+    [0x00, 0x00, 0x6E, 0x33, 0x33, 0x3E, 0x30, 0x78], // q
+    [0x00, 0x00, 0x3B, 0x6E, 0x66, 0x06, 0x0F, 0x00], // r
+    [0x00, 0x00, 0x3E, 0x03, 0x1E, 0x30, 0x1F, 0x00], // s
+    [0x08, 0x0C, 0x3E, 0x0C, 0x0C, 0x2C, 0x18, 0x00], // t
+    [0x00, 0x00, 0x33, 0x33, 0x33, 0x33, 0x6E, 0x00], // u
+    [0x00, 0x00, 0x33, 0x33, 0x33, 0x1E, 0x0C, 0x00], // v
+    [0x00, 0x00, 0x63, 0x6B, 0x7F, 0x7F, 0x36, 0x00], // w
+    [0x00, 0x00, 0x63, 0x36, 0x1C, 0x36, 0x63, 0x00], // x
+    [0x00, 0x00, 0x33, 0x33, 0x33, 0x3E, 0x30, 0x1F], // y
+    [0x00, 0x00, 0x3F, 0x19, 0x0C, 0x26, 0x3F, 0x00], // z
+    [0x38, 0x0C, 0x0C, 0x07, 0x0C, 0x0C, 0x38, 0x00], // {
+    [0x18, 0x18, 0x18, 0x00, 0x18, 0x18, 0x18, 0x00], // |
+    [0x07, 0x0C, 0x0C, 0x38, 0x0C, 0x0C, 0x07, 0x00], // }
+    [0x6E, 0x3B, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // ~
+];
+
+/// A clip rectangle `(left, top, right, bottom)`, the right and bottom edges are exclusive.
+type Rect = (i64, i64, i64, i64);
+
+/// The rectangle covering `image`.
+fn image_rect<P, C>(image: &ImageBuffer<P, C>) -> Rect
+    where P: Pixel,
+          C: Deref<Target = [P::Subpixel]>
+{
+    (0, 0, image.width() as i64, image.height() as i64)
+}
+
+/// Sets the pixel at `(x, y)` to `color` if it lies inside of `image`.
+fn plot<P, C>(image: &mut ImageBuffer<P, C>, x: i64, y: i64, color: P)
+    where P: Pixel,
+          C: Deref<Target = [P::Subpixel]> + DerefMut
+{
+    if x >= 0 && y >= 0 && x < image.width() as i64 && y < image.height() as i64 {
+        image[(x as u32, y as u32)] = color;
+    }
+}
+
+/// Returns the inclusive range of steps `i` in `0..=len` for which `origin + sign * i` lies in
+/// `lo..hi`, which is empty if the start exceeds the end.
+fn steps_within(origin: i64, sign: i64, len: i64, lo: i64, hi: i64) -> (i64, i64) {
+    let (first, last) = if sign < 0 {
+        (origin - hi + 1, origin - lo)
+    } else {
+        (lo - origin, hi - 1 - origin)
+    };
+    (cmp::max(first, 0), cmp::min(last, len))
+}
+
+/// Calls `plot` for the points of a line from `start` to `end`, both inclusive, which lie in
+/// `clip`.
+fn line<F: FnMut(i64, i64)>(start: (i32, i32), end: (i32, i32), clip: Rect, mut plot: F) {
+    // The points of Bresenham's algorithm in closed form, such that the parts of the line
+    // outside of the clip are skipped. The major axis advances in every step, the minor axis
+    // after `i` steps by `(2 * i * b + a) / (2 * a)`.
+    let (left, top, right, bottom) = clip;
+    let (x0, y0, x1, y1) = (start.0 as i64, start.1 as i64, end.0 as i64, end.1 as i64);
+    let x_major = (x1 - x0).abs() >= (y1 - y0).abs();
+    let (m0, m1, n0, n1, m_clip, n_clip) = if x_major {
+        (x0, x1, y0, y1, (left, right), (top, bottom))
+    } else {
+        (y0, y1, x0, x1, (top, bottom), (left, right))
+    };
+    let (a, b) = ((m1 - m0).abs(), (n1 - n0).abs());
+    let (sm, sn) = ((m1 - m0).signum(), (n1 - n0).signum());
+    let (mut first, mut last) = steps_within(m0, sm, a, m_clip.0, m_clip.1);
+    // The steps in which the minor offset lies in the clip
+    let (k_first, k_last) = steps_within(n0, sn, b, n_clip.0, n_clip.1);
+    if k_first > k_last {
+        return;
+    }
+    let (a2, b2) = (2 * a as i128, 2 * b as i128);
+    if k_first > 0 {
+        // The first step with `minor(i) >= k_first`
+        let i = (a2 * k_first as i128 - a as i128 + b2 - 1) / b2;
+        first = cmp::max(first, i as i64);
+    }
+    if k_last < b {
+        // The last step with `minor(i) <= k_last`
+        let i = (a2 * (k_last as i128 + 1) - a as i128 - 1) / b2;
+        last = cmp::min(last, i as i64);
+    }
+    for i in first..last + 1 {
+        let k = if a == 0 { 0 } else { ((b2 * i as i128 + a as i128) / a2) as i64 };
+        let (m, n) = (m0 + sm * i, n0 + sn * k);
+        if x_major {
+            plot(m, n);
+        } else {
+            plot(n, m);
+        }
+    }
+}
+
+/// Calls `plot` for the points of the outline of a rectangle which lie in `clip`.
+fn rect_outline<F>(x: i32, y: i32, width: u32, height: u32, clip: Rect, mut plot: F)
+    where F: FnMut(i64, i64)
+{
+    if width == 0 || height == 0 {
+        return;
+    }
+    let (left, top, right, bottom) = clip;
+    let (x0, y0) = (x as i64, y as i64);
+    let (x1, y1) = (x0 + width as i64 - 1, y0 + height as i64 - 1);
+    for x in cmp::max(x0, left)..cmp::min(x1 + 1, right) {
+        plot(x, y0);
+        plot(x, y1);
+    }
+    for y in cmp::max(y0 + 1, top)..cmp::min(y1, bottom) {
+        plot(x0, y);
+        plot(x1, y);
+    }
+}
+
+/// Calls `plot` for the points of the outline of a circle, nothing is plotted if its bounding
+/// box lies outside of `clip`.
+fn circle<F: FnMut(i64, i64)>(center: (i32, i32), radius: u32, clip: Rect, mut plot: F) {
+    let (left, top, right, bottom) = clip;
+    let (cx, cy) = (center.0 as i64, center.1 as i64);
+    let r = radius as i64;
+    if cx + r < left || cx - r >= right || cy + r < top || cy - r >= bottom {
+        return;
+    }
+    // The midpoint algorithm, every point of the first octant is mirrored to the others
+    let (mut x, mut y) = (r, 0i64);
+    let mut error = 1 - x;
+    while x >= y {
+        for &(dx, dy) in &[(x, y), (y, x), (-y, x), (-x, y), (-x, -y), (-y, -x), (y, -x), (x, -y)] {
+            plot(cx + dx, cy + dy);
+        }
+        y += 1;
+        if error < 0 {
+            error += 2 * y + 1;
+        } else {
+            x -= 1;
+            error += 2 * (y - x) + 1;
+        }
+    }
+}
+
+/// Calls `plot` for the points of a cross which lie in `clip`.
+fn cross<F: FnMut(i64, i64)>(center: (i32, i32), size: u32, clip: Rect, mut plot: F) {
+    let (left, top, right, bottom) = clip;
+    let (cx, cy, size) = (center.0 as i64, center.1 as i64, size as i64);
+    if top <= cy && cy < bottom {
+        for x in cmp::max(cx - size, left)..cmp::min(cx + size + 1, right) {
+            plot(x, cy);
+        }
+    }
+    if left <= cx && cx < right {
+        for y in cmp::max(cy - size, top)..cmp::min(cy + size + 1, bottom) {
+            if y != cy {
+                plot(cx, y);
+            }
+        }
+    }
+}
+
+/// Calls `plot` for the set pixels of the glyphs of `text`.
+fn text<F: FnMut(i64, i64)>(x: i32, y: i32, text: &str, mut plot: F) {
+    let size = GLYPH_SIZE as i64;
+    for (row, line) in text.split('\n').enumerate() {
+        let top = y as i64 + row as i64 * size;
+        for (column, c) in line.chars().enumerate() {
+            let left = x as i64 + column as i64 * size;
+            for (dy, bits) in glyph(c).iter().enumerate() {
+                for dx in 0..8 {
+                    if bits >> dx & 1 == 1 {
+                        plot(left + dx, top + dy as i64);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Draws a line of one pixel width from `start` to `end`, both inclusive.
+///
+/// # Examples
+///
+/// ```
+/// # use image_buffer::{GrayImage, color::Gray};
+/// use image_buffer::draw::draw_line;
+/// let mut image = GrayImage::new(4, 4);
+/// draw_line(&mut image, (-2, -2), (10, 10), Gray::new([255]));
+/// assert!((0..4).all(|i| image[(i, i)] == Gray::new([255])));
+/// assert_eq!(image.pixels().filter(|p| p[0] == 255).count(), 4);
+/// ```
+pub fn draw_line<P, C>(image: &mut ImageBuffer<P, C>, start: (i32, i32), end: (i32, i32), color: P)
+    where P: Pixel,
+          C: Deref<Target = [P::Subpixel]> + DerefMut
+{
+    let clip = image_rect(image);
+    line(start, end, clip, |x, y| plot(image, x, y, color));
+}
+
+/// Draws the outline of the rectangle with the top left corner `(x, y)` and the dimensions
+/// `(width, height)`.
+///
+/// # Examples
+///
+/// ```
+/// # use image_buffer::{GrayImage, color::Gray};
+/// use image_buffer::draw::draw_rect;
+/// let mut image = GrayImage::new(5, 5);
+/// draw_rect(&mut image, 1, 1, 3, 3, Gray::new([255]));
+/// assert_eq!(image.pixels().filter(|p| p[0] == 255).count(), 8);
+/// assert_eq!(image[(2, 2)], Gray::new([0]));
+/// ```
+pub fn draw_rect<P, C>(image: &mut ImageBuffer<P, C>,
+                       x: i32,
+                       y: i32,
+                       width: u32,
+                       height: u32,
+                       color: P)
+    where P: Pixel,
+          C: Deref<Target = [P::Subpixel]> + DerefMut
+{
+    let clip = image_rect(image);
+    rect_outline(x, y, width, height, clip, |x, y| plot(image, x, y, color));
+}
+
+/// Fills the rectangle with the top left corner `(x, y)` and the dimensions `(width, height)`.
+///
+/// Unlike `ImageBuffer::fill_rect` the corner may lie outside of the image.
+pub fn fill_rect<P, C>(image: &mut ImageBuffer<P, C>,
+                       x: i32,
+                       y: i32,
+                       width: u32,
+                       height: u32,
+                       color: P)
+    where P: Pixel,
+          C: Deref<Target = [P::Subpixel]> + DerefMut
+{
+    let clip = |v: i32, len: u32| (v.max(0) as u32, (len as i64 + v.min(0) as i64).max(0) as u32);
+    let ((x, width), (y, height)) = (clip(x, width), clip(y, height));
+    image.fill_rect(x, y, width, height, color)
+}
+
+/// Draws the outline of the circle around `center` with the given `radius`.
+///
+/// # Examples
+///
+/// ```
+/// # use image_buffer::{GrayImage, color::Gray};
+/// use image_buffer::draw::draw_circle;
+/// let mut image = GrayImage::new(9, 9);
+/// draw_circle(&mut image, (4, 4), 3, Gray::new([255]));
+/// assert_eq!(image[(4, 1)], Gray::new([255]));
+/// assert_eq!(image[(7, 4)], Gray::new([255]));
+/// assert_eq!(image[(4, 4)], Gray::new([0]));
+/// ```
+pub fn draw_circle<P, C>(image: &mut ImageBuffer<P, C>, center: (i32, i32), radius: u32, color: P)
+    where P: Pixel,
+          C: Deref<Target = [P::Subpixel]> + DerefMut
+{
+    let clip = image_rect(image);
+    circle(center, radius, clip, |x, y| plot(image, x, y, color));
+}
+
+/// Draws a cross of a horizontal and a vertical line through `center` reaching `size` pixels
+/// in each direction, a marker for keypoints.
+///
+/// # Examples
+///
+/// ```
+/// # use image_buffer::{GrayImage, color::Gray};
+/// use image_buffer::draw::draw_cross;
+/// let mut image = GrayImage::new(5, 5);
+/// draw_cross(&mut image, (0, 0), 2, Gray::new([255]));
+/// assert_eq!(image.pixels().filter(|p| p[0] == 255).count(), 5);
+/// ```
+pub fn draw_cross<P, C>(image: &mut ImageBuffer<P, C>, center: (i32, i32), size: u32, color: P)
+    where P: Pixel,
+          C: Deref<Target = [P::Subpixel]> + DerefMut
+{
+    let clip = image_rect(image);
+    cross(center, size, clip, |x, y| plot(image, x, y, color));
+}
+
+/// Returns the glyph of `c`, or of `?` if it is not a printable ASCII character.
+fn glyph(c: char) -> &'static [u8; 8] {
+    match c {
+        ' '..='~' => &FONT[c as usize - 32],
+        _ => &FONT['?' as usize - 32],
+    }
+}
+
+/// Returns the width and height of `text` drawn by `draw_text_bitmap`.
+pub fn text_bitmap_size(text: &str) -> (u32, u32) {
+    let lines = text.split('\n');
+    let columns = lines.clone().map(|line| line.chars().count()).max().unwrap_or(0);
+    (columns as u32 * GLYPH_SIZE, lines.count() as u32 * GLYPH_SIZE)
+}
+
+impl<P, C> ImageBuffer<P, C>
+    where P: Pixel,
+          C: Deref<Target = [P::Subpixel]> + DerefMut
+{
+    /// Draws `text` with the top left corner at `(x, y)` using an embedded 8×8 bitmap font,
+    /// for labels and frame numbers of debug overlays.
+    ///
+    /// Only the pixels of the glyphs are set to `pixel`, the background is left unchanged. A
+    /// newline starts a new line below, characters other than printable ASCII are drawn as
+    /// `?`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{GrayImage, color::Gray};
+    /// let mut frame = GrayImage::new(40, 10);
+    /// frame.draw_text_bitmap(1, 1, "#42", Gray::new([255]));
+    /// assert!(frame.pixels().any(|p| p[0] == 255));
+    /// assert_eq!(frame[(30, 5)], Gray::new([0]));
+    /// ```
+    pub fn draw_text_bitmap(&mut self, x: i32, y: i32, text: &str, pixel: P) {
+        self::text(x, y, text, |x, y| plot(self, x, y, pixel));
+    }
+}
+
+/// Approximates the signed distance of `(x, y)` to the ellipse around the origin with the
+/// given radii, negative inside.
+fn ellipse_distance(x: f32, y: f32, (rx, ry): (f32, f32)) -> f32 {
+    let (rx, ry) = (rx.max(1e-3), ry.max(1e-3));
+    let k0 = ((x / rx).powi(2) + (y / ry).powi(2)).sqrt();
+    let k1 = ((x / (rx * rx)).powi(2) + (y / (ry * ry)).powi(2)).sqrt();
+    if k1 == 0.0 {
+        return -rx.min(ry);
+    }
+    k0 * (k0 - 1.0) / k1
+}
+
+/// Returns the signed distance of `(x, y)` to the rectangle centered at the origin with the
+/// half dimensions `(hw, hh)` and the corner radii in the order top left, top right, bottom
+/// right and bottom left, negative inside.
+fn rounded_rect_distance(x: f32, y: f32, (hw, hh): (f32, f32), radii: [f32; 4]) -> f32 {
+    let r = match (x < 0.0, y < 0.0) {
+        (true, true) => radii[0],
+        (false, true) => radii[1],
+        (false, false) => radii[2],
+        (true, false) => radii[3],
+    };
+    let r = r.max(0.0).min(hw).min(hh);
+    let (qx, qy) = (x.abs() - hw + r, y.abs() - hh + r);
+    qx.max(qy).min(0.0) + (qx.max(0.0).powi(2) + qy.max(0.0).powi(2)).sqrt() - r
+}
+
+/// The coverage of a pixel whose center has the signed distance `d` to the edge of a shape.
+fn edge_coverage(d: f32) -> f32 {
+    (0.5 - d).clamp(0.0, 1.0)
+}
+
+/// An antialiased shape at fractional coordinates, which `rasterize` converts to spans.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Shape {
+    /// The ellipse around `center` with the radii `radii`.
+    Ellipse {
+        /// The center of the ellipse.
+        center: (f32, f32),
+        /// The horizontal and vertical radius.
+        radii: (f32, f32),
+    },
+    /// The arc of an ellipse from the angle `angles.0` to `angles.1`, stroked with `width`
+    /// pixels.
+    ///
+    /// The angles are in radians and increase clockwise from the positive x axis, an arc from
+    /// 0 to `2π` is the whole ellipse. The ends of the arc are cut along the rays from the
+    /// center without antialiasing.
+    EllipseArc {
+        /// The center of the ellipse.
+        center: (f32, f32),
+        /// The horizontal and vertical radius.
+        radii: (f32, f32),
+        /// The start and end angle.
+        angles: (f32, f32),
+        /// The width of the stroke, centered on the ellipse.
+        width: f32,
+    },
+    /// The rectangle with the top left corner `position` and the dimensions `size`, with
+    /// corners rounded by `radii`.
+    ///
+    /// The radii are in the order top left, top right, bottom right and bottom left, radii
+    /// larger than half of a side are reduced to it.
+    RoundedRect {
+        /// The top left corner.
+        position: (f32, f32),
+        /// The width and height.
+        size: (f32, f32),
+        /// The radii of the corners.
+        radii: [f32; 4],
+    },
+    /// The outline of a `RoundedRect` stroked with `width` pixels.
+    RoundedRectOutline {
+        /// The top left corner.
+        position: (f32, f32),
+        /// The width and height.
+        size: (f32, f32),
+        /// The radii of the corners.
+        radii: [f32; 4],
+        /// The width of the stroke, centered on the edge.
+        width: f32,
+    },
+}
+
+impl Shape {
+    /// Returns the rectangle `(left, top, right, bottom)` which contains every pixel partially
+    /// covered by the shape.
+    fn bounds(&self) -> (f32, f32, f32, f32) {
+        let ((x0, y0, x1, y1), stroke) = match *self {
+            Shape::Ellipse { center: (cx, cy), radii: (rx, ry) } => {
+                ((cx - rx, cy - ry, cx + rx, cy + ry), 0.0)
+            }
+            Shape::EllipseArc { center: (cx, cy), radii: (rx, ry), width, .. } => {
+                ((cx - rx, cy - ry, cx + rx, cy + ry), width / 2.0)
+            }
+            Shape::RoundedRect { position: (x, y), size: (w, h), .. } => {
+                ((x, y, x + w, y + h), 0.0)
+            }
+            Shape::RoundedRectOutline { position: (x, y), size: (w, h), width, .. } => {
+                ((x, y, x + w, y + h), width / 2.0)
+            }
+        };
+        let reach = stroke + 1.0;
+        (x0 - reach, y0 - reach, x1 + reach, y1 + reach)
+    }
+
+    /// Returns the fraction of the pixel with the center `(x, y)` covered by the shape.
+    fn coverage(&self, x: f32, y: f32) -> f32 {
+        match *self {
+            Shape::Ellipse { center: (cx, cy), radii } => {
+                edge_coverage(ellipse_distance(x - cx, y - cy, radii))
+            }
+            Shape::EllipseArc { center: (cx, cy), radii, angles, width } => {
+                let (dx, dy) = (x - cx, y - cy);
+                let sweep = angles.1 - angles.0;
+                if sweep < 2.0 * PI && (dy.atan2(dx) - angles.0).rem_euclid(2.0 * PI) > sweep {
+                    return 0.0;
+                }
+                edge_coverage(ellipse_distance(dx, dy, radii).abs() - width / 2.0)
+            }
+            Shape::RoundedRect { position, size, radii } => {
+                let half = (size.0 / 2.0, size.1 / 2.0);
+                let (dx, dy) = (x - position.0 - half.0, y - position.1 - half.1);
+                edge_coverage(rounded_rect_distance(dx, dy, half, radii))
+            }
+            Shape::RoundedRectOutline { position, size, radii, width } => {
+                let half = (size.0 / 2.0, size.1 / 2.0);
+                let (dx, dy) = (x - position.0 - half.0, y - position.1 - half.1);
+                edge_coverage(rounded_rect_distance(dx, dy, half, radii).abs() - width / 2.0)
+            }
+        }
+    }
+}
+
+/// A run of pixels of one row which are covered by a shape to the same fraction.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Span {
+    /// The row of the span.
+    pub y: u32,
+    /// The first column of the span.
+    pub x_start: u32,
+    /// The column after the last column of the span.
+    pub x_end: u32,
+    /// The fraction of every pixel of the span covered by the shape, greater than 0 and at
+    /// most 1.
+    pub coverage: f32,
+}
+
+/// Calls `emit` with the spans of the pixels covered by `shape` in an image of the given
+/// dimensions, row by row from left to right.
+///
+/// Consecutive pixels with the same coverage are merged into one span, such that the interior
+/// of a shape is usually a single span per row. This separates the geometry of a shape from
+/// the way it is painted, see `paint_shape`.
+///
+/// # Examples
+///
+/// ```
+/// use image_buffer::draw::{rasterize, Shape, Span};
+/// let shape = Shape::RoundedRect { position: (1.0, 0.0), size: (3.0, 1.0), radii: [0.0; 4] };
+/// let mut spans = Vec::new();
+/// rasterize(&shape, (8, 2), |span| spans.push(span));
+/// assert_eq!(spans, [Span { y: 0, x_start: 1, x_end: 4, coverage: 1.0 }]);
+/// ```
+pub fn rasterize<F>(shape: &Shape, (width, height): (u32, u32), mut emit: F)
+    where F: FnMut(Span)
+{
+    let (left, top, right, bottom) = shape.bounds();
+    let clamp = |v: f32, len: u32| v.max(0.0).min(len as f32) as u32;
+    let (x0, x1) = (clamp(left.floor(), width), clamp(right.ceil(), width));
+    let (y0, y1) = (clamp(top.floor(), height), clamp(bottom.ceil(), height));
+    for y in y0..y1 {
+        let mut span: Option<Span> = None;
+        for x in x0..x1 {
+            let coverage = shape.coverage(x as f32 + 0.5, y as f32 + 0.5);
+            match span {
+                Some(ref mut span) if span.coverage == coverage => {
+                    span.x_end = x + 1;
+                    continue;
+                }
+                Some(finished) => emit(finished),
+                None => {}
+            }
+            span = if coverage > 0.0 {
+                Some(Span {
+                    y,
+                    x_start: x,
+                    x_end: x + 1,
+                    coverage,
+                })
+            } else {
+                None
+            };
+        }
+        if let Some(span) = span {
+            emit(span);
+        }
+    }
+}
+
+/// Composites the colors returned by `paint` for every pixel covered by `shape` over the
+/// view, weighted with the coverage.
+///
+/// `paint` is called with the coordinates of each covered pixel, such that gradients and
+/// patterns can be drawn in the shape.
+///
+/// # Examples
+///
+/// ```
+/// # use image_buffer::{GrayImage, color::Gray};
+/// use image_buffer::draw::{paint_shape, Shape};
+/// let mut image = GrayImage::new(10, 10);
+/// let shape = Shape::Ellipse { center: (5.0, 5.0), radii: (4.0, 4.0) };
+/// paint_shape(&mut image, &shape, |x, _| Gray::new([x as u8 * 20]));
+/// assert_eq!(image[(3, 5)], Gray::new([60]));
+/// assert_eq!(image[(6, 5)], Gray::new([120]));
+/// ```
+pub fn paint_shape<P, V, F>(view: &mut V, shape: &Shape, mut paint: F)
+    where P: Pixel + Blend,
+          P::Subpixel: ChannelMax,
+          V: ImageViewMut<P>,
+          F: FnMut(u32, u32) -> P
+{
+    rasterize(shape, view.dimensions(), |span| {
+        for x in span.x_start..span.x_end {
+            let color = paint(x, span.y);
+            view[(x, span.y)].blend_coverage(&color, span.coverage);
+        }
+    });
+}
+
+/// A source of the colors with which shapes are painted.
+pub trait PaintSource<P: Pixel> {
+    /// Returns the color of the pixel `(x, y)`.
+    fn color_at(&self, x: u32, y: u32) -> P;
+}
+
+/// A single color.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Solid<P>(pub P);
+
+impl<P: Pixel> PaintSource<P> for Solid<P> {
+    fn color_at(&self, _: u32, _: u32) -> P {
+        self.0
+    }
+}
+
+/// Colors which change linearly from `start_color` at the point `start` to `end_color` at the
+/// point `end`.
+///
+/// The color is constant along lines perpendicular to the line from `start` to `end` and does
+/// not change beyond the two points.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LinearGradient<P> {
+    /// The point with the color `start_color`.
+    pub start: (f32, f32),
+    /// The point with the color `end_color`.
+    pub end: (f32, f32),
+    /// The color at `start`.
+    pub start_color: P,
+    /// The color at `end`.
+    pub end_color: P,
+}
+
+impl<P> PaintSource<P> for LinearGradient<P>
+    where P: Pixel,
+          P::Subpixel: ChannelMax
+{
+    fn color_at(&self, x: u32, y: u32) -> P {
+        let (dx, dy) = (self.end.0 - self.start.0, self.end.1 - self.start.1);
+        let len = dx * dx + dy * dy;
+        if len == 0.0 {
+            return self.start_color;
+        }
+        let (px, py) = (x as f32 + 0.5 - self.start.0, y as f32 + 0.5 - self.start.1);
+        let t = (px * dx + py * dy) / len;
+        self.start_color.lerp(&self.end_color, t.clamp(0.0, 1.0))
+    }
+}
+
+/// Colors which change linearly from `inner_color` at `center` to `outer_color` at the
+/// distance `radius` from it, beyond which they stay constant.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RadialGradient<P> {
+    /// The center of the gradient.
+    pub center: (f32, f32),
+    /// The distance from the center at which the color is `outer_color`.
+    pub radius: f32,
+    /// The color at the center.
+    pub inner_color: P,
+    /// The color at `radius` and beyond.
+    pub outer_color: P,
+}
+
+impl<P> PaintSource<P> for RadialGradient<P>
+    where P: Pixel,
+          P::Subpixel: ChannelMax
+{
+    fn color_at(&self, x: u32, y: u32) -> P {
+        let (dx, dy) = (x as f32 + 0.5 - self.center.0, y as f32 + 0.5 - self.center.1);
+        let distance = (dx * dx + dy * dy).sqrt();
+        let t = if self.radius > 0.0 { distance / self.radius } else { 1.0 };
+        self.inner_color.lerp(&self.outer_color, t.min(1.0))
+    }
+}
+
+/// An image repeated in both directions, with its top left corner at `offset`.
+///
+/// Painting with an empty image panics.
+pub struct ImagePattern<'a, P, C>
+    where P: Pixel + 'a,
+          C: Deref<Target = [P::Subpixel]> + 'a
+{
+    /// The repeated image.
+    pub image: &'a ImageBuffer<P, C>,
+    /// The position of the top left corner of one repetition.
+    pub offset: (i64, i64),
+}
+
+impl<'a, P, C> PaintSource<P> for ImagePattern<'a, P, C>
+    where P: Pixel,
+          C: Deref<Target = [P::Subpixel]>
+{
+    fn color_at(&self, x: u32, y: u32) -> P {
+        let (width, height) = self.image.dimensions();
+        assert!(width > 0 && height > 0, "pattern image is empty");
+        let x = (x as i64 - self.offset.0).rem_euclid(width as i64);
+        let y = (y as i64 - self.offset.1).rem_euclid(height as i64);
+        self.image[(x as u32, y as u32)]
+    }
+}
+
+/// Composites the colors of `source` for every pixel covered by `shape` over the view,
+/// weighted with the coverage.
+///
+/// # Examples
+///
+/// ```
+/// # use image_buffer::{RgbImage, color::Rgb};
+/// use image_buffer::draw::{fill_shape, LinearGradient, Shape};
+/// let mut image = RgbImage::new(8, 4);
+/// let shape = Shape::RoundedRect { position: (0.0, 0.0), size: (8.0, 4.0), radii: [0.0; 4] };
+/// let gradient = LinearGradient {
+///     start: (0.0, 0.0),
+///     end: (8.0, 0.0),
+///     start_color: Rgb::new([0, 0, 0]),
+///     end_color: Rgb::new([255, 0, 0]),
+/// };
+/// fill_shape(&mut image, &shape, &gradient);
+/// assert_eq!(image[(0, 2)], Rgb::new([16, 0, 0]));
+/// assert_eq!(image[(7, 2)], Rgb::new([239, 0, 0]));
+/// ```
+pub fn fill_shape<P, V, S>(view: &mut V, shape: &Shape, source: &S)
+    where P: Pixel + Blend,
+          P::Subpixel: ChannelMax,
+          V: ImageViewMut<P>,
+          S: PaintSource<P> + ?Sized
+{
+    paint_shape(view, shape, |x, y| source.color_at(x, y));
+}
+
+/// Fills the ellipse around `center` with the radii `(rx, ry)` with antialiased edges.
+///
+/// # Examples
+///
+/// ```
+/// # use image_buffer::{RgbaImage, color::Rgba};
+/// use image_buffer::draw::fill_ellipse_aa;
+/// let mut image = RgbaImage::new(10, 10);
+/// fill_ellipse_aa(&mut image, (5.0, 5.0), (4.0, 3.0), Rgba::new([255, 0, 0, 255]));
+/// assert_eq!(image[(5, 5)], Rgba::new([255, 0, 0, 255]));
+/// assert!(image[(8, 5)][3] > 0 && image[(8, 5)][3] < 255);
+/// assert_eq!(image[(0, 0)], Rgba::new([0, 0, 0, 0]));
+/// ```
+pub fn fill_ellipse_aa<P, C>(image: &mut ImageBuffer<P, C>,
+                             center: (f32, f32),
+                             radii: (f32, f32),
+                             color: P)
+    where P: Pixel + Blend,
+          P::Subpixel: ChannelMax,
+          C: Deref<Target = [P::Subpixel]> + DerefMut
+{
+    paint_shape(image, &Shape::Ellipse { center, radii }, |_, _| color);
+}
+
+/// Draws the arc of the ellipse around `center` with the radii `(rx, ry)` from the angle
+/// `angles.0` to `angles.1` with a stroke of `width` pixels and antialiased edges.
+///
+/// The angles are in radians and increase clockwise from the positive x axis, an arc from 0
+/// to `2π` draws the whole ellipse. The ends of the arc are cut along the rays from the
+/// center without antialiasing.
+///
+/// # Examples
+///
+/// ```
+/// # use image_buffer::{GrayImage, color::Gray};
+/// use std::f32::consts::PI;
+/// use image_buffer::draw::draw_ellipse_arc_aa;
+/// let mut image = GrayImage::new(20, 20);
+/// // The lower right quarter of a circle
+/// let white = Gray::new([255]);
+/// draw_ellipse_arc_aa(&mut image, (10.0, 10.0), (8.0, 8.0), (0.0, PI / 2.0), 2.0, white);
+/// assert_eq!(image[(10, 17)], Gray::new([255]));
+/// assert_eq!(image[(10, 2)], Gray::new([0]));
+/// ```
+pub fn draw_ellipse_arc_aa<P, C>(image: &mut ImageBuffer<P, C>,
+                                 center: (f32, f32),
+                                 radii: (f32, f32),
+                                 angles: (f32, f32),
+                                 width: f32,
+                                 color: P)
+    where P: Pixel + Blend,
+          P::Subpixel: ChannelMax,
+          C: Deref<Target = [P::Subpixel]> + DerefMut
+{
+    let shape = Shape::EllipseArc {
+        center,
+        radii,
+        angles,
+        width,
+    };
+    paint_shape(image, &shape, |_, _| color);
+}
+
+/// Fills the rectangle with the top left corner `(x, y)` and the dimensions `(width, height)`
+/// with corners rounded by the radii in the order top left, top right, bottom right and
+/// bottom left, with antialiased edges.
+///
+/// Radii larger than half of a side are reduced to it.
+///
+/// # Examples
+///
+/// ```
+/// # use image_buffer::{GrayImage, color::Gray};
+/// use image_buffer::draw::fill_rounded_rect_aa;
+/// let mut image = GrayImage::new(10, 10);
+/// let radii = [4.0, 0.0, 0.0, 0.0];
+/// fill_rounded_rect_aa(&mut image, (1.0, 1.0), (8.0, 8.0), radii, Gray::new([255]));
+/// assert_eq!(image[(1, 1)], Gray::new([0]));
+/// assert_eq!(image[(8, 1)], Gray::new([255]));
+/// ```
+pub fn fill_rounded_rect_aa<P, C>(image: &mut ImageBuffer<P, C>,
+                                  position: (f32, f32),
+                                  size: (f32, f32),
+                                  radii: [f32; 4],
+                                  color: P)
+    where P: Pixel + Blend,
+          P::Subpixel: ChannelMax,
+          C: Deref<Target = [P::Subpixel]> + DerefMut
+{
+    let shape = Shape::RoundedRect {
+        position,
+        size,
+        radii,
+    };
+    paint_shape(image, &shape, |_, _| color);
+}
+
+/// Draws the outline of a rounded rectangle like `fill_rounded_rect_aa` with a stroke of
+/// `width` pixels centered on the edge.
+pub fn draw_rounded_rect_aa<P, C>(image: &mut ImageBuffer<P, C>,
+                                  position: (f32, f32),
+                                  size: (f32, f32),
+                                  radii: [f32; 4],
+                                  width: f32,
+                                  color: P)
+    where P: Pixel + Blend,
+          P::Subpixel: ChannelMax,
+          C: Deref<Target = [P::Subpixel]> + DerefMut
+{
+    let shape = Shape::RoundedRectOutline {
+        position,
+        size,
+        radii,
+        width,
+    };
+    paint_shape(image, &shape, |_, _| color);
+}
+
+/// A clip of a `Canvas`, the intersection of its rectangle with the previous clips.
+struct Clip {
+    /// The left, top, right and bottom edge, the right and bottom edge are exclusive.
+    rect: Rect,
+    /// A mask and the position of its top left corner.
+    mask: Option<(GrayImage, i64, i64)>,
+}
+
+/// Draws onto an image through a stack of clip rectangles and clip masks.
+///
+/// Only the pixels inside of every clip on the stack are drawn. Clips are pushed before
+/// drawing a nested element and popped afterwards, like the clip state of a 2D graphics API.
+///
+/// # Examples
+///
+/// ```
+/// # use image_buffer::{GrayImage, color::Gray};
+/// use image_buffer::draw::Canvas;
+/// let mut image = GrayImage::new(10, 10);
+/// {
+///     let mut canvas = Canvas::new(&mut image);
+///     canvas.push_clip_rect(2, 2, 4, 4);
+///     canvas.fill_rect(0, 0, 10, 10, Gray::new([255]));
+///     canvas.pop_clip();
+///     canvas.draw_line((0, 9), (9, 9), Gray::new([128]));
+/// }
+/// assert_eq!(image.pixels().filter(|p| p[0] == 255).count(), 16);
+/// assert_eq!(image[(5, 9)], Gray::new([128]));
+/// ```
+pub struct Canvas<'a, P, C>
+    where P: Pixel + 'a,
+          C: Deref<Target = [P::Subpixel]> + DerefMut + 'a
+{
+    image: &'a mut ImageBuffer<P, C>,
+    clips: Vec<Clip>,
+}
+
+impl<'a, P, C> Canvas<'a, P, C>
+    where P: Pixel + 'a,
+          C: Deref<Target = [P::Subpixel]> + DerefMut + 'a
+{
+    /// Creates a canvas drawing onto `image` without clips.
+    pub fn new(image: &'a mut ImageBuffer<P, C>) -> Canvas<'a, P, C> {
+        Canvas {
+            image,
+            clips: Vec::new(),
+        }
+    }
+
+    /// Returns the image drawn onto.
+    pub fn image(&self) -> &ImageBuffer<P, C> {
+        self.image
+    }
+
+    /// The number of clips on the stack.
+    pub fn clip_depth(&self) -> usize {
+        self.clips.len()
+    }
+
+    /// The current clip rectangle, which lies inside of the image.
+    fn rect(&self) -> Rect {
+        match self.clips.last() {
+            Some(clip) => clip.rect,
+            None => (0, 0, self.image.width() as i64, self.image.height() as i64),
+        }
+    }
+
+    /// Pushes the intersection of the current clip with a rectangle.
+    fn push(&mut self, x: i32, y: i32, width: u32, height: u32, mask: Option<GrayImage>) {
+        let (left, top, right, bottom) = self.rect();
+        let (x, y) = (x as i64, y as i64);
+        let rect = (cmp::max(left, x),
+                    cmp::max(top, y),
+                    cmp::min(right, x + width as i64),
+                    cmp::min(bottom, y + height as i64));
+        self.clips.push(Clip {
+            rect,
+            mask: mask.map(|mask| (mask, x, y)),
+        });
+    }
+
+    /// Restricts drawing to the rectangle with the top left corner `(x, y)` and the dimensions
+    /// `(width, height)` until the next `pop_clip`.
+    pub fn push_clip_rect(&mut self, x: i32, y: i32, width: u32, height: u32) {
+        self.push(x, y, width, height, None);
+    }
+
+    /// Restricts drawing to the non-zero pixels of `mask` placed with its top left corner at
+    /// `(x, y)` until the next `pop_clip`.
+    pub fn push_clip_mask<M>(&mut self, mask: &ImageBuffer<Gray<u8>, M>, x: i32, y: i32)
+        where M: Deref<Target = [u8]>
+    {
+        let (width, height) = mask.dimensions();
+        self.push(x, y, width, height, Some(mask.to_vec()));
+    }
+
+    /// Removes the clip pushed last.
+    ///
+    /// # Panics
+    ///
+    /// If there is no clip on the stack.
+    pub fn pop_clip(&mut self) {
+        assert!(self.clips.pop().is_some(), "no clip to pop");
+    }
+
+    /// Returns `true` if the pixel at `(x, y)` is drawn.
+    pub fn is_visible(&self, x: i32, y: i32) -> bool {
+        self.visible(x as i64, y as i64)
+    }
+
+    fn visible(&self, x: i64, y: i64) -> bool {
+        let (left, top, right, bottom) = self.rect();
+        x >= left && y >= top && x < right && y < bottom &&
+        self.clips.iter().all(|clip| match clip.mask {
+            Some((ref mask, mx, my)) => mask[((x - mx) as u32, (y - my) as u32)][0] != 0,
+            None => true,
+        })
+    }
+
+    fn plot(&mut self, x: i64, y: i64, color: P) {
+        if self.visible(x, y) {
+            self.image[(x as u32, y as u32)] = color;
+        }
+    }
+
+    /// Draws a line like `draw_line`.
+    pub fn draw_line(&mut self, start: (i32, i32), end: (i32, i32), color: P) {
+        let clip = self.rect();
+        line(start, end, clip, |x, y| self.plot(x, y, color));
+    }
+
+    /// Draws the outline of a rectangle like `draw_rect`.
+    pub fn draw_rect(&mut self, x: i32, y: i32, width: u32, height: u32, color: P) {
+        let clip = self.rect();
+        rect_outline(x, y, width, height, clip, |x, y| self.plot(x, y, color));
+    }
+
+    /// Fills a rectangle like `fill_rect`.
+    pub fn fill_rect(&mut self, x: i32, y: i32, width: u32, height: u32, color: P) {
+        let (left, top, right, bottom) = self.rect();
+        let (x, y) = (x as i64, y as i64);
+        for y in cmp::max(top, y)..cmp::min(bottom, y + height as i64) {
+            for x in cmp::max(left, x)..cmp::min(right, x + width as i64) {
+                self.plot(x, y, color);
+            }
+        }
+    }
+
+    /// Draws the outline of a circle like `draw_circle`.
+    pub fn draw_circle(&mut self, center: (i32, i32), radius: u32, color: P) {
+        let clip = self.rect();
+        circle(center, radius, clip, |x, y| self.plot(x, y, color));
+    }
+
+    /// Draws a cross like `draw_cross`.
+    pub fn draw_cross(&mut self, center: (i32, i32), size: u32, color: P) {
+        let clip = self.rect();
+        cross(center, size, clip, |x, y| self.plot(x, y, color));
+    }
+
+    /// Draws text like `ImageBuffer::draw_text_bitmap`.
+    pub fn draw_text_bitmap(&mut self, x: i32, y: i32, text: &str, color: P) {
+        self::text(x, y, text, |x, y| self.plot(x, y, color));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use buffer::{GrayImage, ImageBuffer, RgbImage, RgbaImage};
+    use color_model::{Gray, Rgb, Rgba};
+    use super::{draw_circle, draw_ellipse_arc_aa, draw_line, draw_rounded_rect_aa, fill_ellipse_aa,
+                fill_rect, fill_rounded_rect_aa, fill_shape, paint_shape, rasterize,
+                text_bitmap_size, Canvas, ImagePattern, LinearGradient, PaintSource,
+                RadialGradient, Shape, Solid, Span};
+
+    #[test]
+    fn test_line() {
+        let mut image = GrayImage::new(5, 3);
+        draw_line(&mut image, (4, 2), (0, 0), Gray::new([1]));
+        assert_eq!(image.into_raw(), vec![1, 1, 0, 0, 0, 0, 0, 1, 1, 0, 0, 0, 0, 0, 1]);
+        let mut image = GrayImage::new(2, 2);
+        draw_line(&mut image, (5, -1), (5, 9), Gray::new([1]));
+        draw_line(&mut image, (1, 1), (1, 1), Gray::new([2]));
+        assert_eq!(image.into_raw(), vec![0, 0, 0, 2]);
+    }
+
+    #[test]
+    fn test_clipped_line() {
+        // Clipping does not move the points of a line
+        let ends = [((-7, 3), (31, 18)), ((25, -4), (2, 35)), ((12, 39), (19, -1)),
+                    ((0, 22), (39, 5)), ((33, 33), (-3, 10)), ((14, 14), (14, 30))];
+        for &(start, end) in &ends {
+            let mut full = GrayImage::new(40, 40);
+            draw_line(&mut full, start, end, Gray::new([1]));
+            let mut clipped = GrayImage::new(10, 10);
+            draw_line(&mut clipped,
+                      (start.0 - 15, start.1 - 15),
+                      (end.0 - 15, end.1 - 15),
+                      Gray::new([1]));
+            assert_eq!(clipped.into_raw(), full.crop(15, 15, 10, 10).into_raw());
+        }
+        // Only the visible part is traversed
+        let mut image = GrayImage::new(3, 3);
+        draw_line(&mut image, (i32::MIN, i32::MIN), (i32::MAX, i32::MAX), Gray::new([1]));
+        draw_circle(&mut image, (i32::MIN, 0), u32::MAX / 4, Gray::new([2]));
+        assert_eq!(image.into_raw(), vec![1, 0, 0, 0, 1, 0, 0, 0, 1]);
+    }
+
+    #[test]
+    fn test_fill_rect() {
+        let mut image = RgbImage::new(3, 3);
+        fill_rect(&mut image, -1, -2, 3, 4, Rgb::new([9, 9, 9]));
+        let filled: Vec<bool> = image.pixels().map(|p| p[0] == 9).collect();
+        assert_eq!(filled, [true, true, false, true, true, false, false, false, false]);
+        fill_rect(&mut image, -5, 0, 3, 3, Rgb::new([1, 1, 1]));
+        assert!(image.pixels().all(|p| p[0] != 1));
+    }
+
+    #[test]
+    fn test_circle() {
+        let mut image = GrayImage::new(11, 11);
+        draw_circle(&mut image, (5, 5), 4, Gray::new([1]));
+        for (x, y, p) in image.enumerate_pixels() {
+            let distance = ((x as f32 - 5.0).powi(2) + (y as f32 - 5.0).powi(2)).sqrt();
+            assert!(p[0] == 0 || (distance - 4.0).abs() < 0.6, "({}, {})", x, y);
+        }
+        for &(x, y) in &[(1, 5), (9, 5), (5, 1), (5, 9)] {
+            assert_eq!(image[(x, y)], Gray::new([1]));
+        }
+        draw_circle(&mut image, (-100, 5), 3, Gray::new([2]));
+        assert!(image.pixels().all(|p| p[0] != 2));
+    }
+
+    #[test]
+    fn test_text() {
+        let mut image = GrayImage::new(8, 16);
+        image.draw_text_bitmap(0, 0, "1\n\u{e9}", Gray::new([1]));
+        let row = |image: &GrayImage, y: u32| (0..8).map(|x| image[(x, y)][0]).collect::<Vec<_>>();
+        // The top of the 1 and of the question mark
+        assert_eq!(row(&image, 0), [0, 0, 1, 1, 0, 0, 0, 0]);
+        assert_eq!(row(&image, 8), [0, 1, 1, 1, 1, 0, 0, 0]);
+        image.draw_text_bitmap(-4, 14, "T", Gray::new([2]));
+        assert_eq!(row(&image, 14), [2, 2, 1, 1, 0, 0, 0, 0]);
+        assert_eq!(text_bitmap_size("ab\nc"), (16, 16));
+        assert_eq!(text_bitmap_size(""), (0, 8));
+    }
+
+    #[test]
+    fn test_canvas_clips() {
+        let mut image = GrayImage::new(6, 6);
+        let mask = GrayImage::from_fn(3, 3, |x, y| Gray::new([if x == y { 1 } else { 0 }]));
+        {
+            let mut canvas = Canvas::new(&mut image);
+            canvas.push_clip_rect(-2, 1, 5, 10);
+            canvas.push_clip_mask(&mask, 1, 1);
+            assert_eq!(canvas.clip_depth(), 2);
+            assert!(canvas.is_visible(2, 2) && !canvas.is_visible(3, 3));
+            canvas.fill_rect(-10, -10, 20, 20, Gray::new([9]));
+            canvas.pop_clip();
+            canvas.draw_cross((0, 0), 5, Gray::new([7]));
+            canvas.pop_clip();
+            canvas.draw_text_bitmap(4, 4, "#", Gray::new([3]));
+        }
+        let marked: Vec<(u32, u32, u8)> = image.enumerate_pixels()
+            .filter(|&(_, _, p)| p[0] != 0)
+            .map(|(x, y, p)| (x, y, p[0]))
+            .collect();
+        assert_eq!(marked,
+                   vec![(0, 1, 7), (1, 1, 9), (0, 2, 7), (2, 2, 9), (0, 3, 7), (0, 4, 7), (5, 4, 3),
+                        (0, 5, 7), (5, 5, 3)]);
+    }
+
+    #[test]
+    fn test_ellipse_aa() {
+        let mut image: ImageBuffer<Gray<f32>, _> = ImageBuffer::new(20, 20);
+        fill_ellipse_aa(&mut image, (10.0, 10.0), (6.0, 6.0), Gray::new([1.0]));
+        let area: f32 = image.pixels().map(|p| p[0]).sum();
+        assert!((area - PI * 36.0).abs() < 0.5, "area {}", area);
+        assert_eq!(image[(10, 10)], Gray::new([1.0]));
+        assert!(image.pixels().any(|p| p[0] > 0.0 && p[0] < 1.0));
+
+        let mut image = RgbaImage::new(10, 10);
+        fill_ellipse_aa(&mut image, (5.0, 5.0), (3.0, 3.0), Rgba::new([200, 100, 0, 128]));
+        assert_eq!(image[(5, 5)], Rgba::new([200, 100, 0, 128]));
+        let edge = image[(7, 6)];
+        assert!(edge[3] > 0 && edge[3] < 128, "edge {:?}", edge);
+        assert_eq!(&edge.as_ref()[..3], &[200, 100, 0]);
+
+        let mut image = GrayImage::new(20, 20);
+        let white = Gray::new([255]);
+        draw_ellipse_arc_aa(&mut image, (10.0, 10.0), (8.0, 8.0), (PI, 1.5 * PI), 2.0, white);
+        for (x, y, p) in image.enumerate_pixels() {
+            if p[0] > 0 {
+                assert!(x <= 10 && y <= 10, "({}, {}) outside the arc", x, y);
+            }
+        }
+        assert_eq!(image[(2, 9)][0], 255);
+        assert_eq!(image[(10, 10)][0], 0);
+    }
+
+    #[test]
+    fn test_rounded_rect_aa() {
+        let mut image = GrayImage::new(6, 6);
+        fill_rounded_rect_aa(&mut image, (1.0, 1.0), (4.0, 3.0), [0.0; 4], Gray::new([9]));
+        let filled: Vec<bool> = image.pixels().map(|p| p[0] == 9).collect();
+        let expected: Vec<bool> =
+            (0..36).map(|i| (1..5).contains(&(i % 6)) && (1..4).contains(&(i / 6))).collect();
+        assert_eq!(filled, expected);
+        assert!(image.pixels().all(|p| p[0] == 0 || p[0] == 9));
+
+        let mut image = GrayImage::new(10, 10);
+        let radii = [5.0, 0.0, 9.0, 1.0];
+        fill_rounded_rect_aa(&mut image, (0.0, 0.0), (10.0, 10.0), radii, Gray::new([255]));
+        assert_eq!(image[(0, 0)][0], 0);
+        assert_eq!(image[(9, 0)][0], 255);
+        assert_eq!(image[(9, 9)][0], 0);
+        assert!(image[(0, 9)][0] > 0 && image[(0, 9)][0] < 255);
+        assert_eq!(image[(5, 5)][0], 255);
+
+        let mut image = GrayImage::new(10, 10);
+        draw_rounded_rect_aa(&mut image, (1.0, 1.0), (8.0, 8.0), [2.0; 4], 1.0, Gray::new([255]));
+        assert_eq!(image[(5, 5)][0], 0);
+        assert_eq!(image[(5, 1)][0], 128);
+        assert_eq!(image[(0, 0)][0], 0);
+    }
+
+    #[test]
+    fn test_rasterize() {
+        let shape = Shape::Ellipse { center: (4.0, 4.0), radii: (3.0, 3.0) };
+        let mut spans: Vec<Span> = Vec::new();
+        rasterize(&shape, (8, 8), |span| spans.push(span));
+        assert!(spans.iter().all(|s| s.coverage > 0.0 && s.coverage <= 1.0 && s.x_start < s.x_end));
+        assert!(spans.windows(2).all(|w| (w[0].y, w[0].x_end) <= (w[1].y, w[1].x_start)));
+        let middle: Vec<&Span> = spans.iter().filter(|s| s.y == 4).collect();
+        assert!(middle.iter().any(|s| s.coverage == 1.0 && s.x_start <= 2 && s.x_end >= 6));
+
+        // The spans describe the same coverage as painting the shape
+        let mut image: ImageBuffer<Gray<f32>, _> = ImageBuffer::new(8, 8);
+        paint_shape(&mut image, &shape, |_, _| Gray::new([1.0]));
+        let mut expected: ImageBuffer<Gray<f32>, _> = ImageBuffer::new(8, 8);
+        for span in &spans {
+            for x in span.x_start..span.x_end {
+                expected[(x, span.y)] = Gray::new([span.coverage]);
+            }
+        }
+        assert_eq!(image.into_raw(), expected.into_raw());
+
+        let mut clipped = Vec::new();
+        rasterize(&shape, (4, 2), |span| clipped.push(span));
+        assert!(clipped.iter().all(|s| s.y < 2 && s.x_end <= 4));
+        rasterize(&Shape::Ellipse { center: (-9.0, 0.0), radii: (1.0, 1.0) },
+                  (4, 4),
+                  |_| panic!("span outside of the image"));
+    }
+
+    #[test]
+    fn test_paint_sources() {
+        let red = Rgb::new([255, 0, 0]);
+        assert_eq!(Solid(red).color_at(9, 9), red);
+
+        let linear = LinearGradient {
+            start: (0.0, 2.0),
+            end: (0.0, 6.0),
+            start_color: Gray::new([0u8]),
+            end_color: Gray::new([200]),
+        };
+        let column: Vec<u8> = (0..8).map(|y| linear.color_at(5, y)[0]).collect();
+        assert_eq!(column, [0, 0, 25, 75, 125, 175, 200, 200]);
+        let point = LinearGradient { end: (0.0, 2.0), ..linear };
+        assert_eq!(point.color_at(0, 0), Gray::new([0]));
+
+        let radial = RadialGradient {
+            center: (4.5, 4.5),
+            radius: 2.0,
+            inner_color: Gray::new([1.0f32]),
+            outer_color: Gray::new([0.0]),
+        };
+        assert_eq!(radial.color_at(4, 3), Gray::new([0.5]));
+        assert_eq!(radial.color_at(0, 0), Gray::new([0.0]));
+
+        let tile = GrayImage::from_fn(2, 2, |x, y| Gray::new([(x + 2 * y) as u8]));
+        let pattern = ImagePattern { image: &tile, offset: (1, 0) };
+        let row: Vec<u8> = (0..5).map(|x| pattern.color_at(x, 3)[0]).collect();
+        assert_eq!(row, [3, 2, 3, 2, 3]);
+
+        let mut image = GrayImage::new(4, 4);
+        let shape = Shape::RoundedRect { position: (0.0, 0.0), size: (4.0, 2.0), radii: [0.0; 4] };
+        let source: &dyn PaintSource<Gray<u8>> = &pattern;
+        fill_shape(&mut image, &shape, source);
+        assert_eq!(image.into_raw(), [1, 0, 1, 0, 3, 2, 3, 2, 0, 0, 0, 0, 0, 0, 0, 0]);
+    }
+}
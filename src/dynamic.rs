@@ -0,0 +1,276 @@
+//! Images whose pixel type is only known at runtime.
+
+use buffer::ImageBuffer;
+use color_model::{ColorType, Gray, GrayA, Rgb, Rgba};
+use math::rescale_channel;
+use traits::{ChannelMax, Color as Pixel};
+
+/// The pixel types a `DynamicImageBuffer` can hold.
+pub trait DynamicPixel: Pixel {
+    /// The color type of the pixels.
+    fn color_type() -> ColorType;
+
+    /// Wraps `image` in the matching variant.
+    fn into_dynamic(image: ImageBuffer<Self, Vec<Self::Subpixel>>) -> DynamicImageBuffer;
+
+    /// Returns the buffer of `image` if it holds pixels of this type.
+    fn from_dynamic(image: DynamicImageBuffer)
+                    -> Result<ImageBuffer<Self, Vec<Self::Subpixel>>, DynamicImageBuffer>;
+
+    /// Returns a reference to the buffer of `image` if it holds pixels of this type.
+    fn from_dynamic_ref(image: &DynamicImageBuffer)
+                        -> Option<&ImageBuffer<Self, Vec<Self::Subpixel>>>;
+
+    /// Returns a mutable reference to the buffer of `image` if it holds pixels of this type.
+    fn from_dynamic_mut(image: &mut DynamicImageBuffer)
+                        -> Option<&mut ImageBuffer<Self, Vec<Self::Subpixel>>>;
+}
+
+macro_rules! dynamic_image_buffer {
+    {$(
+        $variant:ident,
+        $pixel:ty,
+        $color_type:expr,
+        $as_fn:ident,
+        #[$doc:meta];
+    )*} => {
+
+/// An image buffer holding one of the common pixel types, chosen at runtime.
+///
+/// Decoders determine the pixel type of an image from its header, so they return this type
+/// instead of a concrete `ImageBuffer`. The pixel type is mapped to a `ColorType` with the
+/// bit depth, 32 bit types hold `f32` channels.
+///
+/// # Examples
+///
+/// ```
+/// # use image_buffer::{DynamicImageBuffer, color::{ColorType, Gray, Rgb}};
+/// let mut image = DynamicImageBuffer::new(ColorType::Gray(16), 2, 1).unwrap();
+/// image.as_buffer_mut::<Gray<u16>>().unwrap()[(1, 0)] = Gray::new([65535]);
+/// assert_eq!(image.color_type(), ColorType::Gray(16));
+/// assert!(image.as_rgb8().is_none());
+/// let rgb = image.convert_to::<Rgb<u8>>();
+/// assert_eq!(rgb[(1, 0)], Rgb::new([255, 255, 255]));
+/// ```
+#[derive(Clone)]
+pub enum DynamicImageBuffer {
+    $(#[$doc] $variant(ImageBuffer<$pixel, Vec<<$pixel as Pixel>::Subpixel>>),)*
+}
+
+$(
+impl DynamicPixel for $pixel {
+    fn color_type() -> ColorType {
+        $color_type
+    }
+
+    fn into_dynamic(image: ImageBuffer<Self, Vec<Self::Subpixel>>) -> DynamicImageBuffer {
+        DynamicImageBuffer::$variant(image)
+    }
+
+    fn from_dynamic(image: DynamicImageBuffer)
+                    -> Result<ImageBuffer<Self, Vec<Self::Subpixel>>, DynamicImageBuffer> {
+        match image {
+            DynamicImageBuffer::$variant(image) => Ok(image),
+            image => Err(image),
+        }
+    }
+
+    fn from_dynamic_ref(image: &DynamicImageBuffer)
+                        -> Option<&ImageBuffer<Self, Vec<Self::Subpixel>>> {
+        match *image {
+            DynamicImageBuffer::$variant(ref image) => Some(image),
+            _ => None,
+        }
+    }
+
+    fn from_dynamic_mut(image: &mut DynamicImageBuffer)
+                        -> Option<&mut ImageBuffer<Self, Vec<Self::Subpixel>>> {
+        match *image {
+            DynamicImageBuffer::$variant(ref mut image) => Some(image),
+            _ => None,
+        }
+    }
+}
+)*
+
+impl DynamicImageBuffer {
+    /// Creates a new image of the pixel type matching `color_type`, filled with zeros.
+    ///
+    /// Returns None if no pixel type matches `color_type`.
+    pub fn new(color_type: ColorType, width: u32, height: u32) -> Option<DynamicImageBuffer> {
+        $(
+        if color_type == $color_type {
+            return Some(DynamicImageBuffer::$variant(ImageBuffer::new(width, height)));
+        }
+        )*
+        None
+    }
+
+    /// The color type of the pixels.
+    pub fn color_type(&self) -> ColorType {
+        match *self {
+            $(DynamicImageBuffer::$variant(_) => $color_type,)*
+        }
+    }
+
+    /// The width and height of this image.
+    pub fn dimensions(&self) -> (u32, u32) {
+        match *self {
+            $(DynamicImageBuffer::$variant(ref image) => image.dimensions(),)*
+        }
+    }
+
+    /// Converts the image to the pixel type `P`, rescaling the channels to its depth.
+    ///
+    /// Color is converted to gray as by `convert_buffer`, a missing alpha channel is opaque.
+    pub fn convert_to<P>(&self) -> ImageBuffer<P, Vec<P::Subpixel>>
+        where P: DynamicPixel,
+              P::Subpixel: ChannelMax,
+              Rgb<f32>: From<Rgb<P::Subpixel>>
+    {
+        if let Some(image) = P::from_dynamic_ref(self) {
+            return image.clone();
+        }
+        match *self {
+            $(DynamicImageBuffer::$variant(ref image) => convert(image),)*
+        }
+    }
+
+    $(
+    #[doc = concat!("Returns the buffer if the image holds `", stringify!($pixel), "` pixels.")]
+    pub fn $as_fn(&self) -> Option<&ImageBuffer<$pixel, Vec<<$pixel as Pixel>::Subpixel>>> {
+        match *self {
+            DynamicImageBuffer::$variant(ref image) => Some(image),
+            _ => None,
+        }
+    }
+    )*
+}
+
+    }
+}
+
+dynamic_image_buffer! {
+    Gray8, Gray<u8>, ColorType::Gray(8), as_gray8, #[doc = "8 bit grayscale."];
+    GrayA8, GrayA<u8>, ColorType::GrayA(8), as_gray_alpha8, #[doc = "8 bit grayscale with alpha."];
+    Rgb8, Rgb<u8>, ColorType::Rgb(8), as_rgb8, #[doc = "8 bit sRGB."];
+    Rgba8, Rgba<u8>, ColorType::Rgba(8), as_rgba8, #[doc = "8 bit sRGB with alpha."];
+    Gray16, Gray<u16>, ColorType::Gray(16), as_gray16, #[doc = "16 bit grayscale."];
+    GrayA16, GrayA<u16>, ColorType::GrayA(16), as_gray_alpha16,
+        #[doc = "16 bit grayscale with alpha."];
+    Rgb16, Rgb<u16>, ColorType::Rgb(16), as_rgb16, #[doc = "16 bit sRGB."];
+    Rgba16, Rgba<u16>, ColorType::Rgba(16), as_rgba16, #[doc = "16 bit sRGB with alpha."];
+    Gray32F, Gray<f32>, ColorType::Gray(32), as_gray32f, #[doc = "Floating point grayscale."];
+    GrayA32F, GrayA<f32>, ColorType::GrayA(32), as_gray_alpha32f,
+        #[doc = "Floating point grayscale with alpha."];
+    Rgb32F, Rgb<f32>, ColorType::Rgb(32), as_rgb32f, #[doc = "Floating point sRGB."];
+    Rgba32F, Rgba<f32>, ColorType::Rgba(32), as_rgba32f,
+        #[doc = "Floating point sRGB with alpha."];
+}
+
+impl DynamicImageBuffer {
+    /// The width of this image.
+    pub fn width(&self) -> u32 {
+        self.dimensions().0
+    }
+
+    /// The height of this image.
+    pub fn height(&self) -> u32 {
+        self.dimensions().1
+    }
+
+    /// Returns the buffer if the image holds pixels of type `P`.
+    pub fn as_buffer<P: DynamicPixel>(&self) -> Option<&ImageBuffer<P, Vec<P::Subpixel>>> {
+        P::from_dynamic_ref(self)
+    }
+
+    /// Returns the mutable buffer if the image holds pixels of type `P`.
+    pub fn as_buffer_mut<P>(&mut self) -> Option<&mut ImageBuffer<P, Vec<P::Subpixel>>>
+        where P: DynamicPixel
+    {
+        P::from_dynamic_mut(self)
+    }
+
+    /// Returns the buffer if the image holds pixels of type `P`, or the image otherwise.
+    pub fn into_buffer<P>(self) -> Result<ImageBuffer<P, Vec<P::Subpixel>>, DynamicImageBuffer>
+        where P: DynamicPixel
+    {
+        P::from_dynamic(self)
+    }
+}
+
+impl<P: DynamicPixel> From<ImageBuffer<P, Vec<P::Subpixel>>> for DynamicImageBuffer {
+    fn from(image: ImageBuffer<P, Vec<P::Subpixel>>) -> DynamicImageBuffer {
+        P::into_dynamic(image)
+    }
+}
+
+/// Converts between gray and RGB pixels with and without alpha, which are distinguished by
+/// their number of channels.
+fn convert<S, P>(image: &ImageBuffer<S, Vec<S::Subpixel>>) -> ImageBuffer<P, Vec<P::Subpixel>>
+    where S: Pixel,
+          S::Subpixel: ChannelMax,
+          P: Pixel,
+          P::Subpixel: ChannelMax,
+          Rgb<f32>: From<Rgb<P::Subpixel>>
+{
+    let max = P::Subpixel::channel_max();
+    image.map_buffer(|p| {
+        let mut c = [max; 4];
+        for (to, &from) in c.iter_mut().zip(p.channels().as_ref()) {
+            *to = rescale_channel(from);
+        }
+        let (rgb, alpha) = match S::channel_count() {
+            1 => ([c[0]; 3], max),
+            2 => ([c[0]; 3], c[1]),
+            3 => ([c[0], c[1], c[2]], max),
+            _ => ([c[0], c[1], c[2]], c[3]),
+        };
+        let channels = match (S::channel_count(), P::channel_count()) {
+            (3, 1) | (3, 2) | (4, 1) | (4, 2) => [Gray::from(Rgb::new(rgb))[0], alpha, max, max],
+            (_, 1) | (_, 2) => [rgb[0], alpha, max, max],
+            _ => [rgb[0], rgb[1], rgb[2], alpha],
+        };
+        *P::from_slice(&channels[..P::channel_count()])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use buffer::{GrayImage, ImageBuffer, RgbaImage};
+    use color_model::{ColorType, Gray, GrayA, Rgb, Rgba};
+    use super::DynamicImageBuffer;
+
+    #[test]
+    fn test_dispatch() {
+        let image = DynamicImageBuffer::from(RgbaImage::new(3, 2));
+        assert_eq!(image.color_type(), ColorType::Rgba(8));
+        assert_eq!(image.dimensions(), (3, 2));
+        assert!(image.as_rgba8().is_some() && image.as_rgb8().is_none());
+        let image = image.into_buffer::<Gray<u8>>().err().unwrap();
+        assert_eq!(image.into_buffer::<Rgba<u8>>().ok().unwrap().into_raw(), vec![0; 24]);
+
+        for &color_type in &[ColorType::GrayA(16), ColorType::Rgb(32), ColorType::Gray(8)] {
+            let image = DynamicImageBuffer::new(color_type, 1, 1).unwrap();
+            assert_eq!(image.color_type(), color_type);
+        }
+        assert!(DynamicImageBuffer::new(ColorType::Cmyk(8), 1, 1).is_none());
+        assert!(DynamicImageBuffer::new(ColorType::Gray(4), 1, 1).is_none());
+    }
+
+    #[test]
+    fn test_convert_to() {
+        let gray = GrayImage::from_raw(2, 1, vec![0, 255]).unwrap();
+        let image = DynamicImageBuffer::from(gray);
+        assert_eq!(image.convert_to::<Gray<u8>>().into_raw(), vec![0, 255]);
+        assert_eq!(image.convert_to::<Rgba<u16>>().into_raw(),
+                   vec![0, 0, 0, 65535, 65535, 65535, 65535, 65535]);
+        assert_eq!(image.convert_to::<GrayA<f32>>().into_raw(), vec![0.0, 1.0, 1.0, 1.0]);
+
+        let rgba = ImageBuffer::from_pixel(1, 1, Rgba::new([1.0f32, 0.0, 0.0, 0.5]));
+        let image = DynamicImageBuffer::from(rgba);
+        assert_eq!(image.convert_to::<Rgb<u8>>()[(0, 0)], Rgb::new([255, 0, 0]));
+        let gray = image.convert_to::<GrayA<u8>>()[(0, 0)];
+        assert_eq!(gray, GrayA::new([Gray::from(Rgb::new([255u8, 0, 0]))[0], 128]));
+    }
+}
@@ -0,0 +1,280 @@
+//! Stylization effects.
+
+use std::ops::{Deref, DerefMut};
+
+use buffer::{GrayImage, ImageBuffer, RgbImage};
+use color_model::{Gray, Hsv, Rgb};
+use preview::luma;
+use traits::Color as Pixel;
+
+/// The direction of the spans sorted by `ImageBuffer::pixel_sort`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SortDirection {
+    /// Sorts spans within rows from left to right.
+    Horizontal,
+    /// Sorts spans within columns from top to bottom.
+    Vertical,
+}
+
+/// The value pixels are sorted by.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SortKey {
+    /// The luma of the pixel.
+    Luma,
+    /// The hue of the pixel.
+    Hue,
+    /// The saturation of the pixel.
+    Saturation,
+}
+
+impl SortKey {
+    /// Returns the key of `rgb` in the range `0..256`.
+    fn of(self, rgb: Rgb<u8>) -> u8 {
+        match self {
+            SortKey::Luma => luma(rgb) as u8,
+            SortKey::Hue => Hsv::from(rgb)[0],
+            SortKey::Saturation => Hsv::from(rgb)[1],
+        }
+    }
+}
+
+impl<P, Container> ImageBuffer<P, Container>
+    where P: Pixel,
+          Rgb<u8>: From<P>,
+          Container: Deref<Target = [P::Subpixel]>
+{
+    /// Returns a mask selecting the pixels whose `key` is in the range `low..=high`.
+    ///
+    /// Selected pixels are 255, all others 0. Intended as mask for `pixel_sort`.
+    pub fn threshold_mask(&self, key: SortKey, low: u8, high: u8) -> GrayImage {
+        self.map_buffer(|&p| {
+            let k = key.of(Rgb::from(p));
+            Gray::new([if low <= k && k <= high { 255 } else { 0 }])
+        })
+    }
+}
+
+impl<P, Container> ImageBuffer<P, Container>
+    where P: Pixel,
+          Rgb<u8>: From<P>,
+          Container: Deref<Target = [P::Subpixel]> + DerefMut
+{
+    /// Sorts spans of pixels in ascending order of their `key`.
+    ///
+    /// A span is a maximal run of consecutive pixels in `direction` which are non-zero in
+    /// `mask`. Sorting is stable, so pixels with equal keys keep their order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the dimensions of `mask` differ from the image.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{GrayImage, color};
+    /// use image_buffer::effects::{SortDirection, SortKey};
+    /// let mut image = GrayImage::from_raw(5, 1, vec![200, 90, 10, 50, 30]).unwrap();
+    /// let mask = GrayImage::from_raw(5, 1, vec![255, 255, 255, 0, 255]).unwrap();
+    /// image.pixel_sort(SortDirection::Horizontal, SortKey::Luma, &mask);
+    /// assert_eq!(image.into_raw(), [10, 90, 200, 50, 30]);
+    /// ```
+    pub fn pixel_sort<M>(&mut self,
+                         direction: SortDirection,
+                         key: SortKey,
+                         mask: &ImageBuffer<Gray<u8>, M>)
+        where M: Deref<Target = [u8]>
+    {
+        assert_eq!(self.dimensions(),
+                   mask.dimensions(),
+                   "dimensions of the mask differ");
+        let (width, height) = self.dimensions();
+        let (lines, len) = match direction {
+            SortDirection::Horizontal => (height, width),
+            SortDirection::Vertical => (width, height),
+        };
+        let position = |line: u32, i: u32| match direction {
+            SortDirection::Horizontal => (i, line),
+            SortDirection::Vertical => (line, i),
+        };
+        let mut span = Vec::new();
+        for line in 0..lines {
+            let mut start = 0;
+            while start < len {
+                if mask[position(line, start)][0] == 0 {
+                    start += 1;
+                    continue;
+                }
+                let mut end = start;
+                while end < len && mask[position(line, end)][0] != 0 {
+                    end += 1;
+                }
+                span.clear();
+                span.extend((start..end).map(|i| self[position(line, i)]));
+                span.sort_by_key(|&p| key.of(Rgb::from(p)));
+                for (i, &p) in (start..end).zip(span.iter()) {
+                    self[position(line, i)] = p;
+                }
+                start = end;
+            }
+        }
+    }
+}
+
+/// Screen of an AM halftone with round dots.
+struct Screen {
+    cell_size: f32,
+    sin: f32,
+    cos: f32,
+}
+
+impl Screen {
+    fn new(cell_size: f32, angle: f32) -> Screen {
+        let (sin, cos) = angle.to_radians().sin_cos();
+        Screen { cell_size, sin, cos }
+    }
+
+    /// Returns the center of the cell containing `(x, y)` and the position of `(x, y)` within
+    /// the cell in the range `-0.5..0.5`.
+    fn locate(&self, x: f32, y: f32) -> ((f32, f32), (f32, f32)) {
+        let u = (x * self.cos + y * self.sin) / self.cell_size;
+        let v = (y * self.cos - x * self.sin) / self.cell_size;
+        let (cu, cv) = (u.floor() + 0.5, v.floor() + 0.5);
+        let center = ((cu * self.cos - cv * self.sin) * self.cell_size,
+                      (cu * self.sin + cv * self.cos) * self.cell_size);
+        (center, (u - cu, v - cv))
+    }
+
+    /// Returns `true` if the offset `(u, v)` within a cell is covered by a dot of the ink
+    /// `coverage` in the range `0..=1`.
+    ///
+    /// Dots are circles around the cell center up to half coverage. Above, the uncovered
+    /// paper forms circles around the cell corners.
+    fn is_inked((u, v): (f32, f32), coverage: f32) -> bool {
+        use std::f32::consts::PI;
+        if coverage <= 0.5 {
+            u * u + v * v < coverage / PI
+        } else {
+            let (du, dv) = (0.5 - u.abs(), 0.5 - v.abs());
+            du * du + dv * dv >= (1.0 - coverage) / PI
+        }
+    }
+}
+
+/// Renders an AM halftone at `scale` times the resolution of an image with the given
+/// dimensions. `ink(channel, x, y)` returns the ink coverage of a channel at a pixel.
+fn render_halftone<F>(width: u32,
+                      height: u32,
+                      scale: u32,
+                      screens: &[Screen],
+                      ink: F)
+                      -> Vec<Vec<bool>>
+    where F: Fn(usize, u32, u32) -> f32
+{
+    let scale_f = scale as f32;
+    screens.iter()
+        .enumerate()
+        .map(|(channel, screen)| {
+            (0..height * scale)
+                .flat_map(|y| (0..width * scale).map(move |x| (x, y)))
+                .map(|(x, y)| {
+                    let (px, py) = ((x as f32 + 0.5) / scale_f, (y as f32 + 0.5) / scale_f);
+                    let ((cx, cy), offset) = screen.locate(px, py);
+                    let sx = (cx.max(0.0) as u32).min(width - 1);
+                    let sy = (cy.max(0.0) as u32).min(height - 1);
+                    Screen::is_inked(offset, ink(channel, sx, sy))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+impl<C: Deref<Target = [u8]>> ImageBuffer<Gray<u8>, C> {
+    /// Renders the image as amplitude modulated halftone with black dots on white.
+    ///
+    /// The dots are placed on a grid with `cell_size` pixels spacing which is rotated by
+    /// `angle` degrees, the classic angle for black is 45°. The size of each dot is chosen such
+    /// that it covers the fraction of its cell given by the darkness of the image. The result
+    /// has `scale` times the resolution of the image to render the dots accurately.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{GrayImage, color};
+    /// let gray = GrayImage::from_pixel(32, 32, color::Gray::new([64]));
+    /// let halftone = gray.halftone(4.0, 45.0, 4);
+    /// let black = halftone.pixels().filter(|p| p[0] == 0).count() as f32;
+    /// assert!((black / (128.0 * 128.0) - 0.75).abs() < 0.03);
+    /// ```
+    pub fn halftone(&self, cell_size: f32, angle: f32, scale: u32) -> GrayImage {
+        let (width, height) = self.dimensions();
+        if width == 0 || height == 0 {
+            return GrayImage::new(width * scale, height * scale);
+        }
+        let screens = [Screen::new(cell_size, angle)];
+        let inked = render_halftone(width, height, scale, &screens, |_, x, y| {
+            1.0 - self[(x, y)][0] as f32 / 255.0
+        });
+        let mut buffer = GrayImage::new(width * scale, height * scale);
+        for (p, &ink) in buffer.pixels_mut().zip(inked[0].iter()) {
+            *p = Gray::new([if ink { 0 } else { 255 }]);
+        }
+        buffer
+    }
+}
+
+impl<C: Deref<Target = [u8]>> ImageBuffer<Rgb<u8>, C> {
+    /// Renders the image as amplitude modulated halftone with a separate screen per channel.
+    ///
+    /// Every channel is screened with its own angle from `angles`, dots remove the channel
+    /// like cyan, magenta and yellow ink remove red, green and blue from white paper. See
+    /// `ImageBuffer<Gray<u8>, _>::halftone` for the other parameters. Common angles are
+    /// `[15.0, 75.0, 0.0]`.
+    pub fn halftone(&self, cell_size: f32, angles: [f32; 3], scale: u32) -> RgbImage {
+        let (width, height) = self.dimensions();
+        if width == 0 || height == 0 {
+            return RgbImage::new(width * scale, height * scale);
+        }
+        let screens: Vec<Screen> = angles.iter().map(|&a| Screen::new(cell_size, a)).collect();
+        let inked = render_halftone(width, height, scale, &screens, |channel, x, y| {
+            1.0 - self[(x, y)][channel] as f32 / 255.0
+        });
+        let mut buffer = RgbImage::new(width * scale, height * scale);
+        for (i, p) in buffer.pixels_mut().enumerate() {
+            let channel = |c: usize| if inked[c][i] { 0 } else { 255 };
+            *p = Rgb::new([channel(0), channel(1), channel(2)]);
+        }
+        buffer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SortDirection, SortKey};
+    use buffer::RgbImage;
+    use color_model::Rgb;
+
+    #[test]
+    fn test_vertical_hue_sort() {
+        let colors = [Rgb::new([0, 0, 255]), Rgb::new([0, 255, 0]), Rgb::new([255, 0, 0])];
+        let mut image = RgbImage::from_fn(2, 3, |_, y| colors[y as usize]);
+        let mask = image.threshold_mask(SortKey::Saturation, 1, 255);
+        image.pixel_sort(SortDirection::Vertical, SortKey::Hue, &mask);
+        for x in 0..2 {
+            assert_eq!(image[(x, 0)], colors[2]);
+            assert_eq!(image[(x, 1)], colors[1]);
+            assert_eq!(image[(x, 2)], colors[0]);
+        }
+    }
+
+    #[test]
+    fn test_halftone_extremes() {
+        let image = RgbImage::from_fn(8, 8, |x, _| {
+            if x < 4 { Rgb::new([255, 255, 255]) } else { Rgb::new([0, 255, 0]) }
+        });
+        let halftone = image.halftone(2.0, [15.0, 75.0, 0.0], 2);
+        assert_eq!(halftone.dimensions(), (16, 16));
+        assert!(halftone.pixels().all(|p| p[1] == 255));
+        assert_eq!(halftone[(1, 1)], Rgb::new([255, 255, 255]));
+        assert_eq!(halftone[(13, 9)], Rgb::new([0, 255, 0]));
+    }
+}
@@ -0,0 +1,566 @@
+//! Contrast enhancement and tone matching.
+
+use std::cmp;
+use std::ops::{Deref, DerefMut};
+
+use num_traits::{Float, NumCast, One, Zero};
+
+use buffer::{GrayImage, ImageBuffer, RgbImage};
+use color_model::{Gray, Rgb};
+use math::{from_f32, to_f32};
+use traits::{ChannelMax, Color as Pixel};
+
+/// Lookup tables of the contrast limited adaptive histogram equalization of every tile.
+struct Clahe {
+    tiles_x: usize,
+    tiles_y: usize,
+    tile_width: f32,
+    tile_height: f32,
+    luts: Vec<[u8; 256]>,
+}
+
+impl Clahe {
+    /// Computes the lookup tables of `tiles_x`×`tiles_y` tiles of the image of the given
+    /// dimensions whose values are given by `value`.
+    fn new<F>(width: u32, height: u32, tiles: (u32, u32), clip_limit: f32, value: F) -> Clahe
+        where F: Fn(u32, u32) -> u8
+    {
+        let tiles_x = cmp::max(1, cmp::min(tiles.0, width)) as usize;
+        let tiles_y = cmp::max(1, cmp::min(tiles.1, height)) as usize;
+        let tile_width = (width as usize).div_ceil(tiles_x);
+        let tile_height = (height as usize).div_ceil(tiles_y);
+        let mut luts = Vec::with_capacity(tiles_x * tiles_y);
+        for ty in 0..tiles_y {
+            for tx in 0..tiles_x {
+                let (x0, y0) = (tx * tile_width, ty * tile_height);
+                let x1 = cmp::min(x0 + tile_width, width as usize);
+                let y1 = cmp::min(y0 + tile_height, height as usize);
+                let mut histogram = [0u32; 256];
+                for y in y0..y1 {
+                    for x in x0..x1 {
+                        histogram[value(x as u32, y as u32) as usize] += 1;
+                    }
+                }
+                let count = (x1.saturating_sub(x0) * y1.saturating_sub(y0)) as u32;
+                luts.push(equalize(&mut histogram, count, clip_limit));
+            }
+        }
+        Clahe {
+            tiles_x,
+            tiles_y,
+            tile_width: tile_width as f32,
+            tile_height: tile_height as f32,
+            luts,
+        }
+    }
+
+    /// Maps the value `v` of the pixel `(x, y)` by interpolating the four nearest tiles.
+    fn map(&self, x: u32, y: u32, v: u8) -> u8 {
+        let neighbors = |p: u32, size: f32, count: usize| {
+            let f = ((p as f32 + 0.5) / size - 0.5).max(0.0);
+            let i0 = cmp::min(f as usize, count - 1);
+            let i1 = cmp::min(i0 + 1, count - 1);
+            (i0, i1, (f - i0 as f32).min(1.0))
+        };
+        let (x0, x1, fx) = neighbors(x, self.tile_width, self.tiles_x);
+        let (y0, y1, fy) = neighbors(y, self.tile_height, self.tiles_y);
+        let lut = |tx: usize, ty: usize| self.luts[ty * self.tiles_x + tx][v as usize] as f32;
+        let top = lut(x0, y0) * (1.0 - fx) + lut(x1, y0) * fx;
+        let bottom = lut(x0, y1) * (1.0 - fx) + lut(x1, y1) * fx;
+        (top * (1.0 - fy) + bottom * fy).round() as u8
+    }
+}
+
+/// Clips `histogram` at `clip_limit` times the mean bin height, redistributes the excess and
+/// returns the equalization lookup table.
+fn equalize(histogram: &mut [u32; 256], count: u32, clip_limit: f32) -> [u8; 256] {
+    let mut lut = [0; 256];
+    if count == 0 {
+        return lut;
+    }
+    let limit = cmp::max(1, (clip_limit * count as f32 / 256.0) as u32);
+    let mut excess = 0;
+    for bin in histogram.iter_mut() {
+        if *bin > limit {
+            excess += *bin - limit;
+            *bin = limit;
+        }
+    }
+    let (share, rest) = (excess / 256, excess % 256);
+    let mut sum = 0;
+    for (i, (bin, entry)) in histogram.iter().zip(lut.iter_mut()).enumerate() {
+        sum += bin + share + if (i as u32) < rest { 1 } else { 0 };
+        *entry = (sum as u64 * 255 / count as u64) as u8;
+    }
+    lut
+}
+
+impl<C: Deref<Target = [u8]>> ImageBuffer<Gray<u8>, C> {
+    /// Enhances the local contrast with contrast limited adaptive histogram equalization.
+    ///
+    /// The image is divided into `tiles.0`×`tiles.1` tiles which are equalized separately. The
+    /// height of the histogram bins is limited to `clip_limit` times their mean height to avoid
+    /// amplifying noise, typical values are between `2.0` and `4.0`. The results of neighboring
+    /// tiles are interpolated bilinearly.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{GrayImage, color};
+    /// let dim = GrayImage::from_fn(64, 64, |x, _| color::Gray::new([100 + x as u8 / 8]));
+    /// let enhanced = dim.equalize_clahe((4, 4), 3.0);
+    /// assert!(enhanced[(63, 0)][0] - enhanced[(0, 0)][0] > 7);
+    /// ```
+    pub fn equalize_clahe(&self, tiles: (u32, u32), clip_limit: f32) -> GrayImage {
+        let (width, height) = self.dimensions();
+        let clahe = Clahe::new(width, height, tiles, clip_limit, |x, y| self[(x, y)][0]);
+        GrayImage::from_fn(width, height, |x, y| {
+            Gray::new([clahe.map(x, y, self[(x, y)][0])])
+        })
+    }
+}
+
+impl<C: Deref<Target = [u8]>> ImageBuffer<Rgb<u8>, C> {
+    /// Enhances the local contrast with contrast limited adaptive histogram equalization.
+    ///
+    /// The value (the maximum of the channels) is equalized and all channels are scaled by
+    /// the same factor, which preserves the hue. See `ImageBuffer<Gray<u8>, _>::equalize_clahe`
+    /// for the meaning of the parameters.
+    pub fn equalize_clahe(&self, tiles: (u32, u32), clip_limit: f32) -> RgbImage {
+        let (width, height) = self.dimensions();
+        let value = |x, y| {
+            let c: &[u8; 3] = self[(x, y)].as_ref();
+            cmp::max(c[0], cmp::max(c[1], c[2]))
+        };
+        let clahe = Clahe::new(width, height, tiles, clip_limit, value);
+        RgbImage::from_fn(width, height, |x, y| {
+            let v = value(x, y);
+            let scale = clahe.map(x, y, v) as f32 / cmp::max(v, 1) as f32;
+            let c: &[u8; 3] = self[(x, y)].as_ref();
+            let scaled = |c: u8| (c as f32 * scale).round().min(255.0) as u8;
+            Rgb::new([scaled(c[0]), scaled(c[1]), scaled(c[2])])
+        })
+    }
+}
+
+/// Returns the cumulative histograms of the channels of `image`, `cdf[c][v]` is the number of
+/// pixels whose channel `c` does not exceed `v`.
+fn cumulative_histograms<P, C>(image: &ImageBuffer<P, C>) -> Vec<[u64; 256]>
+    where P: Pixel<Subpixel = u8>,
+          C: Deref<Target = [u8]>
+{
+    let mut cdf = vec![[0u64; 256]; P::channel_count()];
+    for p in image.pixels() {
+        for (h, &v) in cdf.iter_mut().zip(p.channels().as_ref()) {
+            h[v as usize] += 1;
+        }
+    }
+    for h in &mut cdf {
+        for v in 1..256 {
+            h[v] += h[v - 1];
+        }
+    }
+    cdf
+}
+
+impl<P, C> ImageBuffer<P, C>
+    where P: Pixel<Subpixel = u8>,
+          C: Deref<Target = [u8]>
+{
+    /// Maps the values of every color channel such that its histogram matches the histogram of
+    /// the same channel of `reference`.
+    ///
+    /// Each value is mapped to the smallest reference value whose cumulative frequency reaches
+    /// the cumulative frequency of the value. The images may have different dimensions, the
+    /// alpha channel is copied unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{GrayImage, color::Gray};
+    /// let dark = GrayImage::from_fn(4, 1, |x, _| Gray::new([x as u8 * 10]));
+    /// let bright = GrayImage::from_fn(2, 2, |x, y| Gray::new([200 + (x + 2 * y) as u8]));
+    /// assert_eq!(dark.match_histogram(&bright).into_raw(), vec![200, 201, 202, 203]);
+    /// ```
+    pub fn match_histogram<D>(&self, reference: &ImageBuffer<P, D>) -> ImageBuffer<P, Vec<u8>>
+        where D: Deref<Target = [u8]>
+    {
+        profile_op!("match_histogram",
+                    2 * ::profiling::image_bytes(self) + ::profiling::image_bytes(reference));
+        let alpha = P::layout().alpha_position();
+        let (source, target) = (cumulative_histograms(self), cumulative_histograms(reference));
+        let (source_count, target_count) = (self.pixels().len() as u64,
+                                            reference.pixels().len() as u64);
+        let luts: Vec<[u8; 256]> = source.iter()
+            .zip(&target)
+            .enumerate()
+            .map(|(c, (source, target))| {
+                let mut lut = [0; 256];
+                for (v, entry) in lut.iter_mut().enumerate() {
+                    *entry = if Some(c) == alpha || target_count == 0 {
+                        v as u8
+                    } else {
+                        // Compares source[v] / source_count with target[r] / target_count
+                        let level = target.partition_point(|&t| {
+                            (t as u128) * (source_count as u128) <
+                            (source[v] as u128) * (target_count as u128)
+                        });
+                        level.min(255) as u8
+                    };
+                }
+                lut
+            })
+            .collect();
+        self.map_buffer(|p| {
+            let mut p = *p;
+            for (v, lut) in p.channels_mut().as_mut().iter_mut().zip(&luts) {
+                *v = lut[*v as usize];
+            }
+            p
+        })
+    }
+}
+
+impl<P, C> ImageBuffer<P, C>
+    where P: Pixel,
+          P::Subpixel: Float,
+          C: Deref<Target = [P::Subpixel]>
+{
+    /// Maps the color channels linearly from `[min, max]` to `[0, 1]`, clamping values outside
+    /// of the range.
+    ///
+    /// The alpha channel is copied unchanged. NaN values stay NaN, all values become 0 if `min`
+    /// equals `max`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{ImageBuffer, color::Gray};
+    /// let hdr: ImageBuffer<Gray<f32>, _> =
+    ///     ImageBuffer::from_raw(4, 1, vec![-1.0, 2.0, 4.0, 10.0]).unwrap();
+    /// assert_eq!(hdr.normalize(2.0, 6.0).into_raw(), vec![0.0, 0.0, 0.5, 1.0]);
+    /// ```
+    pub fn normalize(&self,
+                     min: P::Subpixel,
+                     max: P::Subpixel)
+                     -> ImageBuffer<P, Vec<P::Subpixel>> {
+        profile_op!("normalize", 2 * ::profiling::image_bytes(self));
+        let (zero, one) = (P::Subpixel::zero(), P::Subpixel::one());
+        let range = max - min;
+        self.map_buffer(|p| {
+            p.map_with_alpha(|v| {
+                if v.is_nan() {
+                    v
+                } else if range > zero {
+                    ((v - min) / range).max(zero).min(one)
+                } else {
+                    zero
+                }
+            },
+                             |a| a)
+        })
+    }
+
+    /// Returns the values of the percentiles `percentile_low` and `percentile_high` of the
+    /// color channels, or None if there are no values.
+    ///
+    /// The percentiles are given in percent and taken over all color channels together, NaN
+    /// values are ignored.
+    pub fn levels(&self,
+                  percentile_low: f64,
+                  percentile_high: f64)
+                  -> Option<(P::Subpixel, P::Subpixel)> {
+        let alpha = P::layout().alpha_position();
+        let mut values: Vec<P::Subpixel> = self.pixels()
+            .flat_map(|p| {
+                p.channels()
+                    .as_ref()
+                    .iter()
+                    .enumerate()
+                    .filter(|&(c, v)| Some(c) != alpha && !v.is_nan())
+                    .map(|(_, &v)| v)
+            })
+            .collect();
+        if values.is_empty() {
+            return None;
+        }
+        values.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
+        let percentile = |p: f64| {
+            let rank = (p / 100.0).clamp(0.0, 1.0) * (values.len() - 1) as f64;
+            values[rank.round() as usize]
+        };
+        Some((percentile(percentile_low), percentile(percentile_high)))
+    }
+
+    /// Stretches the color channels such that the percentile `percentile_low` becomes 0 and
+    /// the percentile `percentile_high` becomes 1, see `levels` and `normalize`.
+    ///
+    /// Taking the percentiles over all color channels together preserves the hue of colors.
+    /// Images without values apart from NaN are copied unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{ImageBuffer, color::Gray};
+    /// let data: Vec<f32> = (0..101).map(|v| v as f32 * 0.5).collect();
+    /// let image: ImageBuffer<Gray<f32>, _> = ImageBuffer::from_raw(101, 1, data).unwrap();
+    /// assert_eq!(image.levels(10.0, 90.0), Some((5.0, 45.0)));
+    /// let levelled = image.auto_levels(10.0, 90.0);
+    /// assert_eq!((levelled[(0, 0)][0], levelled[(50, 0)][0]), (0.0, 0.5));
+    /// ```
+    pub fn auto_levels(&self,
+                       percentile_low: f64,
+                       percentile_high: f64)
+                       -> ImageBuffer<P, Vec<P::Subpixel>> {
+        match self.levels(percentile_low, percentile_high) {
+            Some((low, high)) => self.normalize(low, high),
+            None => self.map_buffer(|&p| p),
+        }
+    }
+
+    /// Converts to a pixel type with 8 bit channels, mapping `[0, 1]` to `[0, 255]`.
+    ///
+    /// Values outside of `[0, 1]` are clamped and NaN becomes 0. Unlike `convert_buffer` no
+    /// gamma compression is applied, such that normalized data keeps its linear scale.
+    ///
+    /// # Panics
+    ///
+    /// If the number of channels of `Q` differs from `P`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{GrayImage, ImageBuffer, color::Gray};
+    /// let image: ImageBuffer<Gray<f64>, _> =
+    ///     ImageBuffer::from_raw(4, 1, vec![-0.5, 0.5, 1.5, f64::NAN]).unwrap();
+    /// let bytes: GrayImage = image.to_u8_clamped();
+    /// assert_eq!(bytes.into_raw(), vec![0, 128, 255, 0]);
+    /// ```
+    pub fn to_u8_clamped<Q>(&self) -> ImageBuffer<Q, Vec<u8>>
+        where Q: Pixel<Subpixel = u8>
+    {
+        assert_eq!(P::channel_count(), Q::channel_count(), "number of channels differs");
+        let mut buffer = ImageBuffer::new(self.width(), self.height());
+        for (to, from) in buffer.pixels_mut().zip(self.pixels()) {
+            let to: &mut Q = to;
+            for (d, &v) in to.channels_mut().as_mut().iter_mut().zip(from.channels().as_ref()) {
+                let v: f64 = NumCast::from(v).unwrap();
+                *d = if v >= 1.0 {
+                    255
+                } else if v > 0.0 {
+                    (v * 255.0).round() as u8
+                } else {
+                    0
+                };
+            }
+        }
+        buffer
+    }
+}
+
+impl<P, C> ImageBuffer<P, C>
+    where P: Pixel,
+          P::Subpixel: ChannelMax,
+          C: Deref<Target = [P::Subpixel]> + DerefMut
+{
+    /// Applies the tone curve `f` to the color channels, leaving alpha unchanged.
+    ///
+    /// `f` maps values normalized to `[0, 1]`, integer results are rounded and clamped to the
+    /// range of the subpixel type. Values for which `f` returns NaN are left unchanged. 8 bit
+    /// channels are mapped through a lookup table.
+    fn apply_tone<F: Fn(f32) -> f32>(&mut self, f: F) {
+        let max = to_f32(P::Subpixel::channel_max());
+        let tone = |v: P::Subpixel| {
+            let t = f(to_f32(v) / max);
+            if t.is_nan() { v } else { from_f32::<P::Subpixel>(t * max) }
+        };
+        if max == 255.0 {
+            let lut: Vec<P::Subpixel> = (0..256u32).map(|v| tone(NumCast::from(v).unwrap()))
+                .collect();
+            let lookup = |v: P::Subpixel| {
+                let i: usize = NumCast::from(v).unwrap();
+                lut[i]
+            };
+            for p in self.pixels_mut() {
+                p.apply_with_alpha(lookup, |a| a);
+            }
+        } else {
+            for p in self.pixels_mut() {
+                p.apply_with_alpha(tone, |a| a);
+            }
+        }
+    }
+
+    /// Raises the normalized color channels to the power `gamma` in place, values above 1
+    /// darken the image and values below 1 brighten it.
+    ///
+    /// Negative float values become 0, the alpha channel is left unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{GrayImage, color::Gray};
+    /// let mut image = GrayImage::from_fn(3, 1, |x, _| Gray::new([x as u8 * 127]));
+    /// image.adjust_gamma(2.0);
+    /// assert_eq!(image.into_raw(), vec![0, 63, 253]);
+    /// ```
+    pub fn adjust_gamma(&mut self, gamma: f32) {
+        profile_op!("adjust_gamma", 2 * ::profiling::image_bytes(self));
+        self.apply_tone(|v| v.max(0.0).powf(gamma));
+    }
+
+    /// Adds `amount` to the color channels in place, negative amounts darken the image.
+    ///
+    /// `amount` is given on the 8 bit scale and rescaled to the range of the subpixel type,
+    /// such that it has the same effect at every bit depth. The alpha channel is left
+    /// unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{GrayImage, ImageBuffer, color::Gray};
+    /// let mut image = GrayImage::from_raw(3, 1, vec![0, 100, 250]).unwrap();
+    /// image.brighten(10);
+    /// assert_eq!(image.into_raw(), vec![10, 110, 255]);
+    /// let mut deep: ImageBuffer<Gray<u16>, _> = ImageBuffer::from_raw(1, 1, vec![0]).unwrap();
+    /// deep.brighten(1);
+    /// assert_eq!(deep.into_raw(), vec![257]);
+    /// ```
+    pub fn brighten(&mut self, amount: i32) {
+        profile_op!("brighten", 2 * ::profiling::image_bytes(self));
+        let offset = amount as f32 / 255.0;
+        self.apply_tone(|v| v + offset);
+    }
+
+    /// Scales the distance of the color channels from the middle of their range in place.
+    ///
+    /// `contrast` is given in percent, the distance is multiplied by
+    /// `((100 + contrast) / 100)²`, such that positive values increase the contrast and
+    /// negative values down to -100 reduce it. The alpha channel is left unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{GrayImage, color::Gray};
+    /// let mut image = GrayImage::from_raw(3, 1, vec![64, 128, 200]).unwrap();
+    /// image.adjust_contrast(50.0);
+    /// assert_eq!(image.into_raw(), vec![0, 129, 255]);
+    /// ```
+    pub fn adjust_contrast(&mut self, contrast: f32) {
+        profile_op!("adjust_contrast", 2 * ::profiling::image_bytes(self));
+        let factor = ((100.0 + contrast) / 100.0).powi(2);
+        self.apply_tone(|v| (v - 0.5) * factor + 0.5);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32;
+
+    use buffer::{GrayImage, ImageBuffer, RgbImage, RgbaImage};
+    use color_model::{convert_linear, Gray, Rgb, Rgba};
+    use traits::Color;
+
+    #[test]
+    fn test_clahe_local_contrast() {
+        // Two regions of different brightness with faint stripes
+        let image = GrayImage::from_fn(64, 64, |x, y| {
+            let base = if x < 32 { 40 } else { 200 };
+            Gray::new([base + (y / 4 % 2) as u8 * 6])
+        });
+        let enhanced = image.equalize_clahe((4, 4), 40.0);
+        for &x in &[8, 56] {
+            let contrast = enhanced[(x, 36)][0] as i32 - enhanced[(x, 32)][0] as i32;
+            assert!(contrast > 20, "{}", contrast);
+        }
+    }
+
+    #[test]
+    fn test_match_histogram() {
+        let noise = ::test_images::noise(16, 16, 9);
+        assert_eq!(noise.match_histogram(&noise).into_raw(), noise.clone().into_raw());
+
+        // Matching a ramp to an image with the inverted tones yields the same ramp
+        let ramp = GrayImage::from_fn(256, 1, |x, _| Gray::new([x as u8]));
+        let inverted = GrayImage::from_fn(16, 16, |x, y| Gray::new([255 - (x + 16 * y) as u8]));
+        assert_eq!(ramp.match_histogram(&inverted).into_raw(), ramp.clone().into_raw());
+
+        let rgba = RgbaImage::from_fn(2, 1, |x, _| Rgba::new([x as u8, 0, 5, 77]));
+        let reference = RgbaImage::from_fn(2, 1, |x, _| Rgba::new([50 + x as u8 * 100, 9, 9, 0]));
+        assert_eq!(rgba.match_histogram(&reference).into_raw(),
+                   vec![50, 9, 9, 77, 150, 9, 9, 77]);
+    }
+
+    #[test]
+    fn test_clahe_rgb_preserves_hue() {
+        let image = RgbImage::from_fn(16, 16, |x, _| Rgb::new([100 + x as u8, 50, 0]));
+        let enhanced = image.equalize_clahe((2, 2), 2.0);
+        for p in enhanced.pixels() {
+            let c: &[u8; 3] = p.as_ref();
+            assert_eq!(c[2], 0);
+            assert!(c[0] > c[1]);
+        }
+    }
+
+    #[test]
+    fn test_levels() {
+        let data = vec![0.0f32, 2.0, 4.0, 0.5, f32::NAN, 8.0, 1.0, 0.25];
+        let image: ImageBuffer<Rgba<f32>, _> = ImageBuffer::from_raw(2, 1, data).unwrap();
+        let normalized = image.normalize(0.0, 8.0).into_raw();
+        assert_eq!(&normalized[..4], &[0.0, 0.25, 0.5, 0.5]);
+        assert!(normalized[4].is_nan());
+        assert_eq!(&normalized[5..], &[1.0, 0.125, 0.25]);
+
+        assert_eq!(image.levels(0.0, 100.0), Some((0.0, 8.0)));
+        let levelled = image.auto_levels(0.0, 100.0);
+        assert_eq!(levelled[(0, 0)][2], 0.5);
+        let flat: ImageBuffer<Gray<f32>, _> = ImageBuffer::from_pixel(2, 2, Gray::new([3.0]));
+        assert_eq!(flat.normalize(3.0, 3.0).into_raw(), vec![0.0; 4]);
+        let nan = ImageBuffer::<Gray<f32>, _>::from_pixel(1, 1, Gray::new([f32::NAN]));
+        assert_eq!(nan.levels(1.0, 99.0), None);
+        assert!(nan.auto_levels(1.0, 99.0)[(0, 0)][0].is_nan());
+
+        let bytes: RgbaImage = levelled.to_u8_clamped();
+        assert_eq!(bytes.into_raw(), vec![0, 64, 128, 128, 0, 255, 32, 64]);
+    }
+
+    #[test]
+    fn test_tone_adjustments() {
+        // The lookup tables of 8 bit channels match the direct computation of 16 bit channels
+        let mut image = RgbaImage::from_fn(64, 4, |x, y| {
+            Rgba::new([x as u8 * 4, y as u8 * 60, 255 - x as u8, 90])
+        });
+        let mut deep: ImageBuffer<Rgba<u16>, _> = image.map_buffer(|&p| convert_linear(p));
+        image.adjust_gamma(0.5);
+        image.brighten(-20);
+        image.adjust_contrast(30.0);
+        deep.adjust_gamma(0.5);
+        deep.brighten(-20);
+        deep.adjust_contrast(30.0);
+        for (p, q) in image.pixels().zip(deep.pixels()) {
+            for (&a, &b) in p.channels().as_ref().iter().zip(q.channels().as_ref()) {
+                assert!((a as i32 - (b as i32 + 128) / 257).abs() <= 1, "{} {}", a, b);
+            }
+            assert_eq!(p[3], 90);
+        }
+
+        let mut float: ImageBuffer<Gray<f32>, _> =
+            ImageBuffer::from_raw(3, 1, vec![-0.5, 0.25, 2.0]).unwrap();
+        float.adjust_gamma(2.0);
+        assert_eq!(float.clone().into_raw(), vec![0.0, 0.0625, 4.0]);
+        float.brighten(51);
+        assert_eq!(float.into_raw(), vec![0.2, 0.2625, 4.2]);
+    }
+
+    #[test]
+    fn test_tone_adjustments_nan() {
+        // NaN parameters and results such as 0^NaN leave the values unchanged
+        let mut image = GrayImage::from_raw(3, 1, vec![0, 128, 255]).unwrap();
+        image.adjust_gamma(f32::NAN);
+        image.adjust_contrast(f32::NAN);
+        assert_eq!(image.into_raw(), vec![0, 128, 255]);
+        let mut deep: ImageBuffer<Gray<u16>, _> = ImageBuffer::from_raw(2, 1, vec![0, 9]).unwrap();
+        deep.adjust_gamma(f32::NAN);
+        assert_eq!(deep.into_raw(), vec![0, 9]);
+    }
+}
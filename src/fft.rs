@@ -0,0 +1,99 @@
+//! Fast Fourier transforms of power of two sizes.
+
+use std::f64::consts::PI;
+
+/// Transforms the complex sequence `(re, im)` in place.
+///
+/// The inverse transform is scaled by `1 / n`, such that it inverts the forward transform.
+///
+/// # Panics
+///
+/// If the length is not a power of two or the slices differ in length.
+pub fn fft(re: &mut [f64], im: &mut [f64], inverse: bool) {
+    let n = re.len();
+    assert!(n.is_power_of_two() && im.len() == n);
+    // Bit reversal permutation
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+    let sign = if inverse { 1.0 } else { -1.0 };
+    let mut len = 2;
+    while len <= n {
+        let angle = sign * 2.0 * PI / len as f64;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (wr, wi) = ((angle * k as f64).cos(), (angle * k as f64).sin());
+                let (a, b) = (start + k, start + k + len / 2);
+                let tr = re[b] * wr - im[b] * wi;
+                let ti = re[b] * wi + im[b] * wr;
+                re[b] = re[a] - tr;
+                im[b] = im[a] - ti;
+                re[a] += tr;
+                im[a] += ti;
+            }
+        }
+        len <<= 1;
+    }
+    if inverse {
+        for v in re.iter_mut().chain(im.iter_mut()) {
+            *v /= n as f64;
+        }
+    }
+}
+
+/// Transforms the row major `width`×`height` complex array `(re, im)` in place.
+///
+/// # Panics
+///
+/// If a dimension is not a power of two or the slices do not have `width * height` elements.
+pub fn fft_2d(re: &mut [f64], im: &mut [f64], width: usize, height: usize, inverse: bool) {
+    assert!(re.len() == width * height && im.len() == width * height);
+    for (r, i) in re.chunks_mut(width).zip(im.chunks_mut(width)) {
+        fft(r, i, inverse);
+    }
+    let (mut col_re, mut col_im) = (vec![0.0; height], vec![0.0; height]);
+    for x in 0..width {
+        for y in 0..height {
+            col_re[y] = re[y * width + x];
+            col_im[y] = im[y * width + x];
+        }
+        fft(&mut col_re, &mut col_im, inverse);
+        for y in 0..height {
+            re[y * width + x] = col_re[y];
+            im[y * width + x] = col_im[y];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fft, fft_2d};
+
+    #[test]
+    fn test_round_trip() {
+        let input: Vec<f64> = (0..16).map(|v| ((v * 7) % 5) as f64).collect();
+        let (mut re, mut im) = (input.clone(), vec![0.0; 16]);
+        fft(&mut re, &mut im, false);
+        assert!((re[0] - input.iter().sum::<f64>()).abs() < 1e-9);
+        fft(&mut re, &mut im, true);
+        for (a, b) in re.iter().zip(input.iter()) {
+            assert!((a - b).abs() < 1e-9);
+        }
+
+        // A single impulse has a flat spectrum
+        let (mut re, mut im) = (vec![0.0; 32], vec![0.0; 32]);
+        re[0] = 1.0;
+        fft_2d(&mut re, &mut im, 8, 4, false);
+        assert!(re.iter().all(|&v| (v - 1.0).abs() < 1e-12) && im.iter().all(|&v| v == 0.0));
+    }
+}
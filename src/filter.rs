@@ -0,0 +1,194 @@
+//! Linear filtering with convolution kernels.
+//!
+//! The kernels are applied as they are written, centered on each pixel and without mirroring
+//! them, to every channel including alpha. Integer results are rounded and clamped to the range
+//! of the subpixel type, filters with negative results like `SOBEL_X` are best applied to
+//! float images.
+
+use std::ops::Deref;
+
+use buffer::ImageBuffer;
+use math::{from_f32, to_f32};
+use plane::Plane;
+use traits::{ChannelMax, Color as Pixel};
+use window::EdgeMode;
+
+/// Averages the 3×3 neighborhood.
+pub const BOX_3X3: [[f32; 3]; 3] = [[1.0 / 9.0; 3]; 3];
+
+/// Sharpens by subtracting the 4-neighbors from the amplified center.
+pub const SHARPEN: [[f32; 3]; 3] = [[0.0, -1.0, 0.0], [-1.0, 5.0, -1.0], [0.0, -1.0, 0.0]];
+
+/// The horizontal Sobel operator, positive where the values increase to the right.
+pub const SOBEL_X: [[f32; 3]; 3] = [[-1.0, 0.0, 1.0], [-2.0, 0.0, 2.0], [-1.0, 0.0, 1.0]];
+
+/// The vertical Sobel operator, positive where the values increase downwards.
+pub const SOBEL_Y: [[f32; 3]; 3] = [[-1.0, -2.0, -1.0], [0.0, 0.0, 0.0], [1.0, 2.0, 1.0]];
+
+impl Plane {
+    /// Correlates the rows with `kernel` if `horizontal`, otherwise the columns.
+    fn filter_1d(&self, kernel: &[f32], horizontal: bool, edges: EdgeMode) -> Plane {
+        let center = (kernel.len() / 2) as i64;
+        let (len, step) = if horizontal {
+            (self.width as u32, self.channels)
+        } else {
+            (self.height as u32, self.width * self.channels)
+        };
+        // The offsets along the filtered axis of the taps of every position.
+        let taps: Vec<Vec<usize>> = (0..len as i64)
+            .map(|v| {
+                (0..kernel.len() as i64)
+                    .map(|i| edges.resolve(v + i - center, len) as usize * step)
+                    .collect()
+            })
+            .collect();
+        let row_len = self.width * self.channels;
+        let mut data = Vec::with_capacity(self.data.len());
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let (base, taps) = if horizontal {
+                    (y * row_len, &taps[x])
+                } else {
+                    (x * self.channels, &taps[y])
+                };
+                for c in 0..self.channels {
+                    let sum = kernel.iter().zip(taps).map(|(k, &t)| k * self.data[base + t + c]);
+                    data.push(sum.sum());
+                }
+            }
+        }
+        Plane { data, ..*self }
+    }
+}
+
+impl<P, C> ImageBuffer<P, C>
+    where P: Pixel,
+          P::Subpixel: ChannelMax,
+          C: Deref<Target = [P::Subpixel]>
+{
+    /// Filters the image with a 3×3 kernel, `kernel[1][1]` weights the pixel itself and
+    /// `kernel[0][2]` its upper right neighbor.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{EdgeMode, ImageBuffer, color::Gray};
+    /// use image_buffer::filter::SOBEL_X;
+    /// let ramp: ImageBuffer<Gray<f32>, _> =
+    ///     ImageBuffer::from_fn(5, 5, |x, _| Gray::new([x as f32]));
+    /// let gradient = ramp.convolve_3x3(&SOBEL_X, EdgeMode::Clamp);
+    /// assert_eq!(gradient[(2, 2)], Gray::new([8.0]));
+    /// ```
+    pub fn convolve_3x3(&self,
+                        kernel: &[[f32; 3]; 3],
+                        edges: EdgeMode)
+                        -> ImageBuffer<P, Vec<P::Subpixel>> {
+        profile_op!("convolve_3x3", 2 * ::profiling::image_bytes(self));
+        let mut result = ImageBuffer::new(self.width(), self.height());
+        if self.width() == 0 || self.height() == 0 {
+            return result;
+        }
+        for (window, out) in self.windows(1, edges).zip(result.pixels_mut()) {
+            for c in 0..P::channel_count() {
+                let mut sum = 0.0;
+                for (dy, row) in kernel.iter().enumerate() {
+                    for (dx, &k) in row.iter().enumerate() {
+                        let pixel = window.get(dx as i32 - 1, dy as i32 - 1);
+                        sum += k * to_f32(pixel.as_ref().as_ref()[c]);
+                    }
+                }
+                out.as_mut().as_mut()[c] = from_f32(sum);
+            }
+        }
+        result
+    }
+
+    /// Filters the rows with `horizontal` and then the columns with `vertical`, which equals a
+    /// filter with the outer product of the kernels.
+    ///
+    /// The center of a kernel of length `n` is at `n / 2`. The intermediate result keeps full
+    /// `f32` precision.
+    ///
+    /// # Panics
+    ///
+    /// If a kernel is empty.
+    pub fn filter_separable(&self,
+                            horizontal: &[f32],
+                            vertical: &[f32],
+                            edges: EdgeMode)
+                            -> ImageBuffer<P, Vec<P::Subpixel>> {
+        assert!(!horizontal.is_empty() && !vertical.is_empty(), "empty kernel");
+        profile_op!("filter_separable", 2 * ::profiling::image_bytes(self));
+        if self.width() == 0 || self.height() == 0 {
+            return ImageBuffer::new(self.width(), self.height());
+        }
+        Plane::from_image(self)
+            .filter_1d(horizontal, true, edges)
+            .filter_1d(vertical, false, edges)
+            .into_image()
+    }
+
+    /// Averages the square of `2 * radius + 1` pixels per side around every pixel.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{EdgeMode, GrayImage, color::Gray};
+    /// let mut image = GrayImage::new(5, 5);
+    /// image[(2, 2)] = Gray::new([90]);
+    /// let blurred = image.box_blur(1, EdgeMode::Clamp);
+    /// assert_eq!(blurred[(1, 1)], Gray::new([10]));
+    /// assert_eq!(blurred[(0, 0)], Gray::new([0]));
+    /// ```
+    pub fn box_blur(&self, radius: u32, edges: EdgeMode) -> ImageBuffer<P, Vec<P::Subpixel>> {
+        let kernel = vec![1.0 / (2 * radius + 1) as f32; 2 * radius as usize + 1];
+        self.filter_separable(&kernel, &kernel, edges)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buffer::{GrayImage, ImageBuffer, RgbImage};
+    use color_model::{Gray, Rgb};
+    use window::EdgeMode;
+    use super::{BOX_3X3, SHARPEN, SOBEL_X, SOBEL_Y};
+
+    #[test]
+    fn test_constant() {
+        let image = RgbImage::from_pixel(4, 3, Rgb::new([10, 200, 77]));
+        for kernel in &[BOX_3X3, SHARPEN] {
+            assert_eq!(image.convolve_3x3(kernel, EdgeMode::Clamp).into_raw(),
+                       image.clone().into_raw());
+        }
+        assert_eq!(image.box_blur(2, EdgeMode::Wrap).into_raw(), image.clone().into_raw());
+        assert!(image.convolve_3x3(&SOBEL_Y, EdgeMode::Clamp).pixels().all(|p| p[1] == 0));
+    }
+
+    #[test]
+    fn test_separable_sobel() {
+        let noise = ::test_images::noise(9, 7, 5);
+        let image: ImageBuffer<Rgb<f32>, _> = noise.map_buffer(|p| {
+            Rgb::new([p[0] as f32, p[1] as f32, p[2] as f32])
+        });
+        for &edges in &[EdgeMode::Clamp, EdgeMode::Wrap] {
+            let direct = image.convolve_3x3(&SOBEL_X, edges);
+            let separable = image.filter_separable(&[-1.0, 0.0, 1.0], &[1.0, 2.0, 1.0], edges);
+            for (a, b) in direct.pixels().zip(separable.pixels()) {
+                for c in 0..3 {
+                    assert!((a[c] - b[c]).abs() < 1e-3);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_edges() {
+        let image = GrayImage::from_fn(3, 1, |x, _| Gray::new([x as u8 * 30]));
+        let shift = [1.0, 0.0, 0.0];
+        assert_eq!(image.filter_separable(&shift, &[1.0], EdgeMode::Clamp).into_raw(),
+                   vec![0, 0, 30]);
+        assert_eq!(image.filter_separable(&shift, &[1.0], EdgeMode::Wrap).into_raw(),
+                   vec![60, 0, 30]);
+        assert_eq!(GrayImage::new(0, 3).box_blur(1, EdgeMode::Clamp).dimensions(), (0, 3));
+    }
+}
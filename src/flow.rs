@@ -0,0 +1,268 @@
+//! Sparse optical flow.
+//!
+//! Tracks points from one frame to the next with the pyramidal Lucas–Kanade method: the
+//! displacement of a small window around each point is estimated iteratively from the image
+//! gradients, starting at a coarse level of an image pyramid to capture large motions.
+
+use std::ops::Deref;
+
+use buffer::ImageBuffer;
+use color_model::Gray;
+use plane::Plane;
+use traits::{ChannelMax, Primitive};
+
+/// Configuration of `track_points`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FlowConfig {
+    /// The radius of the tracked window, the window has `2 * radius + 1` pixels per side.
+    pub radius: u32,
+    /// The number of pyramid levels in addition to the full resolution. Each level halves the
+    /// resolution and doubles the largest displacement that can be tracked.
+    pub levels: u32,
+    /// The maximal number of iterations per level.
+    pub iterations: u32,
+    /// The iteration stops once an update moves by less than this number of pixels.
+    pub epsilon: f32,
+    /// Points whose window has a smaller minimal eigenvalue of the structure tensor per pixel,
+    /// relative to channel values normalized to `[0, 1]`, are reported as `Untextured`.
+    pub min_eigenvalue: f32,
+}
+
+impl Default for FlowConfig {
+    fn default() -> FlowConfig {
+        FlowConfig {
+            radius: 7,
+            levels: 3,
+            iterations: 20,
+            epsilon: 0.01,
+            min_eigenvalue: 1e-4,
+        }
+    }
+}
+
+/// The outcome of tracking a point.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum FlowStatus {
+    /// The point was tracked.
+    Tracked,
+    /// The window around the point has too little texture to determine its motion.
+    Untextured,
+    /// The point moved out of the image.
+    OutOfBounds,
+}
+
+/// The motion of a point between two frames.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Flow {
+    /// The displacement `(dx, dy)` of the point. Zero unless the point was tracked.
+    pub displacement: (f32, f32),
+    /// Whether the point could be tracked.
+    pub status: FlowStatus,
+}
+
+impl Plane {
+    /// Halves the resolution by averaging blocks of 2×2 pixels.
+    fn downsample(&self) -> Plane {
+        let (width, height) = (self.width.div_ceil(2), self.height.div_ceil(2));
+        let mut data = Vec::with_capacity(width * height);
+        for y in 0..height as isize {
+            for x in 0..width as isize {
+                let (x2, y2) = (2 * x, 2 * y);
+                let sum = self.at(x2, y2) + self.at(x2 + 1, y2) + self.at(x2, y2 + 1) +
+                          self.at(x2 + 1, y2 + 1);
+                data.push(sum / 4.0);
+            }
+        }
+        Plane {
+            width,
+            height,
+            channels: 1,
+            data,
+        }
+    }
+
+    /// Samples the plane bilinearly, coordinates outside are clamped to the border.
+    fn sample(&self, x: f32, y: f32) -> f32 {
+        let x = x.max(0.0).min((self.width - 1) as f32);
+        let y = y.max(0.0).min((self.height - 1) as f32);
+        let (x0, y0) = (x as usize, y as usize);
+        let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+        let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+        let row = |y: usize| {
+            self.data[y * self.width + x0] * (1.0 - fx) + self.data[y * self.width + x1] * fx
+        };
+        row(y0) * (1.0 - fy) + row(y1) * fy
+    }
+}
+
+/// Tracks the window of `radius` around `point` from `prev` to `next`, starting from the
+/// displacement `guess`. Returns the refined displacement or `None` if the window is
+/// untextured.
+fn track_level(prev: &Plane,
+               next: &Plane,
+               point: (f32, f32),
+               guess: (f32, f32),
+               config: &FlowConfig)
+               -> Option<(f32, f32)> {
+    let r = config.radius as i32;
+    let mut window = Vec::with_capacity(((2 * r + 1) * (2 * r + 1)) as usize);
+    let (mut gxx, mut gxy, mut gyy) = (0.0, 0.0, 0.0);
+    for j in -r..r + 1 {
+        for i in -r..r + 1 {
+            let (x, y) = (point.0 + i as f32, point.1 + j as f32);
+            let ix = (prev.sample(x + 1.0, y) - prev.sample(x - 1.0, y)) / 2.0;
+            let iy = (prev.sample(x, y + 1.0) - prev.sample(x, y - 1.0)) / 2.0;
+            gxx += ix * ix;
+            gxy += ix * iy;
+            gyy += iy * iy;
+            window.push((x, y, prev.sample(x, y), ix, iy));
+        }
+    }
+    let n = window.len() as f32;
+    let min_eigenvalue = (gxx + gyy - ((gxx - gyy) * (gxx - gyy) + 4.0 * gxy * gxy).sqrt()) / 2.0;
+    if min_eigenvalue / n < config.min_eigenvalue {
+        return None;
+    }
+    let det = gxx * gyy - gxy * gxy;
+    let (mut dx, mut dy) = guess;
+    for _ in 0..config.iterations {
+        let (mut bx, mut by) = (0.0, 0.0);
+        for &(x, y, value, ix, iy) in &window {
+            let diff = value - next.sample(x + dx, y + dy);
+            bx += diff * ix;
+            by += diff * iy;
+        }
+        let (ux, uy) = ((gyy * bx - gxy * by) / det, (gxx * by - gxy * bx) / det);
+        dx += ux;
+        dy += uy;
+        if ux * ux + uy * uy < config.epsilon * config.epsilon {
+            break;
+        }
+    }
+    Some((dx, dy))
+}
+
+/// Tracks `points` from the frame `prev` to the frame `next`.
+///
+/// Returns the displacement and status of every point in the same order.
+///
+/// # Panics
+///
+/// If the frames have different dimensions or are empty.
+///
+/// # Examples
+///
+/// ```
+/// # use image_buffer::{GrayImage, color::Gray};
+/// use image_buffer::flow::{track_points, FlowConfig, FlowStatus};
+/// let pattern = |x: f32, y: f32| 128.0 + 60.0 * (0.3 * x).sin() + 60.0 * (0.25 * y).cos();
+/// let prev = GrayImage::from_fn(64, 64, |x, y| Gray::new([pattern(x as f32, y as f32) as u8]));
+/// let next = GrayImage::from_fn(64, 64, |x, y| {
+///     Gray::new([pattern(x as f32 - 3.0, y as f32 - 1.0) as u8])
+/// });
+/// let flow = track_points(&prev, &next, &[(32.0, 32.0)], &FlowConfig::default());
+/// assert_eq!(flow[0].status, FlowStatus::Tracked);
+/// let (dx, dy) = flow[0].displacement;
+/// assert!((dx - 3.0).abs() < 0.2 && (dy - 1.0).abs() < 0.2);
+/// ```
+pub fn track_points<T, C1, C2>(prev: &ImageBuffer<Gray<T>, C1>,
+                               next: &ImageBuffer<Gray<T>, C2>,
+                               points: &[(f32, f32)],
+                               config: &FlowConfig)
+                               -> Vec<Flow>
+    where T: Primitive + ChannelMax,
+          C1: Deref<Target = [T]>,
+          C2: Deref<Target = [T]>
+{
+    assert_eq!(prev.dimensions(), next.dimensions(), "the frames must have the same dimensions");
+    assert!(prev.width() > 0 && prev.height() > 0, "the frames must not be empty");
+    let mut pyramids = (vec![Plane::from_image_normalized(prev)],
+                        vec![Plane::from_image_normalized(next)]);
+    for _ in 0..config.levels {
+        let (p, n) = (pyramids.0.last().unwrap(), pyramids.1.last().unwrap());
+        if p.width < 2 * config.radius as usize + 1 || p.height < 2 * config.radius as usize + 1 {
+            break;
+        }
+        let (p, n) = (p.downsample(), n.downsample());
+        pyramids.0.push(p);
+        pyramids.1.push(n);
+    }
+    let (width, height) = (prev.width() as f32, prev.height() as f32);
+    points.iter()
+        .map(|&(x, y)| {
+            let mut displacement = Some((0.0, 0.0));
+            for level in (0..pyramids.0.len()).rev() {
+                let scale = (1 << level) as f32;
+                let guess = match displacement {
+                    Some((dx, dy)) => (dx / scale, dy / scale),
+                    None => break,
+                };
+                displacement = track_level(&pyramids.0[level],
+                                           &pyramids.1[level],
+                                           (x / scale, y / scale),
+                                           guess,
+                                           config)
+                    .map(|(dx, dy)| (dx * scale, dy * scale));
+            }
+            match displacement {
+                None => {
+                    Flow {
+                        displacement: (0.0, 0.0),
+                        status: FlowStatus::Untextured,
+                    }
+                }
+                Some((dx, dy)) if x + dx < 0.0 || y + dy < 0.0 || x + dx > width - 1.0 ||
+                                  y + dy > height - 1.0 => {
+                    Flow {
+                        displacement: (0.0, 0.0),
+                        status: FlowStatus::OutOfBounds,
+                    }
+                }
+                Some(displacement) => {
+                    Flow {
+                        displacement,
+                        status: FlowStatus::Tracked,
+                    }
+                }
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use buffer::GrayImage;
+    use color_model::Gray;
+    use super::{track_points, FlowConfig, FlowStatus};
+
+    fn pattern(x: f32, y: f32) -> f32 {
+        128.0 + 50.0 * (0.3 * x + 0.05 * y).sin() + 50.0 * (0.21 * y - 0.07 * x).cos()
+    }
+
+    fn frame(dx: f32, dy: f32) -> GrayImage {
+        GrayImage::from_fn(96, 96, |x, y| {
+            let v = if x >= 80 && y >= 80 {
+                128.0
+            } else {
+                pattern(x as f32 - dx, y as f32 - dy)
+            };
+            Gray::new([v.round() as u8])
+        })
+    }
+
+    #[test]
+    fn test_track_points() {
+        let (prev, next) = (frame(0.0, 0.0), frame(5.3, -3.6));
+        let points = [(30.0, 40.0), (50.5, 20.0), (88.0, 88.0), (2.0, 50.0)];
+        let flow = track_points(&prev, &next, &points, &FlowConfig::default());
+        for f in &flow[..2] {
+            assert_eq!(f.status, FlowStatus::Tracked);
+            let (dx, dy) = f.displacement;
+            assert!((dx - 5.3).abs() < 0.1 && (dy + 3.6).abs() < 0.1, "{:?}", f);
+        }
+        assert_eq!(flow[2].status, FlowStatus::Untextured);
+
+        let flow = track_points(&next, &prev, &points[3..], &FlowConfig::default());
+        assert_eq!(flow[0].status, FlowStatus::OutOfBounds);
+    }
+}
@@ -0,0 +1,127 @@
+//! Ingestion of the framebuffer layouts of operating systems and graphics APIs.
+//!
+//! Screen captures usually deliver 8 bit BGRA pixels whose alpha is undefined or opaque, with
+//! rows padded to an alignment and, on some platforms, stored bottom-up. The functions convert
+//! such a framebuffer to a packed top-down `RgbaImage` in a single pass over the pixels.
+
+use std::ops::{Deref, DerefMut};
+
+use buffer::{BufferError, ImageBuffer, Origin, RgbaImage, SizeCheck};
+use color_model::Rgba;
+
+/// The order and meaning of the four bytes of a framebuffer pixel.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum FramebufferFormat {
+    /// Blue, green, red and alpha, as used by Windows and macOS.
+    Bgra8,
+    /// Blue, green, red and an unused byte, the pixels are opaque.
+    Bgrx8,
+    /// Red, green, blue and alpha.
+    Rgba8,
+    /// Red, green, blue and an unused byte, the pixels are opaque.
+    Rgbx8,
+}
+
+impl FramebufferFormat {
+    /// Returns the positions of red, green and blue and whether the fourth byte is alpha.
+    fn layout(self) -> ([usize; 3], bool) {
+        match self {
+            FramebufferFormat::Bgra8 => ([2, 1, 0], true),
+            FramebufferFormat::Bgrx8 => ([2, 1, 0], false),
+            FramebufferFormat::Rgba8 => ([0, 1, 2], true),
+            FramebufferFormat::Rgbx8 => ([0, 1, 2], false),
+        }
+    }
+}
+
+/// Converts a framebuffer of `width`×`height` pixels whose rows are `stride` bytes apart to a
+/// new image.
+///
+/// # Examples
+///
+/// ```
+/// # use image_buffer::{Origin, color::Rgba};
+/// use image_buffer::framebuffer::{from_framebuffer, FramebufferFormat};
+/// // Two bottom-up rows of one pixel, padded to 8 bytes
+/// let data = [255, 0, 0, 0, 0, 0, 0, 0, 0, 0, 255, 0];
+/// let image = from_framebuffer(&data, 1, 2, 8, FramebufferFormat::Bgrx8, Origin::BottomLeft)
+///     .unwrap();
+/// assert_eq!(image[(0, 0)], Rgba::new([255, 0, 0, 255]));
+/// assert_eq!(image[(0, 1)], Rgba::new([0, 0, 255, 255]));
+/// ```
+pub fn from_framebuffer(data: &[u8],
+                        width: u32,
+                        height: u32,
+                        stride: usize,
+                        format: FramebufferFormat,
+                        origin: Origin)
+                        -> Result<RgbaImage, BufferError> {
+    let mut image = RgbaImage::new(width, height);
+    read_framebuffer_into(&mut image, data, stride, format, origin)?;
+    Ok(image)
+}
+
+/// Converts a framebuffer with the dimensions of `target` whose rows are `stride` bytes apart
+/// into `target`, which avoids an allocation per captured frame.
+///
+/// `target` is left unchanged if the framebuffer does not fit.
+pub fn read_framebuffer_into<C>(target: &mut ImageBuffer<Rgba<u8>, C>,
+                                data: &[u8],
+                                stride: usize,
+                                format: FramebufferFormat,
+                                origin: Origin)
+                                -> Result<(), BufferError>
+    where C: Deref<Target = [u8]> + DerefMut
+{
+    let (width, height) = target.dimensions();
+    let source: ImageBuffer<Rgba<u8>, &[u8]> =
+        ImageBuffer::try_from_raw_with_stride(width, height, stride, data, SizeCheck::AtLeast)?;
+    let ([r, g, b], has_alpha) = format.layout();
+    let convert = |(dst, src): (&mut [Rgba<u8>], &[Rgba<u8>])| {
+        for (d, s) in dst.iter_mut().zip(src) {
+            let a = if has_alpha { s[3] } else { 255 };
+            *d = Rgba::new([s[r], s[g], s[b], a]);
+        }
+    };
+    match origin {
+        Origin::TopLeft => target.rows_mut().zip(source.rows()).for_each(convert),
+        Origin::BottomLeft => target.rows_mut().zip(source.rows().rev()).for_each(convert),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use buffer::{BufferError, Origin, RgbaImage};
+    use color_model::Rgba;
+    use super::{from_framebuffer, read_framebuffer_into, FramebufferFormat};
+
+    #[test]
+    fn test_formats() {
+        let data = [1, 2, 3, 4, 5, 6, 7, 8];
+        let top_down = |format| from_framebuffer(&data, 2, 1, 8, format, Origin::TopLeft).unwrap();
+        assert_eq!(top_down(FramebufferFormat::Bgra8).into_raw(), vec![3, 2, 1, 4, 7, 6, 5, 8]);
+        assert_eq!(top_down(FramebufferFormat::Bgrx8).into_raw(),
+                   vec![3, 2, 1, 255, 7, 6, 5, 255]);
+        assert_eq!(top_down(FramebufferFormat::Rgba8).into_raw(), data.to_vec());
+        assert_eq!(top_down(FramebufferFormat::Rgbx8).into_raw(),
+                   vec![1, 2, 3, 255, 5, 6, 7, 255]);
+    }
+
+    #[test]
+    fn test_read_into() {
+        let mut frame = RgbaImage::new(1, 3);
+        let data: Vec<u8> = (0..12).collect();
+        read_framebuffer_into(&mut frame, &data, 4, FramebufferFormat::Rgba8, Origin::BottomLeft)
+            .unwrap();
+        assert_eq!(frame[(0, 0)], Rgba::new([8, 9, 10, 11]));
+        assert_eq!(frame[(0, 2)], Rgba::new([0, 1, 2, 3]));
+        let result = read_framebuffer_into(&mut frame,
+                                           &data[..11],
+                                           4,
+                                           FramebufferFormat::Rgba8,
+                                           Origin::TopLeft);
+        assert_eq!(result, Err(BufferError::TooSmall { expected: 12, got: 11 }));
+        assert_eq!(frame[(0, 0)], Rgba::new([8, 9, 10, 11]));
+    }
+}
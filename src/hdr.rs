@@ -0,0 +1,295 @@
+//! Merging of exposure brackets.
+//!
+//! Implements the exposure fusion of Mertens, Kautz and Van Reeth: every pixel of every
+//! exposure is weighted by its local contrast, saturation and well-exposedness, and the
+//! exposures are blended with these weights in a Laplacian pyramid to avoid seams. Unlike a
+//! radiometric HDR merge this needs neither the exposure times nor the response curve of the
+//! camera, and the result is directly displayable.
+
+use std::ops::Deref;
+
+use buffer::ImageBuffer;
+use color_model::Rgb;
+use plane::Plane;
+
+/// Configuration of `merge_exposures`.
+///
+/// The weights are the exponents of the three quality measures, a weight of 0 ignores a
+/// measure.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FusionConfig {
+    /// The exponent of the absolute Laplacian of the luma, which favors details.
+    pub contrast: f32,
+    /// The exponent of the standard deviation of the channels, which favors vivid colors.
+    pub saturation: f32,
+    /// The exponent of the closeness of the channels to 0.5, which favors neither under- nor
+    /// overexposed pixels.
+    pub exposedness: f32,
+    /// The width of the Gaussian measuring the closeness to 0.5.
+    pub sigma: f32,
+}
+
+impl Default for FusionConfig {
+    fn default() -> FusionConfig {
+        FusionConfig {
+            contrast: 1.0,
+            saturation: 1.0,
+            exposedness: 1.0,
+            sigma: 0.2,
+        }
+    }
+}
+
+/// Keeps the weights positive where all measures vanish, such as in flat black regions.
+const MIN_WEIGHT: f32 = 1e-12;
+
+/// The binomial kernel of the Gaussian pyramid.
+const KERNEL: [f32; 5] = [0.0625, 0.25, 0.375, 0.25, 0.0625];
+
+impl Plane {
+    /// Blurs with the binomial kernel and halves the resolution.
+    fn reduce(&self) -> Plane {
+        let (width, height) = (self.width.div_ceil(2), self.height.div_ceil(2));
+        let mut data = Vec::with_capacity(width * height);
+        for y in 0..height as isize {
+            for x in 0..width as isize {
+                let mut sum = 0.0;
+                for (j, ky) in KERNEL.iter().enumerate() {
+                    for (i, kx) in KERNEL.iter().enumerate() {
+                        sum += ky * kx * self.at(2 * x + i as isize - 2, 2 * y + j as isize - 2);
+                    }
+                }
+                data.push(sum);
+            }
+        }
+        Plane {
+            width,
+            height,
+            channels: 1,
+            data,
+        }
+    }
+
+    /// Doubles the resolution to `width`×`height` by interpolating with the binomial kernel.
+    fn expand(&self, width: usize, height: usize) -> Plane {
+        let mut data = Vec::with_capacity(width * height);
+        for y in 0..height as isize {
+            for x in 0..width as isize {
+                let mut sum = 0.0;
+                for (j, ky) in KERNEL.iter().enumerate() {
+                    let sy = y + j as isize - 2;
+                    if sy % 2 != 0 {
+                        continue;
+                    }
+                    for (i, kx) in KERNEL.iter().enumerate() {
+                        let sx = x + i as isize - 2;
+                        if sx % 2 == 0 {
+                            sum += 4.0 * ky * kx * self.at(sx / 2, sy / 2);
+                        }
+                    }
+                }
+                data.push(sum);
+            }
+        }
+        Plane {
+            width,
+            height,
+            channels: 1,
+            data,
+        }
+    }
+}
+
+/// Returns the Gaussian pyramid of `plane` with `levels` levels.
+fn gaussian_pyramid(plane: Plane, levels: usize) -> Vec<Plane> {
+    let mut pyramid = vec![plane];
+    for _ in 1..levels {
+        let next = pyramid.last().unwrap().reduce();
+        pyramid.push(next);
+    }
+    pyramid
+}
+
+/// Returns the Laplacian pyramid of `plane` with `levels` levels, the last level is the coarse
+/// residual.
+fn laplacian_pyramid(plane: Plane, levels: usize) -> Vec<Plane> {
+    let mut pyramid = gaussian_pyramid(plane, levels);
+    for level in 0..levels - 1 {
+        let expanded = pyramid[level + 1].expand(pyramid[level].width, pyramid[level].height);
+        for (v, e) in pyramid[level].data.iter_mut().zip(expanded.data) {
+            *v -= e;
+        }
+    }
+    pyramid
+}
+
+/// Returns the normalized channels and the weight map of `image`.
+fn analyze<C>(image: &ImageBuffer<Rgb<u8>, C>, config: &FusionConfig) -> ([Plane; 3], Plane)
+    where C: Deref<Target = [u8]>
+{
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let plane = |c: usize| {
+        Plane {
+            width,
+            height,
+            channels: 1,
+            data: image.pixels().map(|p| p[c] as f32 / 255.0).collect(),
+        }
+    };
+    let channels = [plane(0), plane(1), plane(2)];
+    let luma = Plane {
+        width,
+        height,
+        channels: 1,
+        data: image.pixels()
+            .map(|p| (0.299 * p[0] as f32 + 0.587 * p[1] as f32 + 0.114 * p[2] as f32) / 255.0)
+            .collect(),
+    };
+    let mut weights = Vec::with_capacity(width * height);
+    for y in 0..height as isize {
+        for x in 0..width as isize {
+            let laplacian = luma.at(x - 1, y) + luma.at(x + 1, y) + luma.at(x, y - 1) +
+                            luma.at(x, y + 1) - 4.0 * luma.at(x, y);
+            let rgb = [channels[0].at(x, y), channels[1].at(x, y), channels[2].at(x, y)];
+            let mean = (rgb[0] + rgb[1] + rgb[2]) / 3.0;
+            let variance = rgb.iter().map(|&v| (v - mean) * (v - mean)).sum::<f32>() / 3.0;
+            let exposedness = rgb.iter()
+                .map(|&v| (-(v - 0.5) * (v - 0.5) / (2.0 * config.sigma * config.sigma)).exp())
+                .product::<f32>();
+            let weight = laplacian.abs().powf(config.contrast) *
+                         variance.sqrt().powf(config.saturation) *
+                         exposedness.powf(config.exposedness);
+            weights.push(weight + MIN_WEIGHT);
+        }
+    }
+    let weights = Plane {
+        width,
+        height,
+        channels: 1,
+        data: weights,
+    };
+    (channels, weights)
+}
+
+/// Fuses differently exposed images of the same scene into a single well exposed image.
+///
+/// The channel values of the result are in `[0, 1]` and encoded like the inputs, the fusion
+/// operates on the encoded values directly.
+///
+/// # Panics
+///
+/// If there are no images or they have different dimensions.
+///
+/// # Examples
+///
+/// ```
+/// # use image_buffer::{RgbImage, color::Rgb};
+/// use image_buffer::hdr::{merge_exposures, FusionConfig};
+/// let scene = |x: u32, gain: f32| ((x as f32 * 8.0 * gain).min(255.0)) as u8;
+/// let dark = RgbImage::from_fn(32, 8, |x, _| Rgb::new([scene(x, 0.5); 3]));
+/// let bright = RgbImage::from_fn(32, 8, |x, _| Rgb::new([scene(x, 2.0); 3]));
+/// let fused = merge_exposures(&[&dark, &bright], &FusionConfig::default());
+/// assert_eq!(fused.dimensions(), (32, 8));
+/// ```
+pub fn merge_exposures<C>(images: &[&ImageBuffer<Rgb<u8>, C>],
+                          config: &FusionConfig)
+                          -> ImageBuffer<Rgb<f32>, Vec<f32>>
+    where C: Deref<Target = [u8]>
+{
+    assert!(!images.is_empty(), "no images to merge");
+    let (width, height) = images[0].dimensions();
+    profile_op!("merge_exposures",
+                (images.len() as u64 + 1) * ::profiling::image_bytes(images[0]));
+    assert!(images.iter().all(|i| i.dimensions() == (width, height)),
+            "the images must have the same dimensions");
+    if width == 0 || height == 0 {
+        return ImageBuffer::new(width, height);
+    }
+    let (width, height) = (width as usize, height as usize);
+    let mut levels = 1;
+    while width >> levels > 0 && height >> levels > 0 {
+        levels += 1;
+    }
+
+    let analyzed: Vec<_> = images.iter().map(|image| analyze(image, config)).collect();
+    let mut total = vec![0.0; width * height];
+    for (_, weights) in &analyzed {
+        for (t, w) in total.iter_mut().zip(&weights.data) {
+            *t += w;
+        }
+    }
+
+    let mut blended: Vec<[Plane; 3]> = Vec::new();
+    for (channels, mut weights) in analyzed {
+        for (w, t) in weights.data.iter_mut().zip(&total) {
+            *w /= t;
+        }
+        let weights = gaussian_pyramid(weights, levels);
+        let channels = channels.map(|c| laplacian_pyramid(c, levels));
+        for level in 0..levels {
+            if blended.len() == level {
+                let w = &weights[level];
+                blended.push([w.zeros_like(), w.zeros_like(), w.zeros_like()]);
+            }
+            for (c, out) in blended[level].iter_mut().enumerate() {
+                let terms = channels[c][level].data.iter().zip(&weights[level].data);
+                for (o, (v, w)) in out.data.iter_mut().zip(terms) {
+                    *o += v * w;
+                }
+            }
+        }
+    }
+
+    while blended.len() > 1 {
+        let coarse = blended.pop().unwrap();
+        let fine = blended.last_mut().unwrap();
+        for (f, c) in fine.iter_mut().zip(coarse.iter()) {
+            let expanded = c.expand(f.width, f.height);
+            for (v, e) in f.data.iter_mut().zip(expanded.data) {
+                *v += e;
+            }
+        }
+    }
+    let result = blended.pop().unwrap();
+    let data = (0..width * height)
+        .flat_map(|i| result.iter().map(move |c| c.data[i].clamp(0.0, 1.0)))
+        .collect();
+    ImageBuffer::from_raw(width as u32, height as u32, data).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use buffer::RgbImage;
+    use color_model::Rgb;
+    use super::{merge_exposures, FusionConfig};
+
+    fn exposure(gain: f32) -> RgbImage {
+        RgbImage::from_fn(40, 24, |x, y| {
+            let v = (x as f32 * 6.0 + (y % 4) as f32 * 10.0) * gain;
+            Rgb::new([v.min(255.0) as u8, (v * 0.8).min(255.0) as u8, (v * 0.5).min(255.0) as u8])
+        })
+    }
+
+    #[test]
+    fn test_identical_exposures() {
+        let image = exposure(1.0);
+        let fused = merge_exposures(&[&image, &image, &image], &FusionConfig::default());
+        for (f, p) in fused.pixels().zip(image.pixels()) {
+            for c in 0..3 {
+                assert!((f[c] - p[c] as f32 / 255.0).abs() < 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn test_bracket() {
+        let (dark, bright) = (exposure(0.4), exposure(2.5));
+        let fused = merge_exposures(&[&dark, &bright], &FusionConfig::default());
+        // The bright exposure is clipped on the right, the fused image keeps the detail
+        for x in 30..40 {
+            assert!(fused[(x, 3)][0] - fused[(x, 0)][0] > 0.02, "{:?}", fused[(x, 0)]);
+        }
+        // The dark exposure is nearly black on the left, the fused image is brighter
+        assert!(fused[(8, 0)][0] > dark[(8, 0)][0] as f32 / 255.0 + 0.05);
+    }
+}
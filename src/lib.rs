@@ -39,29 +39,129 @@
 //! ```
 
 extern crate num_traits;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_test;
 
+/// Records the time until the end of the enclosing block as the operation `$name` touching
+/// `$bytes` bytes, if the `profiling` feature is enabled.
+macro_rules! profile_op {
+    ($name:expr, $bytes:expr) => {
+        #[cfg(feature = "profiling")]
+        let _span = ::profiling::Span::new($name, $bytes);
+    };
+}
+
+mod batch;
+pub mod blend;
 mod buffer;
+mod bytes;
+pub mod calibration;
 mod color_model;
+pub mod curve;
+mod document;
+pub mod draw;
+mod dynamic;
+pub mod effects;
+mod enhance;
+mod fft;
+pub mod filter;
+pub mod flow;
+pub mod framebuffer;
+pub mod hdr;
 mod math;
+pub mod measure;
+pub mod motion;
+mod palette;
+mod planar;
+mod plane;
+#[cfg(feature = "pnm")]
+pub mod pnm;
+mod preview;
+#[cfg(feature = "profiling")]
+pub mod profiling;
+pub mod registration;
+pub mod resize;
+#[cfg(feature = "serde")]
+mod serde_impls;
+mod stats;
+pub mod stego;
+pub mod test_images;
+#[cfg(feature = "testing")]
+pub mod testing;
+mod tiled;
 mod traits;
+mod transform;
+mod view;
+pub mod visualize;
+#[cfg(feature = "wasm")]
+pub mod web;
+mod window;
+pub mod yuv;
 
-#[cfg_attr(rustfmt, rustfmt_skip)]
+pub use batch::BatchBuffer;
+#[rustfmt::skip]
 pub use buffer::{
+	BufferError,
 	ImageBuffer,
 	Pixels,
 	PixelsMut,
+	Rows,
+	RowsMut,
 	EnumeratePixels,
 	EnumeratePixelsMut,
+	EnumerateRows,
+	EnumerateRowsMut,
+	Channel,
+	ChannelMut,
+	FlatSamples,
 	RgbImage,
 	RgbaImage,
 	GrayImage,
 	GrayAlphaImage,
+	IndexedImage,
+	Rgb16Image,
+	Rgba16Image,
+	Gray16Image,
+	GrayAlpha16Image,
+	RgbF32Image,
+	RgbaF32Image,
+	GrayF32Image,
+	GrayAlphaF32Image,
+	Origin,
+	SizeCheck,
+};
+pub use bytes::{ByteSample, Endianness};
+pub use dynamic::{DynamicImageBuffer, DynamicPixel};
+pub use palette::Palette;
+pub use planar::PlanarImageBuffer;
+pub use stats::ChannelStats;
+pub use tiled::{TiledImageBuffer, TiledLayout, TileOrder};
+#[rustfmt::skip]
+pub use window::{
+	EdgeMode,
+	Neighborhood,
+	NeighborhoodPixels,
+	Neighborhoods,
+};
+#[rustfmt::skip]
+pub use view::{
+	SubImage,
+	SubImageMut,
+	TileMut,
 };
-#[cfg_attr(rustfmt, rustfmt_skip)]
+#[rustfmt::skip]
 pub use traits::{
+	AlphaColor,
 	Color,
 	ImageView,
-	Primitive
+	ImageViewMut,
+	PodColor,
+	Primitive,
+	ViewPixels,
 };
 
 pub mod color {
@@ -1,5 +1,9 @@
 //! Utility functions.
 
+use num_traits::NumCast;
+
+use traits::{Primitive, ChannelMax};
+
 /// Clamp the value to a given range.
 #[inline]
 pub fn clamp<N: PartialOrd>(a: N, min: N, max: N) -> N {
@@ -11,3 +15,62 @@ pub fn clamp<N: PartialOrd>(a: N, min: N, max: N) -> N {
     }
     a
 }
+
+/// Converts a channel value to `f32`.
+#[inline]
+pub fn to_f32<T: Primitive>(v: T) -> f32 {
+    NumCast::from(v).unwrap()
+}
+
+/// Converts a `f32` to a channel value.
+///
+/// Integer channels are rounded to the nearest value and clamped to their range.
+#[inline]
+pub fn from_f32<T: Primitive + ChannelMax>(v: f32) -> T {
+    let max = to_f32(T::channel_max());
+    if max > 1.0 {
+        NumCast::from(clamp(v.round(), 0.0, max)).unwrap()
+    } else {
+        NumCast::from(v).unwrap()
+    }
+}
+
+/// Rescales a channel value from the range of `T` to the range of `V`.
+///
+/// Integer results are rounded to the nearest value and clamped to their range.
+#[inline]
+pub fn rescale_channel<T, V>(v: T) -> V
+    where T: Primitive + ChannelMax,
+          V: Primitive + ChannelMax
+{
+    let from_max: f64 = NumCast::from(T::channel_max()).unwrap();
+    let to_max: f64 = NumCast::from(V::channel_max()).unwrap();
+    let v: f64 = NumCast::from(v).unwrap();
+    let scaled = v * to_max / from_max;
+    if to_max > 1.0 {
+        NumCast::from(clamp(scaled.round(), 0.0, to_max)).unwrap()
+    } else {
+        NumCast::from(scaled).unwrap()
+    }
+}
+
+/// Multiplies a channel value by `factor`.
+///
+/// The product is computed in `f64`, integer results are rounded to the nearest value and
+/// clamped to their range.
+#[inline]
+pub fn scale_channel<T: Primitive + ChannelMax>(v: T, factor: f32) -> T {
+    let max: f64 = NumCast::from(T::channel_max()).unwrap();
+    let v: f64 = NumCast::from(v).unwrap();
+    let scaled = v * factor as f64;
+    if max > 1.0 {
+        // NaN becomes zero and the maximum of 64 bit integers is not exact in `f64`
+        NumCast::from(clamp(scaled.round(), 0.0, max)).unwrap_or_else(|| if scaled > 0.0 {
+            T::channel_max()
+        } else {
+            T::zero()
+        })
+    } else {
+        NumCast::from(scaled).unwrap()
+    }
+}
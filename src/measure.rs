@@ -0,0 +1,423 @@
+//! Sharpness, resolution and shape measurements.
+//!
+//! All measurements operate on grayscale images with channel values normalized to `[0, 1]`.
+//! The `region_*` variants restrict the measurement to the rectangle `(x, y, width, height)`.
+
+use std::f64::consts::PI;
+use std::ops::Deref;
+
+use buffer::ImageBuffer;
+use color_model::Gray;
+use math::to_f32;
+use traits::{ChannelMax, Primitive};
+
+/// Oversampling factor of the edge spread function.
+const OVERSAMPLING: f64 = 4.0;
+
+/// A rectangle `(x, y, width, height)`.
+pub type Region = (u32, u32, u32, u32);
+
+/// Reads the normalized values of a region of an image.
+///
+/// # Panics
+///
+/// Panics if the region is not contained in the image.
+fn region_values<T, C>(image: &ImageBuffer<Gray<T>, C>, (x0, y0, w, h): Region) -> Vec<f64>
+    where T: Primitive + ChannelMax,
+          C: Deref<Target = [T]>
+{
+    let view = image.view(x0, y0, w, h);
+    let max = to_f32(T::channel_max()) as f64;
+    let mut values = Vec::with_capacity(w as usize * h as usize);
+    for y in 0..h {
+        for x in 0..w {
+            values.push(to_f32(view[(x, y)][0]) as f64 / max);
+        }
+    }
+    values
+}
+
+/// Returns the variance of the Laplacian of the image.
+///
+/// Higher values indicate a sharper image. The score is only comparable between images of
+/// similar content. Returns `0.0` for images smaller than 3×3 pixels.
+pub fn laplacian_variance<T, C>(image: &ImageBuffer<Gray<T>, C>) -> f64
+    where T: Primitive + ChannelMax,
+          C: Deref<Target = [T]>
+{
+    region_laplacian_variance(image, (0, 0, image.width(), image.height()))
+}
+
+/// Returns the variance of the Laplacian of a region of the image.
+///
+/// # Panics
+///
+/// Panics if the region is not contained in the image.
+pub fn region_laplacian_variance<T, C>(image: &ImageBuffer<Gray<T>, C>, region: Region) -> f64
+    where T: Primitive + ChannelMax,
+          C: Deref<Target = [T]>
+{
+    let (w, h) = (region.2 as usize, region.3 as usize);
+    let v = region_values(image, region);
+    if w < 3 || h < 3 {
+        return 0.0;
+    }
+    let (mut sum, mut sum_sq) = (0.0, 0.0);
+    for y in 1..h - 1 {
+        for x in 1..w - 1 {
+            let i = y * w + x;
+            let l = v[i - w] + v[i + w] + v[i - 1] + v[i + 1] - 4.0 * v[i];
+            sum += l;
+            sum_sq += l * l;
+        }
+    }
+    let n = ((w - 2) * (h - 2)) as f64;
+    let mean = sum / n;
+    sum_sq / n - mean * mean
+}
+
+/// Estimates the spatial frequency in cycles per pixel at which the modulation transfer function
+/// drops to 50% using the slanted-edge method.
+///
+/// The image has to contain a single, nearly vertical edge that is slightly slanted (a few
+/// degrees) such that the edge position varies over the rows. Returns None if no edge can be
+/// located or the MTF does not drop below 50% up to the sampling frequency.
+pub fn mtf50<T, C>(image: &ImageBuffer<Gray<T>, C>) -> Option<f64>
+    where T: Primitive + ChannelMax,
+          C: Deref<Target = [T]>
+{
+    region_mtf50(image, (0, 0, image.width(), image.height()))
+}
+
+/// Estimates the MTF50 of the edge contained in a region of the image.
+///
+/// See `mtf50`.
+///
+/// # Panics
+///
+/// Panics if the region is not contained in the image.
+pub fn region_mtf50<T, C>(image: &ImageBuffer<Gray<T>, C>, region: Region) -> Option<f64>
+    where T: Primitive + ChannelMax,
+          C: Deref<Target = [T]>
+{
+    let (w, h) = (region.2 as usize, region.3 as usize);
+    if w < 4 || h < 2 {
+        return None;
+    }
+    let v = region_values(image, region);
+
+    // Locate the edge in every row by the centroid of the derivative
+    let mut points = Vec::with_capacity(h);
+    for y in 0..h {
+        let row = &v[y * w..(y + 1) * w];
+        let (mut moment, mut total) = (0.0, 0.0);
+        for x in 1..w - 1 {
+            let d = (row[x + 1] - row[x - 1]) / 2.0;
+            moment += d * x as f64;
+            total += d;
+        }
+        if total.abs() > 1e-6 {
+            points.push((y as f64, moment / total));
+        }
+    }
+    if points.len() < 2 {
+        return None;
+    }
+
+    // Fit the line x = a * y + b
+    let n = points.len() as f64;
+    let (sy, sx) = points.iter().fold((0.0, 0.0), |(sy, sx), &(y, x)| (sy + y, sx + x));
+    let (my, mx) = (sy / n, sx / n);
+    let (cov, var) = points.iter()
+        .fold((0.0, 0.0), |(c, v), &(y, x)| (c + (y - my) * (x - mx), v + (y - my) * (y - my)));
+    let a = if var > 0.0 { cov / var } else { 0.0 };
+    let b = mx - a * my;
+    let norm = (1.0 + a * a).sqrt();
+
+    // Project all pixels onto the edge normal to get the oversampled edge spread function
+    let bins = (w as f64 * OVERSAMPLING) as usize;
+    let offset = bins as f64 / 2.0;
+    let (mut esf, mut counts) = (vec![0.0; bins], vec![0usize; bins]);
+    for y in 0..h {
+        for x in 0..w {
+            let d = (x as f64 - a * y as f64 - b) / norm;
+            let bin = (d * OVERSAMPLING + offset).floor();
+            if bin >= 0.0 && (bin as usize) < bins {
+                esf[bin as usize] += v[y * w + x];
+                counts[bin as usize] += 1;
+            }
+        }
+    }
+    // Fill empty bins from their neighbors
+    let mut last = None;
+    for i in 0..bins {
+        if counts[i] > 0 {
+            esf[i] /= counts[i] as f64;
+            last = Some(esf[i]);
+        } else if let Some(value) = last {
+            esf[i] = value;
+        }
+    }
+    let first = counts.iter().position(|&c| c > 0)?;
+    for i in 0..first {
+        esf[i] = esf[first];
+    }
+
+    // Windowed line spread function
+    let lsf: Vec<f64> = (1..bins - 1)
+        .map(|i| {
+            let window = 0.54 - 0.46 * (2.0 * PI * i as f64 / (bins - 1) as f64).cos();
+            (esf[i + 1] - esf[i - 1]) / 2.0 * window
+        })
+        .collect();
+
+    // Modulation transfer function up to the sampling frequency
+    let len = lsf.len() as f64;
+    let dft = |k: usize| {
+        let (re, im) = lsf.iter().enumerate().fold((0.0, 0.0), |(re, im), (i, &l)| {
+            let phase = -2.0 * PI * k as f64 * i as f64 / len;
+            (re + l * phase.cos(), im + l * phase.sin())
+        });
+        (re * re + im * im).sqrt()
+    };
+    let dc = dft(0);
+    if dc < 1e-9 {
+        return None;
+    }
+    let step = OVERSAMPLING / len;
+    let mut previous = 1.0;
+    for k in 1..(len / OVERSAMPLING) as usize + 1 {
+        let mtf = dft(k) / dc;
+        if mtf < 0.5 {
+            let t = (previous - 0.5) / (previous - mtf);
+            return Some((k as f64 - 1.0 + t) * step);
+        }
+        previous = mtf;
+    }
+    None
+}
+
+/// The moments up to order three of a grayscale image.
+///
+/// The normalized intensity of a pixel is treated as its mass, binary images thus yield the
+/// moments of the shape formed by the white pixels. Coordinates refer to the pixel centers,
+/// relative to the top left corner of the measured region.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Moments {
+    raw: [[f64; 4]; 4],
+    central: [[f64; 4]; 4],
+}
+
+impl Moments {
+    /// Returns the raw moment `m_pq`, the sum of `x^p * y^q * I(x, y)`.
+    ///
+    /// # Panics
+    ///
+    /// If `p + q > 3`.
+    pub fn raw(&self, p: usize, q: usize) -> f64 {
+        assert!(p + q <= 3, "moments are available up to order 3");
+        self.raw[p][q]
+    }
+
+    /// Returns the central moment `mu_pq`, the raw moment relative to the centroid.
+    ///
+    /// # Panics
+    ///
+    /// If `p + q > 3`.
+    pub fn central(&self, p: usize, q: usize) -> f64 {
+        assert!(p + q <= 3, "moments are available up to order 3");
+        self.central[p][q]
+    }
+
+    /// Returns the scale invariant moment `eta_pq = mu_pq / mu_00^(1 + (p + q) / 2)`.
+    ///
+    /// Returns `0.0` for an empty image.
+    ///
+    /// # Panics
+    ///
+    /// If `p + q > 3`.
+    pub fn normalized(&self, p: usize, q: usize) -> f64 {
+        let mass = self.central(0, 0);
+        if mass == 0.0 {
+            return 0.0;
+        }
+        self.central(p, q) / mass.powf(1.0 + (p + q) as f64 / 2.0)
+    }
+
+    /// Returns the center of mass `(x, y)` or `None` if the image is empty.
+    pub fn centroid(&self) -> Option<(f64, f64)> {
+        match self.raw[0][0] {
+            0.0 => None,
+            mass => Some((self.raw[1][0] / mass, self.raw[0][1] / mass)),
+        }
+    }
+
+    /// Returns the angle in radians between the x axis and the major axis of the shape.
+    ///
+    /// The angle is in `(-π/2, π/2]`. As the y axis points downwards positive angles are
+    /// clockwise. Returns `None` if the image is empty or the shape has no unique orientation,
+    /// like a disk or a square.
+    pub fn orientation(&self) -> Option<f64> {
+        let (mu11, mu20, mu02) = (self.central[1][1], self.central[2][0], self.central[0][2]);
+        let scale = mu20 + mu02;
+        if scale == 0.0 || (2.0 * mu11).hypot(mu20 - mu02) <= 1e-12 * scale {
+            return None;
+        }
+        Some(0.5 * (2.0 * mu11).atan2(mu20 - mu02))
+    }
+
+    /// Returns the seven moments of Hu which are invariant under translation, scale and
+    /// rotation. The seventh changes its sign under reflection.
+    pub fn hu(&self) -> [f64; 7] {
+        let n = |p, q| self.normalized(p, q);
+        let (n20, n02, n11) = (n(2, 0), n(0, 2), n(1, 1));
+        let (n30, n03, n21, n12) = (n(3, 0), n(0, 3), n(2, 1), n(1, 2));
+        let (a, b) = (n30 + n12, n21 + n03);
+        [n20 + n02,
+         (n20 - n02).powi(2) + 4.0 * n11 * n11,
+         (n30 - 3.0 * n12).powi(2) + (3.0 * n21 - n03).powi(2),
+         a * a + b * b,
+         (n30 - 3.0 * n12) * a * (a * a - 3.0 * b * b) +
+         (3.0 * n21 - n03) * b * (3.0 * a * a - b * b),
+         (n20 - n02) * (a * a - b * b) + 4.0 * n11 * a * b,
+         (3.0 * n21 - n03) * a * (a * a - 3.0 * b * b) -
+         (n30 - 3.0 * n12) * b * (3.0 * a * a - b * b)]
+    }
+}
+
+/// Returns the moments of the image.
+///
+/// # Examples
+///
+/// ```
+/// # use image_buffer::{GrayImage, color::Gray};
+/// use image_buffer::measure::moments;
+/// let mut image = GrayImage::new(10, 10);
+/// image.fill_rect(2, 4, 5, 1, Gray::new([255]));
+/// let moments = moments(&image);
+/// assert_eq!(moments.centroid(), Some((4.0, 4.0)));
+/// assert_eq!(moments.orientation(), Some(0.0));
+/// ```
+pub fn moments<T, C>(image: &ImageBuffer<Gray<T>, C>) -> Moments
+    where T: Primitive + ChannelMax,
+          C: Deref<Target = [T]>
+{
+    region_moments(image, (0, 0, image.width(), image.height()))
+}
+
+/// Returns the moments of a region of the image.
+///
+/// # Panics
+///
+/// Panics if the region is not contained in the image.
+pub fn region_moments<T, C>(image: &ImageBuffer<Gray<T>, C>, region: Region) -> Moments
+    where T: Primitive + ChannelMax,
+          C: Deref<Target = [T]>
+{
+    let values = region_values(image, region);
+    let w = region.2 as usize;
+    // Accumulates the moments about `(cx, cy)`
+    let accumulate = |cx: f64, cy: f64| {
+        let mut m = [[0.0; 4]; 4];
+        for (i, &v) in values.iter().enumerate() {
+            if v == 0.0 {
+                continue;
+            }
+            let (dx, dy) = ((i % w) as f64 - cx, (i / w) as f64 - cy);
+            let mut xp = v;
+            for row in m.iter_mut() {
+                let mut term = xp;
+                for value in row.iter_mut() {
+                    *value += term;
+                    term *= dy;
+                }
+                xp *= dx;
+            }
+        }
+        for (p, row) in m.iter_mut().enumerate() {
+            for value in row[4 - p..].iter_mut() {
+                *value = 0.0;
+            }
+        }
+        m
+    };
+    let raw = accumulate(0.0, 0.0);
+    let central = match raw[0][0] {
+        0.0 => [[0.0; 4]; 4],
+        mass => accumulate(raw[1][0] / mass, raw[0][1] / mass),
+    };
+    Moments { raw, central }
+}
+
+#[cfg(test)]
+mod tests {
+    use buffer::GrayImage;
+    use color_model::Gray;
+    use test_images::{noise, slanted_edge};
+    use super::{laplacian_variance, moments, mtf50, region_laplacian_variance, region_moments};
+
+    fn box_blur(image: &GrayImage) -> GrayImage {
+        let (w, h) = image.dimensions();
+        GrayImage::from_fn(w, h, |x, y| {
+            let (mut sum, mut n) = (0u32, 0u32);
+            for dx in 0..3 {
+                if x + dx >= 1 && x + dx <= w {
+                    sum += image[(x + dx - 1, y)][0] as u32;
+                    n += 1;
+                }
+            }
+            Gray::new([(sum / n) as u8])
+        })
+    }
+
+    #[test]
+    fn test_laplacian_variance() {
+        let flat = GrayImage::from_pixel(16, 16, Gray::new([100]));
+        assert_eq!(laplacian_variance(&flat), 0.0);
+        let noisy: GrayImage = noise(16, 16, 0).convert_buffer();
+        assert!(laplacian_variance(&noisy) > laplacian_variance(&box_blur(&noisy)));
+        assert_eq!(region_laplacian_variance(&noisy, (4, 4, 2, 2)), 0.0);
+    }
+
+    #[test]
+    fn test_mtf50() {
+        let sharp = slanted_edge(64, 64, 5.0, 0.8);
+        let blurred = box_blur(&sharp);
+        let (sharp, blurred) = (mtf50(&sharp).unwrap(), mtf50(&blurred).unwrap());
+        assert!(sharp > blurred);
+        assert!(sharp > 0.2 && sharp < 0.8, "{}", sharp);
+        assert!(mtf50(&GrayImage::from_pixel(16, 16, Gray::new([100]))).is_none());
+    }
+
+    #[test]
+    fn test_moments() {
+        let mut image = GrayImage::new(40, 40);
+        for y in 10..30i32 {
+            for x in 10..30i32 {
+                if (x - 20) * (x - 20) + (y - 20) * (y - 20) * 4 <= 64 {
+                    image[(x as u32, y as u32)] = Gray::new([255]);
+                }
+            }
+        }
+        let m = moments(&image);
+        assert_eq!(m.centroid(), Some((20.0, 20.0)));
+        assert!(m.orientation().unwrap().abs() < 1e-9);
+        assert_eq!(m.central(1, 0), 0.0);
+        assert_eq!(m.raw(0, 0), m.central(0, 0));
+
+        // Hu moments are invariant under translation and rotation
+        let rotated = GrayImage::from_fn(40, 40, |x, y| image[(y, 39 - x)]);
+        let r = region_moments(&rotated, (5, 3, 30, 30));
+        let angle = r.orientation().unwrap();
+        assert!((angle.abs() - ::std::f64::consts::FRAC_PI_2).abs() < 1e-9);
+        for (a, b) in m.hu().iter().zip(r.hu().iter()) {
+            assert!((a - b).abs() <= 1e-9 * a.abs().max(1e-12), "{} != {}", a, b);
+        }
+        assert!(m.hu()[0] > 0.0);
+
+        let empty = moments(&GrayImage::new(4, 4));
+        assert_eq!(empty.centroid(), None);
+        assert_eq!(empty.orientation(), None);
+        assert_eq!(empty.hu(), [0.0; 7]);
+    }
+}
@@ -0,0 +1,503 @@
+//! Frame differencing and accumulation over time.
+//!
+//! `abs_diff` and `change_mask` compare two frames, an `Accumulator` sums or averages a
+//! sequence of frames with an optional exponential decay, which yields motion heatmaps when fed
+//! with differences and simulates long exposures when fed with the frames themselves. A
+//! `Deflicker` evens out the brightness of a sequence such as a timelapse. `temporal_mean` and
+//! `temporal_median` reduce a sequence to a single frame, for noise reduction or to extract the
+//! static background of a scene.
+
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::ops::{Deref, Sub};
+
+use buffer::{GrayImage, ImageBuffer};
+use color_model::Gray;
+use math::{from_f32, to_f32};
+use traits::{ChannelMax, Color as Pixel};
+
+/// Returns `|a - b|` without overflowing unsigned types.
+fn channel_diff<T: PartialOrd + Sub<Output = T>>(a: T, b: T) -> T {
+    if a > b { a - b } else { b - a }
+}
+
+impl<P, C> ImageBuffer<P, C>
+    where P: Pixel,
+          C: Deref<Target = [P::Subpixel]>
+{
+    /// Returns the absolute difference of every channel, including alpha, of `self` and
+    /// `other`.
+    ///
+    /// # Panics
+    ///
+    /// If the images have different dimensions.
+    pub fn abs_diff<D>(&self, other: &ImageBuffer<P, D>) -> ImageBuffer<P, Vec<P::Subpixel>>
+        where D: Deref<Target = [P::Subpixel]>
+    {
+        assert_eq!(self.dimensions(),
+                   other.dimensions(),
+                   "the images must have the same dimensions");
+        let mut diff = ImageBuffer::new(self.width(), self.height());
+        for ((d, a), b) in diff.pixels_mut().zip(self.pixels()).zip(other.pixels()) {
+            *d = a.map2(b, channel_diff);
+        }
+        diff
+    }
+
+    /// Marks the pixels where any channel differs from `other` by more than the corresponding
+    /// channel of `thresholds` with 255, all other pixels are 0.
+    ///
+    /// # Panics
+    ///
+    /// If the images have different dimensions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{RgbImage, color::{Gray, Rgb}};
+    /// let a = RgbImage::from_pixel(2, 1, Rgb::new([100, 100, 100]));
+    /// let mut b = a.clone();
+    /// b[(0, 0)] = Rgb::new([100, 120, 100]);
+    /// b[(1, 0)] = Rgb::new([105, 100, 100]);
+    /// let mask = a.change_mask(&b, Rgb::new([10, 10, 10]));
+    /// assert_eq!(mask.into_raw(), vec![255, 0]);
+    /// ```
+    pub fn change_mask<D>(&self, other: &ImageBuffer<P, D>, thresholds: P) -> GrayImage
+        where D: Deref<Target = [P::Subpixel]>
+    {
+        assert_eq!(self.dimensions(),
+                   other.dimensions(),
+                   "the images must have the same dimensions");
+        let thresholds = thresholds.as_ref().as_ref();
+        let mut mask = GrayImage::new(self.width(), self.height());
+        for ((m, a), b) in mask.pixels_mut().zip(self.pixels()).zip(other.pixels()) {
+            let changed = a.as_ref()
+                .as_ref()
+                .iter()
+                .zip(b.as_ref().as_ref())
+                .zip(thresholds)
+                .any(|((&a, &b), &t)| channel_diff(a, b) > t);
+            *m = Gray::new([if changed { 255 } else { 0 }]);
+        }
+        mask
+    }
+}
+
+/// Sums frames over time in a widened buffer.
+///
+/// Every added frame first scales the previous sums by the decay factor, such that a frame
+/// added `n` frames ago contributes with the weight `decay^n`. A decay of 1 yields plain sums,
+/// smaller values let old frames fade out.
+///
+/// # Examples
+///
+/// ```
+/// # use image_buffer::{GrayImage, color::Gray};
+/// use image_buffer::motion::Accumulator;
+/// let mut exposure = Accumulator::new(1, 1);
+/// exposure.add(&GrayImage::from_pixel(1, 1, Gray::new([100])));
+/// exposure.add(&GrayImage::from_pixel(1, 1, Gray::new([200])));
+/// assert_eq!(exposure.mean()[(0, 0)], Gray::new([150]));
+/// assert_eq!(exposure.sum()[(0, 0)], Gray::new([255]));
+/// ```
+#[derive(Clone, Debug)]
+pub struct Accumulator<P: Pixel> {
+    width: u32,
+    height: u32,
+    decay: f64,
+    weight: f64,
+    frames: usize,
+    sums: Vec<f64>,
+    _pixel_type: PhantomData<P>,
+}
+
+impl<P: Pixel> Accumulator<P>
+    where P::Subpixel: ChannelMax
+{
+    /// Creates an accumulator of plain sums.
+    pub fn new(width: u32, height: u32) -> Accumulator<P> {
+        Accumulator::with_decay(width, height, 1.0)
+    }
+
+    /// Creates an accumulator whose sums decay by `decay` per added frame.
+    ///
+    /// # Panics
+    ///
+    /// If `decay` is not in `(0, 1]`.
+    pub fn with_decay(width: u32, height: u32, decay: f64) -> Accumulator<P> {
+        assert!(decay > 0.0 && decay <= 1.0, "invalid decay: {}", decay);
+        Accumulator {
+            width,
+            height,
+            decay,
+            weight: 0.0,
+            frames: 0,
+            sums: vec![0.0; width as usize * height as usize * P::channel_count()],
+            _pixel_type: PhantomData,
+        }
+    }
+
+    /// The width and height of the accumulated frames.
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// The number of added frames.
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    /// The total weight of the added frames, which equals `frames()` without decay.
+    pub fn weight(&self) -> f64 {
+        self.weight
+    }
+
+    /// The accumulated subpixels, row by row without padding.
+    pub fn sums(&self) -> &[f64] {
+        &self.sums
+    }
+
+    /// Adds `frame` to the sums.
+    ///
+    /// # Panics
+    ///
+    /// If the frame does not have the dimensions of the accumulator.
+    pub fn add<C>(&mut self, frame: &ImageBuffer<P, C>)
+        where C: Deref<Target = [P::Subpixel]>
+    {
+        assert_eq!(frame.dimensions(),
+                   self.dimensions(),
+                   "the frame must have the dimensions of the accumulator");
+        let channels = frame.pixels().flat_map(|p| p.as_ref().as_ref().iter());
+        for (sum, &v) in self.sums.iter_mut().zip(channels) {
+            *sum = *sum * self.decay + to_f32(v) as f64;
+        }
+        self.weight = self.weight * self.decay + 1.0;
+        self.frames += 1;
+    }
+
+    /// Discards all added frames.
+    pub fn reset(&mut self) {
+        for sum in &mut self.sums {
+            *sum = 0.0;
+        }
+        self.weight = 0.0;
+        self.frames = 0;
+    }
+
+    /// Returns the sums, integer channels are clamped to their range.
+    pub fn sum(&self) -> ImageBuffer<P, Vec<P::Subpixel>> {
+        self.to_image(1.0)
+    }
+
+    /// Returns the weighted mean of the added frames, or a black image if there are none.
+    pub fn mean(&self) -> ImageBuffer<P, Vec<P::Subpixel>> {
+        if self.weight == 0.0 {
+            return ImageBuffer::new(self.width, self.height);
+        }
+        self.to_image(1.0 / self.weight)
+    }
+
+    fn to_image(&self, scale: f64) -> ImageBuffer<P, Vec<P::Subpixel>> {
+        let data = self.sums.iter().map(|&s| from_f32((s * scale) as f32)).collect();
+        ImageBuffer::from_raw(self.width, self.height, data).unwrap()
+    }
+}
+
+/// Evens out the global brightness of a sequence of frames, such as the flicker of a timelapse
+/// caused by varying exposures.
+///
+/// The brightness of a frame is the mean of its color channels, alpha is ignored. Every frame
+/// is scaled by the ratio of the mean brightness of the last `window` frames, including
+/// itself, to its own brightness, which keeps slow changes like a sunset while removing the
+/// jumps between consecutive frames.
+///
+/// # Examples
+///
+/// ```
+/// # use image_buffer::{GrayImage, color::Gray};
+/// use image_buffer::motion::Deflicker;
+/// let mut deflicker = Deflicker::new(4);
+/// let frames = [100, 100, 100, 140];
+/// let corrected: Vec<u8> = frames.iter()
+///     .map(|&v| deflicker.correct(&GrayImage::from_pixel(1, 1, Gray::new([v])))[(0, 0)][0])
+///     .collect();
+/// assert_eq!(corrected, vec![100, 100, 100, 110]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Deflicker {
+    window: usize,
+    history: VecDeque<f64>,
+}
+
+impl Deflicker {
+    /// Creates a deflicker smoothing over `window` frames.
+    ///
+    /// # Panics
+    ///
+    /// If `window` is zero.
+    pub fn new(window: usize) -> Deflicker {
+        assert!(window > 0, "empty window");
+        Deflicker {
+            window,
+            history: VecDeque::with_capacity(window),
+        }
+    }
+
+    /// The number of frames the brightness is smoothed over.
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    /// The target brightness, the mean brightness of the frames in the window, or None if no
+    /// frame was added.
+    pub fn target(&self) -> Option<f64> {
+        if self.history.is_empty() {
+            return None;
+        }
+        Some(self.history.iter().sum::<f64>() / self.history.len() as f64)
+    }
+
+    /// Forgets the brightness of all previous frames.
+    pub fn reset(&mut self) {
+        self.history.clear();
+    }
+
+    /// Adds the brightness of `frame` to the window and returns the gain correcting it.
+    ///
+    /// The gain is 1 for black frames.
+    pub fn gain<P, C>(&mut self, frame: &ImageBuffer<P, C>) -> f64
+        where P: Pixel,
+              C: Deref<Target = [P::Subpixel]>
+    {
+        let alpha = P::layout().alpha_position();
+        let (mut sum, mut count) = (0.0, 0usize);
+        for p in frame.pixels() {
+            for (c, &v) in p.as_ref().as_ref().iter().enumerate() {
+                if Some(c) != alpha {
+                    sum += to_f32(v) as f64;
+                    count += 1;
+                }
+            }
+        }
+        let brightness = if count == 0 { 0.0 } else { sum / count as f64 };
+        if self.history.len() == self.window {
+            self.history.pop_front();
+        }
+        self.history.push_back(brightness);
+        if brightness > 0.0 {
+            self.target().unwrap() / brightness
+        } else {
+            1.0
+        }
+    }
+
+    /// Adds `frame` to the window and returns it scaled by its gain.
+    ///
+    /// Integer channels are rounded and clamped to their range, alpha is copied unchanged.
+    pub fn correct<P, C>(&mut self, frame: &ImageBuffer<P, C>) -> ImageBuffer<P, Vec<P::Subpixel>>
+        where P: Pixel,
+              P::Subpixel: ChannelMax,
+              C: Deref<Target = [P::Subpixel]>
+    {
+        let gain = self.gain(frame) as f32;
+        let alpha = P::layout().alpha_position();
+        frame.map_buffer(|p| {
+            let mut p = *p;
+            for (c, v) in p.as_mut().as_mut().iter_mut().enumerate() {
+                if Some(c) != alpha {
+                    *v = from_f32(to_f32(*v) * gain);
+                }
+            }
+            p
+        })
+    }
+}
+
+/// Checks that `frames` is not empty and all frames have the same dimensions, which are
+/// returned.
+fn sequence_dimensions<P, C>(frames: &[&ImageBuffer<P, C>]) -> (u32, u32)
+    where P: Pixel,
+          C: Deref<Target = [P::Subpixel]>
+{
+    assert!(!frames.is_empty(), "no frames");
+    let dimensions = frames[0].dimensions();
+    assert!(frames.iter().all(|f| f.dimensions() == dimensions),
+            "the frames must have the same dimensions");
+    dimensions
+}
+
+/// Returns the mean of every channel over `frames`.
+///
+/// The frames are processed row by row, only a row of sums is kept in memory.
+///
+/// # Panics
+///
+/// If there are no frames or they have different dimensions.
+///
+/// # Examples
+///
+/// ```
+/// # use image_buffer::{GrayImage, color::Gray};
+/// use image_buffer::motion::temporal_mean;
+/// let a = GrayImage::from_pixel(2, 1, Gray::new([100]));
+/// let b = GrayImage::from_pixel(2, 1, Gray::new([201]));
+/// assert_eq!(temporal_mean(&[&a, &b]).into_raw(), vec![151, 151]);
+/// ```
+pub fn temporal_mean<P, C>(frames: &[&ImageBuffer<P, C>]) -> ImageBuffer<P, Vec<P::Subpixel>>
+    where P: Pixel,
+          P::Subpixel: ChannelMax,
+          C: Deref<Target = [P::Subpixel]>
+{
+    let (width, height) = sequence_dimensions(frames);
+    profile_op!("temporal_mean",
+                (frames.len() as u64 + 1) * ::profiling::image_bytes(frames[0]));
+    let mut mean: ImageBuffer<P, Vec<P::Subpixel>> = ImageBuffer::new(width, height);
+    let mut sums = vec![0.0f64; width as usize * P::channel_count()];
+    let scale = 1.0 / frames.len() as f64;
+    let mut sources: Vec<_> = frames.iter().map(|frame| frame.subpixel_rows()).collect();
+    for row in mean.subpixel_rows_mut() {
+        sums.fill(0.0);
+        for source in &mut sources {
+            for (sum, &v) in sums.iter_mut().zip(source.next().unwrap()) {
+                *sum += to_f32(v) as f64;
+            }
+        }
+        for (v, &sum) in row.iter_mut().zip(&sums) {
+            *v = from_f32((sum * scale) as f32);
+        }
+    }
+    mean
+}
+
+/// Returns the median of every channel over `frames`, the mean of the two middle values for
+/// an even number of frames.
+///
+/// The median ignores values which only occur in a minority of the frames, such as passing
+/// objects, so it extracts the static background of a scene. The frames are processed row by
+/// row.
+///
+/// # Panics
+///
+/// If there are no frames or they have different dimensions.
+///
+/// # Examples
+///
+/// ```
+/// # use image_buffer::{GrayImage, color::Gray};
+/// use image_buffer::motion::temporal_median;
+/// let background = GrayImage::from_pixel(2, 1, Gray::new([50]));
+/// let mut passing = background.clone();
+/// passing[(0, 0)] = Gray::new([255]);
+/// let median = temporal_median(&[&background, &passing, &background]);
+/// assert_eq!(median.into_raw(), vec![50, 50]);
+/// ```
+pub fn temporal_median<P, C>(frames: &[&ImageBuffer<P, C>]) -> ImageBuffer<P, Vec<P::Subpixel>>
+    where P: Pixel,
+          P::Subpixel: ChannelMax,
+          C: Deref<Target = [P::Subpixel]>
+{
+    let (width, height) = sequence_dimensions(frames);
+    profile_op!("temporal_median",
+                (frames.len() as u64 + 1) * ::profiling::image_bytes(frames[0]));
+    let mut median: ImageBuffer<P, Vec<P::Subpixel>> = ImageBuffer::new(width, height);
+    let row_len = width as usize * P::channel_count();
+    let n = frames.len();
+    let mut rows = Vec::with_capacity(n * row_len);
+    let mut values = Vec::with_capacity(n);
+    let compare = |a: &P::Subpixel, b: &P::Subpixel| to_f32(*a).total_cmp(&to_f32(*b));
+    let mut sources: Vec<_> = frames.iter().map(|frame| frame.subpixel_rows()).collect();
+    for row in median.subpixel_rows_mut() {
+        rows.clear();
+        for source in &mut sources {
+            rows.extend_from_slice(source.next().unwrap());
+        }
+        for (i, v) in row.iter_mut().enumerate() {
+            values.clear();
+            values.extend((0..n).map(|f| rows[f * row_len + i]));
+            let upper = *values.select_nth_unstable_by(n / 2, compare).1;
+            *v = if n % 2 == 1 {
+                upper
+            } else {
+                let lower = *values[..n / 2].iter().max_by(|a, b| compare(a, b)).unwrap();
+                from_f32((to_f32(lower) + to_f32(upper)) / 2.0)
+            };
+        }
+    }
+    median
+}
+
+#[cfg(test)]
+mod tests {
+    use buffer::{GrayImage, ImageBuffer, RgbImage};
+    use color_model::{Gray, Rgb};
+    use super::{temporal_mean, temporal_median, Accumulator, Deflicker};
+
+    #[test]
+    fn test_abs_diff() {
+        let a = RgbImage::from_fn(3, 1, |x, _| Rgb::new([x as u8 * 100, 50, 0]));
+        let b = RgbImage::from_pixel(3, 1, Rgb::new([120, 60, 255]));
+        assert_eq!(a.abs_diff(&b).into_raw(),
+                   vec![120, 10, 255, 20, 10, 255, 80, 10, 255]);
+        let f: ImageBuffer<Gray<f32>, _> = ImageBuffer::from_pixel(1, 1, Gray::new([0.25]));
+        let g = ImageBuffer::from_pixel(1, 1, Gray::new([0.75]));
+        assert_eq!(f.abs_diff(&g)[(0, 0)], Gray::new([0.5]));
+        assert_eq!(a.change_mask(&b, Rgb::new([120, 255, 255])).into_raw(), vec![0, 0, 0]);
+    }
+
+    #[test]
+    fn test_decay() {
+        let on = GrayImage::from_pixel(2, 1, Gray::new([200]));
+        let off = GrayImage::new(2, 1);
+        let mut heat = Accumulator::with_decay(2, 1, 0.5);
+        heat.add(&on);
+        heat.add(&off);
+        heat.add(&off);
+        assert_eq!(heat.frames(), 3);
+        assert_eq!(heat.weight(), 1.75);
+        assert_eq!(heat.sums(), &[50.0, 50.0]);
+        assert_eq!(heat.sum().into_raw(), vec![50, 50]);
+        assert_eq!(heat.mean().into_raw(), vec![29, 29]);
+        heat.reset();
+        assert_eq!(heat.mean().into_raw(), vec![0, 0]);
+    }
+
+    #[test]
+    fn test_deflicker() {
+        let mut deflicker = Deflicker::new(2);
+        assert_eq!(deflicker.target(), None);
+        let frame = |v: u8| RgbImage::from_pixel(2, 2, Rgb::new([v, v / 2, 0]));
+        assert_eq!(deflicker.gain(&frame(90)), 1.0);
+        let corrected = deflicker.correct(&frame(30));
+        assert_eq!(corrected[(1, 1)], Rgb::new([60, 30, 0]));
+        assert_eq!(deflicker.correct(&frame(0)).into_raw(), frame(0).into_raw());
+        assert_eq!(deflicker.target(), Some(7.5));
+        deflicker.reset();
+        assert_eq!(deflicker.correct(&frame(30)).into_raw(), frame(30).into_raw());
+    }
+
+    #[test]
+    fn test_temporal() {
+        let frames: Vec<RgbImage> = [[10, 0, 255], [30, 0, 0], [20, 9, 255], [255, 1, 0]]
+            .iter()
+            .map(|&c| RgbImage::from_pixel(2, 3, Rgb::new(c)))
+            .collect();
+        let refs: Vec<&RgbImage> = frames.iter().collect();
+        assert_eq!(temporal_median(&refs[..3])[(1, 2)], Rgb::new([20, 0, 255]));
+        assert_eq!(temporal_median(&refs)[(0, 1)], Rgb::new([25, 1, 128]));
+        assert_eq!(temporal_mean(&refs)[(1, 0)], Rgb::new([79, 3, 128]));
+        assert_eq!(temporal_mean(&refs[1..2]).into_raw(), frames[1].clone().into_raw());
+
+        let strided = GrayImage::from_raw_with_stride(1, 2, 2, vec![1, 0, 3]).unwrap();
+        let packed = GrayImage::from_raw(1, 2, vec![5, 7]).unwrap();
+        assert_eq!(temporal_mean(&[&strided, &packed]).into_raw(), vec![3, 5]);
+        assert_eq!(temporal_median(&[&strided, &packed]).into_raw(), vec![3, 5]);
+        let f: ImageBuffer<Gray<f32>, _> = ImageBuffer::from_pixel(1, 1, Gray::new([0.5]));
+        let g = ImageBuffer::from_pixel(1, 1, Gray::new([0.25]));
+        assert_eq!(temporal_median(&[&f, &g])[(0, 0)], Gray::new([0.375]));
+        // NaN sorts above every number, so it does not reorder the others
+        let nan = ImageBuffer::from_pixel(1, 1, Gray::new([f32::NAN]));
+        assert_eq!(temporal_median(&[&nan, &f, &g])[(0, 0)], Gray::new([0.5]));
+        assert_eq!(temporal_median(&[&f, &nan, &g, &g])[(0, 0)], Gray::new([0.375]));
+    }
+}
@@ -0,0 +1,250 @@
+//! Palettes for indexed images.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::ops::{Deref, Index};
+
+use buffer::{ImageBuffer, IndexedImage};
+use color_model::{Indexed, Rgb, Rgba};
+use traits::Color as Pixel;
+
+/// The maximal number of entries of a palette addressed by `Indexed<u8>`.
+const MAX_ENTRIES: usize = 256;
+
+/// A color lookup table for `IndexedImage`s.
+///
+/// # Examples
+///
+/// ```
+/// # use image_buffer::{ImageBuffer, IndexedImage, Palette};
+/// # use image_buffer::color::{Indexed, Rgb};
+/// let palette = Palette::new(vec![Rgb::new([0, 0, 0]), Rgb::new([255, 128, 0])]);
+/// let indexed = IndexedImage::from_pixel(2, 2, Indexed::new([1]));
+/// assert_eq!(indexed.expand_palette(&palette)[(0, 1)], Rgb::new([255, 128, 0]));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Palette<C: Pixel> {
+    colors: Vec<C>,
+}
+
+impl<C: Pixel> Palette<C> {
+    /// Creates a palette from its entries.
+    ///
+    /// # Panics
+    ///
+    /// If there are more than 256 entries.
+    pub fn new(colors: Vec<C>) -> Palette<C> {
+        assert!(colors.len() <= MAX_ENTRIES,
+                "a palette has at most {} entries",
+                MAX_ENTRIES);
+        Palette { colors }
+    }
+
+    /// The number of entries.
+    pub fn len(&self) -> usize {
+        self.colors.len()
+    }
+
+    /// Returns `true` if the palette has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.colors.is_empty()
+    }
+
+    /// The entries of the palette.
+    pub fn colors(&self) -> &[C] {
+        &self.colors
+    }
+
+    /// Returns the entry `index` if it exists.
+    pub fn get(&self, index: u8) -> Option<&C> {
+        self.colors.get(index as usize)
+    }
+
+    /// Appends `color` and returns its index or `None` if the palette is full.
+    pub fn push(&mut self, color: C) -> Option<u8> {
+        if self.colors.len() == MAX_ENTRIES {
+            return None;
+        }
+        self.colors.push(color);
+        Some((self.colors.len() - 1) as u8)
+    }
+
+    /// Returns the index of the first entry equal to `color`.
+    pub fn index_of(&self, color: &C) -> Option<u8>
+        where C: PartialEq
+    {
+        self.colors.iter().position(|c| c == color).map(|i| i as u8)
+    }
+
+    /// Builds the palette of an image with at most 256 distinct colors and the corresponding
+    /// indexed image.
+    ///
+    /// The entries are ordered by their first occurrence. Returns `None` if the image has too
+    /// many colors, it has to be quantized against a palette instead.
+    pub fn from_image<D>(image: &ImageBuffer<C, D>) -> Option<(Palette<C>, IndexedImage)>
+        where C: Eq + Hash,
+              D: Deref<Target = [C::Subpixel]>
+    {
+        let mut palette = Palette { colors: Vec::new() };
+        let mut indices = HashMap::new();
+        let mut indexed = IndexedImage::new(image.width(), image.height());
+        for (to, from) in indexed.pixels_mut().zip(image.pixels()) {
+            let index = match indices.get(from) {
+                Some(&index) => index,
+                None => {
+                    let index = palette.push(*from)?;
+                    indices.insert(*from, index);
+                    index
+                }
+            };
+            *to = Indexed::new([index]);
+        }
+        Some((palette, indexed))
+    }
+}
+
+impl<C: Pixel> Index<u8> for Palette<C> {
+    type Output = C;
+
+    fn index(&self, index: u8) -> &C {
+        &self.colors[index as usize]
+    }
+}
+
+impl Palette<Rgb<u8>> {
+    /// Creates a palette of `levels` evenly spaced grays from black to white.
+    ///
+    /// # Panics
+    ///
+    /// If `levels` is not in `2..=256`.
+    pub fn grayscale(levels: usize) -> Palette<Rgb<u8>> {
+        assert!((2..=MAX_ENTRIES).contains(&levels),
+                "invalid number of gray levels: {}",
+                levels);
+        Palette::new((0..levels)
+            .map(|i| {
+                let v = ((i * 255 + (levels - 1) / 2) / (levels - 1)) as u8;
+                Rgb::new([v, v, v])
+            })
+            .collect())
+    }
+
+    /// Creates the 216 color "web safe" palette, six levels per channel.
+    pub fn web_safe() -> Palette<Rgb<u8>> {
+        let mut colors = Vec::with_capacity(216);
+        for r in 0..6 {
+            for g in 0..6 {
+                for b in 0..6 {
+                    colors.push(Rgb::new([r * 51, g * 51, b * 51]));
+                }
+            }
+        }
+        Palette::new(colors)
+    }
+
+    /// Returns the index of the entry closest to `color` in terms of the euclidean distance.
+    ///
+    /// # Panics
+    ///
+    /// If the palette is empty.
+    pub fn nearest(&self, color: Rgb<u8>) -> u8 {
+        let distance = |c: &Rgb<u8>| {
+            (0..3)
+                .map(|i| {
+                    let d = c[i] as i32 - color[i] as i32;
+                    d * d
+                })
+                .sum::<i32>()
+        };
+        let (index, _) = self.colors
+            .iter()
+            .enumerate()
+            .min_by_key(|&(_, c)| distance(c))
+            .expect("empty palette");
+        index as u8
+    }
+
+    /// Adds an alpha channel to the entries.
+    ///
+    /// The entry `i` gets the alpha value `alphas[i]`, entries without a value are opaque. This
+    /// matches the `tRNS` chunk of PNG, a single transparent GIF entry `i` is given by
+    /// `alphas = [255; i] + [0]`.
+    pub fn with_alpha(&self, alphas: &[u8]) -> Palette<Rgba<u8>> {
+        Palette::new(self.colors
+            .iter()
+            .enumerate()
+            .map(|(i, c)| Rgba::new([c[0], c[1], c[2], *alphas.get(i).unwrap_or(&255)]))
+            .collect())
+    }
+}
+
+impl<D> ImageBuffer<Indexed<u8>, D>
+    where D: Deref<Target = [u8]>
+{
+    /// Replaces the indices by the entries of `palette`.
+    ///
+    /// # Panics
+    ///
+    /// If an index has no entry in the palette.
+    pub fn expand_palette<C>(&self, palette: &Palette<C>) -> ImageBuffer<C, Vec<C::Subpixel>>
+        where C: Pixel
+    {
+        self.map_buffer(|p| palette[p[0]])
+    }
+}
+
+impl<D> ImageBuffer<Rgb<u8>, D>
+    where D: Deref<Target = [u8]>
+{
+    /// Maps every pixel to the index of the nearest entry of `palette`.
+    ///
+    /// # Panics
+    ///
+    /// If the palette is empty.
+    pub fn quantize(&self, palette: &Palette<Rgb<u8>>) -> IndexedImage {
+        self.map_buffer(|p| Indexed::new([palette.nearest(*p)]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buffer::{IndexedImage, RgbImage};
+    use color_model::{Indexed, Rgb, Rgba};
+    use super::Palette;
+
+    #[test]
+    fn test_roundtrip() {
+        let image = RgbImage::from_fn(4, 3, |x, y| Rgb::new([(x * 80) as u8, (y * 100) as u8, 7]));
+        let (palette, indexed) = Palette::from_image(&image).unwrap();
+        assert_eq!(palette.len(), 12);
+        assert_eq!(indexed[(1, 0)], Indexed::new([1]));
+        assert_eq!(indexed.expand_palette(&palette).into_raw(), image.into_raw());
+
+        let noisy = ::test_images::noise(20, 20, 3);
+        assert!(Palette::from_image(&noisy).is_none());
+    }
+
+    #[test]
+    fn test_construction() {
+        let gray = Palette::grayscale(4);
+        assert_eq!(gray.colors(),
+                   &[Rgb::new([0; 3]), Rgb::new([85; 3]), Rgb::new([170; 3]), Rgb::new([255; 3])]);
+        assert_eq!(gray.nearest(Rgb::new([100, 90, 80])), 1);
+        assert_eq!(Palette::web_safe().len(), 216);
+        assert_eq!(Palette::web_safe().index_of(&Rgb::new([0, 0, 255])), Some(5));
+
+        let rgba = gray.with_alpha(&[255, 0]);
+        assert_eq!(rgba[1], Rgba::new([85, 85, 85, 0]));
+        assert_eq!(rgba[3], Rgba::new([255, 255, 255, 255]));
+
+        let mut full = Palette::grayscale(256);
+        assert_eq!(full.push(Rgb::new([1, 2, 3])), None);
+    }
+
+    #[test]
+    fn test_quantize() {
+        let image = RgbImage::from_fn(2, 1, |x, _| Rgb::new([x as u8 * 200; 3]));
+        let indexed: IndexedImage = image.quantize(&Palette::grayscale(2));
+        assert_eq!(indexed.into_raw(), vec![0, 1]);
+    }
+}
@@ -0,0 +1,224 @@
+//! Images whose channels are stored in separate planes.
+
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+
+use num_traits::Zero;
+
+use buffer::ImageBuffer;
+use color_model::Gray;
+use traits::Color as Pixel;
+
+/// An image buffer storing every channel in a separate plane.
+///
+/// The container holds the planes one after another in the canonical channel order, each
+/// plane holds `width * height` subpixels row by row without padding. This is the layout of
+/// planar video formats like RGB24P and of most scientific data.
+///
+/// # Examples
+///
+/// ```
+/// # use image_buffer::{PlanarImageBuffer, RgbImage, color::Rgb};
+/// let image = RgbImage::from_fn(2, 1, |x, _| Rgb::new([x as u8, 10, 20]));
+/// let planar = image.to_planar();
+/// assert_eq!(planar.plane(0), &[0, 1]);
+/// assert_eq!(planar.into_raw(), vec![0, 1, 10, 10, 20, 20]);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PlanarImageBuffer<P: Pixel, Container> {
+    width: u32,
+    height: u32,
+    data: Container,
+    _pixel_type: PhantomData<P>,
+}
+
+impl<P, Container> PlanarImageBuffer<P, Container>
+    where P: Pixel,
+          Container: Deref<Target = [P::Subpixel]>
+{
+    /// Constructs a planar buffer from a generic container holding the planes.
+    ///
+    /// Returns None if the container is smaller than `channels * width * height` subpixels.
+    pub fn from_raw(width: u32,
+                    height: u32,
+                    buf: Container)
+                    -> Option<PlanarImageBuffer<P, Container>> {
+        let plane_len = (width as usize).checked_mul(height as usize)?;
+        if plane_len.checked_mul(P::channel_count())? > buf.len() {
+            return None;
+        }
+        Some(PlanarImageBuffer {
+            width,
+            height,
+            data: buf,
+            _pixel_type: PhantomData,
+        })
+    }
+
+    /// Returns the underlying raw buffer.
+    pub fn into_raw(self) -> Container {
+        self.data
+    }
+
+    /// The width and height of this image.
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// The width of this image.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The height of this image.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn plane_len(&self) -> usize {
+        self.width as usize * self.height as usize
+    }
+
+    /// Returns the plane of the canonical channel `channel`.
+    ///
+    /// # Panics
+    ///
+    /// If the pixel type has no channel `channel`.
+    pub fn plane(&self, channel: usize) -> &[P::Subpixel] {
+        assert!(channel < P::channel_count(), "no channel {}", channel);
+        let len = self.plane_len();
+        &self.data[channel * len..(channel + 1) * len]
+    }
+
+    /// Returns the plane of the canonical channel `channel` as a gray image, such that it can
+    /// be processed like any other image.
+    ///
+    /// # Panics
+    ///
+    /// If the pixel type has no channel `channel`.
+    pub fn plane_image(&self, channel: usize) -> ImageBuffer<Gray<P::Subpixel>, &[P::Subpixel]> {
+        ImageBuffer::from_raw(self.width, self.height, self.plane(channel)).unwrap()
+    }
+
+    /// Gathers the channels of the pixel at `(x, y)`.
+    ///
+    /// # Panics
+    ///
+    /// If `(x, y)` is out of bounds.
+    pub fn get_pixel(&self, x: u32, y: u32) -> P {
+        assert!(x < self.width && y < self.height,
+                "pixel ({}, {}) out of bounds {:?}",
+                x,
+                y,
+                self.dimensions());
+        let (len, offset) = (self.plane_len(), y as usize * self.width as usize + x as usize);
+        let mut pixel = *P::from_slice(&self.data[..P::channel_count()]);
+        for (c, v) in pixel.channels_mut().as_mut().iter_mut().enumerate() {
+            *v = self.data[c * len + offset];
+        }
+        pixel
+    }
+
+    /// Converts the image to an interleaved buffer.
+    pub fn to_interleaved(&self) -> ImageBuffer<P, Vec<P::Subpixel>> {
+        let channels = P::channel_count();
+        let len = self.plane_len();
+        let mut data = Vec::with_capacity(len * channels);
+        for i in 0..len {
+            data.extend((0..channels).map(|c| self.data[c * len + i]));
+        }
+        ImageBuffer::from_raw(self.width, self.height, data).unwrap()
+    }
+}
+
+impl<P, Container> PlanarImageBuffer<P, Container>
+    where P: Pixel,
+          Container: Deref<Target = [P::Subpixel]> + DerefMut
+{
+    /// Returns the mutable plane of the canonical channel `channel`.
+    ///
+    /// # Panics
+    ///
+    /// If the pixel type has no channel `channel`.
+    pub fn plane_mut(&mut self, channel: usize) -> &mut [P::Subpixel] {
+        assert!(channel < P::channel_count(), "no channel {}", channel);
+        let len = self.plane_len();
+        &mut self.data[channel * len..(channel + 1) * len]
+    }
+
+    /// Scatters the channels of `pixel` to `(x, y)`.
+    ///
+    /// # Panics
+    ///
+    /// If `(x, y)` is out of bounds.
+    pub fn put_pixel(&mut self, x: u32, y: u32, pixel: P) {
+        assert!(x < self.width && y < self.height,
+                "pixel ({}, {}) out of bounds {:?}",
+                x,
+                y,
+                self.dimensions());
+        let (len, offset) = (self.plane_len(), y as usize * self.width as usize + x as usize);
+        for (c, &v) in pixel.channels().as_ref().iter().enumerate() {
+            self.data[c * len + offset] = v;
+        }
+    }
+}
+
+impl<P: Pixel> PlanarImageBuffer<P, Vec<P::Subpixel>> {
+    /// Creates a new planar buffer filled with zeros.
+    pub fn new(width: u32, height: u32) -> PlanarImageBuffer<P, Vec<P::Subpixel>> {
+        let len = width as usize * height as usize * P::channel_count();
+        PlanarImageBuffer::from_raw(width, height, vec![Zero::zero(); len]).unwrap()
+    }
+}
+
+impl<P, Container> ImageBuffer<P, Container>
+    where P: Pixel,
+          Container: Deref<Target = [P::Subpixel]>
+{
+    /// Converts the image to a planar buffer.
+    pub fn to_planar(&self) -> PlanarImageBuffer<P, Vec<P::Subpixel>> {
+        let mut planar = PlanarImageBuffer::new(self.width(), self.height());
+        let len = planar.plane_len();
+        for (i, p) in self.pixels().enumerate() {
+            for (c, &v) in p.channels().as_ref().iter().enumerate() {
+                planar.data[c * len + i] = v;
+            }
+        }
+        planar
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buffer::{ImageBuffer, RgbaImage};
+    use color_model::{Gray, Rgba};
+    use super::PlanarImageBuffer;
+
+    #[test]
+    fn test_roundtrip() {
+        let data: Vec<u8> = (0..40).collect();
+        let image = RgbaImage::from_raw_with_stride(3, 2, 16, data).unwrap();
+        let mut planar = image.to_planar();
+        assert_eq!(planar.plane(3), &[3, 7, 11, 19, 23, 27]);
+        assert_eq!(planar.get_pixel(2, 1), Rgba::new([24, 25, 26, 27]));
+        assert_eq!(planar.to_interleaved().into_raw(),
+                   image.pixels().flat_map(|p| p.as_ref().to_vec()).collect::<Vec<_>>());
+
+        planar.put_pixel(0, 1, Rgba::new([1, 2, 3, 4]));
+        planar.plane_mut(0)[0] = 99;
+        let interleaved = planar.to_interleaved();
+        assert_eq!(interleaved[(0, 1)], Rgba::new([1, 2, 3, 4]));
+        assert_eq!(interleaved[(0, 0)], Rgba::new([99, 1, 2, 3]));
+        assert_eq!(planar.plane_image(2)[(1, 0)], Gray::new([6]));
+    }
+
+    #[test]
+    fn test_from_raw() {
+        let planes = vec![0.5f32; 12];
+        assert!(PlanarImageBuffer::<Rgba<f32>, _>::from_raw(2, 2, &planes[..]).is_none());
+        let planar = PlanarImageBuffer::<Gray<f32>, _>::from_raw(3, 4, &planes[..]).unwrap();
+        let image: ImageBuffer<Gray<f32>, _> = planar.to_interleaved();
+        assert_eq!(image.into_raw(), planes);
+    }
+}
@@ -0,0 +1,75 @@
+//! Planes of `f32` samples, on which the algorithms computing in floating point work.
+
+use std::ops::Deref;
+
+use buffer::ImageBuffer;
+use math::{from_f32, to_f32};
+use traits::{ChannelMax, Color as Pixel};
+
+/// The subpixels of an image as `f32`, row by row without padding.
+#[derive(Clone)]
+pub struct Plane {
+    pub width: usize,
+    pub height: usize,
+    pub channels: usize,
+    pub data: Vec<f32>,
+}
+
+impl Plane {
+    /// Converts the subpixels of `image` without scaling them.
+    pub fn from_image<P, C>(image: &ImageBuffer<P, C>) -> Plane
+        where P: Pixel,
+              C: Deref<Target = [P::Subpixel]>
+    {
+        Plane {
+            width: image.width() as usize,
+            height: image.height() as usize,
+            channels: P::channel_count(),
+            data: image.pixels()
+                .flat_map(|p| p.as_ref().as_ref().iter().map(|&v| to_f32(v)))
+                .collect(),
+        }
+    }
+
+    /// Converts the subpixels of `image` scaled from the range of the subpixel type to
+    /// `[0, 1]`.
+    pub fn from_image_normalized<P, C>(image: &ImageBuffer<P, C>) -> Plane
+        where P: Pixel,
+              P::Subpixel: ChannelMax,
+              C: Deref<Target = [P::Subpixel]>
+    {
+        let mut plane = Plane::from_image(image);
+        let max = to_f32(P::Subpixel::channel_max());
+        for v in &mut plane.data {
+            *v /= max;
+        }
+        plane
+    }
+
+    /// A plane of zeros with the dimensions of this one.
+    pub fn zeros_like(&self) -> Plane {
+        Plane {
+            data: vec![0.0; self.data.len()],
+            ..*self
+        }
+    }
+
+    /// Returns the sample of a single channel plane at `(x, y)`, coordinates outside are clamped
+    /// to the border. Empty planes have no border and return 0.
+    pub fn at(&self, x: isize, y: isize) -> f32 {
+        if self.data.is_empty() {
+            return 0.0;
+        }
+        let x = x.clamp(0, self.width as isize - 1) as usize;
+        let y = y.clamp(0, self.height as isize - 1) as usize;
+        self.data[y * self.width + x]
+    }
+
+    /// Converts the samples to an image, integer subpixels are rounded and clamped.
+    pub fn into_image<P: Pixel>(self) -> ImageBuffer<P, Vec<P::Subpixel>>
+        where P::Subpixel: ChannelMax
+    {
+        let data = self.data.into_iter().map(from_f32).collect();
+        ImageBuffer::from_raw(self.width as u32, self.height as u32, data).unwrap()
+    }
+}
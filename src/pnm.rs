@@ -0,0 +1,320 @@
+//! Reading and writing of binary PNM files, enabled by the `pnm` feature.
+//!
+//! The [Netpbm](https://en.wikipedia.org/wiki/Netpbm) formats PBM (`P4`), PGM (`P5`) and
+//! PPM (`P6`) store bilevel, gray and RGB images behind a short text header, which makes them
+//! a dependency-free way to persist images and to exchange them with other tools. Samples of
+//! more than 8 bits are stored big endian. The plain text variants `P1` to `P3` are not
+//! supported.
+//!
+//! # Examples
+//!
+//! ```
+//! # use image_buffer::{RgbImage, color::Rgb};
+//! use image_buffer::pnm::{read_pnm, write_pnm};
+//! let image = RgbImage::from_pixel(2, 1, Rgb::new([255, 128, 0]));
+//! let mut file = Vec::new();
+//! write_pnm(&image, &mut file).unwrap();
+//! assert_eq!(&file[..11], b"P6\n2 1\n255\n");
+//! let decoded = read_pnm(&file[..]).unwrap();
+//! assert_eq!(decoded.as_rgb8().unwrap()[(1, 0)], Rgb::new([255, 128, 0]));
+//! ```
+
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::ops::Deref;
+
+use buffer::ImageBuffer;
+use color_model::{AlphaMode, Gray, PixelLayout, Rgb};
+use dynamic::DynamicImageBuffer;
+use traits::{Color as Pixel, Primitive};
+
+/// An error that occurred while reading or writing a PNM file.
+#[derive(Debug)]
+pub enum PnmError {
+    /// The underlying reader or writer failed.
+    Io(io::Error),
+    /// The file is not a valid PNM file.
+    Format(String),
+    /// The file or the pixel type is valid but cannot be stored or read.
+    Unsupported(String),
+}
+
+impl fmt::Display for PnmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PnmError::Io(ref err) => write!(f, "I/O error: {}", err),
+            PnmError::Format(ref msg) => write!(f, "invalid PNM file: {}", msg),
+            PnmError::Unsupported(ref msg) => write!(f, "unsupported PNM image: {}", msg),
+        }
+    }
+}
+
+impl Error for PnmError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            PnmError::Io(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for PnmError {
+    fn from(err: io::Error) -> PnmError {
+        PnmError::Io(err)
+    }
+}
+
+/// Channel types which can be stored in PGM and PPM files.
+pub trait PnmSample: Primitive {
+    /// The maximal value of a sample, written to the header.
+    const MAXVAL: u16;
+
+    /// Appends the big endian bytes of the sample to `bytes`.
+    fn extend_bytes(self, bytes: &mut Vec<u8>);
+}
+
+impl PnmSample for u8 {
+    const MAXVAL: u16 = 255;
+
+    fn extend_bytes(self, bytes: &mut Vec<u8>) {
+        bytes.push(self);
+    }
+}
+
+impl PnmSample for u16 {
+    const MAXVAL: u16 = 65535;
+
+    fn extend_bytes(self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(&self.to_be_bytes());
+    }
+}
+
+/// Writes `image` as a PGM file if it is gray or as a PPM file if it is RGB.
+///
+/// Returns `PnmError::Unsupported` for all other pixel types, including those with alpha.
+pub fn write_pnm<P, C, W>(image: &ImageBuffer<P, C>, mut writer: W) -> Result<(), PnmError>
+    where P: Pixel,
+          P::Subpixel: PnmSample,
+          C: Deref<Target = [P::Subpixel]>,
+          W: Write
+{
+    let magic = match (P::color_model(), P::layout()) {
+        ("Y", layout) if layout == PixelLayout::canonical(1, AlphaMode::None) => 5,
+        ("RGB", layout) if layout == PixelLayout::canonical(3, AlphaMode::None) => 6,
+        (model, _) => {
+            return Err(PnmError::Unsupported(format!("pixels of the color model {} with {} \
+                                                      channels",
+                                                     model,
+                                                     P::channel_count())))
+        }
+    };
+    let (width, height) = image.dimensions();
+    write!(writer,
+           "P{}\n{} {}\n{}\n",
+           magic,
+           width,
+           height,
+           P::Subpixel::MAXVAL)?;
+    let mut bytes = Vec::new();
+    for row in image.subpixel_rows() {
+        bytes.clear();
+        for &v in row {
+            v.extend_bytes(&mut bytes);
+        }
+        writer.write_all(&bytes)?;
+    }
+    Ok(())
+}
+
+/// Writes `image` as a PBM file, pixels darker than 128 are black.
+pub fn write_pbm<C, W>(image: &ImageBuffer<Gray<u8>, C>, mut writer: W) -> Result<(), PnmError>
+    where C: Deref<Target = [u8]>,
+          W: Write
+{
+    let (width, height) = image.dimensions();
+    write!(writer, "P4\n{} {}\n", width, height)?;
+    let mut bytes = Vec::new();
+    for row in image.rows() {
+        bytes.clear();
+        bytes.resize((width as usize).div_ceil(8), 0);
+        for (x, p) in row.iter().enumerate() {
+            if p[0] < 128 {
+                bytes[x / 8] |= 0x80 >> (x % 8);
+            }
+        }
+        writer.write_all(&bytes)?;
+    }
+    Ok(())
+}
+
+/// Reads a PBM, PGM or PPM file.
+///
+/// Bilevel images are read as 8 bit gray images with black as 0 and white as 255. Gray and
+/// RGB images are read with 8 bit channels if the maximal value is at most 255 and with 16
+/// bit channels otherwise, the samples are rescaled to the full range of the channels.
+pub fn read_pnm<R: Read>(mut reader: R) -> Result<DynamicImageBuffer, PnmError> {
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+    let mut header = Header {
+        data: &data,
+        pos: 0,
+    };
+    if header.data.first() != Some(&b'P') {
+        return Err(PnmError::Format("missing magic number".into()));
+    }
+    let magic = header.data.get(1).cloned().unwrap_or(0);
+    header.pos = 2;
+    let channels = match magic {
+        b'4' | b'5' => 1,
+        b'6' => 3,
+        b'1'..=b'3' | b'7' => {
+            return Err(PnmError::Unsupported(format!("format P{}", magic as char)));
+        }
+        _ => return Err(PnmError::Format("invalid magic number".into())),
+    };
+    let width = header.number()?;
+    let height = header.number()?;
+    let maxval = if magic == b'4' { 1 } else { header.number()? };
+    if maxval == 0 || maxval > 65535 {
+        return Err(PnmError::Format(format!("invalid maximal value {}", maxval)));
+    }
+    if !header.data.get(header.pos).is_some_and(u8::is_ascii_whitespace) {
+        return Err(PnmError::Format("missing whitespace after the header".into()));
+    }
+    let samples = &data[header.pos + 1..];
+
+    let pixels = (width as usize)
+        .checked_mul(height as usize)
+        .and_then(|n| n.checked_mul(channels))
+        .ok_or_else(|| PnmError::Format("image dimensions overflow usize".into()))?;
+    let truncated = || PnmError::Format("truncated image data".into());
+    if magic == b'4' {
+        let row_bytes = (width as usize).div_ceil(8);
+        let rows = samples.get(..row_bytes * height as usize).ok_or_else(truncated)?;
+        let mut image = ImageBuffer::<Gray<u8>, _>::new(width, height);
+        for (row, bits) in image.rows_mut().zip(rows.chunks(row_bytes.max(1))) {
+            for (x, p) in row.iter_mut().enumerate() {
+                let black = bits[x / 8] & (0x80 >> (x % 8)) != 0;
+                *p = Gray::new([if black { 0 } else { 255 }]);
+            }
+        }
+        return Ok(image.into());
+    }
+    if maxval <= 255 {
+        let samples = samples.get(..pixels).ok_or_else(truncated)?;
+        let data = samples.iter().map(|&v| rescale(v as u32, maxval, 255) as u8).collect();
+        return Ok(match channels {
+            1 => ImageBuffer::<Gray<u8>, _>::from_raw(width, height, data).unwrap().into(),
+            _ => ImageBuffer::<Rgb<u8>, _>::from_raw(width, height, data).unwrap().into(),
+        });
+    }
+    let samples = samples.get(..2 * pixels).ok_or_else(truncated)?;
+    let data = samples.chunks(2)
+        .map(|v| rescale(u16::from_be_bytes([v[0], v[1]]) as u32, maxval, 65535) as u16)
+        .collect();
+    Ok(match channels {
+        1 => ImageBuffer::<Gray<u16>, _>::from_raw(width, height, data).unwrap().into(),
+        _ => ImageBuffer::<Rgb<u16>, _>::from_raw(width, height, data).unwrap().into(),
+    })
+}
+
+/// Rescales `v` from `0..=from` to `0..=to`, values above `from` are clamped.
+fn rescale(v: u32, from: u32, to: u32) -> u32 {
+    if from == to {
+        return v.min(to);
+    }
+    (v.min(from) * to + from / 2) / from
+}
+
+/// Tokenizer of the text header of a PNM file.
+struct Header<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Header<'a> {
+    /// Skips whitespace and comments and parses the following decimal number.
+    fn number(&mut self) -> Result<u32, PnmError> {
+        loop {
+            match self.data.get(self.pos) {
+                Some(b) if b.is_ascii_whitespace() => self.pos += 1,
+                Some(&b'#') => {
+                    while self.data.get(self.pos).is_some_and(|&b| b != b'\n') {
+                        self.pos += 1;
+                    }
+                }
+                _ => break,
+            }
+        }
+        let start = self.pos;
+        while self.data.get(self.pos).is_some_and(u8::is_ascii_digit) {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return Err(PnmError::Format("incomplete header".into()));
+        }
+        String::from_utf8_lossy(&self.data[start..self.pos])
+            .parse()
+            .map_err(|_| PnmError::Format("header value out of range".into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buffer::{GrayImage, ImageBuffer, RgbaImage};
+    use color_model::{ColorType, Gray, Rgb};
+    use super::{read_pnm, write_pbm, write_pnm, PnmError};
+
+    #[test]
+    fn test_roundtrip() {
+        let gray = GrayImage::from_raw_with_stride(3, 2, 4, vec![0, 1, 2, 9, 3, 4, 5]).unwrap();
+        let mut file = Vec::new();
+        write_pnm(&gray, &mut file).unwrap();
+        assert_eq!(file, b"P5\n3 2\n255\n\x00\x01\x02\x03\x04\x05".to_vec());
+        let decoded = read_pnm(&file[..]).unwrap();
+        assert_eq!(decoded.as_gray8().unwrap().clone().into_raw(), vec![0, 1, 2, 3, 4, 5]);
+
+        let rgb = ImageBuffer::from_pixel(1, 1, Rgb::new([1u16, 256, 65535]));
+        file.clear();
+        write_pnm(&rgb, &mut file).unwrap();
+        assert_eq!(&file[13..], &[0, 1, 1, 0, 255, 255]);
+        let decoded = read_pnm(&file[..]).unwrap();
+        assert_eq!(decoded.color_type(), ColorType::Rgb(16));
+        assert_eq!(decoded.as_rgb16().unwrap()[(0, 0)], Rgb::new([1, 256, 65535]));
+
+        match write_pnm(&RgbaImage::new(1, 1), Vec::new()) {
+            Err(PnmError::Unsupported(_)) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_pbm() {
+        let image = GrayImage::from_fn(9, 2, |x, y| Gray::new([if x == y { 0 } else { 255 }]));
+        let mut file = Vec::new();
+        write_pbm(&image, &mut file).unwrap();
+        assert_eq!(file, b"P4\n9 2\n\x80\x00\x40\x00".to_vec());
+        let decoded = read_pnm(&file[..]).unwrap();
+        assert_eq!(decoded.as_gray8().unwrap().clone().into_raw(), image.into_raw());
+    }
+
+    #[test]
+    fn test_read() {
+        let file = b"P5 # comment\n2 1 # width and height\n15\n\x00\x0f";
+        let decoded = read_pnm(&file[..]).unwrap();
+        assert_eq!(decoded.as_gray8().unwrap().clone().into_raw(), vec![0, 255]);
+        let decoded = read_pnm(&b"P5 1 1 1023 \x02\x00"[..]).unwrap();
+        assert_eq!(decoded.as_gray16().unwrap()[(0, 0)], Gray::new([32800]));
+
+        let error = |file: &[u8]| match read_pnm(file) {
+            Err(PnmError::Format(msg)) => msg,
+            other => panic!("unexpected result {:?}", other.map(|i| i.color_type())),
+        };
+        assert_eq!(error(b"P6 2 1 255\n\x00"), "truncated image data");
+        assert_eq!(error(b"P5 2"), "incomplete header");
+        assert_eq!(error(b"P5 2 1 0 \x00\x00"), "invalid maximal value 0");
+        assert_eq!(error(b"GIF89a"), "missing magic number");
+        assert!(matches!(read_pnm(&b"P3 1 1 255 0 0 0"[..]), Err(PnmError::Unsupported(_))));
+    }
+}
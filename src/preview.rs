@@ -0,0 +1,200 @@
+//! Text previews of images for debugging.
+
+use std::cmp;
+use std::io::{self, Write};
+use std::ops::Deref;
+
+use buffer::ImageBuffer;
+use color_model::Rgb;
+use traits::Color as Pixel;
+
+/// Characters of increasing brightness used by `to_ascii_art`.
+const ASCII_RAMP: &[u8] = b" .:-=+*#%@";
+
+/// Bit of the Braille pattern for each dot `(x, y)` of a 2×4 cell, indexed by `y * 2 + x`.
+const BRAILLE_BITS: [u32; 8] = [0x01, 0x08, 0x02, 0x10, 0x04, 0x20, 0x40, 0x80];
+
+/// Quadrant block characters indexed by the bits top left, top right, bottom left, bottom right.
+const QUADRANTS: [char; 16] = [' ', '\u{2598}', '\u{259D}', '\u{2580}', '\u{2596}', '\u{258C}',
+                               '\u{259E}', '\u{259B}', '\u{2597}', '\u{259A}', '\u{2590}',
+                               '\u{259C}', '\u{2584}', '\u{2599}', '\u{259F}', '\u{2588}'];
+
+/// Approximate luma of an sRGB value in the range `0..256`.
+pub(crate) fn luma(rgb: Rgb<u8>) -> u32 {
+    let c = rgb.as_ref();
+    (2126 * c[0] as u32 + 7152 * c[1] as u32 + 722 * c[2] as u32) / 10000
+}
+
+impl<P, Container> ImageBuffer<P, Container>
+    where P: Pixel,
+          Rgb<u8>: From<P>,
+          Container: Deref<Target = [P::Subpixel]>
+{
+    /// Renders the image as ASCII art with at most `max_cols` characters per line.
+    ///
+    /// Each character covers a block of pixels which is twice as high as it is wide to account
+    /// for the aspect ratio of terminal fonts. Lines are terminated by `\n`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{GrayImage, color};
+    /// let buffer = GrayImage::from_fn(4, 2, |x, _| color::Gray::new([(x * 85) as u8]));
+    /// assert_eq!(buffer.to_ascii_art(80), " -*@\n");
+    /// ```
+    pub fn to_ascii_art(&self, max_cols: u32) -> String {
+        let (width, height) = self.dimensions();
+        if width == 0 || height == 0 || max_cols == 0 {
+            return String::new();
+        }
+        let cell_width = width.div_ceil(cmp::min(width, max_cols));
+        // Rounding the cell width up may leave fewer columns than `max_cols`
+        let cols = width.div_ceil(cell_width);
+        let cell_height = cell_width * 2;
+        let rows = height.div_ceil(cell_height);
+        let mut art = String::with_capacity(((cols + 1) * rows) as usize);
+        for row in 0..rows {
+            for col in 0..cols {
+                let (x0, y0) = (col * cell_width, row * cell_height);
+                let x1 = cmp::min(x0 + cell_width, width);
+                let y1 = cmp::min(y0 + cell_height, height);
+                let (mut sum, mut count) = (0, 0);
+                for y in y0..y1 {
+                    for x in x0..x1 {
+                        sum += luma(Rgb::from(self[(x, y)]));
+                        count += 1;
+                    }
+                }
+                let value = sum.checked_div(count).unwrap_or(0);
+                art.push(ASCII_RAMP[value as usize * ASCII_RAMP.len() / 256] as char);
+            }
+            art.push('\n');
+        }
+        art
+    }
+
+    /// Renders the image as a binary mask using Braille characters.
+    ///
+    /// Every character covers 2×4 pixels. A dot is set if the luma of the pixel exceeds
+    /// `threshold`. Lines are terminated by `\n`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{GrayImage, color};
+    /// let mask = GrayImage::from_fn(4, 4, |x, y| {
+    ///     color::Gray::new([if x == y { 255 } else { 0 }])
+    /// });
+    /// assert_eq!(mask.to_braille(127), "\u{2811}\u{2884}\n");
+    /// ```
+    pub fn to_braille(&self, threshold: u8) -> String {
+        self.mask_art(2, 4, threshold, |bits| {
+            let pattern = BRAILLE_BITS.iter()
+                .enumerate()
+                .filter(|&(i, _)| bits & (1 << i) != 0)
+                .fold(0, |p, (_, &bit)| p | bit);
+            ::std::char::from_u32(0x2800 + pattern).unwrap()
+        })
+    }
+
+    /// Renders the image as a binary mask using quadrant block characters.
+    ///
+    /// Every character covers 2×2 pixels. A quadrant is filled if the luma of the pixel exceeds
+    /// `threshold`. Lines are terminated by `\n`.
+    pub fn to_block_art(&self, threshold: u8) -> String {
+        self.mask_art(2, 2, threshold, |bits| QUADRANTS[bits as usize])
+    }
+
+    /// Renders cells of `cell_width`×`cell_height` pixels using `glyph`.
+    ///
+    /// The argument of `glyph` has the bit `y * cell_width + x` set if the pixel `(x, y)` of
+    /// the cell exceeds the threshold.
+    fn mask_art<F>(&self, cell_width: u32, cell_height: u32, threshold: u8, glyph: F) -> String
+        where F: Fn(u32) -> char
+    {
+        let (width, height) = self.dimensions();
+        let mut art = String::new();
+        for y0 in (0..height).step_by(cell_height as usize) {
+            for x0 in (0..width).step_by(cell_width as usize) {
+                let mut bits = 0;
+                for dy in 0..cmp::min(cell_height, height - y0) {
+                    for dx in 0..cmp::min(cell_width, width - x0) {
+                        if luma(Rgb::from(self[(x0 + dx, y0 + dy)])) > threshold as u32 {
+                            bits |= 1 << (dy * cell_width + dx);
+                        }
+                    }
+                }
+                art.push(glyph(bits));
+            }
+            art.push('\n');
+        }
+        art
+    }
+
+    /// Writes the image as 24-bit ANSI colored text.
+    ///
+    /// Every character cell shows two vertically adjacent pixels using the upper half block
+    /// character. No scaling is performed, so this is only useful for small images.
+    pub fn write_ansi<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let (width, height) = self.dimensions();
+        for y in (0..height).step_by(2) {
+            for x in 0..width {
+                let top = Rgb::from(self[(x, y)]);
+                let t = top.as_ref();
+                write!(w, "\x1b[38;2;{};{};{}m", t[0], t[1], t[2])?;
+                if y + 1 < height {
+                    let bottom = Rgb::from(self[(x, y + 1)]);
+                    let b = bottom.as_ref();
+                    write!(w, "\x1b[48;2;{};{};{}m", b[0], b[1], b[2])?;
+                }
+                w.write_all("\u{2580}".as_bytes())?;
+            }
+            w.write_all(b"\x1b[0m\n")?;
+        }
+        Ok(())
+    }
+
+    /// Prints the image as 24-bit ANSI colored text to the standard output.
+    ///
+    /// See `write_ansi`.
+    pub fn print_to_terminal(&self) {
+        let stdout = io::stdout();
+        let mut lock = stdout.lock();
+        let _ = self.write_ansi(&mut lock).and_then(|_| lock.flush());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buffer::RgbImage;
+    use color_model::Rgb;
+
+    #[test]
+    fn test_ascii_art_scaling() {
+        let buffer = RgbImage::from_fn(8, 8, |x, _| {
+            if x < 4 { Rgb::new([0, 0, 0]) } else { Rgb::new([255, 255, 255]) }
+        });
+        assert_eq!(buffer.to_ascii_art(4), "  @@\n  @@\n");
+
+        // Cells of 3 pixels cover 9 pixels with 3 instead of 4 columns
+        let white = RgbImage::from_pixel(9, 6, Rgb::new([255, 255, 255]));
+        assert_eq!(white.to_ascii_art(4), "@@@\n");
+    }
+
+    #[test]
+    fn test_block_art() {
+        let buffer = RgbImage::from_fn(3, 2, |x, y| {
+            if x == y { Rgb::new([255, 255, 255]) } else { Rgb::new([0, 0, 0]) }
+        });
+        assert_eq!(buffer.to_block_art(127), "\u{259A} \n");
+    }
+
+    #[test]
+    fn test_write_ansi() {
+        let buffer = RgbImage::from_pixel(1, 1, Rgb::new([1, 2, 3]));
+        let mut out = Vec::new();
+        buffer.write_ansi(&mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(),
+                   "\x1b[38;2;1;2;3m\u{2580}\x1b[0m\n");
+    }
+}
@@ -0,0 +1,159 @@
+//! Timing of the operations of this crate, enabled by the `profiling` feature.
+//!
+//! Every instrumented operation records its wall time and the number of bytes it reads and
+//! writes in a global registry, from all threads. The times are inclusive, an operation built
+//! on another instrumented one, like `box_blur` on `filter_separable`, is recorded with both
+//! names. Instrumentation has no cost when the feature is disabled.
+//!
+//! # Examples
+//!
+//! ```
+//! # use image_buffer::{EdgeMode, GrayImage};
+//! use image_buffer::profiling;
+//! let image = GrayImage::new(64, 64);
+//! let _ = image.box_blur(2, EdgeMode::Clamp);
+//! let report = profiling::report();
+//! let blur = report.get("filter_separable").unwrap();
+//! assert!(blur.calls >= 1);
+//! assert!(blur.bytes >= 2 * 64 * 64);
+//! ```
+
+use std::collections::BTreeMap;
+use std::mem;
+use std::ops::Deref;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use buffer::ImageBuffer;
+use traits::Color as Pixel;
+
+/// The statistics of one operation.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct OpTiming {
+    /// The name of the operation, usually the name of the method.
+    pub name: &'static str,
+    /// The number of calls.
+    pub calls: u64,
+    /// The total wall time of all calls.
+    pub total: Duration,
+    /// The total number of bytes read and written by all calls.
+    pub bytes: u64,
+}
+
+impl OpTiming {
+    /// The mean wall time of a call.
+    pub fn mean(&self) -> Duration {
+        if self.calls == 0 {
+            return Duration::default();
+        }
+        self.total / self.calls as u32
+    }
+}
+
+/// A snapshot of the statistics of all operations called since the last `reset`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Report {
+    /// The operations, the most time consuming first.
+    pub ops: Vec<OpTiming>,
+}
+
+impl Report {
+    /// Returns the statistics of the operation `name`, or None if it was not called.
+    pub fn get(&self, name: &str) -> Option<&OpTiming> {
+        self.ops.iter().find(|op| op.name == name)
+    }
+
+    /// The total wall time of all operations, counting nested operations multiple times.
+    pub fn total(&self) -> Duration {
+        self.ops.iter().map(|op| op.total).sum()
+    }
+}
+
+static REGISTRY: Mutex<BTreeMap<&'static str, OpTiming>> = Mutex::new(BTreeMap::new());
+
+/// Returns the statistics recorded since the last `reset`.
+pub fn report() -> Report {
+    let registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+    let mut ops: Vec<OpTiming> = registry.values().cloned().collect();
+    ops.sort_by(|a, b| b.total.cmp(&a.total).then(a.name.cmp(b.name)));
+    Report { ops }
+}
+
+/// Discards all recorded statistics.
+pub fn reset() {
+    REGISTRY.lock().unwrap_or_else(|e| e.into_inner()).clear();
+}
+
+/// Records an operation when dropped.
+pub(crate) struct Span {
+    name: &'static str,
+    bytes: u64,
+    start: Instant,
+}
+
+impl Span {
+    pub(crate) fn new(name: &'static str, bytes: u64) -> Span {
+        Span {
+            name,
+            bytes,
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        let elapsed = self.start.elapsed();
+        let mut registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+        let op = registry.entry(self.name).or_insert(OpTiming {
+            name: self.name,
+            calls: 0,
+            total: Duration::default(),
+            bytes: 0,
+        });
+        op.calls += 1;
+        op.total += elapsed;
+        op.bytes += self.bytes;
+    }
+}
+
+/// The number of bytes of the pixels of `image`, without padding.
+pub(crate) fn image_bytes<P, C>(image: &ImageBuffer<P, C>) -> u64
+    where P: Pixel,
+          C: Deref<Target = [P::Subpixel]>
+{
+    pixel_bytes::<P>(image.width(), image.height())
+}
+
+/// The number of bytes of `width`×`height` pixels of type `P`.
+pub(crate) fn pixel_bytes<P: Pixel>(width: u32, height: u32) -> u64 {
+    width as u64 * height as u64 * (P::channel_count() * mem::size_of::<P::Subpixel>()) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{report, OpTiming, Span};
+
+    #[test]
+    fn test_span() {
+        {
+            let _outer = Span::new("test_span", 10);
+            let _inner = Span::new("test_span", 5);
+        }
+        let report = report();
+        let op = report.get("test_span").unwrap();
+        assert_eq!((op.calls, op.bytes), (2, 15));
+        assert!(report.get("test_missing").is_none());
+        assert!(report.total() >= op.total);
+
+        let timing = OpTiming {
+            name: "x",
+            calls: 4,
+            total: Duration::from_millis(10),
+            bytes: 0,
+        };
+        assert_eq!(timing.mean(), Duration::from_micros(2500));
+    }
+}
@@ -0,0 +1,147 @@
+//! Image registration.
+
+use std::f64::consts::PI;
+use std::ops::Deref;
+
+use buffer::ImageBuffer;
+use color_model::Gray;
+use fft::fft_2d;
+use math::to_f32;
+use traits::Primitive;
+
+/// Copies the image, minus its mean and multiplied with a Hann window, into the top left
+/// corner of a zero padded `width`×`height` array.
+fn windowed<T, C>(image: &ImageBuffer<Gray<T>, C>, width: usize, height: usize) -> Vec<f64>
+    where T: Primitive,
+          C: Deref<Target = [T]>
+{
+    let (w, h) = (image.width() as usize, image.height() as usize);
+    let mean = image.pixels().map(|p| to_f32(p[0]) as f64).sum::<f64>() / (w * h) as f64;
+    let hann = |i: usize, n: usize| 0.5 - 0.5 * (2.0 * PI * (i as f64 + 0.5) / n as f64).cos();
+    let mut values = vec![0.0; width * height];
+    for (x, y, p) in image.enumerate_pixels() {
+        let (x, y) = (x as usize, y as usize);
+        values[y * width + x] = (to_f32(p[0]) as f64 - mean) * hann(x, w) * hann(y, h);
+    }
+    values
+}
+
+/// Returns the subpixel offset of the maximum of a parabola through `(-1, l)`, `(0, c)` and
+/// `(1, r)`.
+fn parabolic_peak(l: f64, c: f64, r: f64) -> f64 {
+    let curvature = l - 2.0 * c + r;
+    if curvature >= 0.0 {
+        return 0.0;
+    }
+    ((l - r) / (2.0 * curvature)).clamp(-0.5, 0.5)
+}
+
+/// Estimates the translation `(dx, dy)` that moves the content of `a` onto `b` by phase
+/// correlation.
+///
+/// That is, `b(x, y)` is approximately `a(x - dx, y - dy)`. The images are windowed and zero
+/// padded to a power of two. The location of the correlation peak is refined to subpixel
+/// accuracy by fitting a parabola in each direction. Shifts of more than half the image size
+/// cannot be distinguished from shifts in the opposite direction.
+///
+/// # Panics
+///
+/// If the images have different dimensions or are empty.
+///
+/// # Examples
+///
+/// ```
+/// # use image_buffer::{GrayImage, test_images};
+/// use image_buffer::registration::register_translation;
+/// let scene: GrayImage = test_images::noise(96, 96, 1).convert_buffer();
+/// let a = GrayImage::from_fn(64, 64, |x, y| scene[(x + 16, y + 16)]);
+/// let b = GrayImage::from_fn(64, 64, |x, y| scene[(x + 12, y + 19)]);
+/// let (dx, dy) = register_translation(&a, &b);
+/// assert!((dx - 4.0).abs() < 0.1 && (dy + 3.0).abs() < 0.1);
+/// ```
+pub fn register_translation<T, C1, C2>(a: &ImageBuffer<Gray<T>, C1>,
+                                       b: &ImageBuffer<Gray<T>, C2>)
+                                       -> (f64, f64)
+    where T: Primitive,
+          C1: Deref<Target = [T]>,
+          C2: Deref<Target = [T]>
+{
+    assert_eq!(a.dimensions(), b.dimensions(), "the images must have the same dimensions");
+    assert!(a.width() > 0 && a.height() > 0, "the images must not be empty");
+    let width = (a.width() as usize).next_power_of_two();
+    let height = (a.height() as usize).next_power_of_two();
+
+    let (mut re_a, mut im_a) = (windowed(a, width, height), vec![0.0; width * height]);
+    let (mut re_b, mut im_b) = (windowed(b, width, height), vec![0.0; width * height]);
+    fft_2d(&mut re_a, &mut im_a, width, height, false);
+    fft_2d(&mut re_b, &mut im_b, width, height, false);
+
+    // Normalized cross-power spectrum `B * conj(A) / |B * conj(A)|`
+    for i in 0..width * height {
+        let re = re_b[i] * re_a[i] + im_b[i] * im_a[i];
+        let im = im_b[i] * re_a[i] - re_b[i] * im_a[i];
+        let magnitude = re.hypot(im);
+        let (re, im) = if magnitude > 1e-12 {
+            (re / magnitude, im / magnitude)
+        } else {
+            (0.0, 0.0)
+        };
+        re_a[i] = re;
+        im_a[i] = im;
+    }
+    fft_2d(&mut re_a, &mut im_a, width, height, true);
+    let correlation = re_a;
+
+    let peak = (0..width * height)
+        .max_by(|&i, &j| correlation[i].total_cmp(&correlation[j]))
+        .unwrap();
+    let (px, py) = (peak % width, peak / width);
+    let at = |x: usize, y: usize| correlation[(y % height) * width + x % width];
+    let fx = parabolic_peak(at(px + width - 1, py), at(px, py), at(px + 1, py));
+    let fy = parabolic_peak(at(px, py + height - 1), at(px, py), at(px, py + 1));
+    let wrap = |p: usize, n: usize| if p > n / 2 {
+        p as f64 - n as f64
+    } else {
+        p as f64
+    };
+    (wrap(px, width) + fx, wrap(py, height) + fy)
+}
+
+#[cfg(test)]
+mod tests {
+    use buffer::GrayImage;
+    use color_model::Gray;
+    use test_images::noise;
+    use super::register_translation;
+
+    #[test]
+    fn test_integer_shift() {
+        let scene: GrayImage = noise(100, 80, 5).convert_buffer();
+        let crop = |x0: i32, y0: i32| {
+            GrayImage::from_fn(50, 40, |x, y| scene[(x + x0 as u32, y + y0 as u32)])
+        };
+        let a = crop(20, 20);
+        for &(dx, dy) in &[(0, 0), (7, -5), (-11, 3)] {
+            let b = crop(20 - dx, 20 - dy);
+            let (ex, ey) = register_translation(&a, &b);
+            assert!((ex - dx as f64).abs() < 0.1 && (ey - dy as f64).abs() < 0.1,
+                    "({}, {}) != ({}, {})",
+                    ex,
+                    ey,
+                    dx,
+                    dy);
+        }
+    }
+
+    #[test]
+    fn test_half_pixel_shift() {
+        let scene: GrayImage = noise(80, 80, 9).convert_buffer();
+        let a = GrayImage::from_fn(64, 64, |x, y| scene[(x + 8, y + 8)]);
+        let b = GrayImage::from_fn(64, 64, |x, y| {
+            let (p, q) = (scene[(x + 8, y + 5)][0] as u16, scene[(x + 7, y + 5)][0] as u16);
+            Gray::new([((p + q) / 2) as u8])
+        });
+        let (dx, dy) = register_translation(&a, &b);
+        assert!((dx - 0.5).abs() < 0.2 && (dy - 3.0).abs() < 0.1, "({}, {})", dx, dy);
+    }
+}
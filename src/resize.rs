@@ -0,0 +1,365 @@
+//! Resampling of images.
+
+use std::convert::TryFrom;
+use std::ops::Deref;
+
+use buffer::ImageBuffer;
+use color_model::ColorSpace;
+use math::{from_f32, to_f32};
+use traits::{ChannelMax, Color as Pixel, ImageViewMut};
+
+/// The reconstruction filter used by `ImageBuffer::resize`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Filter {
+    /// Takes the nearest pixel. Fast, but blocky when enlarging and aliased when shrinking.
+    Nearest,
+    /// Interpolates linearly between the four nearest pixels. Aliased when shrinking to less
+    /// than half the size.
+    Bilinear,
+    /// Linear interpolation which widens the filter when shrinking, such that every source
+    /// pixel contributes to the result.
+    Triangle,
+    /// Averages the source pixels covered by a target pixel, weighted by the covered area.
+    /// Fast and sharp when shrinking, blocky when enlarging.
+    Box,
+}
+
+/// Contribution of the source pixels `start..start + weights.len()` to a target pixel.
+struct Contribution {
+    start: usize,
+    weights: Vec<f32>,
+}
+
+/// Computes the contributions of a triangle filter of radius `scale` resampling `src` pixels
+/// to `dst` pixels.
+fn contributions(src: u32, dst: u32, scale: f32) -> Vec<Contribution> {
+    let ratio = src as f32 / dst as f32;
+    (0..dst)
+        .map(|i| {
+            let center = (i as f32 + 0.5) * ratio;
+            let first = ((center - scale).floor().max(0.0)) as usize;
+            let last = ((center + scale).ceil() as usize).min(src as usize);
+            let mut weights: Vec<f32> = (first..last)
+                .map(|j| (1.0 - ((j as f32 + 0.5 - center) / scale).abs()).max(0.0))
+                .collect();
+            let sum: f32 = weights.iter().sum();
+            if sum == 0.0 {
+                // No pixel is in reach of the filter, take the nearest one
+                return Contribution {
+                    start: (center as usize).min(src as usize - 1),
+                    weights: vec![1.0],
+                };
+            }
+            for w in weights.iter_mut() {
+                *w /= sum;
+            }
+            Contribution {
+                start: first,
+                weights,
+            }
+        })
+        .collect()
+}
+
+/// Computes the contributions of the area covered by every target pixel resampling `src`
+/// pixels to `dst` pixels.
+fn box_contributions(src: u32, dst: u32) -> Vec<Contribution> {
+    let ratio = src as f64 / dst as f64;
+    (0..dst)
+        .map(|i| {
+            let (left, right) = (i as f64 * ratio, (i + 1) as f64 * ratio);
+            let first = (left.floor() as usize).min(src as usize - 1);
+            let last = (right.ceil() as usize).clamp(first + 1, src as usize);
+            let weights = (first..last)
+                .map(|j| ((right.min(j as f64 + 1.0) - left.max(j as f64)) / ratio) as f32)
+                .collect();
+            Contribution {
+                start: first,
+                weights,
+            }
+        })
+        .collect()
+}
+
+impl<P, Container> ImageBuffer<P, Container>
+    where P: Pixel,
+          P::Subpixel: ChannelMax,
+          Container: Deref<Target = [P::Subpixel]>
+{
+    /// Returns a copy of the image resampled to `width`×`height` pixels with `filter`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{GrayImage, color};
+    /// use image_buffer::resize::Filter;
+    /// let image = GrayImage::from_raw(2, 1, vec![0, 255]).unwrap();
+    /// assert_eq!(image.resize(4, 1, Filter::Nearest).into_raw(), [0, 0, 255, 255]);
+    /// assert_eq!(image.resize(4, 1, Filter::Bilinear).into_raw(), [0, 64, 191, 255]);
+    /// assert_eq!(image.resize(1, 1, Filter::Triangle).into_raw(), [128]);
+    /// ```
+    pub fn resize(&self,
+                  width: u32,
+                  height: u32,
+                  filter: Filter)
+                  -> ImageBuffer<P, Vec<P::Subpixel>> {
+        self.resize_in(width, height, filter, ColorSpace::Linear)
+    }
+
+    /// Like `resize` but interprets the color channels according to `space`.
+    ///
+    /// With `ColorSpace::Srgb` the pixels are averaged in linear light, which keeps the
+    /// brightness of fine detail and of edges between saturated colors.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{GrayImage, color::{ColorSpace, Gray}};
+    /// use image_buffer::resize::Filter;
+    /// let checkers = GrayImage::from_fn(2, 2, |x, y| Gray::new([((x + y) % 2 * 255) as u8]));
+    /// let average = |space| checkers.resize_in(1, 1, Filter::Triangle, space)[(0, 0)][0];
+    /// assert_eq!(average(ColorSpace::Linear), 128);
+    /// assert_eq!(average(ColorSpace::Srgb), 188);
+    /// ```
+    pub fn resize_in(&self,
+                     width: u32,
+                     height: u32,
+                     filter: Filter,
+                     space: ColorSpace)
+                     -> ImageBuffer<P, Vec<P::Subpixel>> {
+        profile_op!("resize",
+                    ::profiling::image_bytes(self) + ::profiling::pixel_bytes::<P>(width, height));
+        let (src_width, src_height) = self.dimensions();
+        if src_width == 0 || src_height == 0 || width == 0 || height == 0 {
+            return ImageBuffer::new(width, height);
+        }
+        let scale = |src: u32, dst: u32| match filter {
+            Filter::Nearest | Filter::Bilinear | Filter::Box => 1.0,
+            Filter::Triangle => (src as f32 / dst as f32).max(1.0),
+        };
+        if filter == Filter::Nearest {
+            return ImageBuffer::from_fn(width, height, |x, y| {
+                let sx = ((x as u64 * src_width as u64) / width as u64) as u32;
+                let sy = ((y as u64 * src_height as u64) / height as u64) as u32;
+                self[(sx, sy)]
+            });
+        }
+        let channels = P::channel_count();
+        let alpha = P::layout().alpha_position();
+        let max = to_f32(P::Subpixel::channel_max());
+        let decode = |c: usize, v: P::Subpixel| if Some(c) == alpha {
+            to_f32(v)
+        } else {
+            space.decode(to_f32(v) / max) * max
+        };
+        let encode = |c: usize, v: f32| if Some(c) == alpha {
+            from_f32(v)
+        } else {
+            from_f32(space.encode(v / max) * max)
+        };
+        let contributions = |src: u32, dst: u32| match filter {
+            Filter::Box => box_contributions(src, dst),
+            _ => contributions(src, dst, scale(src, dst)),
+        };
+        let horizontal = contributions(src_width, width);
+        let vertical = contributions(src_height, height);
+        // Resample the rows into an intermediate image of floats
+        let mut rows = vec![0.0f32; width as usize * src_height as usize * channels];
+        for (row, out) in self.subpixel_rows().zip(rows.chunks_mut(width as usize * channels)) {
+            for (c, out) in horizontal.iter().zip(out.chunks_mut(channels)) {
+                for (p, &w) in row[c.start * channels..].chunks(channels).zip(c.weights.iter()) {
+                    for (i, (o, &v)) in out.iter_mut().zip(p).enumerate() {
+                        *o += decode(i, v) * w;
+                    }
+                }
+            }
+        }
+        let mut buffer: ImageBuffer<P, Vec<P::Subpixel>> = ImageBuffer::new(width, height);
+        let row_len = width as usize * channels;
+        for (c, out) in vertical.iter().zip(buffer.subpixel_rows_mut()) {
+            let mut sums = vec![0.0f32; row_len];
+            for (j, &w) in c.weights.iter().enumerate() {
+                let start = (c.start + j) * row_len;
+                for (s, &v) in sums.iter_mut().zip(rows[start..start + row_len].iter()) {
+                    *s += v * w;
+                }
+            }
+            for (i, (o, &s)) in out.iter_mut().zip(sums.iter()).enumerate() {
+                *o = encode(i % channels, s);
+            }
+        }
+        buffer
+    }
+
+    /// Returns a copy of the image shrunk to fit into `max_width`×`max_height` pixels while
+    /// keeping its aspect ratio, images which already fit are copied unchanged. Returns an
+    /// empty image if a maximum is zero.
+    ///
+    /// Every target pixel is the average of the source pixels it covers, as by `Filter::Box`,
+    /// which is fast and keeps fine detail from aliasing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{GrayImage, color::Gray};
+    /// let stripes = GrayImage::from_fn(400, 300, |x, _| Gray::new([(x % 2 * 200) as u8]));
+    /// let thumbnail = stripes.thumbnail(100, 100);
+    /// assert_eq!(thumbnail.dimensions(), (100, 75));
+    /// assert!(thumbnail.pixels().all(|p| p[0] == 100));
+    /// ```
+    pub fn thumbnail(&self, max_width: u32, max_height: u32) -> ImageBuffer<P, Vec<P::Subpixel>> {
+        let (width, height) = self.dimensions();
+        if width <= max_width && height <= max_height {
+            return self.to_vec();
+        }
+        if max_width == 0 || max_height == 0 {
+            return ImageBuffer::new(0, 0);
+        }
+        let scale = f64::min(max_width as f64 / width as f64, max_height as f64 / height as f64);
+        let fit = |len: u32, max: u32| ((len as f64 * scale).round() as u32).max(1).min(max);
+        self.resize(fit(width, max_width), fit(height, max_height), Filter::Box)
+    }
+}
+
+/// Scales `frames` to a uniform `height` and places them side by side, separated by columns
+/// of `separator_width` pixels of `separator`.
+///
+/// The frames keep their aspect ratio and are resampled in linear light, as by `resize_in`
+/// with `ColorSpace::Srgb`, such that the thumbnails keep the brightness of fine detail.
+///
+/// # Panics
+///
+/// Panics with "the width of the filmstrip overflows u32" if the scaled frames and the
+/// separators together are wider than `u32::MAX` pixels.
+///
+/// # Examples
+///
+/// ```
+/// # use image_buffer::{GrayImage, color::Gray};
+/// use image_buffer::resize::filmstrip;
+/// let wide = GrayImage::from_pixel(40, 20, Gray::new([200]));
+/// let tall = GrayImage::from_pixel(10, 20, Gray::new([100]));
+/// let strip = filmstrip(&[&wide, &tall], 10, 2, Gray::new([0]));
+/// assert_eq!(strip.dimensions(), (20 + 2 + 5, 10));
+/// assert_eq!(strip[(20, 5)], Gray::new([0]));
+/// assert_eq!(strip[(26, 9)], Gray::new([100]));
+/// ```
+pub fn filmstrip<P, C>(frames: &[&ImageBuffer<P, C>],
+                       height: u32,
+                       separator_width: u32,
+                       separator: P)
+                       -> ImageBuffer<P, Vec<P::Subpixel>>
+    where P: Pixel,
+          P::Subpixel: ChannelMax,
+          C: Deref<Target = [P::Subpixel]>
+{
+    const OVERFLOW: &str = "the width of the filmstrip overflows u32";
+    let widths: Vec<u32> = frames.iter()
+        .map(|frame| {
+            let (w, h) = frame.dimensions();
+            if h == 0 {
+                return 0;
+            }
+            let width = ((w as u64 * height as u64 + h as u64 / 2) / h as u64).max(1);
+            u32::try_from(width).expect(OVERFLOW)
+        })
+        .collect();
+    let separators = u32::try_from(frames.len().saturating_sub(1))
+        .ok()
+        .and_then(|n| n.checked_mul(separator_width));
+    let total_width = separators
+        .and_then(|separators| widths.iter().try_fold(separators, |sum, &w| sum.checked_add(w)))
+        .expect(OVERFLOW);
+    let mut strip = ImageBuffer::from_pixel(total_width, height, separator);
+    let mut x = 0u32;
+    for (frame, &width) in frames.iter().zip(&widths) {
+        let thumbnail = frame.resize_in(width, height, Filter::Triangle, ColorSpace::Srgb);
+        strip.copy_view(&thumbnail, x, 0);
+        // Only saturates behind the last frame
+        x = x.saturating_add(width).saturating_add(separator_width);
+    }
+    strip
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{filmstrip, Filter};
+    use buffer::{GrayAlphaImage, GrayImage, RgbImage};
+    use color_model::{ColorSpace, Gray, GrayA, Rgb};
+
+    #[test]
+    fn test_constant_image() {
+        let image = RgbImage::from_pixel(7, 5, Rgb::new([10, 20, 30]));
+        for &filter in &[Filter::Nearest, Filter::Bilinear, Filter::Triangle, Filter::Box] {
+            for &(w, h) in &[(3, 2), (14, 11), (1, 1)] {
+                let resized = image.resize(w, h, filter);
+                assert_eq!(resized.dimensions(), (w, h));
+                assert!(resized.pixels().all(|&p| p == Rgb::new([10, 20, 30])));
+            }
+        }
+    }
+
+    #[test]
+    fn test_linear_light() {
+        // Alpha stays linear, only the color channels are decoded
+        let image = GrayAlphaImage::from_fn(2, 1, |x, _| GrayA::new([x as u8 * 255; 2]));
+        let half = image.resize_in(1, 1, Filter::Triangle, ColorSpace::Srgb);
+        assert_eq!(half[(0, 0)], GrayA::new([188, 128]));
+        let wide = image.resize_in(4, 1, Filter::Bilinear, ColorSpace::Srgb);
+        assert_eq!(wide[(0, 0)], GrayA::new([0, 0]));
+        assert_eq!(wide[(3, 0)], GrayA::new([255, 255]));
+    }
+
+    #[test]
+    fn test_triangle_antialiasing() {
+        // Alternating columns average out when shrinking
+        let stripes = GrayImage::from_fn(16, 1, |x, _| Gray::new([(x % 2 * 200) as u8]));
+        let small = stripes.resize(4, 1, Filter::Triangle);
+        assert!(small.pixels().all(|p| (p[0] as i32 - 100).abs() <= 15));
+    }
+
+    #[test]
+    fn test_box() {
+        let image = GrayImage::from_raw(3, 1, vec![0, 90, 180]).unwrap();
+        assert_eq!(image.resize(2, 1, Filter::Box).into_raw(), vec![30, 150]);
+        assert_eq!(image.resize(6, 1, Filter::Box).into_raw(), vec![0, 0, 90, 90, 180, 180]);
+        assert_eq!(image.resize(1, 1, Filter::Box).into_raw(), vec![90]);
+
+        assert_eq!(image.thumbnail(10, 10).into_raw(), vec![0, 90, 180]);
+        assert_eq!(image.thumbnail(1, 10).dimensions(), (1, 1));
+        assert_eq!(image.thumbnail(0, 10).dimensions(), (0, 0));
+        let tall = RgbImage::from_pixel(10, 1000, Rgb::new([1, 2, 3]));
+        let thumbnail = tall.thumbnail(64, 64);
+        assert_eq!(thumbnail.dimensions(), (1, 64));
+        assert!(thumbnail.pixels().all(|&p| p == Rgb::new([1, 2, 3])));
+    }
+
+    #[test]
+    fn test_filmstrip() {
+        let frame = RgbImage::from_pixel(16, 9, Rgb::new([10, 20, 30]));
+        let empty = RgbImage::new(5, 0);
+        let strip = filmstrip(&[&frame, &empty, &frame], 18, 1, Rgb::new([255, 255, 255]));
+        assert_eq!(strip.dimensions(), (32 + 1 + 1 + 32, 18));
+        assert_eq!(strip[(32, 0)], Rgb::new([255, 255, 255]));
+        assert_eq!(strip[(34, 17)], Rgb::new([10, 20, 30]));
+        assert_eq!(filmstrip::<Rgb<u8>, Vec<u8>>(&[], 18, 1, Rgb::new([0; 3])).dimensions(),
+                   (0, 18));
+        // Thumbnails are at least one pixel wide
+        let line = GrayImage::new(1, 100);
+        assert_eq!(filmstrip(&[&line], 10, 0, Gray::new([0])).dimensions(), (1, 10));
+    }
+
+    #[test]
+    #[should_panic(expected = "the width of the filmstrip overflows u32")]
+    fn test_filmstrip_overflow() {
+        let frame = GrayImage::new(1, 1);
+        filmstrip(&[&frame, &frame], 1 << 31, 0, Gray::new([0]));
+    }
+
+    #[test]
+    #[should_panic(expected = "the width of the filmstrip overflows u32")]
+    fn test_filmstrip_separator_overflow() {
+        let frame = GrayImage::new(1, 1);
+        filmstrip(&[&frame, &frame, &frame], 1, 1 << 31, Gray::new([0]));
+    }
+}
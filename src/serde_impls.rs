@@ -0,0 +1,240 @@
+//! `Serialize` and `Deserialize` implementations, enabled by the `serde` feature.
+//!
+//! Colors are serialized as tuples of their channels, image buffers as a struct with the fields
+//! `width`, `height` and `data`. The data contains the subpixels row by row without padding,
+//! whatever the stride of the serialized buffer.
+
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::Deref;
+
+use num_traits::Zero;
+use serde::{Deserialize, Deserializer};
+use serde::de::{self, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeStruct, SerializeTuple, Serializer};
+
+use buffer::ImageBuffer;
+use color_model::{Alpha, PremultipliedAlpha};
+use traits::Color as Pixel;
+
+fn serialize_channels<S, T>(channels: &[T], serializer: S) -> Result<S::Ok, S::Error>
+    where S: Serializer,
+          T: Serialize
+{
+    let mut tuple = serializer.serialize_tuple(channels.len())?;
+    for channel in channels {
+        tuple.serialize_element(channel)?;
+    }
+    tuple.end()
+}
+
+/// Deserializes a tuple of `N` channels.
+struct ChannelsVisitor<T, const N: usize>(PhantomData<T>);
+
+impl<'de, T, const N: usize> Visitor<'de> for ChannelsVisitor<T, N>
+    where T: Deserialize<'de> + Zero + Copy
+{
+    type Value = [T; N];
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a tuple of {} channels", N)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<[T; N], A::Error> {
+        let mut channels = [T::zero(); N];
+        for (i, channel) in channels.iter_mut().enumerate() {
+            *channel = seq.next_element()?.ok_or_else(|| de::Error::invalid_length(i, &self))?;
+        }
+        Ok(channels)
+    }
+}
+
+fn deserialize_channels<'de, D, T, const N: usize>(deserializer: D) -> Result<[T; N], D::Error>
+    where D: Deserializer<'de>,
+          T: Deserialize<'de> + Zero + Copy
+{
+    deserializer.deserialize_tuple(N, ChannelsVisitor(PhantomData))
+}
+
+impl<C, const N: usize> Serialize for Alpha<C, N>
+    where C: Pixel,
+          C::Subpixel: Serialize
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_channels(self.as_ref(), serializer)
+    }
+}
+
+impl<'de, C, const N: usize> Deserialize<'de> for Alpha<C, N>
+    where C: Pixel,
+          C::Subpixel: Deserialize<'de>
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_channels(deserializer).map(Alpha::new)
+    }
+}
+
+impl<C, const N: usize> Serialize for PremultipliedAlpha<C, N>
+    where C: Pixel,
+          C::Subpixel: Serialize
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_channels(self.as_ref(), serializer)
+    }
+}
+
+impl<'de, C, const N: usize> Deserialize<'de> for PremultipliedAlpha<C, N>
+    where C: Pixel,
+          C::Subpixel: Deserialize<'de>
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_channels(deserializer).map(PremultipliedAlpha::new)
+    }
+}
+
+/// The subpixels of a buffer without padding.
+struct PackedData<'a, P: Pixel + 'a, C: 'a>(&'a ImageBuffer<P, C>)
+    where C: Deref<Target = [P::Subpixel]>;
+
+impl<'a, P, C> Serialize for PackedData<'a, P, C>
+    where P: Pixel,
+          P::Subpixel: Serialize,
+          C: Deref<Target = [P::Subpixel]>
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.subpixel_rows().flatten())
+    }
+}
+
+impl<P, C> Serialize for ImageBuffer<P, C>
+    where P: Pixel,
+          P::Subpixel: Serialize,
+          C: Deref<Target = [P::Subpixel]>
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("ImageBuffer", 3)?;
+        state.serialize_field("width", &self.width())?;
+        state.serialize_field("height", &self.height())?;
+        state.serialize_field("data", &PackedData(self))?;
+        state.end()
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "ImageBuffer")]
+struct RawBuffer<T> {
+    width: u32,
+    height: u32,
+    data: Vec<T>,
+}
+
+impl<'de, P> Deserialize<'de> for ImageBuffer<P, Vec<P::Subpixel>>
+    where P: Pixel,
+          P::Subpixel: Deserialize<'de>
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = RawBuffer::deserialize(deserializer)?;
+        ImageBuffer::from_vec(raw.width, raw.height, raw.data).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Deserializer};
+    use serde_test::{assert_de_tokens, assert_de_tokens_error, assert_ser_tokens, assert_tokens,
+                     Token};
+
+    use buffer::{GrayImage, RgbaImage};
+    use color_model::{ColorType, PremultipliedRgba, Rgb, Rgba};
+
+    /// The dimensions and the data of a deserialized `RgbaImage`, which can be compared.
+    #[derive(PartialEq, Debug)]
+    struct Raw((u32, u32), Vec<u8>);
+
+    impl<'de> Deserialize<'de> for Raw {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let image = RgbaImage::deserialize(deserializer)?;
+            Ok(Raw(image.dimensions(), image.into_raw()))
+        }
+    }
+
+    #[test]
+    fn test_colors() {
+        assert_tokens(&Rgb::new([1u8, 2, 3]),
+                      &[Token::NewtypeStruct { name: "Rgb" },
+                        Token::Tuple { len: 3 },
+                        Token::U8(1),
+                        Token::U8(2),
+                        Token::U8(3),
+                        Token::TupleEnd]);
+        assert_tokens(&Rgba::new([1u16, 2, 3, 65535]),
+                      &[Token::Tuple { len: 4 },
+                        Token::U16(1),
+                        Token::U16(2),
+                        Token::U16(3),
+                        Token::U16(65535),
+                        Token::TupleEnd]);
+        assert_de_tokens(&PremultipliedRgba::new([0.5f32, 0.0, 0.0, 0.5]),
+                         &[Token::Tuple { len: 4 },
+                           Token::F32(0.5),
+                           Token::F32(0.0),
+                           Token::F32(0.0),
+                           Token::F32(0.5),
+                           Token::TupleEnd]);
+        assert_de_tokens_error::<Rgba<u8>>(&[Token::Tuple { len: 3 },
+                                             Token::U8(1),
+                                             Token::U8(2),
+                                             Token::U8(3),
+                                             Token::TupleEnd],
+                                           "invalid length 3, expected a tuple of 4 channels");
+
+        assert_tokens(&ColorType::Gray(16),
+                      &[Token::NewtypeVariant {
+                            name: "ColorType",
+                            variant: "Gray",
+                        },
+                        Token::U8(16)]);
+    }
+
+    #[test]
+    fn test_buffer() {
+        let image = RgbaImage::from_fn(1, 2, |_, y| Rgba::new([y as u8, 1, 7, 255]));
+        let data = [0, 1, 7, 255, 1, 1, 7, 255];
+        let mut tokens = vec![Token::Struct {
+                                  name: "ImageBuffer",
+                                  len: 3,
+                              },
+                              Token::Str("width"),
+                              Token::U32(1),
+                              Token::Str("height"),
+                              Token::U32(2),
+                              Token::Str("data"),
+                              Token::Seq { len: None }];
+        tokens.extend(data.iter().map(|&v| Token::U8(v)));
+        tokens.extend_from_slice(&[Token::SeqEnd, Token::StructEnd]);
+        assert_ser_tokens(&image, &tokens);
+        assert_de_tokens(&Raw((1, 2), data.to_vec()), &tokens);
+
+        // The padding of strided buffers is not serialized
+        let strided = GrayImage::from_raw_with_stride(2, 2, 3, vec![1, 2, 0, 3, 4]).unwrap();
+        let mut tokens = vec![Token::Struct {
+                                  name: "ImageBuffer",
+                                  len: 3,
+                              },
+                              Token::Str("width"),
+                              Token::U32(2),
+                              Token::Str("height"),
+                              Token::U32(2),
+                              Token::Str("data"),
+                              Token::Seq { len: None }];
+        tokens.extend((1..5).map(Token::U8));
+        tokens.extend_from_slice(&[Token::SeqEnd, Token::StructEnd]);
+        assert_ser_tokens(&strided, &tokens);
+
+        // One subpixel short
+        tokens.remove(10);
+        assert_de_tokens_error::<GrayImage>(&tokens,
+                                            "container of 3 subpixels is smaller than the \
+                                             image (4)");
+    }
+}
@@ -0,0 +1,137 @@
+//! Statistics of the channels of an image.
+
+use std::ops::Deref;
+
+use num_traits::NumCast;
+
+use buffer::ImageBuffer;
+use traits::Color as Pixel;
+
+/// The statistics of one channel of an image.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ChannelStats<T> {
+    /// The smallest value.
+    pub min: T,
+    /// The largest value.
+    pub max: T,
+    /// The arithmetic mean.
+    pub mean: f64,
+    /// The population standard deviation.
+    pub std_dev: f64,
+}
+
+impl<P, Container> ImageBuffer<P, Container>
+    where P: Pixel,
+          Container: Deref<Target = [P::Subpixel]>
+{
+    /// Returns the minimum, maximum, mean and standard deviation of every channel, in the order
+    /// of the channels of `P`.
+    ///
+    /// All statistics are computed in one pass over the image. Returns None if the image is
+    /// empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{GrayAlphaImage, color::GrayA};
+    /// let image = GrayAlphaImage::from_fn(4, 1, |x, _| GrayA::new([x as u8 * 2, 255]));
+    /// let stats = image.channel_stats().unwrap();
+    /// assert_eq!((stats[0].min, stats[0].max, stats[0].mean), (0, 6, 3.0));
+    /// assert_eq!(stats[0].std_dev, 5.0f64.sqrt());
+    /// assert_eq!((stats[1].mean, stats[1].std_dev), (255.0, 0.0));
+    /// ```
+    pub fn channel_stats(&self) -> Option<Vec<ChannelStats<P::Subpixel>>> {
+        let first = *self.pixels().next()?;
+        let mut stats: Vec<_> = first.channels()
+            .as_ref()
+            .iter()
+            .map(|&v| {
+                ChannelStats {
+                    min: v,
+                    max: v,
+                    mean: 0.0,
+                    std_dev: 0.0,
+                }
+            })
+            .collect();
+        // Welford's algorithm, `std_dev` holds the sum of squared differences until the end
+        let mut count = 0.0;
+        for p in self.pixels() {
+            count += 1.0;
+            for (s, &v) in stats.iter_mut().zip(p.channels().as_ref()) {
+                if v < s.min {
+                    s.min = v;
+                }
+                if v > s.max {
+                    s.max = v;
+                }
+                let value: f64 = NumCast::from(v).unwrap();
+                let delta = value - s.mean;
+                s.mean += delta / count;
+                s.std_dev += delta * (value - s.mean);
+            }
+        }
+        for s in &mut stats {
+            s.std_dev = (s.std_dev / count).sqrt();
+        }
+        Some(stats)
+    }
+
+    /// Returns the pixels holding the minimum and the maximum of every channel, or None if the
+    /// image is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{ImageBuffer, color::Rgb};
+    /// let image: ImageBuffer<Rgb<f32>, _> =
+    ///     ImageBuffer::from_raw(2, 1, vec![0.5, -1.0, 2.0, 0.25, 3.0, 2.0]).unwrap();
+    /// let (min, max) = image.min_max().unwrap();
+    /// assert_eq!((min, max), (Rgb::new([0.25, -1.0, 2.0]), Rgb::new([0.5, 3.0, 2.0])));
+    /// ```
+    pub fn min_max(&self) -> Option<(P, P)> {
+        let first = *self.pixels().next()?;
+        let (mut min, mut max) = (first, first);
+        for p in self.pixels() {
+            let channels = p.channels().as_ref();
+            let lower = min.channels_mut().as_mut().iter_mut();
+            let upper = max.channels_mut().as_mut().iter_mut();
+            for ((lo, hi), &v) in lower.zip(upper).zip(channels) {
+                if v < *lo {
+                    *lo = v;
+                }
+                if v > *hi {
+                    *hi = v;
+                }
+            }
+        }
+        Some((min, max))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buffer::{GrayImage, ImageBuffer, RgbImage};
+    use color_model::{Gray, Rgb};
+
+    #[test]
+    fn test_channel_stats() {
+        let data = vec![1u8, 9, 200, 0, 3, 7, 100, 0, 99];
+        let image = RgbImage::from_raw_with_stride(1, 2, 4, data).unwrap();
+        let stats = image.channel_stats().unwrap();
+        assert_eq!(stats.len(), 3);
+        assert_eq!((stats[0].min, stats[0].max, stats[0].mean, stats[0].std_dev),
+                   (1, 3, 2.0, 1.0));
+        assert_eq!((stats[2].min, stats[2].max, stats[2].mean), (100, 200, 150.0));
+        assert_eq!(image.min_max(), Some((Rgb::new([1, 7, 100]), Rgb::new([3, 9, 200]))));
+
+        assert!(GrayImage::new(0, 3).channel_stats().is_none());
+        assert!(GrayImage::new(3, 0).min_max().is_none());
+
+        // Large offsets do not cancel out the variance
+        let image: ImageBuffer<Gray<f64>, _> =
+            ImageBuffer::from_fn(1000, 1, |x, _| Gray::new([1e9 + (x % 2) as f64]));
+        let stats = image.channel_stats().unwrap();
+        assert_eq!((stats[0].mean, stats[0].std_dev), (1e9 + 0.5, 0.5));
+    }
+}
@@ -0,0 +1,260 @@
+//! Hiding data in the least significant bits of the subpixels.
+//!
+//! The payload is split into groups of `bits_per_channel` bits (least significant bit of each
+//! byte first) which replace the low bits of consecutive subpixels. The scattered variants
+//! visit the subpixels in a pseudo-random order derived from a seed, which must be known to
+//! extract the data again.
+//!
+//! ```
+//! # use image_buffer::RgbImage;
+//! let mut image = RgbImage::new(16, 16);
+//! image.embed_lsb_scattered(b"hello", 2, 42).unwrap();
+//! assert_eq!(image.extract_lsb_scattered(5, 2, 42).unwrap(), b"hello");
+//! ```
+
+use std::error::Error;
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+use num_traits::{NumCast, PrimInt, ToPrimitive, Unsigned};
+
+use buffer::ImageBuffer;
+use test_images::XorShift;
+use traits::Color as Pixel;
+
+/// The payload does not fit into the image.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CapacityError {
+    /// The number of bytes that were to be embedded or extracted.
+    pub required: usize,
+    /// The number of bytes the image can hold.
+    pub available: usize,
+}
+
+impl fmt::Display for CapacityError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,
+               "payload of {} bytes exceeds the capacity of {} bytes",
+               self.required,
+               self.available)
+    }
+}
+
+impl Error for CapacityError {}
+
+/// Returns the offsets of the first `count` subpixels to visit.
+///
+/// The scattered order is a partial Fisher-Yates shuffle, thus the first offsets do not depend
+/// on `count`.
+fn positions<P, C>(image: &ImageBuffer<P, C>, count: usize, seed: Option<u64>) -> Vec<usize>
+    where P: Pixel,
+          C: Deref<Target = [P::Subpixel]>
+{
+    let row_len = image.width() as usize * P::channel_count();
+    let stride = image.stride();
+    let mut offsets: Vec<usize> = (0..image.height() as usize)
+        .flat_map(|y| y * stride..y * stride + row_len)
+        .collect();
+    if let Some(seed) = seed {
+        let mut rng = XorShift::new(seed);
+        let len = offsets.len();
+        for i in 0..count {
+            let j = i + (rng.next() % (len - i) as u64) as usize;
+            offsets.swap(i, j);
+        }
+    }
+    offsets.truncate(count);
+    offsets
+}
+
+/// Returns the number of bits in `T`.
+fn bit_width<T: PrimInt>() -> u32 {
+    T::zero().count_zeros()
+}
+
+/// Returns the `bits` wide group `index` of the bit stream `data`.
+fn read_bits(data: &[u8], index: usize, bits: u32) -> u64 {
+    let start = index * bits as usize;
+    let mut value = 0;
+    for k in 0..bits as usize {
+        let bit = start + k;
+        if bit < data.len() * 8 && data[bit / 8] & (1 << (bit % 8)) != 0 {
+            value |= 1 << k;
+        }
+    }
+    value
+}
+
+impl<P, C> ImageBuffer<P, C>
+    where P: Pixel,
+          P::Subpixel: PrimInt + Unsigned,
+          C: Deref<Target = [P::Subpixel]>
+{
+    /// Returns the number of bytes that can be embedded using `bits_per_channel` bits of every
+    /// subpixel.
+    ///
+    /// # Panics
+    ///
+    /// If `bits_per_channel` is zero or exceeds the width of the subpixel type.
+    pub fn lsb_capacity(&self, bits_per_channel: u32) -> usize {
+        assert!(bits_per_channel > 0 && bits_per_channel <= bit_width::<P::Subpixel>(),
+                "invalid number of bits per channel: {}",
+                bits_per_channel);
+        let subpixels = self.width() as usize * self.height() as usize * P::channel_count();
+        subpixels * bits_per_channel as usize / 8
+    }
+
+    /// Extracts `len` bytes embedded by `embed_lsb`.
+    ///
+    /// # Panics
+    ///
+    /// If `bits_per_channel` is zero or exceeds the width of the subpixel type.
+    pub fn extract_lsb(&self, len: usize, bits_per_channel: u32) -> Result<Vec<u8>, CapacityError> {
+        self.extract_lsb_impl(len, bits_per_channel, None)
+    }
+
+    /// Extracts `len` bytes embedded by `embed_lsb_scattered` with the same `seed`.
+    ///
+    /// # Panics
+    ///
+    /// If `bits_per_channel` is zero or exceeds the width of the subpixel type.
+    pub fn extract_lsb_scattered(&self,
+                                 len: usize,
+                                 bits_per_channel: u32,
+                                 seed: u64)
+                                 -> Result<Vec<u8>, CapacityError> {
+        self.extract_lsb_impl(len, bits_per_channel, Some(seed))
+    }
+
+    fn extract_lsb_impl(&self,
+                        len: usize,
+                        bits: u32,
+                        seed: Option<u64>)
+                        -> Result<Vec<u8>, CapacityError> {
+        let count = self.lsb_subpixels(len, bits)?;
+        let mask = u64::MAX >> (64 - bits);
+        let samples: &[P::Subpixel] = self;
+        let mut data = vec![0u8; len];
+        for (i, offset) in positions(self, count, seed).into_iter().enumerate() {
+            let value = samples[offset].to_u64().unwrap() & mask;
+            for k in 0..bits as usize {
+                let bit = i * bits as usize + k;
+                if bit < len * 8 && value & (1 << k) != 0 {
+                    data[bit / 8] |= 1 << (bit % 8);
+                }
+            }
+        }
+        Ok(data)
+    }
+
+    /// Returns the number of subpixels needed to store `len` bytes.
+    fn lsb_subpixels(&self, len: usize, bits: u32) -> Result<usize, CapacityError> {
+        let available = self.lsb_capacity(bits);
+        if len > available {
+            return Err(CapacityError {
+                required: len,
+                available,
+            });
+        }
+        Ok((len * 8).div_ceil(bits as usize))
+    }
+}
+
+impl<P, C> ImageBuffer<P, C>
+    where P: Pixel,
+          P::Subpixel: PrimInt + Unsigned,
+          C: Deref<Target = [P::Subpixel]> + DerefMut
+{
+    /// Replaces the `bits_per_channel` least significant bits of the subpixels, row by row, with
+    /// `data`.
+    ///
+    /// Fails without modifying the image if `data` exceeds `lsb_capacity(bits_per_channel)`.
+    ///
+    /// # Panics
+    ///
+    /// If `bits_per_channel` is zero or exceeds the width of the subpixel type.
+    pub fn embed_lsb(&mut self, data: &[u8], bits_per_channel: u32) -> Result<(), CapacityError> {
+        self.embed_lsb_impl(data, bits_per_channel, None)
+    }
+
+    /// Like `embed_lsb` but spreads `data` over the whole image in an order derived from
+    /// `seed`.
+    ///
+    /// # Panics
+    ///
+    /// If `bits_per_channel` is zero or exceeds the width of the subpixel type.
+    pub fn embed_lsb_scattered(&mut self,
+                               data: &[u8],
+                               bits_per_channel: u32,
+                               seed: u64)
+                               -> Result<(), CapacityError> {
+        self.embed_lsb_impl(data, bits_per_channel, Some(seed))
+    }
+
+    fn embed_lsb_impl(&mut self,
+                      data: &[u8],
+                      bits: u32,
+                      seed: Option<u64>)
+                      -> Result<(), CapacityError> {
+        let count = self.lsb_subpixels(data.len(), bits)?;
+        let mask = u64::MAX >> (64 - bits);
+        let positions = positions(self, count, seed);
+        let samples: &mut [P::Subpixel] = self;
+        for (i, offset) in positions.into_iter().enumerate() {
+            let value = samples[offset].to_u64().unwrap() & !mask | read_bits(data, i, bits);
+            samples[offset] = <P::Subpixel as NumCast>::from(value).unwrap();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buffer::{GrayImage, ImageBuffer};
+    use color_model::Rgb;
+    use test_images::noise;
+
+    #[test]
+    fn test_roundtrip() {
+        let payload: Vec<u8> = (0..200u32).map(|v| (v * 37) as u8).collect();
+        for &bits in &[1, 3, 8] {
+            let mut image = noise(40, 30, 7);
+            image.embed_lsb(&payload, bits).unwrap();
+            assert_eq!(image.extract_lsb(payload.len(), bits).unwrap(), payload);
+
+            let mut image = noise(40, 30, 7);
+            let original = image.clone();
+            image.embed_lsb_scattered(&payload, bits, 1234).unwrap();
+            assert_eq!(image.extract_lsb_scattered(payload.len(), bits, 1234).unwrap(),
+                       payload);
+            let max = (1 << bits) - 1;
+            for (a, b) in image.pixels().zip(original.pixels()) {
+                for c in 0..3 {
+                    assert!((a[c] as i32 - b[c] as i32).abs() <= max);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_capacity() {
+        let image = GrayImage::new(10, 3);
+        assert_eq!(image.lsb_capacity(1), 3);
+        assert_eq!(image.lsb_capacity(2), 7);
+        let mut image: ImageBuffer<Rgb<u16>, _> = ImageBuffer::new(2, 2);
+        assert_eq!(image.lsb_capacity(16), 24);
+        let err = image.embed_lsb(&[0; 25], 16).unwrap_err();
+        assert_eq!((err.required, err.available), (25, 24));
+        assert!(image.extract_lsb(25, 16).is_err());
+    }
+
+    #[test]
+    fn test_stride() {
+        let mut strided = ImageBuffer::<Rgb<u8>, _>::from_raw_with_stride(2, 2, 9, vec![0; 15])
+            .unwrap();
+        strided.embed_lsb(&[0xff], 1).unwrap();
+        let raw = strided.into_raw();
+        assert_eq!(&raw[6..9], &[0, 0, 0]);
+        assert_eq!(raw.iter().filter(|&&v| v == 1).count(), 8);
+    }
+}
@@ -0,0 +1,253 @@
+//! Deterministic synthetic test images.
+//!
+//! The images are meant as standard stimuli for testing filters, resampling and color
+//! conversions. All generators are deterministic, such that the same parameters always produce
+//! the same image.
+
+use std::f32::consts::PI;
+
+use buffer::{GrayImage, RgbImage, RgbaImage};
+use color_model::{Gray, Rgb, Rgba};
+use math::from_f32;
+
+/// A named image of the test corpus.
+pub struct TestImage {
+    /// A short unique name describing the image.
+    pub name: &'static str,
+    /// The image data.
+    pub image: RgbaImage,
+}
+
+/// Returns the standard corpus of test images.
+///
+/// The corpus covers gradients, zone plates, frequency sweeps, noise and alpha patterns in
+/// various sizes including degenerate ones like single pixel images. Grayscale patterns are
+/// stored as opaque gray `Rgba` images.
+pub fn corpus() -> Vec<TestImage> {
+    vec![
+        TestImage { name: "gradient_256x16", image: gray_to_rgba(&gradient(256, 16)) },
+        TestImage { name: "zone_plate_128x128", image: gray_to_rgba(&zone_plate(128, 128, 1.0)) },
+        TestImage { name: "zone_plate_97x61", image: gray_to_rgba(&zone_plate(97, 61, 0.5)) },
+        TestImage {
+            name: "frequency_sweep_256x32",
+            image: gray_to_rgba(&frequency_sweep(256, 32)),
+        },
+        TestImage { name: "siemens_star_128", image: gray_to_rgba(&siemens_star(128, 36, 1.0)) },
+        TestImage {
+            name: "slanted_edge_64x64",
+            image: gray_to_rgba(&slanted_edge(64, 64, 5.0, 0.6)),
+        },
+        TestImage { name: "noise_64x64", image: rgb_to_rgba(&noise(64, 64, 0)) },
+        TestImage { name: "noise_1x1", image: rgb_to_rgba(&noise(1, 1, 1)) },
+        TestImage { name: "noise_1x33", image: rgb_to_rgba(&noise(1, 33, 2)) },
+        TestImage { name: "noise_257x3", image: rgb_to_rgba(&noise(257, 3, 3)) },
+        TestImage { name: "color_bars_64x64", image: rgb_to_rgba(&color_bars(64, 64)) },
+        TestImage { name: "alpha_pattern_64x64", image: alpha_pattern(64, 64) },
+    ]
+}
+
+/// A horizontal gradient from black on the left to white on the right.
+pub fn gradient(width: u32, height: u32) -> GrayImage {
+    let scale = 255.0 / (width.max(2) - 1) as f32;
+    GrayImage::from_fn(width, height, |x, _| Gray::new([from_f32(x as f32 * scale)]))
+}
+
+/// Maps a signal in the range `[0, 1]` to a gray value with the Michelson contrast `contrast`
+/// around mid gray.
+fn level(v: f32, contrast: f32) -> Gray<u8> {
+    Gray::new([from_f32(127.5 + 127.5 * contrast * (2.0 * v - 1.0))])
+}
+
+/// Number of samples per pixel and axis used by the supersampled generators.
+const SUPERSAMPLING: u32 = 4;
+
+/// Generates an image by averaging `f` over a grid of samples in every pixel.
+///
+/// `f` is evaluated at positions relative to the center of the image and returns a signal in
+/// the range `[0, 1]`.
+fn supersample<F>(width: u32, height: u32, contrast: f32, f: F) -> GrayImage
+    where F: Fn(f32, f32) -> f32
+{
+    let (cx, cy) = (width as f32 / 2.0, height as f32 / 2.0);
+    let step = 1.0 / SUPERSAMPLING as f32;
+    GrayImage::from_fn(width, height, |x, y| {
+        let mut sum = 0.0;
+        for sy in 0..SUPERSAMPLING {
+            for sx in 0..SUPERSAMPLING {
+                let px = x as f32 + (sx as f32 + 0.5) * step - cx;
+                let py = y as f32 + (sy as f32 + 0.5) * step - cy;
+                sum += f(px, py);
+            }
+        }
+        level(sum * step * step, contrast)
+    })
+}
+
+/// A circular zone plate centered in the image.
+///
+/// The spatial frequency increases linearly with the distance from the center and reaches the
+/// Nyquist frequency at the edges of the longer side. Any aliasing introduced by resampling
+/// shows up as additional rings. `contrast` is the Michelson contrast in the range `[0, 1]`.
+pub fn zone_plate(width: u32, height: u32, contrast: f32) -> GrayImage {
+    let size = width.max(height).max(1) as f32;
+    let (cx, cy) = (width as f32 / 2.0, height as f32 / 2.0);
+    GrayImage::from_fn(width, height, |x, y| {
+        let (dx, dy) = (x as f32 + 0.5 - cx, y as f32 + 0.5 - cy);
+        let phase = PI * (dx * dx + dy * dy) / size;
+        level(0.5 + 0.5 * phase.cos(), contrast)
+    })
+}
+
+/// A Siemens star with `spokes` pairs of dark and bright sectors in a square image.
+///
+/// The spatial frequency increases towards the center, which makes the star suitable to
+/// measure the resolution limit of a filter. `contrast` is the Michelson contrast in the range
+/// `[0, 1]`. The pixels are supersampled to avoid aliasing of the pattern itself.
+pub fn siemens_star(size: u32, spokes: u32, contrast: f32) -> GrayImage {
+    let spokes = spokes as f32;
+    supersample(size, size, contrast, |x, y| {
+        if (spokes * y.atan2(x)).sin() >= 0.0 { 1.0 } else { 0.0 }
+    })
+}
+
+/// A straight edge through the center of the image, dark on the left and bright on the right.
+///
+/// The edge is rotated clockwise by `angle` degrees from the vertical. Slightly slanted edges
+/// (typically 5°) are used to measure the modulation transfer function with the slanted-edge
+/// method. `contrast` is the Michelson contrast in the range `[0, 1]`. The pixels are
+/// supersampled to avoid aliasing of the edge itself.
+pub fn slanted_edge(width: u32, height: u32, angle: f32, contrast: f32) -> GrayImage {
+    let (sin, cos) = angle.to_radians().sin_cos();
+    supersample(width, height, contrast, |x, y| {
+        if x * cos + y * sin >= 0.0 { 1.0 } else { 0.0 }
+    })
+}
+
+/// A horizontal sine sweep whose frequency increases linearly from zero on the left to the
+/// Nyquist frequency on the right.
+pub fn frequency_sweep(width: u32, height: u32) -> GrayImage {
+    let w = width.max(1) as f32;
+    GrayImage::from_fn(width, height, |x, _| {
+        let x = x as f32;
+        Gray::new([from_f32(127.5 + 127.5 * (PI * x * x / (2.0 * w)).cos())])
+    })
+}
+
+/// Uniformly distributed color noise generated from `seed`.
+pub fn noise(width: u32, height: u32, seed: u64) -> RgbImage {
+    let mut rng = XorShift::new(seed);
+    let mut image = RgbImage::new(width, height);
+    for p in image.pixels_mut() {
+        let v = rng.next();
+        *p = Rgb::new([v as u8, (v >> 8) as u8, (v >> 16) as u8]);
+    }
+    image
+}
+
+/// Vertical bars of the primary and secondary colors, white and black.
+pub fn color_bars(width: u32, height: u32) -> RgbImage {
+    const BARS: [[u8; 3]; 8] = [[255, 255, 255], [255, 255, 0], [0, 255, 255], [0, 255, 0],
+                                [255, 0, 255], [255, 0, 0], [0, 0, 255], [0, 0, 0]];
+    let w = width.max(1) as u64;
+    RgbImage::from_fn(width, height, |x, _| Rgb::new(BARS[(x as u64 * 8 / w) as usize]))
+}
+
+/// Color bars with alpha decreasing from opaque at the top to transparent at the bottom.
+///
+/// Fully transparent pixels keep their color, which exposes conversions that do not handle
+/// transparent pixels properly.
+pub fn alpha_pattern(width: u32, height: u32) -> RgbaImage {
+    let bars = color_bars(width, height);
+    let scale = 255.0 / (height.max(2) - 1) as f32;
+    RgbaImage::from_fn(width, height, |x, y| {
+        let c = bars[(x, y)];
+        Rgba::new([c[0], c[1], c[2], from_f32(255.0 - y as f32 * scale)])
+    })
+}
+
+fn gray_to_rgba(image: &GrayImage) -> RgbaImage {
+    RgbaImage::from_fn(image.width(), image.height(), |x, y| {
+        let v = image[(x, y)][0];
+        Rgba::new([v, v, v, 255])
+    })
+}
+
+fn rgb_to_rgba(image: &RgbImage) -> RgbaImage {
+    image.convert_buffer()
+}
+
+/// The xorshift64* pseudo-random number generator.
+pub(crate) struct XorShift(u64);
+
+impl XorShift {
+    pub(crate) fn new(seed: u64) -> XorShift {
+        // The state must not be zero, otherwise the generator only returns zeros. The mixing
+        // is a bijection, so the single seed mapped to zero gets another state.
+        match splitmix64(seed.wrapping_add(0x9E37_79B9_7F4A_7C15)) {
+            0 => XorShift(0x9E37_79B9_7F4A_7C15),
+            state => XorShift(state),
+        }
+    }
+
+    pub(crate) fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+}
+
+/// The finalizer of the SplitMix64 generator, a bijection which scrambles the bits of `z`.
+pub(crate) fn splitmix64(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{corpus, slanted_edge, siemens_star, splitmix64, zone_plate, XorShift};
+
+    #[test]
+    fn test_corpus_is_deterministic() {
+        let (a, b) = (corpus(), corpus());
+        assert_eq!(a.len(), b.len());
+        for (a, b) in a.iter().zip(b.iter()) {
+            assert_eq!(a.name, b.name);
+            assert_eq!(&*a.image, &*b.image);
+        }
+    }
+
+    #[test]
+    fn test_charts() {
+        let plate = zone_plate(64, 64, 1.0);
+        assert_eq!(plate[(32, 32)][0], 255);
+        assert_eq!(plate[(0, 32)][0], plate[(63, 32)][0]);
+        let plate = zone_plate(64, 64, 0.5);
+        assert_eq!(plate[(32, 32)][0], 191);
+
+        let star = siemens_star(64, 8, 1.0);
+        assert_eq!(star[(63, 31)][0], 0);
+        assert_eq!(star[(63, 32)][0], 255);
+
+        let edge = slanted_edge(16, 16, 0.0, 1.0);
+        assert_eq!(edge[(7, 0)][0], 0);
+        assert_eq!(edge[(8, 15)][0], 255);
+        let edge = slanted_edge(16, 16, 45.0, 1.0);
+        assert_eq!(edge[(0, 0)][0], 0);
+        assert_eq!(edge[(15, 15)][0], 255);
+        assert_eq!(edge[(15, 0)][0], 159);
+    }
+
+    #[test]
+    fn test_xorshift_seeds() {
+        // The seed which would otherwise be mapped to a zero state
+        let zero_seed = 0u64.wrapping_sub(0x9E37_79B9_7F4A_7C15);
+        assert_eq!(splitmix64(zero_seed.wrapping_add(0x9E37_79B9_7F4A_7C15)), 0);
+        for seed in [0, 1, zero_seed, 0x9E37_79B9_7F4A_7C15] {
+            let mut rng = XorShift::new(seed);
+            assert!((0..4).all(|_| rng.next() != 0), "seed {:#x}", seed);
+        }
+        assert_ne!(XorShift::new(0).next(), XorShift::new(1).next());
+    }
+}
@@ -0,0 +1,230 @@
+//! Golden image tests.
+//!
+//! Compares images produced by a test against reference images ("goldens") stored on disk as
+//! PGM or PPM files with the `pnm` codec, which this feature enables. If the environment
+//! variable `IMAGE_BUFFER_UPDATE_GOLDENS` is set, the goldens are (re)written instead.
+//!
+//! On a mismatch the produced image is stored next to the golden with the extension
+//! `actual.pgm` or `actual.ppm` together with a grayscale difference image with the extension
+//! `diff.pgm`.
+//!
+//! ```no_run
+//! # use image_buffer::{GrayImage, testing};
+//! let image = GrayImage::new(10, 10);
+//! testing::assert_golden(&image, "tests/goldens/black.pgm", 0);
+//! ```
+
+use std::error::Error;
+use std::fmt;
+use std::env;
+use std::fs::{self, File};
+use std::io;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+use buffer::{GrayImage, ImageBuffer};
+use color_model::ColorType;
+use dynamic::DynamicPixel;
+use pnm::{read_pnm, write_pnm, PnmError};
+
+/// Environment variable which causes the goldens to be regenerated.
+pub const UPDATE_ENV_VAR: &str = "IMAGE_BUFFER_UPDATE_GOLDENS";
+
+/// An error that occurred during the comparison with a golden image.
+#[derive(Debug)]
+pub enum GoldenError {
+    /// The golden could not be read or the artifacts could not be written.
+    Io(io::Error),
+    /// The golden is not a valid PNM file or the image cannot be stored as one.
+    Pnm(PnmError),
+    /// The golden has different dimensions or a different color type.
+    Shape {
+        /// Width, height and color type of the golden.
+        expected: (u32, u32, ColorType),
+        /// Width, height and color type of the produced image.
+        found: (u32, u32, ColorType),
+    },
+    /// Pixels differ by more than the tolerance.
+    Mismatch {
+        /// Number of pixels which exceed the tolerance.
+        pixels: usize,
+        /// The maximal difference of a channel.
+        max_difference: u8,
+    },
+}
+
+impl fmt::Display for GoldenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GoldenError::Io(ref err) => write!(f, "I/O error: {}", err),
+            GoldenError::Pnm(ref err) => write!(f, "{}", err),
+            GoldenError::Shape { expected, found } => {
+                write!(f,
+                       "expected an image of shape {:?} but found {:?}",
+                       expected,
+                       found)
+            }
+            GoldenError::Mismatch { pixels, max_difference } => {
+                write!(f,
+                       "{} pixels differ from the golden (maximal difference {})",
+                       pixels,
+                       max_difference)
+            }
+        }
+    }
+}
+
+impl Error for GoldenError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            GoldenError::Io(ref err) => Some(err),
+            GoldenError::Pnm(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for GoldenError {
+    fn from(err: io::Error) -> GoldenError {
+        GoldenError::Io(err)
+    }
+}
+
+impl From<PnmError> for GoldenError {
+    fn from(err: PnmError) -> GoldenError {
+        GoldenError::Pnm(err)
+    }
+}
+
+/// Compares `image` with the golden stored at `path`.
+///
+/// Channels may differ by up to `tolerance`. Writes the golden if the environment variable
+/// `IMAGE_BUFFER_UPDATE_GOLDENS` is set. On a mismatch the produced image and a difference
+/// image are written next to the golden.
+///
+/// Only gray and RGB images can be stored as goldens, other pixel types return
+/// `GoldenError::Pnm`.
+pub fn compare_golden<P, C, Q>(image: &ImageBuffer<P, C>,
+                               path: Q,
+                               tolerance: u8)
+                               -> Result<(), GoldenError>
+    where P: DynamicPixel<Subpixel = u8>,
+          C: Deref<Target = [u8]>,
+          Q: AsRef<Path>
+{
+    let path = path.as_ref();
+    if env::var_os(UPDATE_ENV_VAR).is_some() {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        return write_golden(image, path);
+    }
+    let golden = read_pnm(File::open(path)?)?;
+    let golden = match golden.as_buffer::<P>() {
+        Some(golden) if golden.dimensions() == image.dimensions() => golden,
+        _ => {
+            write_golden(image, &artifact_path::<P>(path, "actual"))?;
+            let (width, height) = golden.dimensions();
+            return Err(GoldenError::Shape {
+                expected: (width, height, golden.color_type()),
+                found: (image.width(), image.height(), P::color_type()),
+            });
+        }
+    };
+    let mut diff = Vec::with_capacity(golden.len() / P::channel_count());
+    let mut pixels = 0;
+    let mut max_difference = 0;
+    for (a, b) in image.pixels().zip(golden.pixels()) {
+        let d = a.channels()
+            .as_ref()
+            .iter()
+            .zip(b.channels().as_ref())
+            .map(|(&a, &b)| a.abs_diff(b))
+            .max()
+            .unwrap();
+        if d > tolerance {
+            pixels += 1;
+        }
+        max_difference = max_difference.max(d);
+        diff.push(d);
+    }
+    if pixels == 0 {
+        return Ok(());
+    }
+    write_golden(image, &artifact_path::<P>(path, "actual"))?;
+    let diff = GrayImage::from_raw(image.width(), image.height(), diff).unwrap();
+    write_golden(&diff, &path.with_extension("diff.pgm"))?;
+    Err(GoldenError::Mismatch {
+        pixels,
+        max_difference,
+    })
+}
+
+/// Asserts that `image` matches the golden stored at `path`.
+///
+/// # Panics
+///
+/// Panics if `compare_golden` returns an error.
+pub fn assert_golden<P, C, Q>(image: &ImageBuffer<P, C>, path: Q, tolerance: u8)
+    where P: DynamicPixel<Subpixel = u8>,
+          C: Deref<Target = [u8]>,
+          Q: AsRef<Path>
+{
+    let path = path.as_ref();
+    if let Err(err) = compare_golden(image, path, tolerance) {
+        panic!("golden test {} failed: {} (set {} to update the golden)",
+               path.display(),
+               err,
+               UPDATE_ENV_VAR)
+    }
+}
+
+/// Path of the artifact `kind` of the golden `path`, with the extension of the PNM format
+/// `write_pnm` chooses for `P`.
+fn artifact_path<P: DynamicPixel>(path: &Path, kind: &str) -> PathBuf {
+    let format = if P::channel_count() == 1 { "pgm" } else { "ppm" };
+    path.with_extension(format!("{}.{}", kind, format))
+}
+
+fn write_golden<P, C>(image: &ImageBuffer<P, C>, path: &Path) -> Result<(), GoldenError>
+    where P: DynamicPixel<Subpixel = u8>,
+          C: Deref<Target = [u8]>
+{
+    write_pnm(image, File::create(path)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+
+    use buffer::{GrayImage, RgbImage};
+    use color_model::{ColorType, Gray};
+    use super::{compare_golden, write_golden, GoldenError};
+
+    #[test]
+    fn test_compare_golden() {
+        let dir = env::temp_dir().join("image_buffer_golden_test");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("gradient.pgm");
+        let image = GrayImage::from_fn(4, 4, |x, y| Gray::new([(x * 16 + y) as u8]));
+        write_golden(&image, &path).unwrap();
+        compare_golden(&image, &path, 0).unwrap();
+
+        let mut other = image.clone();
+        other[(1, 1)] = Gray::new([0]);
+        match compare_golden(&other, &path, 0) {
+            Err(GoldenError::Mismatch { pixels: 1, max_difference: 17 }) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(dir.join("gradient.actual.pgm").exists());
+        assert!(dir.join("gradient.diff.pgm").exists());
+        compare_golden(&other, &path, 17).unwrap();
+
+        match compare_golden(&RgbImage::new(4, 4), &path, 0) {
+            Err(GoldenError::Shape { expected: (4, 4, ColorType::Gray(8)), .. }) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
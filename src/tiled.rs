@@ -0,0 +1,325 @@
+//! Images stored in the tiled layouts of GPU and console textures.
+
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut, Index, IndexMut};
+
+use num_traits::Zero;
+
+use buffer::ImageBuffer;
+use curve::morton_index;
+use traits::{Color as Pixel, ImageView, ImageViewMut};
+
+/// The order of the pixels within a tile.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TileOrder {
+    /// Row by row.
+    RowMajor,
+    /// Along the Morton (Z-order) curve, see `curve::morton_index`.
+    Morton,
+}
+
+/// The layout of a tiled image.
+///
+/// The image is divided into tiles of `tile_width * tile_height` pixels which are stored one
+/// after another row by row, each tile holding its pixels in the order `order`. The image is
+/// padded to whole tiles at the right and the bottom.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TiledLayout {
+    tile_width: u32,
+    tile_height: u32,
+    order: TileOrder,
+}
+
+impl TiledLayout {
+    /// A layout with tiles of `tile_width * tile_height` pixels stored row by row.
+    ///
+    /// # Panics
+    ///
+    /// If a dimension of the tiles is zero.
+    pub fn row_major(tile_width: u32, tile_height: u32) -> TiledLayout {
+        assert!(tile_width > 0 && tile_height > 0,
+                "tile size ({}, {}) is empty",
+                tile_width,
+                tile_height);
+        TiledLayout {
+            tile_width,
+            tile_height,
+            order: TileOrder::RowMajor,
+        }
+    }
+
+    /// A layout with square tiles of side `side` stored in Morton order, the swizzled layout
+    /// of many GPUs.
+    ///
+    /// # Panics
+    ///
+    /// If `side` is not a power of two.
+    pub fn morton(side: u32) -> TiledLayout {
+        assert!(side.is_power_of_two(), "tile side {} is not a power of two", side);
+        TiledLayout {
+            tile_width: side,
+            tile_height: side,
+            order: TileOrder::Morton,
+        }
+    }
+
+    /// The width and height of the tiles.
+    pub fn tile_size(&self) -> (u32, u32) {
+        (self.tile_width, self.tile_height)
+    }
+
+    /// The order of the pixels within a tile.
+    pub fn order(&self) -> TileOrder {
+        self.order
+    }
+
+    /// The number of tiles across and down an image of the given dimensions.
+    pub fn tile_counts(&self, (width, height): (u32, u32)) -> (u32, u32) {
+        (width.div_ceil(self.tile_width), height.div_ceil(self.tile_height))
+    }
+
+    /// The number of pixels of an image of the given dimensions including the padding, or
+    /// None on overflow.
+    pub fn len(&self, dimensions: (u32, u32)) -> Option<usize> {
+        let (across, down) = self.tile_counts(dimensions);
+        (across as usize)
+            .checked_mul(down as usize)?
+            .checked_mul(self.tile_width as usize)?
+            .checked_mul(self.tile_height as usize)
+    }
+
+    /// Returns the index of the pixel `(x, y)` of an image of width `width`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::TiledLayout;
+    /// let layout = TiledLayout::row_major(4, 2);
+    /// assert_eq!(layout.pixel_index(6, (1, 1)), 5);
+    /// assert_eq!(layout.pixel_index(6, (4, 0)), 8);
+    /// assert_eq!(TiledLayout::morton(4).pixel_index(6, (1, 1)), 3);
+    /// ```
+    pub fn pixel_index(&self, width: u32, (x, y): (u32, u32)) -> usize {
+        let (tile_width, tile_height) = (self.tile_width as usize, self.tile_height as usize);
+        let across = width.div_ceil(self.tile_width) as usize;
+        let tile = (y / self.tile_height) as usize * across + (x / self.tile_width) as usize;
+        let (tx, ty) = (x % self.tile_width, y % self.tile_height);
+        let within = match self.order {
+            TileOrder::RowMajor => ty as usize * tile_width + tx as usize,
+            TileOrder::Morton => morton_index(tx, ty) as usize,
+        };
+        tile * tile_width * tile_height + within
+    }
+}
+
+/// An image buffer storing its pixels in tiles.
+///
+/// Pixels are addressed by `(x, y)` like in an `ImageBuffer` and the buffer implements
+/// `ImageView` and `ImageViewMut`, such that it can be drawn to and read from directly. The
+/// padding subpixels outside of the image are kept as they are.
+///
+/// # Examples
+///
+/// ```
+/// # use image_buffer::{GrayImage, TiledLayout, color::Gray};
+/// let image = GrayImage::from_fn(4, 4, |x, y| Gray::new([(x + 4 * y) as u8]));
+/// let tiled = image.to_tiled(TiledLayout::row_major(2, 2));
+/// assert_eq!(tiled[(2, 1)], Gray::new([6]));
+/// assert_eq!(&tiled.as_raw()[..8], &[0, 1, 4, 5, 2, 3, 6, 7]);
+/// assert_eq!(tiled.to_linear().into_raw(), image.into_raw());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TiledImageBuffer<P: Pixel, Container> {
+    width: u32,
+    height: u32,
+    layout: TiledLayout,
+    data: Container,
+    _pixel_type: PhantomData<P>,
+}
+
+impl<P, Container> TiledImageBuffer<P, Container>
+    where P: Pixel,
+          Container: Deref<Target = [P::Subpixel]>
+{
+    /// Constructs a tiled buffer from a generic container.
+    ///
+    /// Returns None if the container is smaller than the image padded to whole tiles.
+    pub fn from_raw(width: u32,
+                    height: u32,
+                    layout: TiledLayout,
+                    buf: Container)
+                    -> Option<TiledImageBuffer<P, Container>> {
+        let len = layout.len((width, height))?.checked_mul(P::channel_count())?;
+        if len > buf.len() {
+            return None;
+        }
+        Some(TiledImageBuffer {
+            width,
+            height,
+            layout,
+            data: buf,
+            _pixel_type: PhantomData,
+        })
+    }
+
+    /// Returns the underlying raw buffer.
+    pub fn into_raw(self) -> Container {
+        self.data
+    }
+
+    /// Returns the underlying raw buffer as a slice.
+    pub fn as_raw(&self) -> &[P::Subpixel] {
+        &self.data
+    }
+
+    /// The layout of the tiles.
+    pub fn layout(&self) -> TiledLayout {
+        self.layout
+    }
+
+    /// The width and height of this image.
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// The width of this image.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The height of this image.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn subpixel_index(&self, x: u32, y: u32) -> usize {
+        assert!(x < self.width && y < self.height,
+                "pixel ({}, {}) out of bounds {:?}",
+                x,
+                y,
+                self.dimensions());
+        self.layout.pixel_index(self.width, (x, y)) * P::channel_count()
+    }
+
+    /// Converts the image to a linear buffer.
+    pub fn to_linear(&self) -> ImageBuffer<P, Vec<P::Subpixel>> {
+        ImageBuffer::from_fn(self.width, self.height, |x, y| self[(x, y)])
+    }
+}
+
+impl<P: Pixel> TiledImageBuffer<P, Vec<P::Subpixel>> {
+    /// Creates a new tiled buffer filled with zeros.
+    ///
+    /// # Panics
+    ///
+    /// If the size of the padded image overflows.
+    pub fn new(width: u32,
+               height: u32,
+               layout: TiledLayout)
+               -> TiledImageBuffer<P, Vec<P::Subpixel>> {
+        let len = layout.len((width, height))
+            .and_then(|len| len.checked_mul(P::channel_count()))
+            .expect("tiled image size overflows");
+        TiledImageBuffer::from_raw(width, height, layout, vec![Zero::zero(); len]).unwrap()
+    }
+}
+
+impl<P, Container> Index<(u32, u32)> for TiledImageBuffer<P, Container>
+    where P: Pixel,
+          Container: Deref<Target = [P::Subpixel]>
+{
+    type Output = P;
+
+    fn index(&self, (x, y): (u32, u32)) -> &P {
+        let i = self.subpixel_index(x, y);
+        P::from_slice(&self.data[i..i + P::channel_count()])
+    }
+}
+
+impl<P, Container> IndexMut<(u32, u32)> for TiledImageBuffer<P, Container>
+    where P: Pixel,
+          Container: Deref<Target = [P::Subpixel]> + DerefMut
+{
+    fn index_mut(&mut self, (x, y): (u32, u32)) -> &mut P {
+        let i = self.subpixel_index(x, y);
+        P::from_slice_mut(&mut self.data[i..i + P::channel_count()])
+    }
+}
+
+impl<P, Container> ImageView<P> for TiledImageBuffer<P, Container>
+    where P: Pixel,
+          Container: Deref<Target = [P::Subpixel]>
+{
+    fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+}
+
+impl<P, Container> ImageViewMut<P> for TiledImageBuffer<P, Container>
+    where P: Pixel,
+          Container: Deref<Target = [P::Subpixel]> + DerefMut
+{
+}
+
+impl<P, Container> ImageBuffer<P, Container>
+    where P: Pixel,
+          Container: Deref<Target = [P::Subpixel]>
+{
+    /// Converts the image to a tiled buffer with the layout `layout`.
+    ///
+    /// The padding is filled with zeros.
+    pub fn to_tiled(&self, layout: TiledLayout) -> TiledImageBuffer<P, Vec<P::Subpixel>> {
+        let mut tiled = TiledImageBuffer::new(self.width(), self.height(), layout);
+        for (x, y, &p) in self.enumerate_pixels() {
+            tiled[(x, y)] = p;
+        }
+        tiled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buffer::RgbImage;
+    use color_model::{Gray, Rgb};
+    use traits::ImageViewMut;
+    use super::{TiledImageBuffer, TiledLayout};
+
+    #[test]
+    fn test_roundtrip() {
+        let image = RgbImage::from_fn(5, 3, |x, y| Rgb::new([x as u8, y as u8, 9]));
+        for &layout in &[TiledLayout::row_major(4, 4),
+                         TiledLayout::row_major(2, 3),
+                         TiledLayout::morton(4),
+                         TiledLayout::morton(8)] {
+            let tiled = image.to_tiled(layout);
+            assert_eq!(tiled.as_raw().len(), layout.len((5, 3)).unwrap() * 3);
+            assert_eq!(tiled[(4, 2)], Rgb::new([4, 2, 9]));
+            assert_eq!(tiled.to_linear().into_raw(), image.clone().into_raw());
+        }
+
+        let tiled = image.to_tiled(TiledLayout::morton(4));
+        // The second pixel of the first tile in Morton order
+        assert_eq!(&tiled.as_raw()[3..6], &[1, 0, 9]);
+        // The first pixel of the second tile
+        assert_eq!(&tiled.as_raw()[48..51], &[4, 0, 9]);
+        assert_eq!(TiledLayout::morton(4).tile_counts((5, 3)), (2, 1));
+    }
+
+    #[test]
+    fn test_from_raw() {
+        let layout = TiledLayout::row_major(4, 4);
+        assert!(TiledImageBuffer::<Gray<u8>, _>::from_raw(5, 1, layout, vec![0; 31]).is_none());
+        let mut tiled = TiledImageBuffer::<Gray<u8>, _>::from_raw(5, 1, layout, vec![0; 32])
+            .unwrap();
+        tiled.put_pixel(4, 0, Gray::new([7]));
+        tiled[(1, 0)] = Gray::new([3]);
+        let raw = tiled.into_raw();
+        assert_eq!((raw[1], raw[16]), (3, 7));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_morton_side() {
+        TiledLayout::morton(6);
+    }
+}
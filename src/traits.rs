@@ -1,5 +1,11 @@
+use std::any::TypeId;
+use std::marker::PhantomData;
 use std::ops::{Index, IndexMut};
-use num_traits::{Bounded, Num, NumCast};
+use num_traits::{Bounded, Num, NumCast, Saturating};
+
+use math::{from_f32, scale_channel, to_f32};
+
+use color_model::{AlphaMode, PixelLayout};
 
 /// A generalized pixel.
 ///
@@ -10,50 +16,139 @@ pub trait Color
     /// The underlying subpixel type.
 
     type Subpixel: Primitive;
-    // TODO: Workaround until associated consts work.
+    /// The array of channels, `[Self::Subpixel; Self::NUM_CHANNELS]`.
+    ///
+    /// Sizing the array by `NUM_CHANNELS` directly in the signatures below needs
+    /// `generic_const_exprs`, which is not stable yet.
     type Storage: AsRef<[Self::Subpixel]> + AsMut<[Self::Subpixel]> + 'static;
-    // TODO: The preferred solution would be:
-    // type Subpixel: Primitive;
-    // const NUM_CHANNELS: usize;
+
+    /// The number of channels of this pixel type.
+    const NUM_CHANNELS: usize;
 
     /// Returns the number of channels of this pixel type.
-    // TODO: Remove is NUM_CHANNELS is available
-    fn channel_count() -> usize;
+    #[inline(always)]
+    fn channel_count() -> usize {
+        Self::NUM_CHANNELS
+    }
 
     /// Returns the components as a slice.
     fn channels(&self) -> &Self::Storage;
-    // TODO: The preferred solution would be:
-    // fn channels(&self) -> &[Self::Subpixel; Self::NUM_CHANNELS];
 
     /// Returns the components as a mutable slice
     fn channels_mut(&mut self) -> &mut Self::Storage;
-    // TODO: The preferred solution would be:
-    // fn channels_mut(&mut self) -> &mut [Self::Subpixel; Self::NUM_CHANNELS];
 
-    /// Construct a pixel from the 4 channels a, b, c and d.
-    /// If the pixel does not contain 4 channels the extra are ignored.
-    fn from_channels(Self::Storage) -> Self;
-    // TODO: The preferred solution would be:
-    // fn from_channels([Self::Subpixel; Self::NUM_CHANNELS]) -> Self;
+    /// Constructs a pixel from the array of its channels.
+    ///
+    /// Arrays whose length differs from `NUM_CHANNELS` are rejected at compile time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use image_buffer::{Color, color::Rgb};
+    ///
+    /// assert_eq!(Rgb::from_channels([1u8, 2, 3]), Rgb::new([1, 2, 3]));
+    /// ```
+    ///
+    /// ```compile_fail
+    /// use image_buffer::{Color, color::Rgb};
+    ///
+    /// let _ = Rgb::<u8>::from_channels([1, 2]);
+    /// ```
+    fn from_channels<const N: usize>(channels: [Self::Subpixel; N]) -> Self {
+        const { assert!(N == Self::NUM_CHANNELS, "invalid number of channels") };
+        *Self::from_slice(&channels)
+    }
 
     /// Returns a string that can help to interprete the meaning each channel
     /// See [gimp babl](http://gegl.org/babl/).
     fn color_model() -> &'static str;
 
+    /// Returns the `TypeId` of the opaque color type in canonical channel order, which
+    /// identifies the color model at the type level.
+    ///
+    /// Colors with alpha return the id of their color without alpha, such that types which
+    /// store the same channels in another order should return the id of the canonical type.
+    fn color_model_id() -> TypeId {
+        TypeId::of::<Self>()
+    }
+
+    /// Returns the channel order and alpha interpretation of this pixel type.
+    fn layout() -> PixelLayout {
+        PixelLayout::canonical(Self::channel_count(), AlphaMode::None)
+    }
+
+    /// Returns the storage position of the alpha channel, or None if the color has none.
+    fn alpha_position() -> Option<usize> {
+        Self::layout().alpha_position()
+    }
+
+    /// Returns `true` if the color has an alpha channel.
+    fn has_alpha() -> bool {
+        Self::alpha_position().is_some()
+    }
+
+    /// Returns the alpha value, which is the maximum value for colors without alpha channel.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{Color, color::{Rgb, Rgba}};
+    /// assert_eq!(Rgba::new([1u8, 2, 3, 4]).alpha(), 4);
+    /// assert_eq!(Rgb::new([1u8, 2, 3]).alpha(), 255);
+    /// ```
+    fn alpha(&self) -> Self::Subpixel
+        where Self::Subpixel: ChannelMax
+    {
+        match Self::alpha_position() {
+            Some(alpha) => self.as_ref().as_ref()[alpha],
+            None => Self::Subpixel::channel_max(),
+        }
+    }
+
+    /// Replaces the alpha value, colors without alpha channel are left unchanged.
+    ///
+    /// The color channels are not adjusted, such that premultiplied colors have to be scaled
+    /// separately.
+    fn set_alpha(&mut self, alpha: Self::Subpixel) {
+        if let Some(position) = Self::alpha_position() {
+            self.as_mut().as_mut()[position] = alpha;
+        }
+    }
+
     /// Returns a view into a slice.
     ///
     /// # Panics
     ///
-    /// If the slice it not long enough this method will panic.
+    /// If the length of the slice is not the number of channels.
     fn from_slice<'a>(slice: &'a [Self::Subpixel]) -> &'a Self;
 
     /// Returns mutable view into a mutable slice.
     ///
     /// # Panics
     ///
-    /// If the slice it not long enough this method will panic.
+    /// If the length of the slice is not the number of channels.
     fn from_slice_mut<'a>(slice: &'a mut [Self::Subpixel]) -> &'a mut Self;
 
+    /// Returns a view into a slice, or None if the length of the slice is not the number of
+    /// channels.
+    fn try_from_slice<'a>(slice: &'a [Self::Subpixel]) -> Option<&'a Self> {
+        if slice.len() == Self::NUM_CHANNELS {
+            Some(Self::from_slice(slice))
+        } else {
+            None
+        }
+    }
+
+    /// Returns a mutable view into a mutable slice, or None if the length of the slice is not
+    /// the number of channels.
+    fn try_from_slice_mut<'a>(slice: &'a mut [Self::Subpixel]) -> Option<&'a mut Self> {
+        if slice.len() == Self::NUM_CHANNELS {
+            Some(Self::from_slice_mut(slice))
+        } else {
+            None
+        }
+    }
+
     /// Apply the function ```f``` to each channel of this pixel.
     fn map<F>(&self, f: F) -> Self
         where F: Fn(Self::Subpixel) -> Self::Subpixel
@@ -108,6 +203,104 @@ pub trait Color
         }
 
     }
+
+    /// Interpolates linearly between this color at `t = 0` and `other` at `t = 1`, channel by
+    /// channel including alpha.
+    ///
+    /// Integer channels are rounded to the nearest value and clamped to their range, `t` outside
+    /// of `[0, 1]` extrapolates. A NaN `t` returns this color unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{Color, color::Rgb};
+    /// let mid = Rgb::new([0u8, 100, 255]).lerp(&Rgb::new([255, 100, 0]), 0.5);
+    /// assert_eq!(mid, Rgb::new([128, 100, 128]));
+    /// ```
+    fn lerp(&self, other: &Self, t: f32) -> Self
+        where Self::Subpixel: ChannelMax
+    {
+        if t.is_nan() {
+            return *self;
+        }
+        self.map2(other, |a, b| {
+            let a = to_f32(a);
+            from_f32(a + (to_f32(b) - a) * t)
+        })
+    }
+
+    /// Multiplies the color channels by `factor`, the alpha channel is left unchanged.
+    ///
+    /// Unlike the scalar `Mul`, which requires the scalar to be of the subpixel type, this
+    /// allows fractional gains on integer colors. The product is computed in `f64`, integer
+    /// channels are rounded to the nearest value and clamped to their range instead of
+    /// overflowing. The gain changes the brightness of straight and premultiplied colors alike
+    /// without changing their opacity.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{Color, color::{Rgb, Rgba}};
+    /// assert_eq!(Rgb::new([10u8, 101, 200]).scale_by(1.5), Rgb::new([15, 152, 255]));
+    /// assert_eq!(Rgb::new([0.5f32, 1.0, 2.0]).scale_by(0.5), Rgb::new([0.25, 0.5, 1.0]));
+    /// assert_eq!(Rgba::new([10u8, 20, 30, 128]).scale_by(2.0), Rgba::new([20, 40, 60, 128]));
+    /// ```
+    fn scale_by(&self, factor: f32) -> Self
+        where Self::Subpixel: ChannelMax
+    {
+        self.map_with_alpha(|v| scale_channel(v, factor), |a| a)
+    }
+
+    /// Adds the channels of `other`, saturating at the bounds of the subpixel type instead of
+    /// overflowing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{Color, color::Rgb};
+    /// let sum = Rgb::new([200u8, 10, 0]).saturating_add(&Rgb::new([100, 10, 0]));
+    /// assert_eq!(sum, Rgb::new([255, 20, 0]));
+    /// ```
+    fn saturating_add(&self, other: &Self) -> Self
+        where Self::Subpixel: Saturating
+    {
+        self.map2(other, |a, b| a.saturating_add(b))
+    }
+
+    /// Subtracts the channels of `other`, saturating at the bounds of the subpixel type instead
+    /// of overflowing.
+    fn saturating_sub(&self, other: &Self) -> Self
+        where Self::Subpixel: Saturating
+    {
+        self.map2(other, |a, b| a.saturating_sub(b))
+    }
+}
+
+/// A color which is laid out exactly like its array of channels.
+///
+/// Allows vectors of pixels to be reinterpreted as vectors of subpixels without copying, see
+/// `ImageBuffer::from_pixel_vec`.
+///
+/// # Safety
+///
+/// The type has to be a `repr(transparent)` or `repr(C)` wrapper around `Self::Storage`
+/// without any other fields, and `Self::Storage` has to be an array of `NUM_CHANNELS`
+/// subpixels.
+pub unsafe trait PodColor: Color {}
+
+/// A color with an alpha channel.
+pub trait AlphaColor: Color {
+    /// The color type without the alpha channel.
+    type Opaque: Color<Subpixel = Self::Subpixel>;
+
+    /// Composites this color over the opaque color `background`.
+    fn flatten(&self, background: Self::Opaque) -> Self::Opaque;
+
+    /// Combines the opaque color `color` with the alpha value `alpha`.
+    fn from_opaque(color: Self::Opaque, alpha: Self::Subpixel) -> Self;
+
+    /// Returns the color channels without the alpha channel.
+    fn without_alpha(&self) -> Self::Opaque;
 }
 
 /// Color math operations.
@@ -125,9 +318,157 @@ pub trait ColorMathOps<C: Color>: Sized {
     fn mul(self, rhs: C) -> C;
 }
 
-/// A view into an image
-pub trait ImageView<P: Color>
-    : Index<(u32, u32), Output = P> + IndexMut<(u32, u32)> {
+/// A view into an image.
+///
+/// Implemented by `ImageBuffer`, `SubImage` and `SubImageMut`, such that algorithms can be
+/// written once for all of them. Pixel coordinates are relative to the top left corner of the
+/// view.
+///
+/// Unlike earlier versions, this trait does not require `IndexMut`, such that read-only views
+/// can implement it. Code which writes to a view has to bound on `ImageViewMut` instead.
+///
+/// # Examples
+///
+/// ```
+/// # use image_buffer::{GrayImage, ImageView, color::Gray};
+/// fn sum<V: ImageView<Gray<u8>>>(image: &V) -> u32 {
+///     image.enumerate_pixels().map(|(_, _, p)| p[0] as u32).sum()
+/// }
+/// let buffer = GrayImage::from_fn(4, 4, |x, _| Gray::new([x as u8]));
+/// assert_eq!(sum(&buffer), 24);
+/// assert_eq!(sum(&buffer.view(2, 0, 2, 2)), 10);
+/// ```
+pub trait ImageView<P: Color>: Index<(u32, u32), Output = P> {
+    /// The width and height of the view.
+    fn dimensions(&self) -> (u32, u32);
+
+    /// The width of the view.
+    fn width(&self) -> u32 {
+        self.dimensions().0
+    }
+
+    /// The height of the view.
+    fn height(&self) -> u32 {
+        self.dimensions().1
+    }
+
+    /// Returns `true` if `(x, y)` lies within the view.
+    fn in_bounds(&self, x: u32, y: u32) -> bool {
+        let (width, height) = self.dimensions();
+        x < width && y < height
+    }
+
+    /// Returns the pixel at `(x, y)`.
+    ///
+    /// # Panics
+    ///
+    /// If `(x, y)` is out of bounds.
+    fn get_pixel(&self, x: u32, y: u32) -> &P {
+        &self[(x, y)]
+    }
+
+    /// Returns the pixel at `(x, y)` or `None` if it is out of bounds.
+    fn get_pixel_checked(&self, x: u32, y: u32) -> Option<&P> {
+        if self.in_bounds(x, y) {
+            Some(&self[(x, y)])
+        } else {
+            None
+        }
+    }
+
+    /// Returns an iterator over the coordinates and pixels of the view, row by row.
+    fn enumerate_pixels(&self) -> ViewPixels<'_, Self, P> {
+        ViewPixels {
+            view: self,
+            x: 0,
+            y: 0,
+            _pixel: PhantomData,
+        }
+    }
+}
+
+/// A mutable view into an image.
+pub trait ImageViewMut<P: Color>: ImageView<P> + IndexMut<(u32, u32)> {
+    /// Returns the mutable pixel at `(x, y)`.
+    ///
+    /// # Panics
+    ///
+    /// If `(x, y)` is out of bounds.
+    fn get_pixel_mut(&mut self, x: u32, y: u32) -> &mut P {
+        &mut self[(x, y)]
+    }
+
+    /// Replaces the pixel at `(x, y)`.
+    ///
+    /// # Panics
+    ///
+    /// If `(x, y)` is out of bounds.
+    fn put_pixel(&mut self, x: u32, y: u32, pixel: P) {
+        self[(x, y)] = pixel
+    }
+
+    /// Copies the pixels of `other` into this view with the top left corner at `(x, y)`.
+    ///
+    /// # Panics
+    ///
+    /// If `other` does not fit into this view at `(x, y)`.
+    fn copy_view<V: ImageView<P>>(&mut self, other: &V, x: u32, y: u32)
+        where Self: Sized
+    {
+        let (width, height) = other.dimensions();
+        assert!(x as u64 + width as u64 <= self.width() as u64 &&
+                y as u64 + height as u64 <= self.height() as u64,
+                "image with dimensions ({}, {}) does not fit at ({}, {})",
+                width,
+                height,
+                x,
+                y);
+        for (i, j, p) in other.enumerate_pixels() {
+            self[(x + i, y + j)] = *p;
+        }
+    }
+}
+
+/// Iterator over the coordinates and pixels of an `ImageView`.
+pub struct ViewPixels<'a, V: ?Sized + 'a, P> {
+    view: &'a V,
+    x: u32,
+    y: u32,
+    _pixel: PhantomData<P>,
+}
+
+impl<'a, V, P> Iterator for ViewPixels<'a, V, P>
+    where V: ImageView<P> + ?Sized,
+          P: Color
+{
+    type Item = (u32, u32, &'a P);
+
+    fn next(&mut self) -> Option<(u32, u32, &'a P)> {
+        let (width, height) = self.view.dimensions();
+        if self.x >= width {
+            self.x = 0;
+            self.y += 1;
+        }
+        if self.y >= height || width == 0 {
+            return None;
+        }
+        let (x, y) = (self.x, self.y);
+        self.x += 1;
+        Some((x, y, &self.view[(x, y)]))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (width, height) = self.view.dimensions();
+        let done = self.y as u64 * width as u64 + self.x as u64;
+        let len = (width as u64 * height as u64).saturating_sub(done) as usize;
+        (len, Some(len))
+    }
+}
+
+impl<'a, V, P> ExactSizeIterator for ViewPixels<'a, V, P>
+    where V: ImageView<P> + ?Sized,
+          P: Color
+{
 }
 
 /// Returns value which is used to scale a value of a channel.
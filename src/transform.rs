@@ -0,0 +1,122 @@
+//! Flips and rotations by multiples of 90°.
+
+use std::mem;
+use std::ops::{Deref, DerefMut};
+
+use buffer::ImageBuffer;
+use traits::Color as Pixel;
+
+impl<P, Container> ImageBuffer<P, Container>
+    where P: Pixel,
+          Container: Deref<Target = [P::Subpixel]>
+{
+    /// Returns a copy of the image mirrored along the vertical axis.
+    pub fn flip_horizontal(&self) -> ImageBuffer<P, Vec<P::Subpixel>> {
+        let width = self.width();
+        ImageBuffer::from_fn(width, self.height(), |x, y| self[(width - 1 - x, y)])
+    }
+
+    /// Returns a copy of the image mirrored along the horizontal axis.
+    pub fn flip_vertical(&self) -> ImageBuffer<P, Vec<P::Subpixel>> {
+        let height = self.height();
+        ImageBuffer::from_fn(self.width(), height, |x, y| self[(x, height - 1 - y)])
+    }
+
+    /// Returns a copy of the image rotated by 90° clockwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{GrayImage, color};
+    /// let buffer = GrayImage::from_fn(3, 2, |x, y| color::Gray::new([(x + 3 * y) as u8]));
+    /// assert_eq!(buffer.rotate90().into_raw(), [3, 0, 4, 1, 5, 2]);
+    /// ```
+    pub fn rotate90(&self) -> ImageBuffer<P, Vec<P::Subpixel>> {
+        let (width, height) = self.dimensions();
+        ImageBuffer::from_fn(height, width, |x, y| self[(y, height - 1 - x)])
+    }
+
+    /// Returns a copy of the image rotated by 180°.
+    pub fn rotate180(&self) -> ImageBuffer<P, Vec<P::Subpixel>> {
+        let (width, height) = self.dimensions();
+        ImageBuffer::from_fn(width, height, |x, y| self[(width - 1 - x, height - 1 - y)])
+    }
+
+    /// Returns a copy of the image rotated by 270° clockwise.
+    pub fn rotate270(&self) -> ImageBuffer<P, Vec<P::Subpixel>> {
+        let (width, height) = self.dimensions();
+        ImageBuffer::from_fn(height, width, |x, y| self[(width - 1 - y, x)])
+    }
+}
+
+impl<P, Container> ImageBuffer<P, Container>
+    where P: Pixel,
+          Container: Deref<Target = [P::Subpixel]> + DerefMut
+{
+    /// Mirrors the image along the vertical axis in place.
+    pub fn flip_horizontal_in_place(&mut self) {
+        let channels = P::channel_count();
+        for row in self.subpixel_rows_mut() {
+            let mut pixels = row.chunks_mut(channels);
+            while let (Some(left), Some(right)) = (pixels.next(), pixels.next_back()) {
+                left.swap_with_slice(right)
+            }
+        }
+    }
+
+    /// Mirrors the image along the horizontal axis in place.
+    pub fn flip_vertical_in_place(&mut self) {
+        let mut rows = self.subpixel_rows_mut();
+        while let (Some(top), Some(bottom)) = (rows.next(), rows.next_back()) {
+            top.swap_with_slice(bottom)
+        }
+    }
+
+    /// Rotates the image by 180° in place.
+    pub fn rotate180_in_place(&mut self) {
+        let mut pixels = self.pixels_mut();
+        while let (Some(first), Some(last)) = (pixels.next(), pixels.next_back()) {
+            mem::swap(first, last)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buffer::{GrayImage, ImageBuffer};
+    use color_model::Gray;
+
+    fn image() -> GrayImage {
+        GrayImage::from_fn(3, 3, |x, y| Gray::new([(x + 3 * y) as u8]))
+    }
+
+    #[test]
+    fn test_flips() {
+        assert_eq!(image().flip_horizontal().into_raw(), [2, 1, 0, 5, 4, 3, 8, 7, 6]);
+        assert_eq!(image().flip_vertical().into_raw(), [6, 7, 8, 3, 4, 5, 0, 1, 2]);
+        let mut buffer = image();
+        buffer.flip_horizontal_in_place();
+        assert_eq!(buffer.clone().into_raw(), image().flip_horizontal().into_raw());
+        buffer.flip_vertical_in_place();
+        assert_eq!(buffer.into_raw(), image().rotate180().into_raw());
+    }
+
+    #[test]
+    fn test_rotations() {
+        let buffer = GrayImage::from_fn(3, 2, |x, y| Gray::new([(x + 3 * y) as u8]));
+        assert_eq!(buffer.rotate270().into_raw(), [2, 5, 1, 4, 0, 3]);
+        assert_eq!(buffer.rotate90().rotate90().into_raw(), buffer.rotate180().into_raw());
+        assert_eq!(buffer.rotate90().rotate270().into_raw(), buffer.clone().into_raw());
+        let mut rotated = buffer.clone();
+        rotated.rotate180_in_place();
+        assert_eq!(rotated.into_raw(), [5, 4, 3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn test_in_place_with_stride() {
+        let mut buffer: ImageBuffer<Gray<u8>, _> =
+            ImageBuffer::from_raw_with_stride(2, 2, 3, vec![0, 1, 9, 2, 3]).unwrap();
+        buffer.rotate180_in_place();
+        assert_eq!(buffer.into_raw(), [3, 2, 9, 1, 0]);
+    }
+}
@@ -0,0 +1,737 @@
+use std::cmp;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut, Index, IndexMut};
+use std::slice;
+
+use buffer::{pixel_slice, pixel_slice_mut, ImageBuffer};
+use traits::{Color as Pixel, ImageView, ImageViewMut, PodColor};
+
+/// A borrowed view into a rectangular region of an image buffer.
+///
+/// Created by `ImageBuffer::view`. Pixel coordinates are relative to the top left corner of the
+/// region.
+pub struct SubImage<'a, P: Pixel + 'a, Container: Deref<Target = [P::Subpixel]> + 'a> {
+    buffer: &'a ImageBuffer<P, Container>,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+/// A mutably borrowed view into a rectangular region of an image buffer.
+///
+/// Created by `ImageBuffer::view_mut`. Pixel coordinates are relative to the top left corner of
+/// the region.
+pub struct SubImageMut<'a, P: Pixel + 'a, Container: Deref<Target = [P::Subpixel]> + 'a> {
+    buffer: &'a mut ImageBuffer<P, Container>,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+/// A mutable view into a tile of an image buffer.
+///
+/// Created by `ImageBuffer::tiles_mut`. Unlike `SubImageMut`, the tiles of one image can be
+/// borrowed at the same time, for example to process them on different threads. Pixel
+/// coordinates are relative to the top left corner of the tile.
+pub struct TileMut<'a, P: Pixel + 'a> {
+    /// The first subpixel of the tile
+    data: *mut P::Subpixel,
+    stride: usize,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    _buffer: PhantomData<&'a mut [P::Subpixel]>,
+}
+
+// A tile only accesses its own pixels, which are disjoint from those of every other tile
+unsafe impl<'a, P: Pixel> Send for TileMut<'a, P> where P::Subpixel: Send {}
+unsafe impl<'a, P: Pixel> Sync for TileMut<'a, P> where P::Subpixel: Sync {}
+
+/// Returns the number of tiles of the given size per row and in total, counting partial tiles.
+fn tile_grid((width, height): (u32, u32), tile_width: u32, tile_height: u32) -> (u32, usize) {
+    assert!(tile_width > 0 && tile_height > 0,
+            "tile dimensions ({}, {}) must not be zero",
+            tile_width,
+            tile_height);
+    let columns = width.div_ceil(tile_width);
+    // The number of tiles can exceed `u32::MAX`, for example with 1×1 tiles
+    (columns, columns as usize * height.div_ceil(tile_height) as usize)
+}
+
+/// Returns the region `(x, y, width, height)` of tile `index` of a grid with `columns` tiles
+/// per row, clipped to the image.
+fn tile_region((width, height): (u32, u32),
+               (tile_width, tile_height): (u32, u32),
+               columns: u32,
+               index: usize)
+               -> (u32, u32, u32, u32) {
+    let (column, row) = (index % columns as usize, index / columns as usize);
+    let (x, y) = (column as u32 * tile_width, row as u32 * tile_height);
+    (x, y, cmp::min(tile_width, width - x), cmp::min(tile_height, height - y))
+}
+
+/// Panics if the region is not contained in an image of the given dimensions.
+pub(crate) fn check_region((width, height): (u32, u32), x: u32, y: u32, w: u32, h: u32) {
+    assert!(x as u64 + w as u64 <= width as u64 && y as u64 + h as u64 <= height as u64,
+            "region ({}, {}, {}, {}) out of bounds of image with dimensions ({}, {})",
+            x,
+            y,
+            w,
+            h,
+            width,
+            height);
+}
+
+macro_rules! implement_sub_image {
+    {$(
+        $ident: ident,
+    )*} => {
+$( // START Implementations
+
+impl<'a, P, Container> $ident<'a, P, Container>
+    where P: Pixel,
+          Container: Deref<Target = [P::Subpixel]>
+{
+    /// The width and height of this view.
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// The width of this view.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The height of this view.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The position of the top left corner of this view in the parent image.
+    pub fn offsets(&self) -> (u32, u32) {
+        (self.x, self.y)
+    }
+}
+
+impl<'a, P, Container> Index<(u32, u32)> for $ident<'a, P, Container>
+    where P: Pixel,
+          Container: Deref<Target = [P::Subpixel]>
+{
+    type Output = P;
+
+    fn index(&self, (x, y): (u32, u32)) -> &P {
+        assert!(x < self.width && y < self.height,
+                "pixel ({}, {}) out of bounds of view with dimensions ({}, {})",
+                x,
+                y,
+                self.width,
+                self.height);
+        &self.buffer[(self.x + x, self.y + y)]
+    }
+}
+
+impl<'a, P, Container> ImageView<P> for $ident<'a, P, Container>
+    where P: Pixel,
+          Container: Deref<Target = [P::Subpixel]>
+{
+    fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+}
+
+)* // END Implementations
+    }
+}
+
+implement_sub_image!(
+    SubImage,
+    SubImageMut,
+);
+
+impl<'a, P, Container> IndexMut<(u32, u32)> for SubImageMut<'a, P, Container>
+    where P: Pixel,
+          Container: Deref<Target = [P::Subpixel]> + DerefMut
+{
+    fn index_mut(&mut self, (x, y): (u32, u32)) -> &mut P {
+        assert!(x < self.width && y < self.height,
+                "pixel ({}, {}) out of bounds of view with dimensions ({}, {})",
+                x,
+                y,
+                self.width,
+                self.height);
+        &mut self.buffer[(self.x + x, self.y + y)]
+    }
+}
+
+impl<'a, P, Container> ImageViewMut<P> for SubImageMut<'a, P, Container>
+    where P: Pixel,
+          Container: Deref<Target = [P::Subpixel]> + DerefMut
+{
+}
+
+impl<'a, P: Pixel> TileMut<'a, P> {
+    /// The width and height of this tile.
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// The width of this tile.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The height of this tile.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The position of the top left corner of this tile in the parent image.
+    pub fn offsets(&self) -> (u32, u32) {
+        (self.x, self.y)
+    }
+
+    /// Returns the subpixels of row `y` of the tile.
+    fn subpixel_row(&self, y: u32) -> &[P::Subpixel] {
+        let (start, len) = self.row_range(y);
+        // The row lies within the pixels of this tile
+        unsafe { slice::from_raw_parts(self.data.add(start), len) }
+    }
+
+    /// Returns the mutable subpixels of row `y` of the tile.
+    fn subpixel_row_mut(&mut self, y: u32) -> &mut [P::Subpixel] {
+        let (start, len) = self.row_range(y);
+        // The row lies within the pixels of this tile
+        unsafe { slice::from_raw_parts_mut(self.data.add(start), len) }
+    }
+
+    /// Returns the offset and length in subpixels of row `y`.
+    fn row_range(&self, y: u32) -> (usize, usize) {
+        assert!(y < self.height,
+                "row {} out of bounds of tile with height {}",
+                y,
+                self.height);
+        (y as usize * self.stride, self.width as usize * P::channel_count())
+    }
+
+    /// Panics if `(x, y)` is out of bounds.
+    fn check_pixel(&self, x: u32, y: u32) {
+        assert!(x < self.width && y < self.height,
+                "pixel ({}, {}) out of bounds of tile with dimensions ({}, {})",
+                x,
+                y,
+                self.width,
+                self.height);
+    }
+}
+
+impl<'a, P: PodColor> TileMut<'a, P> {
+    /// Returns the pixels of row `y` of the tile.
+    ///
+    /// # Panics
+    ///
+    /// If `y` is out of bounds.
+    pub fn row(&self, y: u32) -> &[P] {
+        pixel_slice(self.subpixel_row(y))
+    }
+
+    /// Returns the mutable pixels of row `y` of the tile.
+    ///
+    /// # Panics
+    ///
+    /// If `y` is out of bounds.
+    pub fn row_mut(&mut self, y: u32) -> &mut [P] {
+        pixel_slice_mut(self.subpixel_row_mut(y))
+    }
+}
+
+impl<'a, P: Pixel> Index<(u32, u32)> for TileMut<'a, P> {
+    type Output = P;
+
+    fn index(&self, (x, y): (u32, u32)) -> &P {
+        self.check_pixel(x, y);
+        let channels = P::channel_count();
+        let start = x as usize * channels;
+        P::from_slice(&self.subpixel_row(y)[start..start + channels])
+    }
+}
+
+impl<'a, P: Pixel> IndexMut<(u32, u32)> for TileMut<'a, P> {
+    fn index_mut(&mut self, (x, y): (u32, u32)) -> &mut P {
+        self.check_pixel(x, y);
+        let channels = P::channel_count();
+        let start = x as usize * channels;
+        P::from_slice_mut(&mut self.subpixel_row_mut(y)[start..start + channels])
+    }
+}
+
+impl<'a, P: Pixel> ImageView<P> for TileMut<'a, P> {
+    fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+}
+
+impl<'a, P: Pixel> ImageViewMut<P> for TileMut<'a, P> {}
+
+impl<P, Container> ImageBuffer<P, Container>
+    where P: Pixel,
+          Container: Deref<Target = [P::Subpixel]>
+{
+    /// Returns a view into the rectangular region with the top left corner `(x, y)` and the
+    /// dimensions `(width, height)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the region is not contained in the image.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{GrayImage, color};
+    /// let buffer = GrayImage::from_fn(10, 10, |x, y| color::Gray::new([(x + y) as u8]));
+    /// let view = buffer.view(5, 5, 2, 2);
+    /// assert_eq!(view[(1, 1)], color::Gray::new([12]));
+    /// ```
+    pub fn view<'a>(&'a self,
+                    x: u32,
+                    y: u32,
+                    width: u32,
+                    height: u32)
+                    -> SubImage<'a, P, Container> {
+        check_region(self.dimensions(), x, y, width, height);
+        SubImage {
+            buffer: self,
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Returns an iterator over views of the tiles of the image, row by row.
+    ///
+    /// The tiles are `tile_width` by `tile_height` pixels large and do not overlap, the tiles at
+    /// the right and bottom edges are smaller if the dimensions of the image are not multiples
+    /// of the tile size.
+    ///
+    /// # Panics
+    ///
+    /// If `tile_width` or `tile_height` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{GrayImage, color};
+    /// let buffer = GrayImage::from_fn(5, 3, |x, y| color::Gray::new([(x + 5 * y) as u8]));
+    /// let tiles: Vec<_> = buffer.tiles(2, 2).collect();
+    /// assert_eq!(tiles.len(), 6);
+    /// assert_eq!((tiles[2].offsets(), tiles[2].dimensions()), ((4, 0), (1, 2)));
+    /// assert_eq!(tiles[4][(1, 0)], color::Gray::new([13]));
+    /// ```
+    pub fn tiles<'a>(&'a self,
+                     tile_width: u32,
+                     tile_height: u32)
+                     -> impl ExactSizeIterator<Item = SubImage<'a, P, Container>> + 'a {
+        let (columns, count) = tile_grid(self.dimensions(), tile_width, tile_height);
+        let tile = (tile_width, tile_height);
+        (0..count).map(move |i| {
+            let (x, y, width, height) = tile_region(self.dimensions(), tile, columns, i);
+            self.view(x, y, width, height)
+        })
+    }
+
+    /// Copies the rectangular region with the top left corner `(x, y)` and the dimensions
+    /// `(width, height)` into a new buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the region is not contained in the image.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{GrayImage, color};
+    /// let buffer = GrayImage::from_fn(4, 4, |x, y| color::Gray::new([(x + 4 * y) as u8]));
+    /// assert_eq!(buffer.crop(1, 2, 2, 2).into_raw(), vec![9, 10, 13, 14]);
+    /// ```
+    pub fn crop(&self,
+                x: u32,
+                y: u32,
+                width: u32,
+                height: u32)
+                -> ImageBuffer<P, Vec<P::Subpixel>> {
+        check_region(self.dimensions(), x, y, width, height);
+        profile_op!("crop", 2 * ::profiling::pixel_bytes::<P>(width, height));
+        let channels = P::channel_count();
+        let (start, len) = (x as usize * channels, width as usize * channels);
+        let mut data = Vec::with_capacity(len * height as usize);
+        for row in self.subpixel_rows().skip(y as usize).take(height as usize) {
+            data.extend_from_slice(&row[start..start + len]);
+        }
+        ImageBuffer::from_raw(width, height, data).unwrap()
+    }
+}
+
+impl<P, Container> ImageBuffer<P, Container>
+    where P: Pixel,
+          Container: Deref<Target = [P::Subpixel]> + DerefMut
+{
+    /// Returns a mutable view into the rectangular region with the top left corner `(x, y)` and
+    /// the dimensions `(width, height)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the region is not contained in the image.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{GrayImage, color};
+    /// let mut buffer = GrayImage::new(10, 10);
+    /// buffer.view_mut(5, 5, 2, 2)[(1, 1)] = color::Gray::new([255]);
+    /// assert_eq!(buffer[(6, 6)], color::Gray::new([255]));
+    /// ```
+    pub fn view_mut<'a>(&'a mut self,
+                        x: u32,
+                        y: u32,
+                        width: u32,
+                        height: u32)
+                        -> SubImageMut<'a, P, Container> {
+        check_region(self.dimensions(), x, y, width, height);
+        SubImageMut {
+            buffer: self,
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Returns an iterator over mutable views of the tiles of the image, row by row.
+    ///
+    /// The tiles are laid out like those of `tiles`. They do not overlap, so all of them can be
+    /// held at the same time.
+    ///
+    /// # Panics
+    ///
+    /// If `tile_width` or `tile_height` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{GrayImage, color};
+    /// let mut buffer = GrayImage::new(5, 3);
+    /// for (i, mut tile) in buffer.tiles_mut(2, 2).enumerate() {
+    ///     tile[(0, 0)] = color::Gray::new([i as u8 + 1]);
+    /// }
+    /// assert_eq!(buffer.into_raw(), [1, 0, 2, 0, 3, 0, 0, 0, 0, 0, 4, 0, 5, 0, 6]);
+    /// ```
+    pub fn tiles_mut<'a>(&'a mut self,
+                         tile_width: u32,
+                         tile_height: u32)
+                         -> impl ExactSizeIterator<Item = TileMut<'a, P>> + 'a {
+        let dimensions = self.dimensions();
+        let (columns, count) = tile_grid(dimensions, tile_width, tile_height);
+        let tile = (tile_width, tile_height);
+        let stride = self.stride();
+        let channels = P::channel_count();
+        let data: &'a mut [P::Subpixel] = self;
+        let data = data.as_mut_ptr();
+        (0..count).map(move |i| {
+            let (x, y, width, height) = tile_region(dimensions, tile, columns, i);
+            TileMut {
+                // The tile lies within the image, so the offset is in bounds of the buffer
+                data: unsafe { data.add(y as usize * stride + x as usize * channels) },
+                stride,
+                x,
+                y,
+                width,
+                height,
+                _buffer: PhantomData,
+            }
+        })
+    }
+}
+
+impl<P, Container> ImageBuffer<P, Container>
+    where P: Pixel,
+          Container: Deref<Target = [P::Subpixel]> + DerefMut
+{
+    /// Copies all pixels of `other` into this image with the top left corner at `(x, y)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `other` does not fit into the image at `(x, y)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{GrayImage, color};
+    /// let mut buffer = GrayImage::new(10, 10);
+    /// let sprite = GrayImage::from_pixel(2, 2, color::Gray::new([255]));
+    /// buffer.copy_from(&sprite, 3, 4);
+    /// assert_eq!(buffer[(4, 5)], color::Gray::new([255]));
+    /// ```
+    pub fn copy_from<C>(&mut self, other: &ImageBuffer<P, C>, x: u32, y: u32)
+        where C: Deref<Target = [P::Subpixel]>
+    {
+        let (width, height) = other.dimensions();
+        self.copy_region_from(other, (0, 0, width, height), x, y)
+    }
+
+    /// Copies all pixels of the view `other` into this image with the top left corner at
+    /// `(x, y)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `other` does not fit into the image at `(x, y)`.
+    pub fn copy_from_view<C>(&mut self, other: &SubImage<P, C>, x: u32, y: u32)
+        where C: Deref<Target = [P::Subpixel]>
+    {
+        let region = (other.x, other.y, other.width, other.height);
+        self.copy_region_from(other.buffer, region, x, y)
+    }
+
+    /// Copies the region `(x, y, width, height)` of `other` to `(dst_x, dst_y)`.
+    fn copy_region_from<C>(&mut self,
+                           other: &ImageBuffer<P, C>,
+                           (x, y, width, height): (u32, u32, u32, u32),
+                           dst_x: u32,
+                           dst_y: u32)
+        where C: Deref<Target = [P::Subpixel]>
+    {
+        check_region(other.dimensions(), x, y, width, height);
+        check_region(self.dimensions(), dst_x, dst_y, width, height);
+        let channels = P::channel_count();
+        let len = width as usize * channels;
+        let (src_stride, dst_stride) = (other.stride(), self.stride());
+        let src: &[P::Subpixel] = other;
+        let dst: &mut [P::Subpixel] = self;
+        for row in 0..height as usize {
+            let from = (y as usize + row) * src_stride + x as usize * channels;
+            let to = (dst_y as usize + row) * dst_stride + dst_x as usize * channels;
+            dst[to..to + len].copy_from_slice(&src[from..from + len]);
+        }
+    }
+
+    /// Copies the region `(x, y, width, height)` of this image to `(dst_x, dst_y)`.
+    ///
+    /// The source and destination regions may overlap.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the source or destination region is not contained in the image.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{GrayImage, color};
+    /// let mut buffer = GrayImage::from_fn(4, 1, |x, _| color::Gray::new([x as u8]));
+    /// buffer.copy_within((0, 0, 3, 1), 1, 0);
+    /// assert_eq!(buffer.into_raw(), [0, 0, 1, 2]);
+    /// ```
+    pub fn copy_within(&mut self,
+                       (x, y, width, height): (u32, u32, u32, u32),
+                       dst_x: u32,
+                       dst_y: u32) {
+        check_region(self.dimensions(), x, y, width, height);
+        check_region(self.dimensions(), dst_x, dst_y, width, height);
+        let channels = P::channel_count();
+        let len = width as usize * channels;
+        let stride = self.stride();
+        let data: &mut [P::Subpixel] = self;
+        let copy_row = |data: &mut [P::Subpixel], row: usize| {
+            let from = (y as usize + row) * stride + x as usize * channels;
+            let to = (dst_y as usize + row) * stride + dst_x as usize * channels;
+            data.copy_within(from..from + len, to);
+        };
+        // Copy the rows in an order which does not overwrite rows that still have to be read
+        if dst_y > y {
+            for row in (0..height as usize).rev() {
+                copy_row(data, row)
+            }
+        } else {
+            for row in 0..height as usize {
+                copy_row(data, row)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{tile_grid, tile_region};
+    use buffer::GrayImage;
+    use color_model::Gray;
+    use traits::{ImageView, ImageViewMut};
+
+    fn image() -> GrayImage {
+        GrayImage::from_fn(3, 3, |x, y| Gray::new([(x + 3 * y) as u8]))
+    }
+
+    #[test]
+    fn test_view() {
+        let data: Vec<u8> = (0..14).collect();
+        let strided = GrayImage::from_raw_with_stride(3, 3, 5, data).unwrap();
+        let view = strided.view(1, 1, 2, 2);
+        assert_eq!((view.offsets(), view.dimensions()), ((1, 1), (2, 2)));
+        assert_eq!((view.width(), view.height()), (2, 2));
+        assert_eq!([view[(0, 0)][0], view[(1, 0)][0], view[(0, 1)][0], view[(1, 1)][0]],
+                   [6, 7, 11, 12]);
+        // Empty views at the edges are allowed
+        assert_eq!(strided.view(3, 0, 0, 3).dimensions(), (0, 3));
+        assert_eq!(strided.view(0, 3, 3, 0).dimensions(), (3, 0));
+    }
+
+    #[test]
+    fn test_view_mut() {
+        let mut buffer = image();
+        {
+            let mut view = buffer.view_mut(2, 0, 1, 2);
+            assert_eq!(view.offsets(), (2, 0));
+            view[(0, 1)] = Gray::new([100]);
+            assert_eq!(view[(0, 0)], Gray::new([2]));
+        }
+        assert_eq!(buffer.into_raw(), [0, 1, 2, 3, 4, 100, 6, 7, 8]);
+    }
+
+    #[test]
+    #[should_panic(expected = "region (2, 2, 2, 1) out of bounds")]
+    fn test_view_out_of_bounds() {
+        image().view(2, 2, 2, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "pixel (2, 0) out of bounds of view")]
+    fn test_view_index_out_of_bounds() {
+        // The pixel is within the parent image but outside of the view
+        let buffer = image();
+        let _ = buffer.view(0, 0, 2, 2)[(2, 0)];
+    }
+
+    #[test]
+    #[should_panic(expected = "pixel (0, 1) out of bounds of view")]
+    fn test_view_mut_index_out_of_bounds() {
+        let mut buffer = image();
+        buffer.view_mut(1, 1, 2, 1)[(0, 1)] = Gray::new([0]);
+    }
+
+    #[test]
+    fn test_crop() {
+        let data: Vec<u8> = (0..14).collect();
+        let strided = GrayImage::from_raw_with_stride(3, 3, 5, data).unwrap();
+        assert_eq!(strided.crop(1, 1, 2, 2).into_raw(), vec![6, 7, 11, 12]);
+        assert_eq!(strided.crop(3, 0, 0, 3).dimensions(), (0, 3));
+
+        let mut cropped = strided.clone();
+        cropped.crop_in_place(1, 1, 2, 2);
+        assert!(cropped.is_packed());
+        assert_eq!(cropped.into_raw(), vec![6, 7, 11, 12]);
+        let mut empty = strided;
+        empty.crop_in_place(0, 3, 3, 0);
+        assert_eq!(empty.dimensions(), (3, 0));
+        assert!(empty.into_raw().is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_crop_out_of_bounds() {
+        image().crop(2, 2, 2, 1);
+    }
+
+    #[test]
+    fn test_copy_within_overlapping() {
+        let mut buffer = image();
+        buffer.copy_within((0, 0, 2, 2), 1, 1);
+        assert_eq!(buffer.into_raw(), [0, 1, 2, 3, 0, 1, 6, 3, 4]);
+        let mut buffer = image();
+        buffer.copy_within((1, 1, 2, 2), 0, 0);
+        assert_eq!(buffer.into_raw(), [4, 5, 2, 7, 8, 5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn test_copy_from_view() {
+        let source = image();
+        let mut buffer = GrayImage::new(3, 3);
+        buffer.copy_from_view(&source.view(1, 0, 2, 2), 0, 1);
+        assert_eq!(buffer.into_raw(), [0, 0, 0, 1, 2, 0, 4, 5, 0]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_copy_from_out_of_bounds() {
+        let mut buffer = GrayImage::new(3, 3);
+        buffer.copy_from(&image(), 1, 0);
+    }
+
+    fn invert<V: ImageViewMut<Gray<u8>>>(view: &mut V) {
+        let (width, height) = view.dimensions();
+        for y in 0..height {
+            for x in 0..width {
+                let v = view.get_pixel(x, y)[0];
+                view.put_pixel(x, y, Gray::new([255 - v]));
+            }
+        }
+    }
+
+    #[test]
+    fn test_generic_views() {
+        let mut buffer = image();
+        invert(&mut buffer.view_mut(1, 1, 2, 2));
+        assert_eq!(buffer.into_raw(), [0, 1, 2, 3, 251, 250, 6, 248, 247]);
+
+        let buffer = image();
+        let view = buffer.view(1, 0, 2, 3);
+        assert_eq!(ImageView::dimensions(&view), (2, 3));
+        assert_eq!(view.get_pixel_checked(1, 2), Some(&Gray::new([8])));
+        assert_eq!(view.get_pixel_checked(2, 0), None);
+        let pixels = view.enumerate_pixels();
+        assert_eq!(pixels.len(), 6);
+        let coords: Vec<(u32, u32, u8)> = pixels.map(|(x, y, p)| (x, y, p[0])).collect();
+        assert_eq!(coords, [(0, 0, 1), (1, 0, 2), (0, 1, 4), (1, 1, 5), (0, 2, 7), (1, 2, 8)]);
+
+        let mut target = GrayImage::new(4, 4);
+        target.copy_view(&view, 2, 1);
+        assert_eq!(target[(3, 3)], Gray::new([8]));
+        assert_eq!(target.get_pixel_mut(2, 1)[0], 1);
+        assert_eq!(ImageView::enumerate_pixels(&GrayImage::new(0, 3)).count(), 0);
+    }
+
+    #[test]
+    fn test_tiles() {
+        let data: Vec<u8> = (0..24).collect();
+        let mut strided = GrayImage::from_raw_with_stride(5, 3, 7, data).unwrap();
+        let regions: Vec<_> = strided.tiles(3, 2).map(|t| (t.offsets(), t.dimensions())).collect();
+        assert_eq!(regions,
+                   [((0, 0), (3, 2)), ((3, 0), (2, 2)), ((0, 2), (3, 1)), ((3, 2), (2, 1))]);
+        assert_eq!(strided.tiles(3, 2).nth(3).unwrap()[(1, 0)], Gray::new([18]));
+        assert_eq!(strided.tiles(5, 5).len(), 1);
+        assert_eq!(GrayImage::new(0, 4).tiles(1, 1).len(), 0);
+
+        let mut tiles: Vec<_> = strided.tiles_mut(3, 2).collect();
+        let (first, rest) = tiles.split_at_mut(1);
+        first[0][(2, 1)] = Gray::new([100]);
+        rest[2].row_mut(0).copy_from_slice(&[Gray::new([200]), Gray::new([201])]);
+        assert_eq!(rest[0].row(1), &[Gray::new([10]), Gray::new([11])]);
+        invert(&mut rest[1]);
+        assert_eq!(strided.into_raw(),
+                   [0, 1, 2, 3, 4, 5, 6, 7, 8, 100, 10, 11, 12, 13, 241, 240, 239, 200, 201,
+                    19, 20, 21, 22, 23]);
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn test_tile_grid_overflow() {
+        let (columns, count) = tile_grid((65536, 65536), 1, 1);
+        assert_eq!(count, 1 << 32);
+        assert_eq!(tile_region((65536, 65536), (1, 1), columns, count - 1), (65535, 65535, 1, 1));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_tiles_zero_size() {
+        image().tiles(0, 1).count();
+    }
+}
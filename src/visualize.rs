@@ -0,0 +1,336 @@
+//! Diagnostic visualizations of color data.
+
+use std::ops::Deref;
+
+use buffer::{GrayImage, ImageBuffer, RgbImage};
+use color_model::{ChromaticAdaptation, Gray, Lab, Matrix3, Rgb, RgbSpace, Xyz, YCbCr};
+use math::from_f32;
+use traits::Color as Pixel;
+use color_model::rgb::srgb_compress_gamma_rounded;
+use preview::luma;
+use color_model::{transform, D65};
+
+/// Colors of the checkerboard marking out-of-gamut regions.
+const OUT_OF_GAMUT: [Rgb<u8>; 2] = [Rgb::new([128, 128, 128]), Rgb::new([96, 96, 96])];
+
+/// Size of the cells of the out-of-gamut checkerboard in pixels.
+const CHECKER_SIZE: u32 = 8;
+
+/// Renders the plane of constant lightness `lightness` of the CIE L*a*b* space and marks the
+/// colors which are not representable in `space`.
+///
+/// The image has `size`×`size` pixels, the a* axis runs from `-range` on the left to `range` on
+/// the right and the b* axis from `range` at the top to `-range` at the bottom. The Lab values
+/// are relative to the white point of `space`. In-gamut colors are shown as sRGB, out-of-gamut
+/// colors are covered by a gray checkerboard.
+///
+/// # Examples
+///
+/// ```
+/// # use image_buffer::color::{RgbSpace, Rgb};
+/// use image_buffer::visualize::gamut_slice;
+/// let slice = gamut_slice(&RgbSpace::SRGB, 50.0, 65, 128.0);
+/// // The neutral center is always in gamut
+/// assert_eq!(slice[(32, 32)], Rgb::new([119, 119, 119]));
+/// ```
+pub fn gamut_slice(space: &RgbSpace, lightness: f32, size: u32, range: f32) -> RgbImage {
+    render_slice(space, size, range, |a, b| Lab::new([lightness, a, b]).to_xyz(space.white))
+}
+
+/// Renders the plane of constant lightness `lightness` of the Oklab space and marks the colors
+/// which are not representable in `space`.
+///
+/// Works like `gamut_slice`, with the lightness in `[0, 1]` and the a and b axes typically
+/// within `[-0.4, 0.4]`. Oklab is defined relative to D65, the colors are adapted to the white
+/// point of `space` before they are tested.
+///
+/// # Examples
+///
+/// ```
+/// # use image_buffer::color::{RgbSpace, Rgb};
+/// use image_buffer::visualize::oklab_gamut_slice;
+/// let slice = oklab_gamut_slice(&RgbSpace::SRGB, 0.5, 65, 0.4);
+/// // The neutral center is always in gamut
+/// assert_eq!(slice[(32, 32)], Rgb::new([99, 99, 99]));
+/// ```
+pub fn oklab_gamut_slice(space: &RgbSpace, lightness: f32, size: u32, range: f32) -> RgbImage {
+    render_slice(space, size, range, |a, b| {
+        oklab_to_xyz([lightness, a, b]).adapt(D65, space.white, ChromaticAdaptation::Bradford)
+    })
+}
+
+/// Renders the `size`×`size` plane spanned by the chroma axes from `-range` to `range`, where
+/// `to_xyz` returns the color at the chroma `(a, b)` relative to the white point of `space`.
+fn render_slice<F>(space: &RgbSpace, size: u32, range: f32, to_xyz: F) -> RgbImage
+    where F: Fn(f32, f32) -> Xyz<f32>
+{
+    let to_space = space.xyz_to_rgb_matrix();
+    let to_srgb = RgbSpace::SRGB.xyz_to_rgb_matrix();
+    let scale = 2.0 * range / size as f32;
+    RgbImage::from_fn(size, size, |x, y| {
+        let a = (x as f32 + 0.5) * scale - range;
+        let b = range - (y as f32 + 0.5) * scale;
+        let xyz = to_xyz(a, b);
+        let rgb = transform(&to_space, [xyz[0], xyz[1], xyz[2]]);
+        if rgb.iter().any(|&c| !(-1e-4..=1.0 + 1e-4).contains(&c)) {
+            return OUT_OF_GAMUT[((x / CHECKER_SIZE + y / CHECKER_SIZE) % 2) as usize];
+        }
+        let xyz = xyz.adapt(space.white, D65, ChromaticAdaptation::Bradford);
+        let srgb = transform(&to_srgb, [xyz[0], xyz[1], xyz[2]]);
+        let encode = |c: f32| srgb_compress_gamma_rounded(c.clamp(0.0, 1.0));
+        Rgb::new([encode(srgb[0]), encode(srgb[1]), encode(srgb[2])])
+    })
+}
+
+/// Converts an Oklab color to CIE XYZ relative to D65.
+fn oklab_to_xyz(lab: [f32; 3]) -> Xyz<f32> {
+    const LAB_TO_LMS: Matrix3 = [[1.0, 0.396_337_78, 0.215_803_76],
+                                 [1.0, -0.105_561_346, -0.063_854_17],
+                                 [1.0, -0.089_484_18, -1.291_485_5]];
+    // To linear sRGB, which maps the white of Oklab exactly to the D65 white
+    const LMS_TO_SRGB: Matrix3 = [[4.076_741_7, -3.307_711_6, 0.230_969_94],
+                                  [-1.268_438, 2.609_757_4, -0.341_319_38],
+                                  [-0.004_196_086_3, -0.703_418_6, 1.707_614_7]];
+    let lms = transform(&LAB_TO_LMS, lab);
+    let rgb = transform(&LMS_TO_SRGB, [lms[0].powi(3), lms[1].powi(3), lms[2].powi(3)]);
+    Xyz::new(transform(&RgbSpace::SRGB.rgb_to_xyz_matrix(), rgb))
+}
+
+/// The data shown by `render_histogram`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum HistogramMode {
+    /// A single white histogram of the luma.
+    Luma,
+    /// Overlaid red, green and blue histograms of the channels.
+    Channels,
+}
+
+/// Configuration of `render_histogram`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct HistogramConfig {
+    /// The width of the rendered image.
+    pub width: u32,
+    /// The height of the rendered image.
+    pub height: u32,
+    /// The data shown by the histogram.
+    pub mode: HistogramMode,
+    /// Scales the bars logarithmically, which makes small counts visible.
+    pub log_scale: bool,
+}
+
+impl Default for HistogramConfig {
+    fn default() -> HistogramConfig {
+        HistogramConfig {
+            width: 256,
+            height: 128,
+            mode: HistogramMode::Luma,
+            log_scale: false,
+        }
+    }
+}
+
+/// Renders a histogram of the 8-bit sRGB values of `image` as bars on a black background.
+///
+/// Each column of the result shows the bins of the values falling into it. Histograms of
+/// multiple channels are blended additively where they overlap.
+///
+/// # Examples
+///
+/// ```
+/// # use image_buffer::{GrayImage, color};
+/// use image_buffer::visualize::{render_histogram, HistogramConfig};
+/// let image = GrayImage::from_fn(256, 1, |x, _| color::Gray::new([x as u8]));
+/// let plot = render_histogram(&image, &HistogramConfig::default());
+/// assert_eq!(plot.dimensions(), (256, 128));
+/// assert_eq!(plot[(17, 0)], color::Rgb::new([255, 255, 255]));
+/// ```
+pub fn render_histogram<P, C>(image: &ImageBuffer<P, C>, config: &HistogramConfig) -> RgbImage
+    where P: Pixel,
+          Rgb<u8>: From<P>,
+          C: Deref<Target = [P::Subpixel]>
+{
+    let mut bins = [[0u64; 256]; 3];
+    for &p in image.pixels() {
+        let rgb = Rgb::from(p);
+        match config.mode {
+            HistogramMode::Luma => bins[0][luma(rgb) as usize] += 1,
+            HistogramMode::Channels => {
+                for (channel, &v) in bins.iter_mut().zip(rgb.as_ref().iter()) {
+                    channel[v as usize] += 1;
+                }
+            }
+        }
+    }
+    let (width, height) = (config.width, config.height);
+    let scale = |count: u64| if config.log_scale {
+        (count as f64).ln_1p()
+    } else {
+        count as f64
+    };
+    // Height of the bar of every channel in each column
+    let columns: Vec<[u32; 3]> = (0..width as usize)
+        .map(|x| {
+            let (from, to) = (x * 256 / width as usize, ((x + 1) * 256).div_ceil(width as usize));
+            let mut bars = [0; 3];
+            for (bar, channel) in bars.iter_mut().zip(bins.iter()) {
+                let max = channel.iter().cloned().max().unwrap_or(0);
+                if max > 0 {
+                    let count = channel[from..to].iter().cloned().max().unwrap_or(0);
+                    *bar = (scale(count) / scale(max) * height as f64).round() as u32;
+                }
+            }
+            bars
+        })
+        .collect();
+    RgbImage::from_fn(width, height, |x, y| {
+        let bars = columns[x as usize];
+        let level = height - y;
+        match config.mode {
+            HistogramMode::Luma => {
+                let v = if bars[0] >= level { 255 } else { 0 };
+                Rgb::new([v, v, v])
+            }
+            HistogramMode::Channels => {
+                let on = |bar: u32| if bar >= level { 255 } else { 0 };
+                Rgb::new([on(bars[0]), on(bars[1]), on(bars[2])])
+            }
+        }
+    })
+}
+
+/// Converts hit counts to an image with the brightness proportional to the square root of the
+/// counts, such that rarely hit positions remain visible.
+fn density_image(width: u32, height: u32, counts: &[u32]) -> GrayImage {
+    let max = counts.iter().cloned().max().unwrap_or(0).max(1) as f32;
+    GrayImage::from_fn(width, height, |x, y| {
+        let count = counts[(y * width + x) as usize] as f32;
+        Gray::new([from_f32((count / max).sqrt() * 255.0)])
+    })
+}
+
+/// Renders a broadcast-style luma waveform of `image` with the given `height`.
+///
+/// Every column of the result shows the distribution of the luma values of the corresponding
+/// column of `image`, with black at the bottom and white at the top. The brightness indicates
+/// how many pixels have the luma value.
+///
+/// # Examples
+///
+/// ```
+/// # use image_buffer::{GrayImage, color};
+/// use image_buffer::visualize::render_waveform;
+/// let image = GrayImage::from_fn(4, 10, |x, _| color::Gray::new([x as u8 * 85]));
+/// let waveform = render_waveform(&image, 256);
+/// assert_eq!(waveform[(0, 255)], color::Gray::new([255]));
+/// assert_eq!(waveform[(3, 0)], color::Gray::new([255]));
+/// ```
+pub fn render_waveform<P, C>(image: &ImageBuffer<P, C>, height: u32) -> GrayImage
+    where P: Pixel,
+          Rgb<u8>: From<P>,
+          C: Deref<Target = [P::Subpixel]>
+{
+    let width = image.width();
+    let mut counts = vec![0u32; width as usize * height as usize];
+    if height == 0 {
+        return density_image(width, height, &counts);
+    }
+    for (x, _, &p) in image.enumerate_pixels() {
+        let y = (255 - luma(Rgb::from(p))) * height / 256;
+        counts[(y * width + x) as usize] += 1;
+    }
+    density_image(width, height, &counts)
+}
+
+/// Renders a vectorscope of `image` with `size`×`size` pixels.
+///
+/// Every pixel of `image` is plotted at the position given by its BT.601 chroma, with Cb
+/// increasing to the right and Cr increasing to the top. Neutral colors are plotted at the
+/// center. The brightness indicates how many pixels have the chroma.
+///
+/// # Examples
+///
+/// ```
+/// # use image_buffer::{RgbImage, color};
+/// use image_buffer::visualize::render_vectorscope;
+/// let image = RgbImage::from_pixel(4, 4, color::Rgb::new([128, 128, 128]));
+/// let scope = render_vectorscope(&image, 64);
+/// assert_eq!(scope[(32, 31)], color::Gray::new([255]));
+/// ```
+pub fn render_vectorscope<P, C>(image: &ImageBuffer<P, C>, size: u32) -> GrayImage
+    where P: Pixel,
+          Rgb<u8>: From<P>,
+          C: Deref<Target = [P::Subpixel]>
+{
+    let mut counts = vec![0u32; size as usize * size as usize];
+    for &p in image.pixels() {
+        let ycbcr = YCbCr::from(Rgb::from(p));
+        let x = ycbcr[1] as u32 * size / 256;
+        let y = (255 - ycbcr[2] as u32) * size / 256;
+        counts[(y * size + x) as usize] += 1;
+    }
+    density_image(size, size, &counts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{gamut_slice, oklab_gamut_slice, render_histogram, render_vectorscope,
+                HistogramConfig, HistogramMode, OUT_OF_GAMUT};
+    use buffer::RgbImage;
+    use color_model::{Rgb, RgbSpace};
+
+    fn in_gamut_area(slice: fn(&RgbSpace) -> RgbImage, space: &RgbSpace) -> usize {
+        slice(space).pixels().filter(|p| !OUT_OF_GAMUT.contains(p)).count()
+    }
+
+    #[test]
+    fn test_gamut_sizes() {
+        let lab = |space: &RgbSpace| gamut_slice(space, 60.0, 64, 128.0);
+        let oklab = |space: &RgbSpace| oklab_gamut_slice(space, 0.65, 64, 0.4);
+        for &slice in &[lab as fn(&RgbSpace) -> RgbImage, oklab] {
+            let srgb = in_gamut_area(slice, &RgbSpace::SRGB);
+            let p3 = in_gamut_area(slice, &RgbSpace::DISPLAY_P3);
+            let rec2020 = in_gamut_area(slice, &RgbSpace::REC2020);
+            assert!(0 < srgb && srgb < p3 && p3 < rec2020 && rec2020 < 64 * 64);
+        }
+    }
+
+    #[test]
+    fn test_oklab_white() {
+        // Oklab white at L = 1 is sRGB white
+        let slice = oklab_gamut_slice(&RgbSpace::SRGB, 1.0, 2, 0.0);
+        assert!(slice.pixels().all(|&p| p == Rgb::new([255, 255, 255])));
+    }
+
+    #[test]
+    fn test_channel_histogram() {
+        // Three quarters red, one quarter yellow
+        let image = RgbImage::from_fn(4, 1, |x, _| {
+            if x == 0 { Rgb::new([255, 255, 0]) } else { Rgb::new([255, 0, 0]) }
+        });
+        let config = HistogramConfig {
+            width: 256,
+            height: 8,
+            mode: HistogramMode::Channels,
+            log_scale: false,
+        };
+        let plot = render_histogram(&image, &config);
+        assert_eq!(plot[(255, 0)], Rgb::new([255, 0, 0]));
+        assert_eq!(plot[(255, 7)], Rgb::new([255, 255, 0]));
+        assert_eq!(plot[(0, 0)], Rgb::new([0, 255, 255]));
+        assert_eq!(plot[(128, 7)], Rgb::new([0, 0, 0]));
+    }
+
+    #[test]
+    fn test_vectorscope_primaries() {
+        let image = RgbImage::from_fn(2, 1, |x, _| {
+            if x == 0 { Rgb::new([255, 0, 0]) } else { Rgb::new([0, 0, 255]) }
+        });
+        let scope = render_vectorscope(&image, 16);
+        // Red has a large Cr, blue a large Cb
+        let lit: Vec<(u32, u32)> = scope.enumerate_pixels()
+            .filter(|&(_, _, p)| p[0] > 0)
+            .map(|(x, y, _)| (x, y))
+            .collect();
+        assert_eq!(lit, [(5, 0), (15, 9)]);
+    }
+}
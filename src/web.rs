@@ -0,0 +1,99 @@
+//! Interoperation with the `ImageData` of the HTML canvas, enabled by the `wasm` feature.
+//!
+//! The `data` of an `ImageData` is a `Uint8ClampedArray` of packed RGBA pixels, row by row
+//! without padding. The color channels are sRGB encoded and the alpha is straight, not
+//! premultiplied. Canvases store premultiplied colors internally, so colors of pixels with a
+//! low alpha lose precision on a round trip through a canvas. Images with premultiplied alpha
+//! are unpremultiplied by `premultiplied_to_image_data`.
+//!
+//! The functions borrow the bytes of the array, which wasm bindings expose as a `&[u8]` view
+//! into the memory of the module or as a copied `Vec<u8>`, without further copies.
+
+use std::borrow::Cow;
+use std::ops::Deref;
+
+use buffer::{BufferError, ImageBuffer, SizeCheck};
+use color_model::{PremultipliedRgba, Rgba};
+use traits::Color;
+
+/// Interprets the `data` of an `ImageData` with the given dimensions as an image.
+///
+/// # Examples
+///
+/// ```
+/// # use image_buffer::color::Rgba;
+/// use image_buffer::web::{from_image_data, to_image_data};
+/// let data = [255, 0, 0, 255, 0, 0, 255, 128];
+/// let image = from_image_data(&data, 2, 1).unwrap();
+/// assert_eq!(image[(1, 0)], Rgba::new([0, 0, 255, 128]));
+/// assert_eq!(&*to_image_data(&image), &data);
+/// ```
+pub fn from_image_data(data: &[u8],
+                       width: u32,
+                       height: u32)
+                       -> Result<ImageBuffer<Rgba<u8>, &[u8]>, BufferError> {
+    ImageBuffer::try_from_raw(width, height, data, SizeCheck::Exact)
+}
+
+/// Interprets the mutable `data` of an `ImageData` with the given dimensions as an image, such
+/// that it can be drawn on and written back with `putImageData`.
+pub fn from_image_data_mut(data: &mut [u8],
+                           width: u32,
+                           height: u32)
+                           -> Result<ImageBuffer<Rgba<u8>, &mut [u8]>, BufferError> {
+    ImageBuffer::try_from_raw(width, height, data, SizeCheck::Exact)
+}
+
+/// Returns the pixels of `image` in the layout of the `data` of an `ImageData`.
+///
+/// The pixels are borrowed if the image is packed and copied otherwise.
+pub fn to_image_data<C>(image: &ImageBuffer<Rgba<u8>, C>) -> Cow<'_, [u8]>
+    where C: Deref<Target = [u8]>
+{
+    if image.is_packed() {
+        let data: &[u8] = image;
+        Cow::Borrowed(&data[..image.logical_len()])
+    } else {
+        Cow::Owned(image.to_vec().into_raw())
+    }
+}
+
+/// Returns the pixels of an image with premultiplied alpha in the layout of the `data` of an
+/// `ImageData`, with straight alpha.
+pub fn premultiplied_to_image_data<C>(image: &ImageBuffer<PremultipliedRgba<u8>, C>) -> Vec<u8>
+    where C: Deref<Target = [u8]>
+{
+    image.pixels().flat_map(|p| *p.unpremultiply().channels()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use buffer::{BufferError, RgbaImage};
+    use color_model::{PremultipliedRgba, Rgba};
+    use super::{from_image_data, from_image_data_mut, premultiplied_to_image_data, to_image_data};
+
+    #[test]
+    fn test_image_data() {
+        let mut data = vec![0; 16];
+        {
+            let mut image = from_image_data_mut(&mut data, 2, 2).unwrap();
+            image[(1, 1)] = Rgba::new([1, 2, 3, 4]);
+            assert!(match to_image_data(&image) {
+                Cow::Borrowed(_) => true,
+                Cow::Owned(_) => false,
+            });
+        }
+        assert_eq!(&data[12..], &[1, 2, 3, 4]);
+        assert_eq!(from_image_data(&data, 2, 1).err(),
+                   Some(BufferError::TooLarge { expected: 8, got: 16 }));
+
+        let strided = RgbaImage::from_raw_with_stride(1, 2, 8, data.clone()).unwrap();
+        assert_eq!(to_image_data(&strided).into_owned(), vec![0, 0, 0, 0, 0, 0, 0, 0]);
+
+        let premultiplied = vec![PremultipliedRgba::new([100, 50, 0, 128])];
+        let image = ::buffer::ImageBuffer::from_pixel_vec(1, 1, premultiplied).unwrap();
+        assert_eq!(premultiplied_to_image_data(&image), vec![199, 100, 0, 128]);
+    }
+}
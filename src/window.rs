@@ -0,0 +1,241 @@
+//! Iteration over the neighborhoods of pixels.
+
+use std::iter::FusedIterator;
+use std::ops::Deref;
+
+use buffer::ImageBuffer;
+use traits::Color as Pixel;
+
+/// How coordinates outside of an image are mapped into it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum EdgeMode {
+    /// Coordinates are clamped to the border, repeating the edge pixels.
+    Clamp,
+    /// Coordinates wrap around, the image is tiled.
+    Wrap,
+}
+
+impl EdgeMode {
+    /// Maps `v` into `0..len`, `len` must not be zero.
+    #[inline]
+    pub(crate) fn resolve(self, v: i64, len: u32) -> u32 {
+        match self {
+            EdgeMode::Clamp => v.clamp(0, len as i64 - 1) as u32,
+            EdgeMode::Wrap => v.rem_euclid(len as i64) as u32,
+        }
+    }
+}
+
+/// The pixels of a packed or strided image without the container type.
+#[derive(Copy, Clone)]
+struct Samples<'a, P: Pixel + 'a> {
+    data: &'a [P::Subpixel],
+    width: u32,
+    height: u32,
+    stride: usize,
+}
+
+impl<'a, P: Pixel + 'a> Samples<'a, P> {
+    #[inline]
+    fn get(&self, x: u32, y: u32) -> &'a P {
+        let channels = P::channel_count();
+        let index = y as usize * self.stride + x as usize * channels;
+        P::from_slice(&self.data[index..index + channels])
+    }
+}
+
+/// The region of `width`×`height` pixels around a pixel, yielded by `Neighborhoods`.
+///
+/// The pixel is at the offset `(width / 2, height / 2)` of the region, pixels outside of the
+/// image are replaced according to the `EdgeMode`.
+#[derive(Copy, Clone)]
+pub struct Neighborhood<'a, P: Pixel + 'a> {
+    samples: Samples<'a, P>,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    edges: EdgeMode,
+}
+
+impl<'a, P: Pixel + 'a> Neighborhood<'a, P> {
+    /// The coordinates of the pixel in the image.
+    pub fn position(&self) -> (u32, u32) {
+        (self.x, self.y)
+    }
+
+    /// The width and height of the region.
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// The pixel itself.
+    pub fn center(&self) -> &'a P {
+        self.samples.get(self.x, self.y)
+    }
+
+    /// Returns the pixel at the offset `(dx, dy)` relative to the center, which may lie
+    /// outside of the region.
+    pub fn get(&self, dx: i32, dy: i32) -> &'a P {
+        let x = self.edges.resolve(self.x as i64 + dx as i64, self.samples.width);
+        let y = self.edges.resolve(self.y as i64 + dy as i64, self.samples.height);
+        self.samples.get(x, y)
+    }
+
+    /// Returns the pixels of the region row by row.
+    pub fn pixels(&self) -> NeighborhoodPixels<'a, P> {
+        NeighborhoodPixels {
+            neighborhood: *self,
+            index: 0,
+        }
+    }
+}
+
+/// Iterator over the pixels of a `Neighborhood`.
+pub struct NeighborhoodPixels<'a, P: Pixel + 'a> {
+    neighborhood: Neighborhood<'a, P>,
+    index: u32,
+}
+
+impl<'a, P: Pixel + 'a> Iterator for NeighborhoodPixels<'a, P> {
+    type Item = &'a P;
+
+    fn next(&mut self) -> Option<&'a P> {
+        let n = &self.neighborhood;
+        if self.index == n.width * n.height {
+            return None;
+        }
+        let dx = (self.index % n.width) as i32 - (n.width / 2) as i32;
+        let dy = (self.index / n.width) as i32 - (n.height / 2) as i32;
+        self.index += 1;
+        Some(n.get(dx, dy))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = (self.neighborhood.width * self.neighborhood.height - self.index) as usize;
+        (len, Some(len))
+    }
+}
+
+impl<'a, P: Pixel + 'a> ExactSizeIterator for NeighborhoodPixels<'a, P> {}
+
+impl<'a, P: Pixel + 'a> FusedIterator for NeighborhoodPixels<'a, P> {}
+
+/// Iterator over the neighborhoods of all pixels of an image, row by row.
+pub struct Neighborhoods<'a, P: Pixel + 'a> {
+    samples: Samples<'a, P>,
+    width: u32,
+    height: u32,
+    edges: EdgeMode,
+    index: usize,
+}
+
+impl<'a, P: Pixel + 'a> Iterator for Neighborhoods<'a, P> {
+    type Item = Neighborhood<'a, P>;
+
+    fn next(&mut self) -> Option<Neighborhood<'a, P>> {
+        let image_width = self.samples.width as usize;
+        if self.index == image_width * self.samples.height as usize {
+            return None;
+        }
+        let (x, y) = ((self.index % image_width) as u32, (self.index / image_width) as u32);
+        self.index += 1;
+        Some(Neighborhood {
+            samples: self.samples,
+            x,
+            y,
+            width: self.width,
+            height: self.height,
+            edges: self.edges,
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.samples.width as usize * self.samples.height as usize - self.index;
+        (len, Some(len))
+    }
+}
+
+impl<'a, P: Pixel + 'a> ExactSizeIterator for Neighborhoods<'a, P> {}
+
+impl<'a, P: Pixel + 'a> FusedIterator for Neighborhoods<'a, P> {}
+
+impl<P, C> ImageBuffer<P, C>
+    where P: Pixel,
+          C: Deref<Target = [P::Subpixel]>
+{
+    /// Iterates over the square neighborhoods of `2 * radius + 1` pixels per side around
+    /// every pixel.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use image_buffer::{EdgeMode, GrayImage, color::Gray};
+    /// let image = GrayImage::from_fn(4, 4, |x, y| Gray::new([(x + 4 * y) as u8]));
+    /// let medians: Vec<u8> = image.windows(1, EdgeMode::Clamp)
+    ///     .map(|window| {
+    ///         let mut values: Vec<u8> = window.pixels().map(|p| p[0]).collect();
+    ///         values.sort();
+    ///         values[4]
+    ///     })
+    ///     .collect();
+    /// assert_eq!(medians[5], 5);
+    /// ```
+    pub fn windows(&self, radius: u32, edges: EdgeMode) -> Neighborhoods<'_, P> {
+        self.neighborhoods(2 * radius + 1, 2 * radius + 1, edges)
+    }
+
+    /// Iterates over the neighborhoods of `width`×`height` pixels around every pixel.
+    ///
+    /// Each pixel is at the offset `(width / 2, height / 2)` of its neighborhood.
+    ///
+    /// # Panics
+    ///
+    /// If `width` or `height` is zero.
+    pub fn neighborhoods(&self, width: u32, height: u32, edges: EdgeMode) -> Neighborhoods<'_, P> {
+        assert!(width > 0 && height > 0, "empty neighborhood");
+        Neighborhoods {
+            samples: Samples {
+                data: self,
+                width: self.width(),
+                height: self.height(),
+                stride: self.stride(),
+            },
+            width,
+            height,
+            edges,
+            index: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buffer::GrayImage;
+    use color_model::Gray;
+    use super::EdgeMode;
+
+    #[test]
+    fn test_neighborhoods() {
+        let data: Vec<u8> = (0..12).collect();
+        let image = GrayImage::from_raw_with_stride(3, 3, 4, data).unwrap();
+        let sums: Vec<u32> = image.windows(1, EdgeMode::Clamp)
+            .map(|w| w.pixels().map(|p| p[0] as u32).sum())
+            .collect();
+        assert_eq!(sums[0], 15);
+        assert_eq!(sums[4], 45);
+
+        let mut neighborhoods = image.neighborhoods(2, 1, EdgeMode::Wrap);
+        assert_eq!(neighborhoods.len(), 9);
+        let first = neighborhoods.next().unwrap();
+        assert_eq!(first.position(), (0, 0));
+        assert_eq!(first.dimensions(), (2, 1));
+        assert_eq!(first.pixels().collect::<Vec<_>>(), vec![&Gray::new([2]), &Gray::new([0])]);
+        assert_eq!(first.get(-1, -1), &Gray::new([10]));
+        let last = neighborhoods.nth(7).unwrap();
+        assert_eq!(last.position(), (2, 2));
+        assert_eq!(last.center(), &Gray::new([10]));
+        assert_eq!(last.get(1, 1), &Gray::new([0]));
+        assert!(neighborhoods.next().is_none());
+    }
+}
@@ -0,0 +1,398 @@
+//! Chroma subsampled Y'CbCr images as produced by cameras and video codecs.
+//!
+//! Both layouts store 8 bit values and subsample the chroma by 2 in each direction (4:2:0),
+//! one chroma sample covers a block of 2×2 pixels. Images with odd dimensions have a chroma
+//! sample for the partial blocks at the right and bottom. Pixels are reconstructed with the
+//! chroma of their block, without interpolation.
+
+use std::ops::{Deref, DerefMut};
+
+use buffer::{ImageBuffer, RgbImage};
+use color_model::{LumaWeights, Rgb, YCbCr, YCbCrRange};
+
+/// The width and height of the chroma planes of an image with the given dimensions.
+fn chroma_dimensions(width: u32, height: u32) -> (usize, usize) {
+    (width.div_ceil(2) as usize, height.div_ceil(2) as usize)
+}
+
+/// The number of bytes of a 4:2:0 image with the given dimensions, if it fits into `usize`.
+fn required_len(width: u32, height: u32) -> Option<usize> {
+    let (cw, ch) = chroma_dimensions(width, height);
+    (width as usize).checked_mul(height as usize)?.checked_add(cw.checked_mul(ch)?.checked_mul(2)?)
+}
+
+/// Converts `image` to Y'CbCr, with the chroma averaged over the 2×2 blocks.
+///
+/// Returns the luma and the Cb and Cr samples of the blocks.
+fn subsample<C>(image: &ImageBuffer<Rgb<u8>, C>,
+                weights: LumaWeights,
+                range: YCbCrRange)
+                -> (Vec<u8>, Vec<u8>, Vec<u8>)
+    where C: Deref<Target = [u8]>
+{
+    let (cw, ch) = chroma_dimensions(image.width(), image.height());
+    let mut luma = Vec::with_capacity(image.width() as usize * image.height() as usize);
+    let mut sums = vec![(0u32, 0u32, 0u32); cw * ch];
+    for (x, y, &p) in image.enumerate_pixels() {
+        let ycbcr = YCbCr::from_rgb(p, weights, range);
+        luma.push(ycbcr[0]);
+        let sum = &mut sums[(y / 2) as usize * cw + (x / 2) as usize];
+        sum.0 += ycbcr[1] as u32;
+        sum.1 += ycbcr[2] as u32;
+        sum.2 += 1;
+    }
+    let average = |sum: u32, n: u32| ((sum + n / 2) / n) as u8;
+    let cb = sums.iter().map(|&(cb, _, n)| average(cb, n)).collect();
+    let cr = sums.iter().map(|&(_, cr, n)| average(cr, n)).collect();
+    (luma, cb, cr)
+}
+
+/// A 4:2:0 image in the planar I420 layout.
+///
+/// The container holds the full resolution Y' plane followed by the Cb and the Cr plane, each
+/// `ceil(width / 2)`×`ceil(height / 2)` samples. All planes are stored row by row without
+/// padding.
+///
+/// # Examples
+///
+/// ```
+/// # use image_buffer::{RgbImage, color::{LumaWeights, Rgb, YCbCrRange}};
+/// use image_buffer::yuv::Yuv420Image;
+/// let image = RgbImage::from_pixel(4, 2, Rgb::new([255, 255, 255]));
+/// let yuv = Yuv420Image::from_rgb(&image, LumaWeights::Bt709, YCbCrRange::Studio);
+/// assert_eq!(yuv.y_plane(), &[235; 8]);
+/// assert_eq!(yuv.u_plane(), &[128, 128]);
+/// let rgb = yuv.to_rgb(LumaWeights::Bt709, YCbCrRange::Studio);
+/// assert_eq!(rgb.into_raw(), image.into_raw());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Yuv420Image<Container> {
+    width: u32,
+    height: u32,
+    data: Container,
+}
+
+impl<Container> Yuv420Image<Container>
+    where Container: Deref<Target = [u8]>
+{
+    /// The offsets of the Cb and the Cr plane and the length of a chroma plane.
+    fn chroma_layout(&self) -> (usize, usize, usize) {
+        let (cw, ch) = chroma_dimensions(self.width, self.height);
+        let luma_len = self.width as usize * self.height as usize;
+        (luma_len, luma_len + cw * ch, cw * ch)
+    }
+
+    /// The Cb plane.
+    pub fn u_plane(&self) -> &[u8] {
+        let (u, _, len) = self.chroma_layout();
+        &self.data[u..u + len]
+    }
+
+    /// The Cr plane.
+    pub fn v_plane(&self) -> &[u8] {
+        let (_, v, len) = self.chroma_layout();
+        &self.data[v..v + len]
+    }
+
+    /// Returns the color of the pixel at `(x, y)`.
+    ///
+    /// # Panics
+    ///
+    /// If `(x, y)` is out of bounds.
+    pub fn get_ycbcr(&self, x: u32, y: u32) -> YCbCr<u8> {
+        let (luma, chroma) = self.indices(x, y);
+        let (u, v, _) = self.chroma_layout();
+        YCbCr::new([self.data[luma], self.data[u + chroma], self.data[v + chroma]])
+    }
+}
+
+impl<Container> Yuv420Image<Container>
+    where Container: Deref<Target = [u8]> + DerefMut
+{
+    /// The mutable Cb plane.
+    pub fn u_plane_mut(&mut self) -> &mut [u8] {
+        let (u, _, len) = self.chroma_layout();
+        &mut self.data[u..u + len]
+    }
+
+    /// The mutable Cr plane.
+    pub fn v_plane_mut(&mut self) -> &mut [u8] {
+        let (_, v, len) = self.chroma_layout();
+        &mut self.data[v..v + len]
+    }
+}
+
+impl Yuv420Image<Vec<u8>> {
+    /// Converts `image` with the luma coefficients `weights` and the value range `range`, the
+    /// chroma is averaged over each block.
+    pub fn from_rgb<C>(image: &ImageBuffer<Rgb<u8>, C>,
+                       weights: LumaWeights,
+                       range: YCbCrRange)
+                       -> Yuv420Image<Vec<u8>>
+        where C: Deref<Target = [u8]>
+    {
+        let (mut data, cb, cr) = subsample(image, weights, range);
+        data.extend(cb);
+        data.extend(cr);
+        Yuv420Image::from_raw(image.width(), image.height(), data).unwrap()
+    }
+}
+
+/// A 4:2:0 image in the semi-planar NV12 layout.
+///
+/// The container holds the full resolution Y' plane followed by a plane of
+/// `ceil(width / 2)`×`ceil(height / 2)` interleaved Cb and Cr pairs. Both planes are stored row
+/// by row without padding.
+///
+/// # Examples
+///
+/// ```
+/// # use image_buffer::color::{LumaWeights, Rgb, YCbCr, YCbCrRange};
+/// use image_buffer::yuv::Nv12Image;
+/// let data = vec![16, 235, 16, 235, 90, 240];
+/// let nv12 = Nv12Image::from_raw(2, 2, data).unwrap();
+/// assert_eq!(nv12.get_ycbcr(1, 0), YCbCr::new([235, 90, 240]));
+/// let red = nv12.get_rgb(0, 0, LumaWeights::Bt601, YCbCrRange::Studio);
+/// assert!(red[0] > red[1] && red[0] > red[2]);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Nv12Image<Container> {
+    width: u32,
+    height: u32,
+    data: Container,
+}
+
+impl<Container> Nv12Image<Container>
+    where Container: Deref<Target = [u8]>
+{
+    /// The offset and the length of the chroma plane.
+    fn chroma_layout(&self) -> (usize, usize) {
+        let (cw, ch) = chroma_dimensions(self.width, self.height);
+        (self.width as usize * self.height as usize, 2 * cw * ch)
+    }
+
+    /// The plane of interleaved Cb and Cr samples.
+    pub fn uv_plane(&self) -> &[u8] {
+        let (offset, len) = self.chroma_layout();
+        &self.data[offset..offset + len]
+    }
+
+    /// Returns the color of the pixel at `(x, y)`.
+    ///
+    /// # Panics
+    ///
+    /// If `(x, y)` is out of bounds.
+    pub fn get_ycbcr(&self, x: u32, y: u32) -> YCbCr<u8> {
+        let (luma, chroma) = self.indices(x, y);
+        let uv = self.chroma_layout().0 + 2 * chroma;
+        YCbCr::new([self.data[luma], self.data[uv], self.data[uv + 1]])
+    }
+}
+
+impl<Container> Nv12Image<Container>
+    where Container: Deref<Target = [u8]> + DerefMut
+{
+    /// The mutable plane of interleaved Cb and Cr samples.
+    pub fn uv_plane_mut(&mut self) -> &mut [u8] {
+        let (offset, len) = self.chroma_layout();
+        &mut self.data[offset..offset + len]
+    }
+}
+
+impl Nv12Image<Vec<u8>> {
+    /// Converts `image` with the luma coefficients `weights` and the value range `range`, the
+    /// chroma is averaged over each block.
+    pub fn from_rgb<C>(image: &ImageBuffer<Rgb<u8>, C>,
+                       weights: LumaWeights,
+                       range: YCbCrRange)
+                       -> Nv12Image<Vec<u8>>
+        where C: Deref<Target = [u8]>
+    {
+        let (mut data, cb, cr) = subsample(image, weights, range);
+        data.reserve(2 * cb.len());
+        for (cb, cr) in cb.into_iter().zip(cr) {
+            data.push(cb);
+            data.push(cr);
+        }
+        Nv12Image::from_raw(image.width(), image.height(), data).unwrap()
+    }
+}
+
+/// Implements the accessors which the 4:2:0 layouts share, all of them store the luma plane
+/// first.
+macro_rules! subsampled_image_impls {
+    ($($ident:ident),*) => {$(
+        impl<Container> $ident<Container>
+            where Container: Deref<Target = [u8]>
+        {
+            /// Constructs an image from the planes in `buf`.
+            ///
+            /// Returns None if the container is too small for the planes.
+            pub fn from_raw(width: u32, height: u32, buf: Container) -> Option<$ident<Container>> {
+                if required_len(width, height)? > buf.len() {
+                    return None;
+                }
+                Some($ident {
+                    width,
+                    height,
+                    data: buf,
+                })
+            }
+
+            /// Returns the underlying raw buffer.
+            pub fn into_raw(self) -> Container {
+                self.data
+            }
+
+            /// The width and height of this image.
+            pub fn dimensions(&self) -> (u32, u32) {
+                (self.width, self.height)
+            }
+
+            /// The width of this image.
+            pub fn width(&self) -> u32 {
+                self.width
+            }
+
+            /// The height of this image.
+            pub fn height(&self) -> u32 {
+                self.height
+            }
+
+            /// The luma plane.
+            pub fn y_plane(&self) -> &[u8] {
+                &self.data[..self.width as usize * self.height as usize]
+            }
+
+            /// The index of the luma sample of `(x, y)` and the index of its block among the
+            /// chroma samples.
+            fn indices(&self, x: u32, y: u32) -> (usize, usize) {
+                assert!(x < self.width && y < self.height,
+                        "pixel ({}, {}) out of bounds {:?}",
+                        x,
+                        y,
+                        self.dimensions());
+                let chroma = (y / 2) as usize * self.width.div_ceil(2) as usize + (x / 2) as usize;
+                (y as usize * self.width as usize + x as usize, chroma)
+            }
+
+            /// Returns the pixel at `(x, y)` converted to RGB with the luma coefficients
+            /// `weights` and the value range `range`.
+            ///
+            /// # Panics
+            ///
+            /// If `(x, y)` is out of bounds.
+            pub fn get_rgb(&self,
+                           x: u32,
+                           y: u32,
+                           weights: LumaWeights,
+                           range: YCbCrRange)
+                           -> Rgb<u8> {
+                self.get_ycbcr(x, y).to_rgb(weights, range)
+            }
+
+            /// Converts the image to RGB with the luma coefficients `weights` and the value
+            /// range `range`.
+            pub fn to_rgb(&self, weights: LumaWeights, range: YCbCrRange) -> RgbImage {
+                RgbImage::from_fn(self.width,
+                                  self.height,
+                                  |x, y| self.get_rgb(x, y, weights, range))
+            }
+        }
+
+        impl<Container> $ident<Container>
+            where Container: Deref<Target = [u8]> + DerefMut
+        {
+            /// The mutable luma plane.
+            pub fn y_plane_mut(&mut self) -> &mut [u8] {
+                let len = self.width as usize * self.height as usize;
+                &mut self.data[..len]
+            }
+        }
+
+        impl $ident<Vec<u8>> {
+            /// Creates a black image in the full range.
+            pub fn new(width: u32, height: u32) -> $ident<Vec<u8>> {
+                let len = required_len(width, height).expect("image too large");
+                let mut data = vec![128; len];
+                for v in &mut data[..width as usize * height as usize] {
+                    *v = 0;
+                }
+                $ident::from_raw(width, height, data).unwrap()
+            }
+        }
+    )*}
+}
+
+subsampled_image_impls!(Yuv420Image, Nv12Image);
+
+impl<Container> From<Nv12Image<Container>> for Yuv420Image<Vec<u8>>
+    where Container: Deref<Target = [u8]>
+{
+    fn from(other: Nv12Image<Container>) -> Self {
+        let mut data = other.y_plane().to_vec();
+        data.extend(other.uv_plane().iter().step_by(2));
+        data.extend(other.uv_plane().iter().skip(1).step_by(2));
+        Yuv420Image::from_raw(other.width, other.height, data).unwrap()
+    }
+}
+
+impl<Container> From<Yuv420Image<Container>> for Nv12Image<Vec<u8>>
+    where Container: Deref<Target = [u8]>
+{
+    fn from(other: Yuv420Image<Container>) -> Self {
+        let mut data = other.y_plane().to_vec();
+        for (&cb, &cr) in other.u_plane().iter().zip(other.v_plane()) {
+            data.push(cb);
+            data.push(cr);
+        }
+        Nv12Image::from_raw(other.width, other.height, data).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buffer::RgbImage;
+    use color_model::{LumaWeights, Rgb, YCbCr, YCbCrRange};
+    use super::{Nv12Image, Yuv420Image};
+
+    #[test]
+    fn test_odd_dimensions() {
+        let image = RgbImage::from_fn(3, 3, |x, y| {
+            if x < 2 && y < 2 {
+                Rgb::new([200, 30, 30])
+            } else {
+                Rgb::new([30, 30, 200])
+            }
+        });
+        let (weights, range) = (LumaWeights::Bt601, YCbCrRange::Full);
+        let yuv = Yuv420Image::from_rgb(&image, weights, range);
+        assert_eq!(yuv.clone().into_raw().len(), 9 + 2 * 4);
+        assert_eq!(yuv.u_plane().len(), 4);
+        let rgb = yuv.to_rgb(weights, range);
+        for (a, b) in rgb.into_raw().into_iter().zip(image.clone().into_raw()) {
+            assert!((a as i32 - b as i32).abs() <= 1);
+        }
+
+        let nv12 = Nv12Image::from(yuv.clone());
+        assert_eq!(nv12, Nv12Image::from_rgb(&image, weights, range));
+        assert_eq!(nv12.get_ycbcr(2, 2), yuv.get_ycbcr(2, 2));
+        assert_eq!(Yuv420Image::from(nv12), yuv);
+    }
+
+    #[test]
+    fn test_planes() {
+        let mut yuv = Yuv420Image::new(2, 2);
+        assert_eq!(yuv.get_ycbcr(1, 1), YCbCr::new([0, 128, 128]));
+        yuv.y_plane_mut()[3] = 50;
+        yuv.v_plane_mut()[0] = 10;
+        assert_eq!(yuv.get_ycbcr(1, 1), YCbCr::new([50, 128, 10]));
+        assert_eq!(yuv.into_raw(), vec![0, 0, 0, 50, 128, 10]);
+
+        let mut nv12 = Nv12Image::new(4, 2);
+        nv12.uv_plane_mut()[3] = 7;
+        assert_eq!(nv12.get_ycbcr(3, 0), YCbCr::new([0, 128, 7]));
+        assert!(Nv12Image::from_raw(4, 2, vec![0; 11]).is_none());
+        assert!(Yuv420Image::from_raw(u32::MAX, u32::MAX, &[][..]).is_none());
+    }
+}